@@ -0,0 +1,59 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * The C API of libtimeknight, built with `cargo build --release --features ffi`.
+ * Strings are UTF-8 and null-terminated; the ones returned are released with timek_string_free.
+ */
+
+#ifndef TIMEKNIGHT_H
+#define TIMEKNIGHT_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TIMEK_OK 0
+#define TIMEK_UNKNOWN_PROJECT -1
+#define TIMEK_NOT_RUNNING -2
+#define TIMEK_STORAGE -3
+#define TIMEK_UNWRITABLE -4
+#define TIMEK_INVALID_ARGUMENT -5
+#define TIMEK_ARCHIVED -6
+
+typedef struct Tracker Tracker;
+
+/* A tracker for the database at location, e.g. ~/.timeknight, or NULL */
+Tracker *timek_tracker_new(const char *location);
+void timek_tracker_free(Tracker *tracker);
+
+/* Starts tracking the project name, stopping whatever is running */
+int timek_start(const Tracker *tracker, const char *name);
+/* Stops everything that's running */
+int timek_stop(const Tracker *tracker);
+
+/* {"running": [...], "last": ...}, sessions with their project, start, end and seconds, or NULL */
+char *timek_status_json(const Tracker *tracker);
+/* [{"project", "day", "seconds"}, ...] for a period, e.g. "week", or NULL */
+char *timek_report_json(const Tracker *tracker, const char *period);
+
+void timek_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,708 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::core::{Context, Labels};
+use crate::locale::{self, Language, Locale};
+use crate::theme::Theme;
+use crate::zone::Zone;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeZone, Weekday};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const CONFIG_FILE: &str = "config.toml";
+const DEVICE_FILE: &str = "device";
+
+/// User settings, read from `config.toml` next to the database. Everything in it is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+  /// Time of day past which running records are stopped, as of the next invocation
+  #[serde(deserialize_with = "time_of_day")]
+  pub hard_stop: Option<NaiveTime>,
+  /// How long a record can run before being reminded about it, e.g. "3h" or "1h30m"
+  #[serde(deserialize_with = "duration")]
+  pub remind_after: Option<Duration>,
+  /// When to expect something to be tracked
+  pub work_hours: Option<WorkHours>,
+  /// How long nothing can be tracked during work hours before `nag` notifies about it
+  #[serde(deserialize_with = "duration")]
+  pub nag_after: Option<Duration>,
+  /// How much to track every work day, see `streak`
+  #[serde(deserialize_with = "duration")]
+  pub daily_target: Option<Duration>,
+  /// The timezone records are bucketed into days and shown in: local, utc, recorded, i.e. the
+  /// offset each was tracked at, or an offset like "+05:30"
+  #[serde(deserialize_with = "zone")]
+  pub timezone: Zone,
+  /// How often `heartbeat` runs, e.g. from cron. Three beats missed while a record was running
+  /// means the machine was off, and offers to stop the record at the last one.
+  #[serde(deserialize_with = "duration")]
+  pub heartbeat: Option<Duration>,
+  /// Whether to celebrate reaching the daily target when stopping
+  pub celebrate: bool,
+  /// Whether to note the machine, git branch and directory each record is started from, as
+  /// `project info` then shows
+  pub capture_context: bool,
+  /// Who the records started here are attributed to, when the database is shared with others
+  /// through a synced directory, e.g. "alice". Noted along with the context when it's captured,
+  /// the login name then being the default.
+  pub user: Option<String>,
+  /// Days off, without a target, as YYYY-MM-DD
+  #[serde(deserialize_with = "dates")]
+  pub holidays: Vec<NaiveDate>,
+  /// Hourly rate the time is billed at, for templates to compute amounts with
+  pub rate: Option<f64>,
+  /// Whether the database directory is synced across machines, e.g. by Dropbox, in which case
+  /// conflicting copies of the log the sync leaves behind get merged back in
+  pub synced: bool,
+  /// Whether to seal every entry appended to the log onto a hash chain, for `verify --audit` to
+  /// tell whether any was tampered with since
+  pub audit: bool,
+  /// Language reports are written in: en, de, es, fr, it or nl
+  pub language: Language,
+  /// How reports print days, as a strftime pattern, e.g. "%d/%m/%Y", defaulting to the language's
+  #[serde(deserialize_with = "date_format")]
+  pub date_format: Option<String>,
+  /// How output is styled: default, solarized or monochrome, or a table picking the style of
+  /// success, failure, warning, info and muted output, on top of a `base` theme, e.g.
+  /// `theme = { base = "monochrome", failure = "red.bold" }`
+  #[serde(deserialize_with = "theme")]
+  pub theme: Theme,
+  /// Who the timesheets are from and for
+  pub timesheet: TimesheetConfig,
+  /// Per project settings, overriding the ones above, keyed by project name
+  projects: BTreeMap<String, ProjectConfig>,
+  /// Who projects are billed to, and how, keyed by client name
+  clients: BTreeMap<String, ClientConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProjectConfig {
+  #[serde(deserialize_with = "duration")]
+  pub remind_after: Option<Duration>,
+  pub rate: Option<f64>,
+  /// Where work on the project happens, e.g. ["~/src/backend"], see `project_at`
+  #[serde(deserialize_with = "directories")]
+  pub directories: Vec<PathBuf>,
+  /// Whether new records are billable, unless started otherwise. They are by default.
+  pub billable: Option<bool>,
+  /// What new records are tagged with, unless started otherwise, e.g. ["internal"]
+  pub tags: Option<Vec<String>>,
+  /// How much time is to be spent on the project overall, e.g. "120h"
+  #[serde(deserialize_with = "duration")]
+  pub budget: Option<Duration>,
+  /// The day the budget is to be spent by, as YYYY-MM-DD
+  #[serde(deserialize_with = "date")]
+  pub deadline: Option<NaiveDate>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ClientConfig {
+  /// The projects billed to this client
+  pub projects: Vec<String>,
+  /// What time billed to this client is rounded up to, e.g. "6m" or "15m"
+  #[serde(deserialize_with = "duration")]
+  pub increment: Option<Duration>,
+  /// The least time billed to this client for anything worked on
+  #[serde(deserialize_with = "duration")]
+  pub minimum: Option<Duration>,
+  /// Hourly rate of the projects billed to this client, unless they have their own
+  pub rate: Option<f64>,
+  /// What amounts billed to this client are in, e.g. "EUR"
+  pub currency: Option<String>,
+  /// Tax added to invoices, as a percentage, e.g. 20.0 for 20% VAT
+  pub tax: Option<f64>,
+}
+
+impl ClientConfig {
+  /// Whether `project` is billed to this client. Project names are case insensitive.
+  pub fn bills(&self, project: &str) -> bool {
+    self
+      .projects
+      .iter()
+      .any(|name| name.to_lowercase() == project.to_lowercase())
+  }
+
+  /// The seconds billed for having worked `secs` on a project: at least the minimum, rounded up
+  /// to the increment. Not having worked isn't billed.
+  pub fn bill(&self, secs: u64) -> u64 {
+    if secs == 0 {
+      return 0;
+    }
+    let secs = secs.max(self.minimum.map_or(0, |minimum| minimum.as_secs()));
+    match self.increment.map(|increment| increment.as_secs()) {
+      Some(increment) => secs.div_ceil(increment) * increment,
+      None => secs,
+    }
+  }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TimesheetConfig {
+  pub employee: Option<String>,
+  pub client: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WorkHours {
+  #[serde(deserialize_with = "time")]
+  pub start: NaiveTime,
+  #[serde(deserialize_with = "time")]
+  pub end: NaiveTime,
+  /// Defaults to Monday through Friday
+  #[serde(default = "weekdays", deserialize_with = "days")]
+  pub days: Vec<Weekday>,
+}
+
+impl WorkHours {
+  /// How much of the work hours of the day of `now` have elapsed, from 0 to 1
+  pub fn elapsed<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> f64 {
+    let hours = (self.end - self.start).num_seconds();
+    let done = (now.time() - self.start).num_seconds();
+    match hours {
+      hours if hours <= 0 => 1.0,
+      hours => (done as f64 / hours as f64).clamp(0.0, 1.0),
+    }
+  }
+
+  /// When work started on the day of `now`, if `now` is within work hours
+  pub fn started<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+    let time = now.time();
+    match self.days.contains(&now.weekday()) && self.start <= time && time < self.end {
+      true => now.date().and_time(self.start),
+      false => None,
+    }
+  }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+  Unreadable(io::Error),
+  Invalid(toml::de::Error),
+}
+
+impl Display for ConfigError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ConfigError::Unreadable(err) => write!(f, "{}", err),
+      ConfigError::Invalid(err) => write!(f, "{}", err),
+    }
+  }
+}
+
+impl Config {
+  /// Reads the configuration living in `location`, defaulting everything when there is none
+  pub fn load(location: &Path) -> Result<Config, ConfigError> {
+    match fs::read_to_string(Self::file(location)) {
+      Ok(content) => Self::parse(&content),
+      Err(err) if err.kind() == ErrorKind::NotFound => Ok(Config::default()),
+      Err(err) => Err(ConfigError::Unreadable(err)),
+    }
+  }
+
+  /// The settings specific to `name`, if any. Project names are case insensitive.
+  pub fn project(&self, name: &str) -> Option<&ProjectConfig> {
+    self
+      .projects
+      .iter()
+      .find(|(key, _)| key.to_lowercase() == name.to_lowercase())
+      .map(|(_, config)| config)
+  }
+
+  /// How each project configured labels its new records
+  pub fn defaults(&self) -> impl Iterator<Item = (&str, Labels)> {
+    self.projects.iter().map(|(name, config)| {
+      let labels = Labels {
+        billable: config.billable,
+        tags: config.tags.clone(),
+      };
+      (name.as_str(), labels)
+    })
+  }
+
+  /// What records started here are noted with, as per `capture_context` and `user`, if anything
+  pub fn context(&self) -> Option<Context> {
+    let context = self.capture_context.then(context_here);
+    match &self.user {
+      Some(user) => Some(Context {
+        user: Some(user.clone()),
+        ..context.unwrap_or_default()
+      }),
+      None => context,
+    }
+  }
+
+  pub fn remind_after(&self, name: &str) -> Option<Duration> {
+    self
+      .project(name)
+      .and_then(|p| p.remind_after)
+      .or(self.remind_after)
+  }
+
+  /// The client called `name`, along with its name as configured. Client names are case
+  /// insensitive.
+  pub fn client(&self, name: &str) -> Option<(&str, &ClientConfig)> {
+    self
+      .clients
+      .iter()
+      .find(|(key, _)| key.to_lowercase() == name.to_lowercase())
+      .map(|(key, config)| (key.as_str(), config))
+  }
+
+  pub fn clients(&self) -> impl Iterator<Item = &str> {
+    self.clients.keys().map(String::as_str)
+  }
+
+  /// The client `project` is billed to, if any
+  pub fn client_of(&self, project: &str) -> Option<&ClientConfig> {
+    self.clients.values().find(|client| client.bills(project))
+  }
+
+  /// The hourly rate of `name`: its own, or the one of the client it's billed to, or the default
+  pub fn rate(&self, name: &str) -> Option<f64> {
+    self
+      .project(name)
+      .and_then(|p| p.rate)
+      .or_else(|| self.client_of(name).and_then(|c| c.rate))
+      .or(self.rate)
+  }
+
+  /// The project `path` belongs to, as named in the configuration: the one with the directory
+  /// closest to it, if any contains it
+  pub fn project_at(&self, path: &Path) -> Option<&str> {
+    self
+      .projects
+      .iter()
+      .flat_map(|(name, p)| p.directories.iter().map(move |dir| (name, dir)))
+      .filter(|(_, dir)| path.starts_with(dir))
+      .max_by_key(|(_, dir)| dir.components().count())
+      .map(|(name, _)| name.as_str())
+  }
+
+  /// How much to track on `day`, if anything: nothing on days off and holidays
+  pub fn target_on(&self, day: NaiveDate) -> Option<Duration> {
+    match self.is_workday(day) {
+      true => self.daily_target,
+      false => None,
+    }
+  }
+
+  /// Whether work is expected on `day`, neither a day off nor a holiday
+  pub fn is_workday(&self, day: NaiveDate) -> bool {
+    self.workdays().contains(&day.weekday()) && !self.holidays.contains(&day)
+  }
+
+  /// The days of the week work is expected on, as per the work hours, Monday through Friday
+  /// otherwise
+  pub fn workdays(&self) -> Vec<Weekday> {
+    match &self.work_hours {
+      Some(hours) => hours.days.clone(),
+      None => weekdays(),
+    }
+  }
+
+  /// How reports spell out dates and durations
+  pub fn locale(&self) -> Locale {
+    Locale::new(self.language, self.date_format.clone())
+  }
+
+  pub fn file(location: &Path) -> PathBuf {
+    location.join(CONFIG_FILE)
+  }
+
+  /// Appends `settings`, in TOML, to the configuration living in `location`, leaving it as it was
+  /// should they make it invalid
+  pub fn append(location: &Path, settings: &str) -> Result<(), ConfigError> {
+    let content = match fs::read_to_string(Self::file(location)) {
+      Ok(content) => content,
+      Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+      Err(err) => return Err(ConfigError::Unreadable(err)),
+    };
+    let content = match content.is_empty() || content.ends_with('\n') {
+      true => format!("{}\n{}", content, settings),
+      false => format!("{}\n\n{}", content, settings),
+    };
+    Self::parse(&content)?;
+    fs::write(Self::file(location), content).map_err(ConfigError::Unreadable)
+  }
+
+  fn parse(content: &str) -> Result<Config, ConfigError> {
+    toml::from_str(content).map_err(ConfigError::Invalid)
+  }
+}
+
+/// Identifies this machine in the WAL, generated on first use. It lives outside of the database
+/// directory, which may well be synced across machines, and can be edited to something friendlier.
+pub fn device_id() -> io::Result<String> {
+  let dir = match dirs::data_local_dir() {
+    Some(dir) => dir.join("timeknight"),
+    None => {
+      return Err(io::Error::new(
+        ErrorKind::NotFound,
+        "no local data directory",
+      ))
+    }
+  };
+  match fs::read_to_string(dir.join(DEVICE_FILE)) {
+    Ok(id) if !id.trim().is_empty() => Ok(id.trim().to_string()),
+    Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+    _ => {
+      let mut hasher = RandomState::new().build_hasher();
+      hasher.write_u32(std::process::id());
+      if let Ok(elapsed) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(elapsed.as_nanos());
+      }
+      let id = format!("{:016x}", hasher.finish());
+      fs::create_dir_all(&dir)?;
+      fs::write(dir.join(DEVICE_FILE), format!("{}\n", id))?;
+      Ok(id)
+    }
+  }
+}
+
+/// Where this is run from: the machine, the git branch checked out and the directory
+fn context_here() -> Context {
+  let cwd = std::env::current_dir().ok();
+  let branch = cwd.as_ref().and_then(|cwd| {
+    let repository = cwd.ancestors().find(|dir| dir.join(".git").exists())?;
+    let head = fs::read_to_string(repository.join(".git").join("HEAD")).ok()?;
+    head
+      .trim()
+      .strip_prefix("ref: refs/heads/")
+      .map(str::to_string)
+  });
+  let host = fs::read_to_string("/etc/hostname")
+    .ok()
+    .map(|host| host.trim().to_string())
+    .filter(|host| !host.is_empty())
+    .or_else(|| std::env::var("HOSTNAME").ok())
+    .or_else(|| std::env::var("COMPUTERNAME").ok());
+  Context {
+    host,
+    branch,
+    cwd: cwd.map(|cwd| cwd.display().to_string()),
+    user: std::env::var("USER")
+      .or_else(|_| std::env::var("USERNAME"))
+      .ok(),
+  }
+}
+
+fn time_of_day<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveTime>, D::Error> {
+  time(deserializer).map(Some)
+}
+
+fn time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+  let raw = String::deserialize(deserializer)?;
+  match NaiveTime::parse_from_str(&raw, "%H:%M") {
+    Ok(time) => Ok(time),
+    Err(_) => Err(D::Error::custom(format!(
+      "invalid time of day '{}', expected HH:MM",
+      raw
+    ))),
+  }
+}
+
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+  let raw = String::deserialize(deserializer)?;
+  match parse_duration(&raw) {
+    Some(duration) => Ok(Some(duration)),
+    None => Err(D::Error::custom(format!(
+      "invalid duration '{}', expected e.g. 3h, 45m or 1h30m",
+      raw
+    ))),
+  }
+}
+
+/// Hours and minutes, in that order, either being optional: "3h", "45m", "1h30m"
+pub(crate) fn parse_duration(raw: &str) -> Option<Duration> {
+  let (hours, minutes) = match raw.split_once('h') {
+    Some((hours, minutes)) => (hours, minutes),
+    None => ("0", raw),
+  };
+  let minutes = match minutes {
+    "" => "0",
+    minutes => minutes.strip_suffix('m')?,
+  };
+  let secs = hours.parse::<u64>().ok()? * 3600 + minutes.parse::<u64>().ok()? * 60;
+  match secs {
+    0 => None,
+    secs => Some(Duration::from_secs(secs)),
+  }
+}
+
+/// Directories, expanding a leading `~` to the home directory
+fn directories<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<PathBuf>, D::Error> {
+  let raw: Vec<String> = Vec::deserialize(deserializer)?;
+  Ok(
+    raw
+      .iter()
+      .map(|dir| match (dir.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(dir),
+      })
+      .collect(),
+  )
+}
+
+fn dates<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<NaiveDate>, D::Error> {
+  Vec::<String>::deserialize(deserializer)?
+    .iter()
+    .map(|day| {
+      NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .map_err(|_| D::Error::custom(format!("invalid date '{}', expected YYYY-MM-DD", day)))
+    })
+    .collect()
+}
+
+fn date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDate>, D::Error> {
+  let raw = String::deserialize(deserializer)?;
+  match NaiveDate::parse_from_str(&raw, "%Y-%m-%d") {
+    Ok(day) => Ok(Some(day)),
+    Err(_) => Err(D::Error::custom(format!(
+      "invalid date '{}', expected YYYY-MM-DD",
+      raw
+    ))),
+  }
+}
+
+fn zone<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Zone, D::Error> {
+  let raw = String::deserialize(deserializer)?;
+  raw.parse().map_err(D::Error::custom)
+}
+
+fn date_format<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+  let raw = String::deserialize(deserializer)?;
+  match locale::valid_pattern(&raw) {
+    true => Ok(Some(raw)),
+    false => Err(D::Error::custom(format!(
+      "invalid date format '{}', expected a strftime pattern, e.g. %d/%m/%Y",
+      raw
+    ))),
+  }
+}
+
+fn theme<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Theme, D::Error> {
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Raw {
+    Named(String),
+    Custom(BTreeMap<String, String>),
+  }
+  match Raw::deserialize(deserializer)? {
+    Raw::Named(name) => name.parse().map_err(D::Error::custom),
+    Raw::Custom(mut styles) => {
+      let base = match styles.remove("base") {
+        Some(name) => name.parse().map_err(D::Error::custom)?,
+        None => Theme::default(),
+      };
+      styles
+        .iter()
+        .try_fold(base, |theme, (role, style)| theme.with(role, style))
+        .map_err(D::Error::custom)
+    }
+  }
+}
+
+fn weekdays() -> Vec<Weekday> {
+  vec![
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+  ]
+}
+
+fn days<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Weekday>, D::Error> {
+  Vec::<String>::deserialize(deserializer)?
+    .iter()
+    .map(|day| {
+      day
+        .parse::<Weekday>()
+        .map_err(|_| D::Error::custom(format!("invalid day '{}', expected e.g. mon", day)))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::Config;
+  use crate::theme::Theme;
+  use chrono::{DateTime, NaiveDate, NaiveTime};
+  use std::path::Path;
+  use std::time::Duration;
+
+  #[test]
+  fn defaults_when_empty() {
+    let config = Config::parse("").expect("empty is valid");
+    assert_eq!(config.hard_stop, None);
+  }
+
+  #[test]
+  fn notes_the_user_even_without_the_context() {
+    assert!(Config::parse("").unwrap().context().is_none());
+    let config = Config::parse("user = \"alice\"").unwrap();
+    let context = config.context().expect("has a user");
+    assert_eq!(context.user.as_deref(), Some("alice"));
+    assert_eq!(context.host, None);
+    let config = Config::parse("user = \"alice\"\ncapture-context = true").unwrap();
+    assert_eq!(config.context().unwrap().user.as_deref(), Some("alice"));
+  }
+
+  #[test]
+  fn parses_hard_stop() {
+    let config = Config::parse("hard-stop = \"19:00\"").expect("valid");
+    assert_eq!(config.hard_stop, Some(NaiveTime::from_hms(19, 0, 0)));
+    assert!(Config::parse("hard-stop = \"7pm\"").is_err());
+    assert!(Config::parse("hard-stpo = \"19:00\"").is_err());
+  }
+
+  #[test]
+  fn project_settings_override_defaults() {
+    let config = Config::parse(
+      "remind-after = \"3h\"\n\
+       rate = 100.0\n\
+       [projects.Oncall]\n\
+       remind-after = \"10h30m\"\n\
+       rate = 150.0\n\
+       budget = \"120h\"\n\
+       deadline = \"2022-06-30\"\n",
+    )
+    .expect("valid");
+    let oncall = config.project("oncall").expect("configured");
+    assert_eq!(oncall.budget, Some(Duration::from_secs(120 * 3600)));
+    assert_eq!(oncall.deadline, Some(NaiveDate::from_ymd(2022, 6, 30)));
+    assert_eq!(
+      config.remind_after("backend"),
+      Some(Duration::from_secs(3 * 3600))
+    );
+    assert_eq!(
+      config.remind_after("oncall"),
+      Some(Duration::from_secs(37800))
+    );
+    assert_eq!(config.rate("backend"), Some(100.0));
+    assert_eq!(config.rate("oncall"), Some(150.0));
+    assert!(Config::parse("remind-after = \"3 hours\"").is_err());
+    assert!(Config::parse("remind-after = \"0m\"").is_err());
+    assert!(Config::parse("[projects.Oncall]\ndeadline = \"June\"").is_err());
+  }
+
+  #[test]
+  fn work_hours() {
+    let config = Config::parse(
+      "nag-after = \"20m\"\n\
+       work-hours = { start = \"09:00\", end = \"17:30\" }\n",
+    )
+    .expect("valid");
+    let hours = config.work_hours.expect("set");
+    // A Monday
+    let during = DateTime::parse_from_rfc3339("2022-03-28T10:15:00-04:00").unwrap();
+    assert_eq!(
+      hours.started(&during),
+      Some(DateTime::parse_from_rfc3339("2022-03-28T09:00:00-04:00").unwrap()),
+    );
+    let after = DateTime::parse_from_rfc3339("2022-03-28T17:30:00-04:00").unwrap();
+    assert_eq!(hours.started(&after), None);
+    let sunday = DateTime::parse_from_rfc3339("2022-03-27T10:15:00-04:00").unwrap();
+    assert_eq!(hours.started(&sunday), None);
+    assert!((hours.elapsed(&during) - 1.25 / 8.5).abs() < 1e-9);
+    assert_eq!(hours.elapsed(&after), 1.0);
+    assert!(Config::parse("work-hours = { start = \"09:00\", days = [\"caturday\"] }").is_err());
+  }
+
+  #[test]
+  fn clients_bill_in_increments() {
+    let config = Config::parse(
+      "[clients.Acme]\n\
+       projects = [\"Backend\"]\n\
+       increment = \"6m\"\n\
+       minimum = \"15m\"\n\
+       rate = 120.0\n",
+    )
+    .expect("valid");
+    let (name, acme) = config.client("acme").expect("configured");
+    assert_eq!(name, "Acme");
+    assert!(acme.bills("backend"));
+    assert!(!acme.bills("frontend"));
+    assert_eq!(acme.bill(0), 0);
+    assert_eq!(acme.bill(60), 18 * 60);
+    assert_eq!(acme.bill(30 * 60), 30 * 60);
+    assert_eq!(acme.bill(30 * 60 + 1), 36 * 60);
+    assert!(config.client("globex").is_none());
+    assert_eq!(config.rate("backend"), Some(120.0));
+    assert_eq!(config.rate("frontend"), None);
+  }
+
+  #[test]
+  fn projects_by_directory() {
+    let config = Config::parse(
+      "[projects.Backend]\n\
+       directories = [\"/src/backend\", \"/src/shared\"]\n\
+       [projects.Storage]\n\
+       directories = [\"/src/backend/storage\"]\n",
+    )
+    .expect("valid");
+    let at = |path: &str| config.project_at(Path::new(path));
+    assert_eq!(at("/src/backend/api/main.rs"), Some("Backend"));
+    assert_eq!(at("/src/shared"), Some("Backend"));
+    assert_eq!(at("/src/backend/storage/wal.rs"), Some("Storage"));
+    assert_eq!(at("/src/backend-old"), None);
+  }
+
+  #[test]
+  fn no_target_on_days_off() {
+    let config = Config::parse(
+      "daily-target = \"6h\"\n\
+       holidays = [\"2022-03-28\"]\n",
+    )
+    .expect("valid");
+    let six_hours = Some(Duration::from_secs(6 * 3600));
+    assert_eq!(
+      config.target_on(NaiveDate::from_ymd(2022, 3, 25)),
+      six_hours
+    );
+    assert_eq!(config.target_on(NaiveDate::from_ymd(2022, 3, 26)), None);
+    assert_eq!(config.target_on(NaiveDate::from_ymd(2022, 3, 28)), None);
+    assert!(Config::parse("holidays = [\"Dec 25\"]").is_err());
+  }
+
+  #[test]
+  fn themes_are_named_or_mapped() {
+    let config = Config::parse("theme = \"solarized\"").expect("valid");
+    assert_eq!(config.theme, Theme::solarized());
+    let config =
+      Config::parse("theme = { base = \"monochrome\", failure = \"red.bold\" }").expect("valid");
+    let expected = Theme::monochrome().with("failure", "red.bold");
+    assert_eq!(Ok(config.theme), expected);
+    assert_eq!(Config::parse("").expect("valid").theme, Theme::default());
+    assert!(Config::parse("theme = \"neon\"").is_err());
+    assert!(Config::parse("theme = { failure = \"scarlet\" }").is_err());
+    assert!(Config::parse("theme = { error = \"red\" }").is_err());
+  }
+}
@@ -0,0 +1,89 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use chrono::{DateTime, FixedOffset};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Money spent on a project, e.g. a train ticket, to be billed alongside the time tracked on it
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Expense {
+  at: DateTime<FixedOffset>,
+  /// Hundredths of whatever currency the project is billed in
+  cents: i64,
+  description: String,
+}
+
+impl Expense {
+  pub fn new(at: DateTime<FixedOffset>, cents: i64, description: String) -> Self {
+    Expense {
+      at,
+      cents,
+      description,
+    }
+  }
+
+  pub fn at(&self) -> DateTime<FixedOffset> {
+    self.at
+  }
+
+  pub fn cents(&self) -> i64 {
+    self.cents
+  }
+
+  pub fn amount(&self) -> f64 {
+    self.cents as f64 / 100.0
+  }
+
+  pub fn description(&self) -> &str {
+    &self.description
+  }
+
+  /// Parses an amount such as "34.50" or "12" into cents, refusing fractions of a cent
+  pub fn parse_amount(raw: &str) -> Option<i64> {
+    let (units, fraction) = raw.split_once('.').unwrap_or((raw, ""));
+    if units.is_empty()
+      || fraction.len() > 2
+      || !(units.chars().chain(fraction.chars())).all(|c| c.is_ascii_digit())
+    {
+      return None;
+    }
+    let cents = format!("{:0<2}", fraction).parse::<i64>().ok()?;
+    units
+      .parse::<i64>()
+      .ok()?
+      .checked_mul(100)?
+      .checked_add(cents)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::core::Expense;
+
+  #[test]
+  fn parses_amounts() {
+    assert_eq!(Expense::parse_amount("34.50"), Some(3450));
+    assert_eq!(Expense::parse_amount("34.5"), Some(3450));
+    assert_eq!(Expense::parse_amount("12"), Some(1200));
+    assert_eq!(Expense::parse_amount("0.05"), Some(5));
+    assert_eq!(Expense::parse_amount("1.234"), None);
+    assert_eq!(Expense::parse_amount("-3"), None);
+    assert_eq!(Expense::parse_amount(".5"), None);
+    assert_eq!(Expense::parse_amount("ten"), None);
+  }
+}
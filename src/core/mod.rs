@@ -14,8 +14,10 @@
  * limitations under the License.
  */
 
+mod expense;
 mod project;
 mod record;
 
+pub use expense::Expense;
 pub use project::Project;
-pub use record::Record;
+pub use record::{Context, Labels, Record};
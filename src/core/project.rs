@@ -14,12 +14,16 @@
  * limitations under the License.
  */
 
-use chrono::{DateTime, FixedOffset};
+use chrono::{DateTime, Datelike, FixedOffset};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::slice::Iter;
+use std::time::Duration;
 
 use crate::core::record::{IllegalStateError, RResult, RecordEnded};
-use crate::core::Record;
+use crate::core::{Context, Expense, Labels, Record};
 
 type AdditionResult = Result<RecordAdded, IllegalStateError>;
 
@@ -31,9 +35,17 @@ pub enum RecordAdded {
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Project {
   name: String,
   records: Vec<Record>,
+  expenses: Vec<Expense>,
+  /// Kept out of the way, history included, and can't be started
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "std::ops::Not::not")
+  )]
+  archived: bool,
 }
 
 impl Project {
@@ -41,6 +53,8 @@ impl Project {
     Project {
       name,
       records: Vec::new(),
+      expenses: Vec::new(),
+      archived: false,
     }
   }
 
@@ -48,10 +62,26 @@ impl Project {
     self.name.as_str()
   }
 
+  pub fn archived(&self) -> bool {
+    self.archived
+  }
+
+  pub fn archive(&mut self, archived: bool) {
+    self.archived = archived;
+  }
+
   pub fn records(&self) -> Iter<'_, Record> {
     self.records.iter()
   }
 
+  pub fn expenses(&self) -> Iter<'_, Expense> {
+    self.expenses.iter()
+  }
+
+  pub fn add_expense(&mut self, expense: Expense) {
+    self.expenses.push(expense);
+  }
+
   pub fn start(&mut self) -> AdditionResult {
     self.add_record(Record::new())
   }
@@ -62,7 +92,7 @@ impl Project {
       .records
       .iter_mut()
       .last()
-      .map(|r| r.crop(record.start()))
+      .map(|r| r.end_at(record.start()))
     {
       None => {
         self.records.push(record);
@@ -87,7 +117,124 @@ impl Project {
       .records
       .last_mut()
       .expect("No record present!")
-      .crop(end)
+      .end_at(end)
+  }
+
+  /// Discards the record in flight, if any, returning it
+  pub fn cancel(&mut self) -> Option<Record> {
+    match self.in_flight() {
+      true => self.records.pop(),
+      false => None,
+    }
+  }
+
+  /// Labels the record in flight, if any
+  pub fn label(&mut self, labels: &Labels) {
+    if let Some(record) = self.records.last_mut().filter(|r| r.is_on_going()) {
+      record.label(labels);
+    }
+  }
+
+  /// Notes where the record in flight, if any, was started from
+  pub fn set_context(&mut self, context: Context) {
+    if let Some(record) = self.records.last_mut().filter(|r| r.is_on_going()) {
+      record.set_context(context);
+    }
+  }
+
+  /// Notes what's being done on the record in flight, if any
+  pub fn note(&mut self, note: String) {
+    if let Some(record) = self.records.last_mut().filter(|r| r.is_on_going()) {
+      record.note(note);
+    }
+  }
+
+  /// Marks the record in flight, if any, as stopped for running past the hard stop
+  pub fn hard_stop(&mut self) {
+    if let Some(record) = self.records.last_mut().filter(|r| r.is_on_going()) {
+      record.hard_stop();
+    }
+  }
+
+  /// Expresses every record at the offsets `offset_at` gives, see [`Record::reoffset`]. Returns
+  /// how many changed.
+  pub fn reoffset(&mut self, offset_at: impl Fn(DateTime<FixedOffset>) -> FixedOffset) -> usize {
+    self
+      .records
+      .iter_mut()
+      .filter_map(|record| record.reoffset(&offset_at).then_some(()))
+      .count()
+  }
+
+  /// Takes the completed record that started at `start` out of the project
+  pub fn remove_record(&mut self, start: DateTime<FixedOffset>) -> Option<Record> {
+    let position = self
+      .records
+      .iter()
+      .position(|r| !r.is_on_going() && r.start() == start)?;
+    Some(self.records.remove(position))
+  }
+
+  /// Puts a completed record back in the project's history, where it belongs, as long as it
+  /// doesn't overlap the records around it
+  pub fn insert_record(&mut self, record: Record) -> Result<(), IllegalStateError> {
+    let end = record.end().ok_or(IllegalStateError::Overlapping)?;
+    let position = self
+      .records
+      .partition_point(|r| r.start() <= record.start());
+    let before = position.checked_sub(1).map(|i| &self.records[i]);
+    let after = self.records.get(position);
+    if before.is_some_and(|r| r.end().is_none_or(|e| e > record.start()))
+      || after.is_some_and(|r| r.start() < end)
+    {
+      return Err(IllegalStateError::Overlapping);
+    }
+    self.records.insert(position, record);
+    Ok(())
+  }
+
+  /// Drops the completed records started before `before`, returning how many were dropped.
+  /// When `aggregate` is set, they are replaced by one record per month, starting on its first day
+  /// and lasting as long as the records it replaces did.
+  pub fn prune(&mut self, before: DateTime<FixedOffset>, aggregate: bool) -> usize {
+    let (old, recent): (Vec<Record>, Vec<Record>) = self
+      .records
+      .drain(..)
+      .partition(|r| !r.is_on_going() && r.start() < before);
+    let pruned = old.len();
+    if aggregate {
+      let mut months: BTreeMap<(i32, u32), (DateTime<FixedOffset>, Duration)> = BTreeMap::new();
+      for record in old {
+        let start = record.start();
+        let month = months
+          .entry((start.year(), start.month()))
+          .or_insert_with(|| {
+            let first = start.date().with_day(1).unwrap().and_hms(0, 0, 0);
+            (first, Duration::ZERO)
+          });
+        month.1 += record.duration();
+      }
+      for (start, duration) in months.into_values() {
+        let mut record = Record::started_on(start);
+        if duration > Duration::ZERO {
+          record
+            .end_at(start + chrono::Duration::seconds(duration.as_secs() as i64))
+            .expect("Positive duration");
+          self.records.push(record);
+        }
+      }
+    }
+    self.records.extend(recent);
+    pruned
+  }
+
+  /// Drops all records but the latest one, and all expenses
+  pub(crate) fn forget_history(&mut self) {
+    self.expenses.clear();
+    let len = self.records.len();
+    if len > 1 {
+      self.records.drain(..len - 1);
+    }
   }
 
   pub fn in_flight(&self) -> bool {
@@ -120,3 +267,118 @@ impl Ord for Record {
     self.start().cmp(&other.start())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::core::project::RecordAdded;
+  use crate::core::{Project, Record};
+  use chrono::DateTime;
+  use std::time::Duration;
+
+  fn record(start: &str, end: &str) -> Record {
+    let mut record = Record::started_on(DateTime::parse_from_rfc3339(start).unwrap());
+    record
+      .end_at(DateTime::parse_from_rfc3339(end).unwrap())
+      .unwrap();
+    record
+  }
+
+  fn project() -> Project {
+    let mut project = Project::new("test".to_string());
+    project
+      .add_record(record(
+        "2020-01-10T09:00:00-05:00",
+        "2020-01-10T10:00:00-05:00",
+      ))
+      .unwrap();
+    project
+      .add_record(record(
+        "2020-01-20T09:00:00-05:00",
+        "2020-01-20T09:30:00-05:00",
+      ))
+      .unwrap();
+    project
+      .add_record(record(
+        "2020-02-03T09:00:00-05:00",
+        "2020-02-03T11:00:00-05:00",
+      ))
+      .unwrap();
+    project
+      .add_record(record(
+        "2022-03-27T09:00:00-04:00",
+        "2022-03-27T10:00:00-04:00",
+      ))
+      .unwrap();
+    project
+  }
+
+  #[test]
+  fn restarts_within_the_same_second() {
+    let at = DateTime::parse_from_rfc3339("2020-01-10T09:00:00-05:00").unwrap();
+    let mut project = Project::new("test".to_string());
+    project.add_record(Record::started_on(at)).unwrap();
+    assert!(matches!(
+      project.add_record(Record::started_on(at)),
+      Ok(RecordAdded::Switched)
+    ));
+    project.end_at(at).unwrap();
+    assert_eq!(project.records().count(), 2);
+    assert!(project.records().all(|r| r.duration() == Duration::ZERO));
+  }
+
+  #[test]
+  fn moves_records_around_without_overlaps() {
+    let mut project = project();
+    let start = DateTime::parse_from_rfc3339("2020-01-20T09:00:00-05:00").unwrap();
+    let mut moved = project.remove_record(start).unwrap();
+    assert_eq!(project.records().count(), 3);
+    assert!(project.remove_record(start).is_none());
+
+    let at = |s| DateTime::parse_from_rfc3339(s).unwrap();
+    moved
+      .retime(
+        at("2020-01-10T09:30:00-05:00"),
+        at("2020-01-10T11:00:00-05:00"),
+      )
+      .unwrap();
+    assert!(project.insert_record(moved.clone()).is_err());
+    moved
+      .retime(
+        at("2020-01-10T10:00:00-05:00"),
+        at("2020-01-10T11:00:00-05:00"),
+      )
+      .unwrap();
+    project.insert_record(moved).unwrap();
+    let starts: Vec<_> = project.records().map(|r| r.start()).collect();
+    assert_eq!(starts[1], at("2020-01-10T10:00:00-05:00"));
+    assert!(starts.windows(2).all(|w| w[0] < w[1]));
+  }
+
+  #[test]
+  fn prune_drops_old_records() {
+    let mut project = project();
+    let before = DateTime::parse_from_rfc3339("2021-01-01T00:00:00-05:00").unwrap();
+    assert_eq!(project.prune(before, false), 3);
+    assert_eq!(project.records().count(), 1);
+    assert_eq!(
+      project.records().next().unwrap().start().timestamp(),
+      1648386000
+    );
+  }
+
+  #[test]
+  fn prune_aggregates_per_month() {
+    let mut project = project();
+    let before = DateTime::parse_from_rfc3339("2021-01-01T00:00:00-05:00").unwrap();
+    assert_eq!(project.prune(before, true), 3);
+    let records: Vec<&Record> = project.records().collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(
+      records[0].start(),
+      DateTime::parse_from_rfc3339("2020-01-01T00:00:00-05:00").unwrap()
+    );
+    assert_eq!(records[0].duration(), Duration::from_secs(90 * 60));
+    assert_eq!(records[1].duration(), Duration::from_secs(120 * 60));
+    assert!(!records[2].is_on_going());
+  }
+}
@@ -14,8 +14,9 @@
  * limitations under the License.
  */
 
-use chrono::{DateTime, FixedOffset, Local};
-use std::cmp::Ordering;
+use chrono::{DateTime, Duration as ChronoDuration, FixedOffset, Local, NaiveTime};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 #[derive(Debug)]
@@ -28,16 +29,73 @@ pub enum RecordEnded {
 #[derive(Debug)]
 pub enum IllegalStateError {
   NegativeDuration,
-  NoDuration,
+  Overlapping,
 }
 
 pub type RResult = Result<RecordEnded, IllegalStateError>;
 
+/// How a record is labelled: whether it's billable, and what it's tagged with. Whatever isn't set
+/// is left as it is.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Labels {
+  pub billable: Option<bool>,
+  pub tags: Option<Vec<String>>,
+}
+
+impl Labels {
+  /// These labels, falling back to `defaults` for whatever they don't set
+  pub fn or(self, defaults: &Labels) -> Labels {
+    Labels {
+      billable: self.billable.or(defaults.billable),
+      tags: self.tags.or_else(|| defaults.tags.clone()),
+    }
+  }
+}
+
+/// Where a record was started from: the machine, the git branch and the directory, as far as
+/// they could be told, and who by
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Context {
+  pub host: Option<String>,
+  pub branch: Option<String>,
+  pub cwd: Option<String>,
+  /// Who started it, in a database shared with others
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub user: Option<String>,
+}
+
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Record {
   start: chrono::DateTime<FixedOffset>,
   end: Option<chrono::DateTime<FixedOffset>>,
   billable: bool,
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Vec::is_empty")
+  )]
+  tags: Vec<String>,
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  context: Option<Context>,
+  /// What was being done, as noted along the way
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Vec::is_empty")
+  )]
+  notes: Vec<String>,
+  /// Whether it was stopped for running past the hard stop, which then is its end
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "std::ops::Not::not")
+  )]
+  hard_stopped: bool,
 }
 
 impl Record {
@@ -50,6 +108,10 @@ impl Record {
       start,
       end: None,
       billable: true,
+      tags: Vec::new(),
+      context: None,
+      notes: Vec::new(),
+      hard_stopped: false,
     }
   }
 
@@ -57,6 +119,10 @@ impl Record {
     self.start
   }
 
+  pub fn end(&self) -> Option<DateTime<FixedOffset>> {
+    self.end
+  }
+
   pub fn is_on_going(&self) -> bool {
     self.end.is_none()
   }
@@ -70,24 +136,81 @@ impl Record {
     Duration::from_secs(duration.num_seconds() as u64)
   }
 
-  pub fn crop(&mut self, new_end: DateTime<FixedOffset>) -> RResult {
-    match self.start.cmp(&new_end) {
-      Ordering::Greater => Err(IllegalStateError::NegativeDuration),
-      Ordering::Equal => Err(IllegalStateError::NoDuration),
-      Ordering::Less => match self.end {
-        None => {
-          self.end = Some(new_end);
-          Ok(RecordEnded::Ended)
-        }
-        Some(end) => {
-          if new_end < end {
-            self.end = Some(new_end);
-            Ok(RecordEnded::Cropped)
-          } else {
-            Ok(RecordEnded::Noop)
-          }
-        }
-      },
+  /// How much of this record falls between `clip_start` and `clip_end`, e.g. within a given day,
+  /// a running record counting up to now
+  pub fn duration_between(
+    &self,
+    clip_start: DateTime<FixedOffset>,
+    clip_end: DateTime<FixedOffset>,
+  ) -> Duration {
+    let start = self.start.max(clip_start);
+    let end = self.end.unwrap_or_else(Record::now).min(clip_end);
+    match end.signed_duration_since(start).to_std() {
+      Ok(duration) => Duration::from_secs(duration.as_secs()),
+      Err(_) => Duration::ZERO,
+    }
+  }
+
+  /// Ends the record at `end` if it is in flight, or shortens it if it ended after `end`, leaving
+  /// it be otherwise. An end before the start is rejected, one right at the start is not: records
+  /// are persisted to the second, so a record stopped within the second it started lasts nothing.
+  pub fn end_at(&mut self, end: DateTime<FixedOffset>) -> RResult {
+    if end < self.start {
+      return Err(IllegalStateError::NegativeDuration);
+    }
+    match self.end {
+      None => {
+        self.end = Some(end);
+        Ok(RecordEnded::Ended)
+      }
+      Some(current) if end < current => {
+        self.end = Some(end);
+        Ok(RecordEnded::Cropped)
+      }
+      Some(_) => Ok(RecordEnded::Noop),
+    }
+  }
+
+  /// Moves the record to `start`..`end`, ending it if it was in flight
+  pub fn retime(
+    &mut self,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+  ) -> Result<(), IllegalStateError> {
+    if end < start {
+      return Err(IllegalStateError::NegativeDuration);
+    }
+    self.start = start;
+    self.end = Some(end);
+    // It ends whenever it was retimed to end now, rather than at the hard stop
+    self.hard_stopped = false;
+    Ok(())
+  }
+
+  /// Expresses the record at the offsets `offset_at` gives for its start and end, the instants it
+  /// covers staying the same. Returns whether any offset changed.
+  pub fn reoffset(&mut self, offset_at: impl Fn(DateTime<FixedOffset>) -> FixedOffset) -> bool {
+    let start = self.start.with_timezone(&offset_at(self.start));
+    let end = self.end.map(|end| end.with_timezone(&offset_at(end)));
+    let offsets = |start: &DateTime<FixedOffset>, end: &Option<DateTime<FixedOffset>>| {
+      (*start.offset(), end.map(|end| *end.offset()))
+    };
+    let changed = offsets(&start, &end) != offsets(&self.start, &self.end);
+    self.start = start;
+    self.end = end;
+    changed
+  }
+
+  /// The first time it's `at` after this record started, in the record's own timezone
+  pub fn next(&self, at: NaiveTime) -> DateTime<FixedOffset> {
+    let same_day = self
+      .start
+      .date()
+      .and_time(at)
+      .expect("Fixed offsets are never ambiguous");
+    match same_day > self.start {
+      true => same_day,
+      false => same_day + ChronoDuration::days(1),
     }
   }
 
@@ -95,6 +218,63 @@ impl Record {
     self.billable
   }
 
+  pub fn tags(&self) -> &[String] {
+    &self.tags
+  }
+
+  pub fn context(&self) -> Option<&Context> {
+    self.context.as_ref()
+  }
+
+  pub fn set_context(&mut self, context: Context) {
+    self.context = Some(context);
+  }
+
+  pub fn notes(&self) -> &[String] {
+    &self.notes
+  }
+
+  pub fn note(&mut self, note: String) {
+    self.notes.push(note);
+  }
+
+  pub fn is_hard_stopped(&self) -> bool {
+    self.hard_stopped
+  }
+
+  /// Marks the record as stopped for running past the hard stop
+  pub fn hard_stop(&mut self) {
+    self.hard_stopped = true;
+  }
+
+  /// A copy of the record without its context, and with its tags and notes replaced by what
+  /// `pseudonym` makes of them, given the kind of value (`"tag"` or `"note"`) and the value
+  pub fn anonymized(&self, pseudonym: impl Fn(&str, &str) -> String) -> Record {
+    let mut record = self.clone();
+    record.tags = self.tags.iter().map(|tag| pseudonym("tag", tag)).collect();
+    record.notes = self
+      .notes
+      .iter()
+      .map(|note| pseudonym("note", note))
+      .collect();
+    record.context = None;
+    record
+  }
+
+  /// Whether the record is labelled any differently than a new one
+  pub fn is_labelled(&self) -> bool {
+    !self.billable || !self.tags.is_empty()
+  }
+
+  pub fn label(&mut self, labels: &Labels) {
+    if let Some(billable) = labels.billable {
+      self.billable = billable;
+    }
+    if let Some(tags) = &labels.tags {
+      self.tags = tags.clone();
+    }
+  }
+
   fn now() -> DateTime<FixedOffset> {
     let now = Local::now();
     now.with_timezone(now.offset())
@@ -109,9 +289,9 @@ impl Default for Record {
 
 #[cfg(test)]
 mod tests {
-  use crate::core::record::Record;
+  use crate::core::record::{IllegalStateError, Record, RecordEnded};
 
-  use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+  use chrono::{DateTime, FixedOffset, NaiveTime, TimeZone, Utc};
   use std::ops::Sub;
   use std::time::Duration;
 
@@ -152,6 +332,117 @@ mod tests {
     assert!(record.is_on_going());
   }
 
+  #[test]
+  fn ends_in_flight_records() {
+    let start = DateTime::parse_from_rfc3339("2022-03-27T17:00:00-04:00").unwrap();
+    let hour = chrono::Duration::hours(1);
+
+    let mut record = Record::started_on(start);
+    assert!(matches!(
+      record.end_at(start + hour),
+      Ok(RecordEnded::Ended)
+    ));
+    assert_eq!(record.end(), Some(start + hour));
+    assert_eq!(record.duration(), Duration::from_secs(3600));
+
+    let mut record = Record::started_on(start);
+    assert!(matches!(record.end_at(start), Ok(RecordEnded::Ended)));
+    assert_eq!(record.end(), Some(start));
+    assert_eq!(record.duration(), Duration::ZERO);
+
+    let mut record = Record::started_on(start);
+    assert!(matches!(
+      record.end_at(start - hour),
+      Err(IllegalStateError::NegativeDuration)
+    ));
+    assert!(record.is_on_going());
+  }
+
+  #[test]
+  fn shortens_ended_records() {
+    let start = DateTime::parse_from_rfc3339("2022-03-27T17:00:00-04:00").unwrap();
+    let hour = chrono::Duration::hours(1);
+    let ended = || {
+      let mut record = Record::started_on(start);
+      record.end_at(start + hour * 2).unwrap();
+      record
+    };
+
+    let mut record = ended();
+    assert!(matches!(
+      record.end_at(start + hour),
+      Ok(RecordEnded::Cropped)
+    ));
+    assert_eq!(record.end(), Some(start + hour));
+
+    let mut record = ended();
+    assert!(matches!(record.end_at(start), Ok(RecordEnded::Cropped)));
+    assert_eq!(record.duration(), Duration::ZERO);
+
+    for later in [start + hour * 2, start + hour * 3] {
+      let mut record = ended();
+      assert!(matches!(record.end_at(later), Ok(RecordEnded::Noop)));
+      assert_eq!(record.end(), Some(start + hour * 2));
+    }
+
+    let mut record = ended();
+    assert!(matches!(
+      record.end_at(start - hour),
+      Err(IllegalStateError::NegativeDuration)
+    ));
+    assert_eq!(record.end(), Some(start + hour * 2));
+  }
+
+  #[test]
+  fn duration_between_clips() {
+    let at = |rfc3339| DateTime::parse_from_rfc3339(rfc3339).unwrap();
+    let mut record = Record::started_on(at("2022-03-27T23:00:00-04:00"));
+    record.end_at(at("2022-03-28T01:30:00-04:00")).unwrap();
+    assert_eq!(record.end(), Some(at("2022-03-28T01:30:00-04:00")));
+    assert_eq!(
+      record.duration_between(
+        at("2022-03-27T00:00:00-04:00"),
+        at("2022-03-28T00:00:00-04:00")
+      ),
+      Duration::from_secs(3600)
+    );
+    assert_eq!(
+      record.duration_between(
+        at("2022-03-28T00:00:00-04:00"),
+        at("2022-03-29T00:00:00-04:00")
+      ),
+      Duration::from_secs(5400)
+    );
+    // Same instants, expressed in another timezone
+    assert_eq!(
+      record.duration_between(at("2022-03-28T04:00:00Z"), at("2022-03-29T04:00:00Z")),
+      Duration::from_secs(5400)
+    );
+    assert_eq!(
+      record.duration_between(
+        at("2022-03-29T00:00:00-04:00"),
+        at("2022-03-30T00:00:00-04:00")
+      ),
+      Duration::ZERO
+    );
+  }
+
+  #[test]
+  #[cfg(feature = "serde")]
+  fn serializes_to_json() {
+    let start = DateTime::parse_from_rfc3339("2022-03-27T17:37:34-04:00").unwrap();
+    let mut record = Record::started_on(start);
+    record.end_at(start + chrono::Duration::hours(1)).unwrap();
+    let json = serde_json::to_string(&record).unwrap();
+    assert_eq!(
+      json,
+      r#"{"start":"2022-03-27T17:37:34-04:00","end":"2022-03-27T18:37:34-04:00","billable":true}"#
+    );
+    let back: Record = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.start(), record.start());
+    assert_eq!(back.end(), record.end());
+  }
+
   #[test]
   fn deconstruct() {
     let now = Record::now();
@@ -164,4 +455,18 @@ mod tests {
     assert_eq!(now.timezone(), other.timezone());
     assert_eq!(ts, other.timestamp_millis());
   }
+
+  #[test]
+  fn next_time_of_day() {
+    let start = DateTime::parse_from_rfc3339("2022-03-27T17:37:34-04:00").unwrap();
+    let record = Record::started_on(start);
+    assert_eq!(
+      record.next(NaiveTime::from_hms(19, 0, 0)),
+      DateTime::parse_from_rfc3339("2022-03-27T19:00:00-04:00").unwrap(),
+    );
+    assert_eq!(
+      record.next(NaiveTime::from_hms(9, 0, 0)),
+      DateTime::parse_from_rfc3339("2022-03-28T09:00:00-04:00").unwrap(),
+    );
+  }
 }
@@ -14,16 +14,22 @@
  * limitations under the License.
  */
 
-use crate::core::Project;
-use crate::db::storage::Action;
-use crate::db::storage::FsStorage;
-use chrono::Local;
+use crate::core::{Context, Labels, Project, Record};
+use crate::db::merge::{merge, MergeError, Sequencer};
+use crate::db::storage::{Action, InvalidAction, ProjectTotals, Session};
+use crate::db::storage::{DryRun, FsStorage};
+use crate::db::verify::check;
+use crate::import::fingerprint;
+use crate::zone::{self, instant};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone};
 use std::borrow::Cow;
 use std::collections::btree_map::Entry;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt::{Display, Formatter};
 use std::io::ErrorKind;
-use std::path::Path;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct SomeDbError;
@@ -36,10 +42,227 @@ impl Display for SomeDbError {
 
 impl std::error::Error for SomeDbError {}
 
+impl From<InvalidAction> for SomeDbError {
+  fn from(_: InvalidAction) -> Self {
+    SomeDbError
+  }
+}
+
 pub struct Database {
   storage: FsStorage,
+  load: Load,
   projects: BTreeMap<ProjectKey, Project>,
-  last_project: Option<ProjectKey>,
+  /// The projects with a record in flight, in the order they were started. Only ever keys of
+  /// `projects`, as running projects can't be deleted.
+  running: Vec<ProjectKey>,
+  /// The project whose record was stopped last, if it still exists: deleting it clears this
+  last_stopped: Option<ProjectKey>,
+  /// Where the WAL's sequence of entries stands, and which device its latest entries came from
+  sequencer: Sequencer,
+  /// The aggregate index, if it was fresh when loading, updated as records complete
+  index: Option<Vec<ProjectTotals>>,
+  /// How many entries of the WAL were skipped on replay, for not making sense
+  skipped: usize,
+  /// How the projects label their new records, unless told otherwise when starting them
+  defaults: BTreeMap<ProjectKey, Labels>,
+  /// Where records get started from, noted on each when captured
+  context: Option<Context>,
+}
+
+/// How soon after starting a project, starting it again is taken to be the same start
+const REPEATED_START: Duration = Duration::from_secs(5);
+
+/// How many times reading without the lock is tried, for as long as the WAL keeps being rewritten
+const READ_ATTEMPTS: usize = 3;
+
+/// How many favorites there can be, each started by the number of its slot
+pub const FAVORITE_SLOTS: usize = 9;
+
+/// A change to a completed record, found by the project it belongs to and when it started
+#[derive(Clone, Debug)]
+pub enum Amendment {
+  /// Moves the record to new start and end times
+  Retime {
+    project: String,
+    start: DateTime<FixedOffset>,
+    to: (DateTime<FixedOffset>, DateTime<FixedOffset>),
+  },
+  /// Moves the record over to another project
+  Reassign {
+    project: String,
+    start: DateTime<FixedOffset>,
+    to: String,
+  },
+  /// Drops the record altogether
+  Delete {
+    project: String,
+    start: DateTime<FixedOffset>,
+  },
+  /// Adds a note to the record
+  Note {
+    project: String,
+    start: DateTime<FixedOffset>,
+    note: String,
+  },
+}
+
+/// Why amendments couldn't be made, in which case none were
+#[derive(Debug)]
+pub enum AmendError {
+  UnknownProject(String),
+  UnknownRecord(String, DateTime<FixedOffset>),
+  NegativeDuration(String),
+  Overlapping(String),
+  /// Names, tags or notes hold control characters, which the log can't hold
+  Unprintable,
+  Storage,
+}
+
+impl Display for AmendError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AmendError::UnknownProject(name) => write!(f, "no project '{}'", name),
+      AmendError::UnknownRecord(name, start) => write!(
+        f,
+        "no completed record of '{}' started at {}",
+        name,
+        start.format("%Y-%m-%d %H:%M:%S")
+      ),
+      AmendError::NegativeDuration(name) => {
+        write!(f, "a record of '{}' would end before it starts", name)
+      }
+      AmendError::Overlapping(name) => write!(f, "records of '{}' would overlap", name),
+      AmendError::Unprintable => write!(f, "names, tags or notes hold control characters"),
+      AmendError::Storage => write!(f, "the log couldn't be rewritten"),
+    }
+  }
+}
+
+impl std::error::Error for AmendError {}
+
+/// Why a record couldn't be started, or stopped, at a given time rather than now
+#[derive(Debug, PartialEq, Eq)]
+pub enum BackdateError {
+  UnknownProject(String),
+  NotRunning(String),
+  Future,
+  /// The record of that project would stop before it started
+  BeforeStart(String),
+  /// A record of that project already covers part of that time
+  Overlapping(String),
+  Storage,
+}
+
+impl Display for BackdateError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      BackdateError::UnknownProject(name) => write!(f, "no project '{}'", name),
+      BackdateError::NotRunning(name) => write!(f, "'{}' isn't running", name),
+      BackdateError::Future => write!(f, "that's in the future"),
+      BackdateError::BeforeStart(name) => {
+        write!(f, "the record of '{}' would end before it starts", name)
+      }
+      BackdateError::Overlapping(name) => {
+        write!(f, "it would overlap a record of '{}'", name)
+      }
+      BackdateError::Storage => write!(f, "the log couldn't be written to"),
+    }
+  }
+}
+
+impl std::error::Error for BackdateError {}
+
+/// Why a project couldn't be deleted
+#[derive(Debug, PartialEq, Eq)]
+pub enum DeleteError {
+  UnknownProject(String),
+  Running(String),
+  Storage,
+}
+
+impl Display for DeleteError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      DeleteError::UnknownProject(name) => write!(f, "no project '{}'", name),
+      DeleteError::Running(name) => write!(f, "'{}' is still being tracked", name),
+      DeleteError::Storage => write!(f, "the log couldn't be written to"),
+    }
+  }
+}
+
+impl std::error::Error for DeleteError {}
+
+/// Why a project couldn't be archived, or unarchived
+#[derive(Debug, PartialEq, Eq)]
+pub enum ArchiveError {
+  UnknownProject(String),
+  Running(String),
+  /// Is archived already, when archiving it
+  Archived(String),
+  /// Isn't archived, when unarchiving it
+  NotArchived(String),
+  Storage,
+}
+
+impl Display for ArchiveError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ArchiveError::UnknownProject(name) => write!(f, "no project '{}'", name),
+      ArchiveError::Running(name) => write!(f, "'{}' is still being tracked", name),
+      ArchiveError::Archived(name) => write!(f, "'{}' is archived already", name),
+      ArchiveError::NotArchived(name) => write!(f, "'{}' isn't archived", name),
+      ArchiveError::Storage => write!(f, "the log couldn't be written to"),
+    }
+  }
+}
+
+impl std::error::Error for ArchiveError {}
+
+/// Why a project couldn't be added to the favorites
+#[derive(Debug, PartialEq, Eq)]
+pub enum FavoriteError {
+  UnknownProject(String),
+  /// All [slots](FAVORITE_SLOTS) are taken
+  Full,
+  Storage,
+}
+
+impl Display for FavoriteError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      FavoriteError::UnknownProject(name) => write!(f, "no project '{}'", name),
+      FavoriteError::Full => write!(f, "all {} slots are taken", FAVORITE_SLOTS),
+      FavoriteError::Storage => write!(f, "the favorites couldn't be written"),
+    }
+  }
+}
+
+impl std::error::Error for FavoriteError {}
+
+/// What became of the records of an import
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Imported {
+  /// Records added to the history
+  pub added: usize,
+  /// Projects created for them
+  pub created: usize,
+  /// Records skipped for being in the history already, or earlier in the import
+  pub duplicates: usize,
+  /// Records skipped for overlapping others of their project
+  pub overlapping: usize,
+  /// Records skipped for not being completed
+  pub in_flight: usize,
+}
+
+/// How much of the history to materialize when opening the database
+#[derive(Clone, Debug)]
+pub enum Load {
+  /// Every record of every project
+  Full,
+  /// Every project, but only its latest record: enough to start or stop tracking
+  Headers,
+  /// Every project, with only the records started within the window (timestamps, in seconds)
+  Within(RangeInclusive<i64>),
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
@@ -63,41 +286,516 @@ impl ProjectKey {
   }
 }
 
+impl Display for ProjectKey {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.key)
+  }
+}
+
 impl Database {
-  pub fn open(location: &Path) -> Result<Self, ErrorKind> {
+  pub fn open(location: &Path, load: Load) -> Result<Self, ErrorKind> {
     match FsStorage::new(location) {
-      Ok(storage) => {
-        let database = Database {
-          storage,
-          projects: BTreeMap::new(),
-          last_project: None,
-        };
-        match load_all(database) {
-          Ok(database) => Ok(database),
-          Err(_) => Err(ErrorKind::InvalidData),
+      Ok(storage) => Self::load(storage, load),
+      Err(e) => Err(e),
+    }
+  }
+
+  /// Opens the database without locking it, all mutations will fail
+  pub fn open_read_only(location: &Path, load: Load) -> Result<Self, ErrorKind> {
+    Self::consistently(location, || match FsStorage::read_only(location) {
+      Ok(storage) => Self::load(storage, load.clone()),
+      Err(e) => Err(e),
+    })
+  }
+
+  /// Reads with `read` until the WAL wasn't rewritten meanwhile, e.g. pruned, as what's read
+  /// without the lock could otherwise mix what came before and after. Gives up with
+  /// `ErrorKind::Interrupted` if it keeps being rewritten.
+  fn consistently<T>(
+    location: &Path,
+    read: impl Fn() -> Result<T, ErrorKind>,
+  ) -> Result<T, ErrorKind> {
+    for _ in 0..READ_ATTEMPTS {
+      let generation = FsStorage::generation(location);
+      let read = read()?;
+      if FsStorage::generation(location) == generation {
+        return Ok(read);
+      }
+    }
+    Err(ErrorKind::Interrupted)
+  }
+
+  /// Where the lock of the database at `location` lives
+  pub fn lock_file(location: &Path) -> PathBuf {
+    FsStorage::lock_file(location)
+  }
+
+  /// What's currently being tracked, in the order it was started, read from the state cache when
+  /// it's fresh, replaying the WAL otherwise. Never takes the lock.
+  pub fn running(location: &Path) -> Result<Vec<(String, Record)>, ErrorKind> {
+    Self::consistently(location, || {
+      if let Some(state) = FsStorage::read_state(location) {
+        return Ok(state.running.into_iter().map(session).collect());
+      }
+      match Self::open_read_only(location, Load::Headers) {
+        Ok(database) => Ok(
+          database
+            .running_projects()
+            .into_iter()
+            .map(|p| (p.name().to_string(), p.records().last().unwrap().clone()))
+            .collect(),
+        ),
+        Err(ErrorKind::NotFound) => Ok(Vec::new()),
+        Err(e) => Err(e),
+      }
+    })
+  }
+
+  /// The last completed record, and the project it belongs to. Like `running`, uses the state
+  /// cache when it's fresh and never takes the lock.
+  pub fn last_session(location: &Path) -> Result<Option<(String, Record)>, ErrorKind> {
+    Self::consistently(location, || {
+      if let Some(state) = FsStorage::read_state(location) {
+        return Ok(state.last.map(session));
+      }
+      match Self::open_read_only(location, Load::Headers) {
+        Ok(database) => Ok(
+          database
+            .last_stopped()
+            .map(|p| (p.name().to_string(), p.records().last().unwrap().clone())),
+        ),
+        Err(ErrorKind::NotFound) => Ok(None),
+        Err(e) => Err(e),
+      }
+    })
+  }
+
+  /// Every record tracked on local `days`, including the one in flight, in the order they started
+  pub fn records(
+    location: &Path,
+    days: RangeInclusive<NaiveDate>,
+  ) -> Result<Vec<(String, Record)>, ErrorKind> {
+    let from = days.start().and_hms(0, 0, 0).timestamp() - 86400;
+    let to = days.end().and_hms(0, 0, 0).timestamp() + 2 * 86400;
+    let database = Self::open_read_only(location, Load::Within(from..=to))?;
+    let zone = zone::current();
+    let within = zone.bounds(*days.start()).start..zone.bounds(*days.end()).end;
+    let mut records: Vec<(String, Record)> = database
+      .records_between(within.start, within.end)
+      .filter_map(|(key, r)| Some((database.project_by_key(key)?.name().to_string(), r.clone())))
+      .collect();
+    records.sort_by_key(|(_, r)| r.start());
+    Ok(records)
+  }
+
+  /// Every project's expenses made on `days`, as projects only carrying those
+  pub fn expenses(
+    location: &Path,
+    days: RangeInclusive<NaiveDate>,
+  ) -> Result<Vec<Project>, ErrorKind> {
+    // Expenses are filtered on the day they were made in their own timezone, hence the slack
+    let from = days.start().and_hms(0, 0, 0).timestamp() - 86400;
+    let to = days.end().and_hms(0, 0, 0).timestamp() + 2 * 86400;
+    let database = Self::open_read_only(location, Load::Within(from..=to))?;
+    Ok(
+      database
+        .list_projects()
+        .into_iter()
+        .map(|project| {
+          let mut expenses = Project::new(project.name().to_string());
+          project
+            .expenses()
+            .filter(|e| days.contains(&zone::current().day(e.at())))
+            .for_each(|e| expenses.add_expense(e.clone()));
+          expenses
+        })
+        .collect(),
+    )
+  }
+
+  /// Per day totals of every project, from the aggregate index when it's fresh, replaying the WAL
+  /// otherwise. The record currently tracked, if any, is accounted for up until now.
+  /// When `days` are provided, only the totals of those days are guaranteed to be accounted for.
+  pub fn daily_totals(
+    location: &Path,
+    days: Option<RangeInclusive<NaiveDate>>,
+  ) -> Result<Vec<ProjectTotals>, ErrorKind> {
+    let indexed = Self::consistently(location, || {
+      let mut projects = match FsStorage::read_index(location) {
+        Some(index) => index.projects,
+        None => return Ok(None),
+      };
+      for (name, record) in Self::running(location)? {
+        if let Some(totals) = projects.iter_mut().find(|p| p.name == name) {
+          totals.add(
+            zone::current().day(record.start()),
+            record.duration().as_secs(),
+          );
         }
       }
-      Err(e) => Err(e),
+      Ok(Some(projects))
+    })?;
+    match indexed {
+      Some(projects) => Ok(projects),
+      None => Ok(Self::open_covering(location, days)?.totals(true)),
+    }
+  }
+
+  /// Like [`Database::daily_totals`], only accounting for the records that `matches`. The index
+  /// doesn't know about records' labels nor context, so the WAL is always replayed.
+  pub fn daily_totals_where(
+    location: &Path,
+    days: Option<RangeInclusive<NaiveDate>>,
+    matches: impl Fn(&Record) -> bool,
+  ) -> Result<Vec<ProjectTotals>, ErrorKind> {
+    Ok(Self::open_covering(location, days)?.totals_where(true, matches))
+  }
+
+  /// Opens enough of the history to account for `days`, all of it when there are none
+  fn open_covering(
+    location: &Path,
+    days: Option<RangeInclusive<NaiveDate>>,
+  ) -> Result<Database, ErrorKind> {
+    match days {
+      Some(days) => {
+        // Records are bucketed by the day they started on in their own timezone, hence the slack
+        let from = days.start().and_hms(0, 0, 0).timestamp() - 86400;
+        let to = days.end().and_hms(0, 0, 0).timestamp() + 2 * 86400;
+        Self::open_read_only(location, Load::Within(from..=to))
+      }
+      None => match Self::open(location, Load::Full) {
+        Err(ErrorKind::AlreadyExists) => Self::open_read_only(location, Load::Full),
+        other => other,
+      },
+    }
+  }
+
+  /// How long was tracked on `day`, and across how many projects
+  pub fn day_total(location: &Path, day: NaiveDate) -> Result<(Duration, usize), ErrorKind> {
+    let totals = Self::daily_totals(location, Some(day..=day))?;
+    let days: Vec<u64> = totals
+      .iter()
+      .filter_map(|p| p.days.get(&day))
+      .filter(|secs| **secs > 0)
+      .copied()
+      .collect();
+    Ok((Duration::from_secs(days.iter().sum()), days.len()))
+  }
+
+  fn load(storage: FsStorage, load: Load) -> Result<Self, ErrorKind> {
+    // Without the full history, the index can only be maintained if it is fresh to begin with
+    let index = match load {
+      Load::Headers if !storage.is_read_only() => match storage.is_empty() {
+        true => Some(Vec::new()),
+        false => FsStorage::read_index(storage.location()).map(|index| index.projects),
+      },
+      _ => None,
+    };
+    let mut database = Database {
+      storage,
+      load,
+      projects: BTreeMap::new(),
+      running: Vec::new(),
+      last_stopped: None,
+      sequencer: Sequencer::default(),
+      index,
+      skipped: 0,
+      defaults: BTreeMap::new(),
+      context: None,
+    };
+    match load_all(&mut database) {
+      Ok(_) => Ok(database),
+      Err(_) => Err(ErrorKind::InvalidData),
+    }
+  }
+
+  /// How many entries of the WAL were skipped when loading, e.g. stops of projects that weren't
+  /// running, left by a bad merge or import. `timek verify` tells which.
+  pub fn skipped(&self) -> usize {
+    self.skipped
+  }
+
+  /// Has new records of `name` labelled with `defaults`, whatever a start doesn't say otherwise
+  pub fn set_defaults(&mut self, name: &str, defaults: Labels) {
+    self.defaults.insert(ProjectKey::new(name), defaults);
+  }
+
+  /// Notes `context` on every record started from now on, as far as the log can hold it
+  pub fn capture_context(&mut self, context: Context) {
+    let printable = |field: Option<String>| field.filter(|text| !text.contains(char::is_control));
+    self.context = Some(Context {
+      host: printable(context.host),
+      branch: printable(context.branch),
+      cwd: printable(context.cwd),
+      user: printable(context.user),
+    });
+  }
+
+  /// Seals everything appended from now on onto the WAL's hash chain, for `verify --audit` to
+  /// tell whether any entry was tampered with since
+  pub fn seal_appends(&mut self) -> Result<(), SomeDbError> {
+    self.storage.seal_appends().map_err(|_| SomeDbError)
+  }
+
+  /// Pretends from now on: changes still apply in memory, but nothing gets written. See
+  /// [`pending`](Database::pending) for what would have been.
+  pub fn dry_run(&mut self) {
+    self.storage.dry_run();
+  }
+
+  /// Where the database lives, along with its configuration
+  pub fn location(&self) -> &Path {
+    self.storage.location()
+  }
+
+  /// What would have been written to the WAL so far, if this is a dry run
+  pub fn pending(&self) -> Option<&DryRun> {
+    self.storage.pending()
+  }
+
+  /// Attributes whatever gets recorded from now on to `device`
+  pub fn attribute_to(&mut self, device: String) {
+    let attributed = self.sequencer.device() == Some(device.as_str());
+    self
+      .storage
+      .attribute_to(device, attributed, self.sequencer.next());
+  }
+
+  /// Numbers an invoice issued to `client` now, following the ones issued before it
+  pub fn issue_invoice(&mut self, client: &str) -> Result<u64, SomeDbError> {
+    self
+      .storage
+      .issue_invoice(client, Local::now().timestamp())
+      .map_err(|_| SomeDbError)
+  }
+
+  /// The favorite projects, in slot order
+  pub fn favorites(&self) -> Vec<String> {
+    FsStorage::read_favorites(self.storage.location())
+  }
+
+  /// Adds `name` to the favorites, returning its slot, counting from 1. Favorites already are in
+  /// theirs, others only fit as long as there are slots left.
+  pub fn add_favorite(&mut self, name: &str) -> Result<usize, FavoriteError> {
+    let name = match self.project(name) {
+      Some(project) => project.name().to_string(),
+      None => return Err(FavoriteError::UnknownProject(name.to_string())),
+    };
+    let mut favorites = self.favorites();
+    if let Some(slot) = favorites
+      .iter()
+      .position(|f| f.to_lowercase() == name.to_lowercase())
+    {
+      return Ok(slot + 1);
+    }
+    if favorites.len() >= FAVORITE_SLOTS {
+      return Err(FavoriteError::Full);
     }
+    favorites.push(name);
+    self
+      .storage
+      .write_favorites(&favorites)
+      .map_err(|_| FavoriteError::Storage)?;
+    Ok(favorites.len())
   }
 
-  pub fn add_project(&mut self, name: String) -> Result<Cow<Project>, SomeDbError> {
+  /// Removes `name` from the favorites, the ones after it moving up a slot
+  pub fn remove_favorite(&mut self, name: &str) -> Result<(), SomeDbError> {
+    let mut favorites = self.favorites();
+    let count = favorites.len();
+    favorites.retain(|f| f.to_lowercase() != name.to_lowercase());
+    if favorites.len() == count {
+      return Err(SomeDbError);
+    }
+    self
+      .storage
+      .write_favorites(&favorites)
+      .map_err(|_| SomeDbError)
+  }
+
+  /// Copies of the WAL sync tools left behind at `location`, to be merged
+  pub fn conflicted_copies(location: &Path) -> Vec<PathBuf> {
+    FsStorage::conflicted_copies(location)
+  }
+
+  /// Merges another copy of the WAL, found at `other`, into this one, refusing to if the result
+  /// wouldn't replay. Returns how many entries were new.
+  pub fn merge(&mut self, other: &Path) -> Result<usize, MergeError> {
+    let theirs = FsStorage::read_log(other).map_err(|_| MergeError::Unreadable)?;
+    let ours: Vec<Action> = match self
+      .storage
+      .replay_actions()
+      .collect::<Result<Vec<_>, ()>>()
+    {
+      Ok(entries) => entries.into_iter().map(|(_, action)| action).collect(),
+      Err(_) => return Err(MergeError::Unreadable),
+    };
+    let entries = |log: &[Action]| {
+      log
+        .iter()
+        .filter(|action| {
+          !matches!(
+            action,
+            Action::Device { .. } | Action::Seal { .. } | Action::Begin { .. }
+          )
+        })
+        .count()
+    };
+    let known = entries(&ours);
+    let merged = merge(vec![ours, theirs])?;
+    let problems = check(
+      merged
+        .iter()
+        .cloned()
+        .map(|action| Ok((action.key(), action))),
+    )
+    .problems;
+    if !problems.is_empty() {
+      return Err(MergeError::Problems(problems));
+    }
+    if self.storage.rewrite(&merged).is_err() {
+      return Err(MergeError::Storage);
+    }
+    if load_all(self).is_err() {
+      return Err(MergeError::Storage);
+    }
+    Ok(entries(&merged) - known)
+  }
+
+  pub fn add_project(&mut self, name: String) -> Result<Cow<'_, Project>, SomeDbError> {
     let entry = self.projects.entry(ProjectKey::new(&name));
     match entry {
-      Entry::Vacant(_) => Self::apply_action(&mut self.storage, entry, Action::ProjectAdd { name }),
+      Entry::Vacant(_) => {
+        let result = Self::apply_action(&mut self.storage, entry, Action::ProjectAdd { name });
+        if let (Ok(project), Some(index)) = (&result, &mut self.index) {
+          index.push(ProjectTotals::new(project.name().to_string()));
+        }
+        result
+      }
       Entry::Occupied(_) => Err(SomeDbError),
     }
   }
 
-  pub fn remove_project(&mut self, name: String) -> Result<Cow<Project>, SomeDbError> {
+  /// Creates all `names` missing, within a single append, e.g. when onboarding a list of clients.
+  /// Returns the names of the projects created, the others already existing.
+  pub fn add_projects(&mut self, names: Vec<String>) -> Result<Vec<String>, SomeDbError> {
+    let mut keys = BTreeSet::new();
+    let adds: Vec<Action> = names
+      .into_iter()
+      .filter(|name| {
+        let key = ProjectKey::new(name);
+        !self.projects.contains_key(&key) && keys.insert(key)
+      })
+      .map(|name| Action::ProjectAdd { name })
+      .collect();
+    let adds = match self.storage.record_actions(adds) {
+      Ok(adds) => adds,
+      Err(_) => return Err(SomeDbError),
+    };
+    let mut created = Vec::with_capacity(adds.len());
+    for add in adds {
+      let key = add.key().expect("Adds have a key");
+      let project = add.apply(self.projects.entry(key))?;
+      if let Some(index) = &mut self.index {
+        index.push(ProjectTotals::new(project.name().to_string()));
+      }
+      created.push(project.name().to_string());
+    }
+    Ok(created)
+  }
+
+  /// Deletes `name`, which can't be running unless `stop_first`, in which case it's stopped within
+  /// the same append. Returns the project as it was last.
+  pub fn remove_project(
+    &mut self,
+    name: String,
+    stop_first: bool,
+  ) -> Result<Cow<'_, Project>, DeleteError> {
     let key = ProjectKey::new(&name);
-    let entry = self.projects.entry(key.clone());
-    match entry {
-      Entry::Occupied(_) => {
-        Self::apply_action(&mut self.storage, entry, Action::ProjectDel { key })
+    let running = match self.projects.get(&key) {
+      None => return Err(DeleteError::UnknownProject(name)),
+      Some(project) if project.in_flight() && !stop_first => {
+        return Err(DeleteError::Running(project.name().to_string()))
       }
-      Entry::Vacant(_) => Err(SomeDbError),
+      Some(project) => project.in_flight(),
+    };
+    let delete = Action::ProjectDel { key: key.clone() };
+    let result = match running {
+      true => {
+        let now = Local::now();
+        let stamp = (now.timestamp(), now.offset().utc_minus_local());
+        match self.stop_then(std::slice::from_ref(&key), Some(delete), stamp) {
+          Ok(Some(delete)) => delete.apply(self.projects.entry(key.clone())).ok(),
+          _ => None,
+        }
+      }
+      false => Self::apply_action(&mut self.storage, self.projects.entry(key.clone()), delete).ok(),
+    };
+    let project = result.ok_or(DeleteError::Storage)?;
+    if self.last_stopped.as_ref() == Some(&key) {
+      self.last_stopped = None;
+    }
+    if let Some(index) = &mut self.index {
+      index.retain(|p| p.name != project.name());
+    }
+    debug_assert!(!self.running.contains(&key));
+    Ok(project)
+  }
+
+  /// Archives `name`, keeping it and its history out of the way, or unarchives it. Archived
+  /// projects can't be started, so running ones can't be archived.
+  pub fn archive(&mut self, name: &str, archived: bool) -> Result<Cow<'_, Project>, ArchiveError> {
+    let key = ProjectKey::new(name);
+    match self.projects.get(&key) {
+      None => return Err(ArchiveError::UnknownProject(name.to_string())),
+      Some(project) if project.archived() == archived => {
+        let name = project.name().to_string();
+        return Err(match archived {
+          true => ArchiveError::Archived(name),
+          false => ArchiveError::NotArchived(name),
+        });
+      }
+      Some(project) if archived && project.in_flight() => {
+        return Err(ArchiveError::Running(project.name().to_string()))
+      }
+      Some(_) => {}
+    }
+    let archive = Action::ProjectArchive {
+      key: key.clone(),
+      archived,
+    };
+    let project = Self::apply_action(&mut self.storage, self.projects.entry(key), archive)
+      .map_err(|_| ArchiveError::Storage)?;
+    if let Some(index) = &mut self.index {
+      if let Some(totals) = index.iter_mut().find(|p| p.name == project.name()) {
+        totals.archived = archived;
+      }
+    }
+    Ok(project)
+  }
+
+  /// Records `cents` spent on `name` now, for `description`
+  pub fn add_expense(
+    &mut self,
+    name: &str,
+    cents: i64,
+    description: &str,
+  ) -> Result<Cow<'_, Project>, SomeDbError> {
+    let key = ProjectKey::new(name);
+    let entry = self.projects.entry(key.clone());
+    if let Entry::Vacant(_) = entry {
+      return Err(SomeDbError);
     }
+    let now = Local::now();
+    let action = Action::Expense {
+      key,
+      ts: now.timestamp(),
+      tz: now.offset().utc_minus_local(),
+      cents,
+      // Entries are delimited by new lines
+      description: description.replace('\n', " "),
+    };
+    Self::apply_action(&mut self.storage, entry, action)
   }
 
   pub fn list_projects(&self) -> Vec<&Project> {
@@ -106,69 +804,815 @@ impl Database {
     projects
   }
 
+  pub fn project(&self, name: &str) -> Option<&Project> {
+    self.projects.get(&ProjectKey::new(name))
+  }
+
+  pub fn project_by_key(&self, key: &ProjectKey) -> Option<&Project> {
+    self.projects.get(key)
+  }
+
+  /// Every loaded record, along with the key of its project, project by project
+  pub fn entries(&self) -> impl Iterator<Item = (&ProjectKey, &Record)> {
+    self
+      .projects
+      .iter()
+      .flat_map(|(key, project)| project.records().map(move |record| (key, record)))
+  }
+
+  /// The loaded records at least partly within `start..end`, the one in flight included. See
+  /// [`Record::duration_between`] for how much of each that is.
+  pub fn records_between(
+    &self,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+  ) -> impl Iterator<Item = (&ProjectKey, &Record)> {
+    self
+      .entries()
+      .filter(move |(_, record)| record.start() < end && record.end().is_none_or(|e| e > start))
+  }
+
+  /// The project started last, if still running
   pub fn current_project(&self) -> Option<&Project> {
-    match &self.last_project {
-      Some(key) => self.projects.get(key),
-      None => None,
+    self.running.last().and_then(|key| self.projects.get(key))
+  }
+
+  /// All projects with a record in flight, in the order they were started
+  pub fn running_projects(&self) -> Vec<&Project> {
+    self
+      .running
+      .iter()
+      .filter_map(|key| self.projects.get(key))
+      .collect()
+  }
+
+  /// The projects not running, most recently stopped first
+  pub fn recent(&self) -> Vec<&Project> {
+    let mut stopped: Vec<(&Project, DateTime<FixedOffset>)> = self
+      .projects
+      .values()
+      .filter_map(|p| p.records().last().and_then(Record::end).map(|end| (p, end)))
+      .collect();
+    stopped.sort_by_key(|(_, end)| std::cmp::Reverse(*end));
+    stopped.into_iter().map(|(project, _)| project).collect()
+  }
+
+  /// The project last stopped, unless it's been restarted since
+  pub fn last_stopped(&self) -> Option<&Project> {
+    self
+      .last_stopped
+      .as_ref()
+      .and_then(|key| self.projects.get(key))
+      .filter(|p| !p.in_flight() && p.records().last().is_some())
+  }
+
+  /// Starts tracking `name`, stopping whatever is running unless `concurrent`. Restarting a
+  /// running project always stops its current record first, unless it was started just before:
+  /// that's the same start again, e.g. a hotkey firing twice, and nothing changes. The new record
+  /// is labelled with `labels`, falling back to the project's defaults.
+  pub fn start_on(
+    &mut self,
+    name: String,
+    concurrent: bool,
+    labels: Labels,
+  ) -> Result<Cow<'_, Project>, SomeDbError> {
+    let key = ProjectKey::new(&name);
+    if !self.projects.contains_key(&key) {
+      return Err(SomeDbError);
+    }
+    let now = Local::now();
+    let unchanged = concurrent || self.running.len() == 1;
+    let latest = self.projects[&key].records().last();
+    if unchanged
+      && self.running.last() == Some(&key)
+      && latest.is_some_and(|r| r.duration() < REPEATED_START)
+    {
+      return Ok(Cow::Borrowed(&self.projects[&key]));
     }
+    self.start_then_label(&key, concurrent, labels, now.into())?;
+    Ok(Cow::Borrowed(&self.projects[&key]))
   }
 
-  pub fn start_on(&mut self, name: String) -> Result<Cow<Project>, SomeDbError> {
-    match self.silent_stop() {
-      Ok(_) => {
-        let key = ProjectKey::new(&name);
-        let entry = self.projects.entry(key.clone());
-        let now = Local::now();
-        match entry {
-          Entry::Occupied(_) => Self::apply_action(
-            &mut self.storage,
-            entry,
-            Action::RecordStart {
-              key,
-              ts: now.timestamp(),
-              tz: now.offset().utc_minus_local(),
-            },
-          ),
-          Entry::Vacant(_) => Err(SomeDbError),
-        }
+  /// Starts tracking `name` as of `at`, as [`start_on`](Database::start_on) would have then. What
+  /// gets stopped has to have started before, and nothing, or only `name` when `concurrent`, can
+  /// have been tracked since.
+  pub fn start_at(
+    &mut self,
+    name: String,
+    concurrent: bool,
+    labels: Labels,
+    at: DateTime<FixedOffset>,
+  ) -> Result<Cow<'_, Project>, BackdateError> {
+    let key = ProjectKey::new(&name);
+    if !self.projects.contains_key(&key) {
+      return Err(BackdateError::UnknownProject(name));
+    }
+    if at > Local::now() {
+      return Err(BackdateError::Future);
+    }
+    for stopping in self.stopping(&key, concurrent) {
+      let project = &self.projects[&stopping];
+      if project.records().last().is_some_and(|r| r.start() > at) {
+        return Err(BackdateError::BeforeStart(project.name().to_string()));
       }
-      Err(_) => Err(SomeDbError),
     }
+    let overlapping = self
+      .projects
+      .iter()
+      .filter(|(other, _)| !concurrent || **other == key)
+      .map(|(_, project)| project)
+      .find(|project| {
+        let end = project.records().last().and_then(|r| r.end());
+        end.is_some_and(|end| end > at)
+      });
+    if let Some(project) = overlapping {
+      return Err(BackdateError::Overlapping(project.name().to_string()));
+    }
+    self
+      .start_then_label(&key, concurrent, labels, at)
+      .map_err(|_| BackdateError::Storage)?;
+    Ok(Cow::Borrowed(&self.projects[&key]))
+  }
+
+  /// What starting `key` stops, from the latest started
+  fn stopping(&self, key: &ProjectKey, concurrent: bool) -> Vec<ProjectKey> {
+    self
+      .running
+      .iter()
+      .rev()
+      .filter(|running| !concurrent || *running == key)
+      .cloned()
+      .collect()
   }
 
-  pub fn stop(&mut self) -> Result<Cow<Project>, SomeDbError> {
-    if self.current_project().is_none() {
+  /// Starts `key` at `at`, stopping whatever it has to then, and labels the new record
+  fn start_then_label(
+    &mut self,
+    key: &ProjectKey,
+    concurrent: bool,
+    labels: Labels,
+    at: DateTime<FixedOffset>,
+  ) -> Result<(), SomeDbError> {
+    let (ts, tz) = (at.timestamp(), at.offset().utc_minus_local());
+    let stopping = self.stopping(key, concurrent);
+    let start = Action::RecordStart {
+      key: key.clone(),
+      ts,
+      tz,
+    };
+    start.validate(key, self.projects.get(key))?;
+    let labels = labels.or(self.defaults.get(key).unwrap_or(&Labels::default()));
+    let billable = labels.billable.unwrap_or(true);
+    let tags = labels.tags.unwrap_or_default();
+    let label = (!billable || !tags.is_empty()).then(|| Action::RecordLabel {
+      key: key.clone(),
+      billable,
+      tags,
+    });
+    // Rather not start at all than have the record end up without its labels
+    if label.as_ref().is_some_and(|label| !label.is_printable()) {
       return Err(SomeDbError);
     }
-    self.silent_stop().map(|o| o.unwrap())
+    let start = self
+      .stop_then(&stopping, Some(start), (ts, tz))?
+      .expect("We just recorded it");
+    self.running.push(key.clone());
+    start.apply(self.projects.entry(key.clone()))?;
+    if let Some(label) = label {
+      Self::apply_action(&mut self.storage, self.projects.entry(key.clone()), label)?;
+    }
+    if let Some(context) = self.context.clone() {
+      let context = Action::RecordContext {
+        key: key.clone(),
+        context,
+      };
+      Self::apply_action(&mut self.storage, self.projects.entry(key.clone()), context)?;
+    }
+    Ok(())
   }
 
-  fn silent_stop(&mut self) -> Result<Option<Cow<Project>>, SomeDbError> {
-    if self.last_project.is_none() {
-      return Ok(None);
+  /// Stops everything that's running, returning what was
+  pub fn stop(&mut self) -> Result<Vec<Project>, SomeDbError> {
+    if self.running.is_empty() {
+      return Err(SomeDbError);
     }
+    let now = Local::now();
+    let stopping: Vec<ProjectKey> = self.running.iter().rev().cloned().collect();
+    self.stop_then(
+      &stopping,
+      None,
+      (now.timestamp(), now.offset().utc_minus_local()),
+    )?;
+    Ok(
+      stopping
+        .iter()
+        .rev()
+        .filter_map(|key| self.projects.get(key))
+        .cloned()
+        .collect(),
+    )
+  }
 
-    let key = self.last_project.take();
-    let entry = self.projects.entry(key.unwrap());
+  /// Stops `name`, leaving whatever else is running alone
+  pub fn stop_on(&mut self, name: &str) -> Result<Cow<'_, Project>, SomeDbError> {
+    let key = ProjectKey::new(name);
+    if !self.running.contains(&key) {
+      return Err(SomeDbError);
+    }
     let now = Local::now();
-    match entry {
-      Entry::Occupied(e) => {
-        if e.get().in_flight() {
-          Self::apply_action(
-            &mut self.storage,
-            Entry::Occupied(e),
-            Action::RecordStop {
-              ts: now.timestamp(),
-              tz: now.offset().utc_minus_local(),
-            },
-          )
-        } else {
-          Ok(Cow::Borrowed(e.into_mut()))
+    self.stop_then(
+      std::slice::from_ref(&key),
+      None,
+      (now.timestamp(), now.offset().utc_minus_local()),
+    )?;
+    Ok(Cow::Borrowed(&self.projects[&key]))
+  }
+
+  /// Notes what's being done on the record `name` has in flight, or the latest started's without
+  /// one
+  pub fn note(
+    &mut self,
+    name: Option<&str>,
+    note: String,
+  ) -> Result<Cow<'_, Project>, SomeDbError> {
+    let key = match name {
+      Some(name) => ProjectKey::new(name),
+      None => self.running.last().cloned().ok_or(SomeDbError)?,
+    };
+    if !self.running.contains(&key) {
+      return Err(SomeDbError);
+    }
+    let note = Action::RecordNote {
+      key: key.clone(),
+      note,
+    };
+    Self::apply_action(&mut self.storage, self.projects.entry(key), note)
+  }
+
+  /// Discards the record `name` has in flight, or the latest started's without one, as if it was
+  /// never started. Returns the record discarded.
+  pub fn cancel(&mut self, name: Option<&str>) -> Result<Record, SomeDbError> {
+    let key = match name {
+      Some(name) => ProjectKey::new(name),
+      None => self.running.last().cloned().ok_or(SomeDbError)?,
+    };
+    if !self.running.contains(&key) {
+      return Err(SomeDbError);
+    }
+    let record = self.projects[&key].records().last().cloned();
+    let cancel = Action::RecordCancel { key: key.clone() };
+    Self::apply_action(&mut self.storage, self.projects.entry(key.clone()), cancel)?;
+    self.running.retain(|running| running != &key);
+    Ok(record.expect("running"))
+  }
+
+  /// Stops `name`, or everything running without one, as of `at` rather than now, e.g. when
+  /// tracking went on past the end of the work. Everything stopped has to have started before.
+  /// A `note` goes on the record of `name`, or the latest started's, within the same append, so
+  /// that it's only ever there if the record was stopped. Returns the projects that were stopped.
+  pub fn stop_as_of(
+    &mut self,
+    name: Option<&str>,
+    at: DateTime<FixedOffset>,
+    note: Option<String>,
+  ) -> Result<Vec<Project>, BackdateError> {
+    let stopping: Vec<ProjectKey> = match name {
+      Some(name) => {
+        let key = ProjectKey::new(name);
+        if !self.projects.contains_key(&key) {
+          return Err(BackdateError::UnknownProject(name.to_string()));
+        }
+        if !self.running.contains(&key) {
+          return Err(BackdateError::NotRunning(name.to_string()));
+        }
+        vec![key]
+      }
+      None => self.running.iter().rev().cloned().collect(),
+    };
+    if at > Local::now() {
+      return Err(BackdateError::Future);
+    }
+    for key in &stopping {
+      let project = &self.projects[key];
+      if project.records().last().is_some_and(|r| r.start() > at) {
+        return Err(BackdateError::BeforeStart(project.name().to_string()));
+      }
+    }
+    let note = note.map(|note| Action::RecordNote {
+      key: stopping[0].clone(),
+      note,
+    });
+    self
+      .stop_between(
+        &stopping,
+        note,
+        None,
+        (at.timestamp(), at.offset().utc_minus_local()),
+      )
+      .map_err(|_| BackdateError::Storage)?;
+    Ok(
+      stopping
+        .iter()
+        .rev()
+        .filter_map(|key| self.projects.get(key))
+        .cloned()
+        .collect(),
+    )
+  }
+
+  /// Stops every record that was already running at `at`, as if stopped then, e.g. when the
+  /// machine went down. Returns the projects that were stopped.
+  pub fn stop_at(&mut self, at: DateTime<FixedOffset>) -> Result<Vec<Project>, SomeDbError> {
+    let mut stopped = Vec::new();
+    for key in self.running.clone().iter().rev() {
+      if self.projects[key].records().last().unwrap().start() <= at {
+        self.stop_then(
+          std::slice::from_ref(key),
+          None,
+          (at.timestamp(), at.offset().utc_minus_local()),
+        )?;
+        stopped.push(self.projects[key].clone());
+      }
+    }
+    Ok(stopped)
+  }
+
+  /// Adds a completed record of `name`, from `start` to `end`, wherever it falls in its history,
+  /// e.g. for work tracked on paper. It can't overlap any other record, or only the project's
+  /// own when `concurrent`; a running record counts as going on until now. Needs the whole
+  /// history loaded.
+  pub fn add_record(
+    &mut self,
+    name: &str,
+    (start, end): (DateTime<FixedOffset>, DateTime<FixedOffset>),
+    concurrent: bool,
+  ) -> Result<Cow<'_, Project>, BackdateError> {
+    if !matches!(self.load, Load::Full) {
+      return Err(BackdateError::Storage);
+    }
+    let key = ProjectKey::new(name);
+    if !self.projects.contains_key(&key) {
+      return Err(BackdateError::UnknownProject(name.to_string()));
+    }
+    let now = Local::now();
+    if end > now {
+      return Err(BackdateError::Future);
+    }
+    if end < start {
+      return Err(BackdateError::BeforeStart(name.to_string()));
+    }
+    let overlapping = self
+      .projects
+      .iter()
+      .filter(|(other, _)| !concurrent || **other == key)
+      .map(|(_, project)| project)
+      .find(|project| {
+        project.records().any(|r| {
+          let until = r.end().unwrap_or_else(|| now.into());
+          r.start() < end && until > start
+        })
+      });
+    if let Some(project) = overlapping {
+      return Err(BackdateError::Overlapping(project.name().to_string()));
+    }
+    let add = Action::RecordAdd {
+      key: key.clone(),
+      ts: start.timestamp(),
+      tz: start.offset().utc_minus_local(),
+      end_ts: end.timestamp(),
+      end_tz: end.offset().utc_minus_local(),
+    };
+    add
+      .validate(&key, self.projects.get(&key))
+      .map_err(|_| BackdateError::Overlapping(name.to_string()))?;
+    let add = self
+      .storage
+      .record_action(add)
+      .map_err(|_| BackdateError::Storage)?;
+    add
+      .apply(self.projects.entry(key.clone()))
+      .map_err(|_| BackdateError::Storage)?;
+    let project = &self.projects[&key];
+    let mut record = Record::started_on(start);
+    record
+      .end_at(end)
+      .expect("We checked it ends after it starts");
+    Self::account(&mut self.index, project.name(), &record);
+    Ok(Cow::Borrowed(project))
+  }
+
+  /// Stamps now as the last time the machine was known to be up
+  pub fn beat(location: &Path) -> Result<(), ErrorKind> {
+    FsStorage::write_heartbeat(location, Local::now().timestamp()).map_err(|err| err.kind())
+  }
+
+  /// The last time the machine was known to be up, if it ever beat
+  pub fn last_beat(location: &Path) -> Option<DateTime<Local>> {
+    FsStorage::read_heartbeat(location).map(|ts| Local.timestamp(ts, 0))
+  }
+
+  /// Stops every record that's been running past `at`, as if stopped then, marking the records as
+  /// such. Returns the projects that were stopped.
+  pub fn hard_stop(&mut self, at: NaiveTime) -> Result<Vec<Project>, SomeDbError> {
+    let now = Local::now();
+    let mut stopped = Vec::new();
+    for key in self.running.clone().iter().rev() {
+      let end = self.projects[key].records().last().unwrap().next(at);
+      if end < now {
+        let hard_stop = Action::RecordHardStop { key: key.clone() };
+        self.stop_between(
+          std::slice::from_ref(key),
+          Some(hard_stop),
+          None,
+          (end.timestamp(), end.offset().utc_minus_local()),
+        )?;
+        stopped.push(self.projects[key].clone());
+      }
+    }
+    Ok(stopped)
+  }
+
+  /// Stops the running `keys`, given from the latest started, then records `then` within the same
+  /// append. Returns `then` as recorded, for the caller to apply.
+  fn stop_then(
+    &mut self,
+    keys: &[ProjectKey],
+    then: Option<Action>,
+    stamp: (i64, i32),
+  ) -> Result<Option<Action>, SomeDbError> {
+    self.stop_between(keys, None, then, stamp)
+  }
+
+  /// Records `before`, about a project still running, then stops the running `keys` and records
+  /// `then`, all within the same append. Returns `then` as recorded, for the caller to apply.
+  fn stop_between(
+    &mut self,
+    keys: &[ProjectKey],
+    before: Option<Action>,
+    then: Option<Action>,
+    (ts, tz): (i64, i32),
+  ) -> Result<Option<Action>, SomeDbError> {
+    let mut actions = Vec::with_capacity(keys.len() + 2);
+    let noted = match before {
+      Some(action) => {
+        let key = action.key().expect("about a project");
+        action.validate(&key, self.projects.get(&key))?;
+        actions.push(action);
+        Some(key)
+      }
+      None => None,
+    };
+    // Stops only need their key when they don't target the latest project started
+    let mut running = self.running.clone();
+    for key in keys {
+      let keyed = running.last() != Some(key);
+      running.retain(|running| running != key);
+      let stop = Action::RecordStop {
+        ts,
+        tz,
+        key: keyed.then(|| key.clone()),
+      };
+      stop.validate(key, self.projects.get(key))?;
+      actions.push(stop);
+    }
+    actions.extend(then);
+    let mut recorded = match self.storage.record_actions(actions) {
+      Ok(actions) => actions.into_iter(),
+      Err(_) => return Err(SomeDbError),
+    };
+
+    if let Some(key) = noted {
+      recorded
+        .next()
+        .expect("We just recorded it")
+        .apply(self.projects.entry(key))?;
+    }
+    for key in keys {
+      let stopped = recorded
+        .next()
+        .expect("We just recorded it")
+        .apply(self.projects.entry(key.clone()))?;
+      let record = stopped.records().last().expect("We just stopped it");
+      Self::account(&mut self.index, stopped.name(), record);
+      self.running.retain(|running| running != key);
+      self.last_stopped = Some(key.clone());
+    }
+    Ok(recorded.next())
+  }
+
+  fn totals(&self, include_running: bool) -> Vec<ProjectTotals> {
+    self.totals_where(include_running, |_| true)
+  }
+
+  fn totals_where(
+    &self,
+    include_running: bool,
+    matches: impl Fn(&Record) -> bool,
+  ) -> Vec<ProjectTotals> {
+    let mut totals: BTreeMap<&ProjectKey, ProjectTotals> = self
+      .projects
+      .iter()
+      .map(|(key, p)| {
+        let mut totals = ProjectTotals::new(p.name().to_string());
+        totals.archived = p.archived();
+        (key, totals)
+      })
+      .collect();
+    for (key, record) in self.entries() {
+      if (include_running || !record.is_on_going()) && matches(record) {
+        if let Some(totals) = totals.get_mut(key) {
+          totals.add(
+            zone::current().day(record.start()),
+            record.duration().as_secs(),
+          );
+        }
+      }
+    }
+    totals.into_values().collect()
+  }
+
+  /// Makes all `amendments` to completed records, in order, then rewrites the WAL to match. None
+  /// are made if any can't be. Needs the whole history loaded, returns how many were made.
+  pub fn amend(&mut self, amendments: Vec<Amendment>) -> Result<usize, AmendError> {
+    if !matches!(self.load, Load::Full) {
+      return Err(AmendError::Storage);
+    }
+    let original = self.projects.clone();
+    let count = amendments.len();
+    for amendment in amendments {
+      if let Err(err) = self.make(amendment) {
+        self.projects = original;
+        return Err(err);
+      }
+    }
+    if count > 0 {
+      self.rewrite_or(original)?;
+    }
+    Ok(count)
+  }
+
+  /// Adds completed `records` to the history of their project, creating the projects missing,
+  /// then rewrites the WAL to match. Records already in the history, as per their
+  /// [fingerprint](crate::import::fingerprint), are skipped, making imports safe to run again.
+  /// Needs the whole history loaded.
+  pub fn import(&mut self, records: Vec<(String, Record)>) -> Result<Imported, AmendError> {
+    if !matches!(self.load, Load::Full) {
+      return Err(AmendError::Storage);
+    }
+    let original = self.projects.clone();
+    let mut seen: HashSet<u64> = self
+      .projects
+      .values()
+      .flat_map(|p| p.records().map(move |r| fingerprint(p.name(), r)))
+      .collect();
+    let mut imported = Imported::default();
+    for (name, record) in records {
+      if record.is_on_going() {
+        imported.in_flight += 1;
+        continue;
+      }
+      let fingerprint = fingerprint(&name, &record);
+      if seen.contains(&fingerprint) {
+        imported.duplicates += 1;
+        continue;
+      }
+      let project = match self.projects.entry(ProjectKey::new(&name)) {
+        Entry::Occupied(e) => e.into_mut(),
+        Entry::Vacant(e) => {
+          imported.created += 1;
+          e.insert(Project::new(name))
+        }
+      };
+      match project.insert_record(record) {
+        Ok(_) => {
+          seen.insert(fingerprint);
+          imported.added += 1;
         }
+        Err(_) => imported.overlapping += 1,
+      }
+    }
+    if imported.added > 0 || imported.created > 0 {
+      self.rewrite_or(original)?;
+    }
+    Ok(imported)
+  }
+
+  /// Expresses every record at the offsets `offset_at` gives, e.g. the display timezone's, then
+  /// rewrites the WAL to match. Only offsets change, never the instants records cover. Needs the
+  /// whole history loaded, returns how many records changed.
+  pub fn reoffset(
+    &mut self,
+    offset_at: impl Fn(DateTime<FixedOffset>) -> FixedOffset,
+  ) -> Result<usize, AmendError> {
+    if !matches!(self.load, Load::Full) {
+      return Err(AmendError::Storage);
+    }
+    let original = self.projects.clone();
+    let changed = self
+      .projects
+      .values_mut()
+      .map(|project| project.reoffset(&offset_at))
+      .sum();
+    if changed > 0 && self.storage.rewrite(&self.snapshot()).is_err() {
+      self.projects = original;
+      return Err(AmendError::Storage);
+    }
+    Ok(changed)
+  }
+
+  /// Rewrites the WAL from the current state, or rolls back to the `original` projects when it
+  /// can't be, e.g. as names or notes that weren't recorded through validated actions hold control
+  /// characters
+  fn rewrite_or(&mut self, original: BTreeMap<ProjectKey, Project>) -> Result<(), AmendError> {
+    let snapshot = self.snapshot();
+    let result = match snapshot.iter().all(Action::is_printable) {
+      true => self
+        .storage
+        .rewrite(&snapshot)
+        .map_err(|_| AmendError::Storage),
+      false => Err(AmendError::Unprintable),
+    };
+    if result.is_err() {
+      self.projects = original;
+    }
+    result
+  }
+
+  fn make(&mut self, amendment: Amendment) -> Result<(), AmendError> {
+    let (project, start) = match &amendment {
+      Amendment::Retime { project, start, .. }
+      | Amendment::Reassign { project, start, .. }
+      | Amendment::Delete { project, start }
+      | Amendment::Note { project, start, .. } => (project, *start),
+    };
+    let mut record = self
+      .projects
+      .get_mut(&ProjectKey::new(project))
+      .ok_or_else(|| AmendError::UnknownProject(project.clone()))?
+      .remove_record(start)
+      .ok_or_else(|| AmendError::UnknownRecord(project.clone(), start))?;
+    let (to, record) = match amendment {
+      Amendment::Retime {
+        project,
+        to: (start, end),
+        ..
+      } => match record.retime(start, end) {
+        Ok(_) => (project, record),
+        Err(_) => return Err(AmendError::NegativeDuration(project)),
+      },
+      Amendment::Reassign { to, .. } => (to, record),
+      Amendment::Note { project, note, .. } => {
+        record.note(note);
+        (project, record)
       }
-      Entry::Vacant(_) => Err(SomeDbError),
+      Amendment::Delete { .. } => return Ok(()),
+    };
+    match self.projects.get_mut(&ProjectKey::new(&to)) {
+      Some(project) => project
+        .insert_record(record)
+        .map_err(|_| AmendError::Overlapping(to)),
+      None => Err(AmendError::UnknownProject(to)),
     }
-    .map(Some)
+  }
+
+  /// Drops, or aggregates into monthly records, all completed records started before `before`,
+  /// rewriting the WAL to only contain what's left. Needs the whole history loaded, returns how
+  /// many records were pruned.
+  pub fn prune(
+    &mut self,
+    before: DateTime<FixedOffset>,
+    aggregate: bool,
+  ) -> Result<usize, SomeDbError> {
+    if !matches!(self.load, Load::Full) {
+      return Err(SomeDbError);
+    }
+    let original = self.projects.clone();
+    let pruned: usize = self
+      .projects
+      .values_mut()
+      .map(|p| p.prune(before, aggregate))
+      .sum();
+    if pruned > 0 && self.storage.rewrite(&self.snapshot()).is_err() {
+      self.projects = original;
+      return Err(SomeDbError);
+    }
+    Ok(pruned)
+  }
+
+  /// The size of the WAL, in bytes
+  pub fn wal_len(&self) -> u64 {
+    self.storage.len()
+  }
+
+  /// The size the WAL would be rewritten to, e.g. when pruning, from the current state of the
+  /// database. Seals aside, when auditing.
+  pub fn compacted_len(&self) -> u64 {
+    self
+      .snapshot()
+      .iter()
+      .map(|action| Vec::<u8>::from(action).len() as u64)
+      .sum()
+  }
+
+  /// The shortest sequence of actions that replays into the current state of the database
+  fn snapshot(&self) -> Vec<Action> {
+    let mut actions = Vec::new();
+    // Records in flight need to come last, in order, key-less stops apply to the latest started
+    for (key, project) in &self.projects {
+      actions.push(Action::ProjectAdd {
+        name: project.name().to_string(),
+      });
+      for expense in project.expenses() {
+        let at = expense.at();
+        actions.push(Action::Expense {
+          key: key.clone(),
+          ts: at.timestamp(),
+          tz: at.offset().utc_minus_local(),
+          cents: expense.cents(),
+          description: expense.description().to_string(),
+        });
+      }
+      for record in project.records() {
+        if let Some(end) = record.end() {
+          actions.push(record_start(key, record));
+          if record.is_labelled() {
+            actions.push(label(key, record));
+          }
+          if let Some(context) = record.context() {
+            actions.push(Action::RecordContext {
+              key: key.clone(),
+              context: context.clone(),
+            });
+          }
+          actions.extend(notes(key, record));
+          if record.is_hard_stopped() {
+            actions.push(Action::RecordHardStop { key: key.clone() });
+          }
+          actions.push(Action::RecordStop {
+            ts: end.timestamp(),
+            tz: end.offset().utc_minus_local(),
+            key: None,
+          });
+        }
+      }
+      // Only once its records are in, as archived projects can't be started
+      if project.archived() {
+        actions.push(Action::ProjectArchive {
+          key: key.clone(),
+          archived: true,
+        });
+      }
+    }
+    for key in &self.running {
+      let record = self.projects[key].records().last().unwrap();
+      actions.push(record_start(key, record));
+      if record.is_labelled() {
+        actions.push(label(key, record));
+      }
+      if let Some(context) = record.context() {
+        actions.push(Action::RecordContext {
+          key: key.clone(),
+          context: context.clone(),
+        });
+      }
+      actions.extend(notes(key, record));
+    }
+    actions
+  }
+
+  /// Adds a just completed `record` of project `name` to the aggregate index, if maintained
+  fn account(index: &mut Option<Vec<ProjectTotals>>, name: &str, record: &Record) {
+    if let Some(index) = index {
+      if let Some(totals) = index.iter_mut().find(|p| p.name == name) {
+        totals.add(
+          zone::current().day(record.start()),
+          record.duration().as_secs(),
+        );
+      }
+    }
+  }
+
+  fn persist_state(&mut self) {
+    let as_session = |p: &Project| {
+      let record = p.records().last().unwrap();
+      let start = record.start();
+      Session {
+        name: p.name().to_string(),
+        ts: start.timestamp(),
+        tz: start.offset().utc_minus_local(),
+        end: record
+          .end()
+          .map(|end| (end.timestamp(), end.offset().utc_minus_local())),
+      }
+    };
+    let running = self
+      .running_projects()
+      .into_iter()
+      .map(as_session)
+      .collect();
+    let last = self.last_stopped().map(as_session);
+    // Only a cache, readers fall back to replaying the WAL if this fails
+    let _ = self.storage.write_state(running, last);
   }
 
   fn apply_action<'a>(
@@ -176,22 +1620,874 @@ impl Database {
     entry: Entry<'a, ProjectKey, Project>,
     action: Action,
   ) -> Result<Cow<'a, Project>, SomeDbError> {
+    let project = match &entry {
+      Entry::Occupied(e) => Some(e.get()),
+      Entry::Vacant(_) => None,
+    };
+    action.validate(entry.key(), project)?;
     match storage.record_action(action) {
-      Ok(action) => action.apply(entry),
+      Ok(action) => Ok(action.apply(entry)?),
       Err(_) => Err(SomeDbError),
     }
   }
 }
 
-fn load_all(mut database: Database) -> Result<Database, ()> {
-  for (key, action) in database.storage.replay_actions() {
-    let key = key.unwrap_or_else(|| database.last_project.take().expect("We need a key here!"));
-    let project = action
-      .apply(database.projects.entry(key))
-      .expect("Something is off with our WAL!");
-    if project.in_flight() {
-      database.last_project = Some(ProjectKey::new(project.name()));
+impl Drop for Database {
+  fn drop(&mut self) {
+    if self.storage.is_read_only() || self.storage.is_dry_run() {
+      return;
+    }
+    self.persist_state();
+    if FsStorage::read_index(self.storage.location()).is_none() {
+      // Same as the state, readers rebuild what they need if this fails
+      match (&self.load, self.index.take()) {
+        (Load::Full, _) => {
+          let _ = self.storage.write_index(self.totals(false));
+        }
+        (_, Some(index)) => {
+          let _ = self.storage.write_index(index);
+        }
+        _ => {}
+      }
+    }
+  }
+}
+
+fn load_all(database: &mut Database) -> Result<(), ()> {
+  database.projects.clear();
+  database.last_stopped = None;
+  database.sequencer = Sequencer::default();
+  database.skipped = 0;
+  // Every start not stopped yet, in order, and whether it was skipped for being out of the window
+  let mut open: Vec<(ProjectKey, bool)> = Vec::new();
+  for entry in database.storage.replay_actions() {
+    let (key, action) = entry?;
+    if database.sequencer.observe(&action).is_none() {
+      continue;
+    }
+    let key = match key.or_else(|| open.last().map(|(key, _)| key.clone())) {
+      Some(key) => key,
+      None => {
+        database.skipped += 1;
+        continue;
+      }
+    };
+    let after = |ts: &i64| matches!(&database.load, Load::Within(window) if ts > window.end());
+    let before = |ts: &i64| matches!(&database.load, Load::Within(window) if ts < window.start());
+    let skipped = match &action {
+      // Starts before the window may still run into it, which only their stop tells
+      Action::RecordStart { ts, .. } => after(ts),
+      Action::RecordAdd { ts, end_ts, .. } => after(ts) || before(end_ts),
+      Action::Expense { ts, .. } => after(ts) || before(ts),
+      // A skipped start takes its matching stop, or cancel, and labels and notes with it
+      Action::RecordStop { .. }
+      | Action::RecordCancel { .. }
+      | Action::RecordLabel { .. }
+      | Action::RecordContext { .. }
+      | Action::RecordNote { .. }
+      | Action::RecordHardStop { .. } => match open.iter().find(|(running, _)| running == &key) {
+        Some((_, skipped)) => *skipped,
+        None => {
+          database.skipped += 1;
+          continue;
+        }
+      },
+      Action::ProjectAdd { .. }
+      | Action::ProjectDel { .. }
+      | Action::ProjectArchive { .. }
+      | Action::Device { .. }
+      | Action::Seal { .. }
+      | Action::Begin { .. } => false,
+    };
+    let (starts, stops, deletes) = match &action {
+      Action::RecordStart { .. } => (true, false, false),
+      Action::RecordStop { .. } => (false, true, false),
+      Action::ProjectDel { .. } => (false, false, true),
+      _ => (false, false, false),
+    };
+    // Leaves nothing running, yet nothing stopped either
+    let cancels = matches!(action, Action::RecordCancel { .. });
+    let applied = skipped
+      || match deletes {
+        // Deleting a running project used to be allowed, older logs may still do so
+        true => database.projects.remove(&key).is_some(),
+        false => action.apply(database.projects.entry(key.clone())).is_ok(),
+      };
+    if !applied {
+      database.skipped += 1;
+      continue;
+    }
+    if starts || stops || cancels || deletes {
+      open.retain(|(running, _)| running != &key);
+    }
+    if starts {
+      open.push((key.clone(), skipped));
+    }
+    if skipped {
+      continue;
+    }
+    if stops && ended_before_window(&database.load, &mut database.projects, &key) {
+      continue;
+    }
+    if stops {
+      database.last_stopped = Some(key.clone());
+    } else if deletes && database.last_stopped.as_ref() == Some(&key) {
+      database.last_stopped = None;
+    }
+    if let Load::Headers = database.load {
+      if let Some(project) = database.projects.get_mut(&key) {
+        project.forget_history();
+      }
+    }
+  }
+  database.running = open
+    .into_iter()
+    .filter(|(_, skipped)| !skipped)
+    .map(|(key, _)| key)
+    .collect();
+  Ok(())
+}
+
+/// Drops the record `key` just stopped if it ended before the window loaded, as it then started
+/// before it too. Returns whether it was dropped.
+fn ended_before_window(
+  load: &Load,
+  projects: &mut BTreeMap<ProjectKey, Project>,
+  key: &ProjectKey,
+) -> bool {
+  let window = match load {
+    Load::Within(window) => window,
+    _ => return false,
+  };
+  let project = match projects.get_mut(key) {
+    Some(project) => project,
+    None => return false,
+  };
+  match project.records().last().map(|r| (r.start(), r.end())) {
+    Some((start, Some(end))) if end.timestamp() < *window.start() => {
+      project.remove_record(start);
+      true
+    }
+    _ => false,
+  }
+}
+
+fn record_start(key: &ProjectKey, record: &Record) -> Action {
+  let start = record.start();
+  Action::RecordStart {
+    key: key.clone(),
+    ts: start.timestamp(),
+    tz: start.offset().utc_minus_local(),
+  }
+}
+
+fn label(key: &ProjectKey, record: &Record) -> Action {
+  Action::RecordLabel {
+    key: key.clone(),
+    billable: record.is_billable(),
+    tags: record.tags().to_vec(),
+  }
+}
+
+fn notes<'a>(key: &'a ProjectKey, record: &'a Record) -> impl Iterator<Item = Action> + 'a {
+  record.notes().iter().map(move |note| Action::RecordNote {
+    key: key.clone(),
+    note: note.clone(),
+  })
+}
+
+fn session(Session { name, ts, tz, end }: Session) -> (String, Record) {
+  let mut record = Record::started_on(instant(ts, tz));
+  if let Some((ts, tz)) = end {
+    let _ = record.end_at(instant(ts, tz));
+  }
+  (name, record)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::core::{Labels, Record};
+  use crate::db::database::{
+    AmendError, Amendment, ArchiveError, BackdateError, Database, DeleteError, FavoriteError, Load,
+    FAVORITE_SLOTS,
+  };
+  use chrono::{DateTime, FixedOffset, Local, NaiveDate, TimeZone};
+  use std::cell::{Cell, RefCell};
+  use std::env;
+  use std::fs::{create_dir, remove_dir_all};
+
+  #[test]
+  fn test_repeated_start_is_a_noop() {
+    let location = env::temp_dir().join("timeknightTest_repeated_start_is_a_noop");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("hotkey".to_string()).unwrap();
+      database
+        .start_on("hotkey".to_string(), false, Labels::default())
+        .unwrap();
+      database
+        .start_on("Hotkey".to_string(), false, Labels::default())
+        .unwrap();
+      assert_eq!(database.project("hotkey").unwrap().records().count(), 1);
+      assert_eq!(database.storage.replay_actions().count(), 2);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_cancelled_records_are_gone_for_good() {
+    let location = env::temp_dir().join("timeknightTest_cancelled_records_are_gone_for_good");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Oncall".to_string()).unwrap();
+      database.add_project("Review".to_string()).unwrap();
+      database
+        .start_on("oncall".to_string(), false, Labels::default())
+        .unwrap();
+      database.stop().unwrap();
+      database
+        .start_on("oncall".to_string(), true, Labels::default())
+        .unwrap();
+      database
+        .start_on("review".to_string(), true, Labels::default())
+        .unwrap();
+      assert!(database.cancel(None).unwrap().is_on_going());
+      assert!(database.cancel(Some("review")).is_err());
+      assert_eq!(database.current_project().unwrap().name(), "Oncall");
+    }
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      assert_eq!(database.project("review").unwrap().records().count(), 0);
+      assert_eq!(database.current_project().unwrap().name(), "Oncall");
+      database.cancel(Some("oncall")).unwrap();
+      assert!(database.current_project().is_none());
+      assert!(database.cancel(None).is_err());
+    }
+    {
+      let database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      assert_eq!(database.project("oncall").unwrap().records().count(), 1);
+      assert!(database.running_projects().is_empty());
+      assert_eq!(database.skipped, 0);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_starts_as_of_when_tracking_was_forgotten() {
+    let location = env::temp_dir().join("timeknightTest_starts_as_of_when_tracking_was_forgotten");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    let now = Local.timestamp(Local::now().timestamp(), 0);
+    let ago = |minutes| DateTime::<FixedOffset>::from(now - chrono::Duration::minutes(minutes));
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Oncall".to_string()).unwrap();
+      database.add_project("Review".to_string()).unwrap();
+      let no_labels = Labels::default;
+      database
+        .start_at("oncall".to_string(), false, no_labels(), ago(60))
+        .unwrap();
+      assert_eq!(
+        database
+          .start_at("review".to_string(), false, no_labels(), ago(90))
+          .err(),
+        Some(BackdateError::BeforeStart("Oncall".to_string()))
+      );
+      database
+        .start_at("review".to_string(), false, no_labels(), ago(30))
+        .unwrap();
+      assert_eq!(
+        database
+          .start_at("oncall".to_string(), false, no_labels(), ago(-5))
+          .err(),
+        Some(BackdateError::Future)
+      );
+      database.stop().unwrap();
+      assert_eq!(
+        database
+          .start_at("oncall".to_string(), true, no_labels(), ago(45))
+          .err(),
+        Some(BackdateError::Overlapping("Oncall".to_string()))
+      );
+      // Review stopped since, but concurrent starts only mind their own records
+      database
+        .start_at("oncall".to_string(), true, no_labels(), ago(20))
+        .unwrap();
+    }
+    {
+      let database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      let oncall: Vec<_> = database.project("oncall").unwrap().records().collect();
+      assert_eq!(oncall.len(), 2);
+      assert_eq!(oncall[0].start(), ago(60));
+      assert_eq!(oncall[0].end(), Some(ago(30)));
+      assert_eq!(oncall[1].start(), ago(20));
+      assert_eq!(database.skipped, 0);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_stops_as_of_when_the_work_ended() {
+    let location = env::temp_dir().join("timeknightTest_stops_as_of_when_the_work_ended");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    let now = Local.timestamp(Local::now().timestamp(), 0);
+    let ago = |minutes| DateTime::<FixedOffset>::from(now - chrono::Duration::minutes(minutes));
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Oncall".to_string()).unwrap();
+      database.add_project("Review".to_string()).unwrap();
+      database
+        .start_at("oncall".to_string(), true, Labels::default(), ago(60))
+        .unwrap();
+      database
+        .start_at("review".to_string(), true, Labels::default(), ago(30))
+        .unwrap();
+      assert_eq!(
+        database.stop_as_of(Some("Revue"), ago(10), None).err(),
+        Some(BackdateError::UnknownProject("Revue".to_string()))
+      );
+      assert_eq!(
+        database
+          .stop_as_of(None, ago(45), Some("wrapped up".to_string()))
+          .err(),
+        Some(BackdateError::BeforeStart("Review".to_string()))
+      );
+      assert_eq!(
+        database.stop_as_of(None, ago(-5), None).err(),
+        Some(BackdateError::Future)
+      );
+      let stopped = database.stop_as_of(Some("oncall"), ago(45), None).unwrap();
+      assert_eq!(stopped[0].records().last().unwrap().end(), Some(ago(45)));
+      assert_eq!(
+        database.stop_as_of(Some("oncall"), ago(10), None).err(),
+        Some(BackdateError::NotRunning("oncall".to_string()))
+      );
+      let stopped = database.stop_as_of(None, ago(10), Some("merged".to_string()));
+      assert_eq!(stopped.unwrap().len(), 1);
+    }
+    {
+      let database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      let review = database
+        .project("review")
+        .unwrap()
+        .records()
+        .last()
+        .unwrap();
+      assert_eq!(review.end(), Some(ago(10)));
+      // Failed stops leave no note behind
+      assert_eq!(review.notes(), ["merged"]);
+      assert!(database.running_projects().is_empty());
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_hard_stops_mark_what_they_stopped() {
+    let location = env::temp_dir().join("timeknightTest_hard_stops_mark_what_they_stopped");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    let now = Local.timestamp(Local::now().timestamp(), 0);
+    let ago = |minutes| DateTime::<FixedOffset>::from(now - chrono::Duration::minutes(minutes));
+    let at = ago(60).time();
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Oncall".to_string()).unwrap();
+      database.add_project("Review".to_string()).unwrap();
+      database
+        .start_at("oncall".to_string(), true, Labels::default(), ago(180))
+        .unwrap();
+      database
+        .start_at("review".to_string(), true, Labels::default(), ago(30))
+        .unwrap();
+      let stopped = database.hard_stop(at).unwrap();
+      assert_eq!(stopped.len(), 1);
+      assert_eq!(stopped[0].records().last().unwrap().end(), Some(ago(60)));
+    }
+    {
+      let database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      let oncall = database
+        .project("oncall")
+        .unwrap()
+        .records()
+        .last()
+        .unwrap();
+      assert_eq!(oncall.end(), Some(ago(60)));
+      assert!(oncall.is_hard_stopped());
+      assert!(oncall.notes().is_empty());
+      assert_eq!(database.running_projects().len(), 1);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_adds_records_tracked_elsewhere() {
+    let location = env::temp_dir().join("timeknightTest_adds_records_tracked_elsewhere");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    let now = Local.timestamp(Local::now().timestamp(), 0);
+    let ago = |minutes| DateTime::<FixedOffset>::from(now - chrono::Duration::minutes(minutes));
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Oncall".to_string()).unwrap();
+      database.add_project("Review".to_string()).unwrap();
+      database
+        .start_at("oncall".to_string(), false, Labels::default(), ago(60))
+        .unwrap();
+      database.stop_as_of(None, ago(30), None).unwrap();
+      database
+        .start_at("review".to_string(), false, Labels::default(), ago(10))
+        .unwrap();
+      assert_eq!(
+        database
+          .add_record("Revue", (ago(50), ago(40)), false)
+          .err(),
+        Some(BackdateError::UnknownProject("Revue".to_string()))
+      );
+      assert_eq!(
+        database
+          .add_record("review", (ago(40), ago(50)), false)
+          .err(),
+        Some(BackdateError::BeforeStart("review".to_string()))
+      );
+      assert_eq!(
+        database
+          .add_record("review", (ago(5), ago(-5)), false)
+          .err(),
+        Some(BackdateError::Future)
+      );
+      assert_eq!(
+        database
+          .add_record("review", (ago(50), ago(40)), false)
+          .err(),
+        Some(BackdateError::Overlapping("Oncall".to_string()))
+      );
+      assert_eq!(
+        database
+          .add_record("oncall", (ago(20), ago(5)), false)
+          .err(),
+        Some(BackdateError::Overlapping("Review".to_string()))
+      );
+      assert_eq!(
+        database
+          .add_record("oncall", (ago(40), ago(20)), true)
+          .err(),
+        Some(BackdateError::Overlapping("Oncall".to_string()))
+      );
+      database
+        .add_record("oncall", (ago(20), ago(5)), true)
+        .unwrap();
+      database
+        .add_record("review", (ago(50), ago(40)), true)
+        .unwrap();
+      database
+        .add_record("review", (ago(120), ago(90)), false)
+        .unwrap();
+    }
+    {
+      let database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      assert_eq!(database.skipped(), 0);
+      let review: Vec<_> = database.project("review").unwrap().records().collect();
+      assert_eq!(review.len(), 3);
+      assert_eq!(review[0].end(), Some(ago(90)));
+      assert_eq!(review[1].start(), ago(50));
+      assert!(review[2].is_on_going());
+      let oncall = database.project("oncall").unwrap().records().count();
+      assert_eq!(oncall, 2);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_adds_the_projects_missing_at_once() {
+    let location = env::temp_dir().join("timeknightTest_adds_the_projects_missing_at_once");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Backend".to_string()).unwrap();
+      let names = ["backend", "Frontend", "Ops", "ops"].map(String::from);
+      let created = database.add_projects(names.to_vec()).unwrap();
+      assert_eq!(created, vec!["Frontend", "Ops"]);
+    }
+    {
+      let database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      let names: Vec<&str> = database.list_projects().iter().map(|p| p.name()).collect();
+      assert_eq!(names, vec!["Backend", "Frontend", "Ops"]);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_notes_stay_on_their_record() {
+    let location = env::temp_dir().join("timeknightTest_notes_stay_on_their_record");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    let now = Local.timestamp(Local::now().timestamp(), 0);
+    let ago = |minutes| DateTime::<FixedOffset>::from(now - chrono::Duration::minutes(minutes));
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Oncall".to_string()).unwrap();
+      database.add_project("Review".to_string()).unwrap();
+      assert!(database.note(None, "paging".to_string()).is_err());
+      database
+        .add_record("oncall", (ago(120), ago(100)), false)
+        .unwrap();
+      database
+        .start_at("oncall".to_string(), false, Labels::default(), ago(60))
+        .unwrap();
+      database.note(None, "paged".to_string()).unwrap();
+      database
+        .start_at("review".to_string(), true, Labels::default(), ago(30))
+        .unwrap();
+      database.note(Some("oncall"), "fixed".to_string()).unwrap();
+      database.note(None, "reading".to_string()).unwrap();
+      database.stop_on("oncall").unwrap();
+      assert!(database.note(Some("oncall"), "late".to_string()).is_err());
+      // Rewrites the WAL from the records left
+      assert_eq!(database.prune(ago(90), false).unwrap(), 1);
+    }
+    {
+      // Rewriting from a partial history would lose the rest of it
+      let mut database = Database::open(location.as_path(), Load::Headers).expect("Failed opening");
+      assert!(database.prune(ago(10), false).is_err());
+    }
+    {
+      let database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      let notes = |name| {
+        database
+          .project(name)
+          .unwrap()
+          .records()
+          .last()
+          .unwrap()
+          .notes()
+      };
+      assert_eq!(notes("oncall"), ["paged", "fixed"]);
+      assert_eq!(notes("review"), ["reading"]);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_reads_again_across_rewrites() {
+    let location = env::temp_dir().join("timeknightTest_reads_again_across_rewrites");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    let now = Local.timestamp(Local::now().timestamp(), 0);
+    let ago = |minutes| DateTime::<FixedOffset>::from(now - chrono::Duration::minutes(minutes));
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Oncall".to_string()).unwrap();
+      database
+        .add_record("oncall", (ago(120), ago(100)), false)
+        .unwrap();
+      let writer = RefCell::new(database);
+      let attempts = Cell::new(0);
+      let records = Database::consistently(location.as_path(), || {
+        attempts.set(attempts.get() + 1);
+        let reader = Database::open_read_only(location.as_path(), Load::Full)?;
+        let records = reader.project("oncall").unwrap().records().len();
+        // Only replays the old WAL, that's pruned under it before being done
+        if attempts.get() == 1 {
+          assert_eq!(writer.borrow_mut().prune(ago(90), false).unwrap(), 1);
+        }
+        Ok(records)
+      });
+      assert_eq!(records, Ok(0));
+      assert_eq!(attempts.get(), 2);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_archived_projects_keep_their_history() {
+    let location = env::temp_dir().join("timeknightTest_archived_projects_keep_their_history");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    let now = Local.timestamp(Local::now().timestamp(), 0);
+    let ago = |minutes| DateTime::<FixedOffset>::from(now - chrono::Duration::minutes(minutes));
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Oncall".to_string()).unwrap();
+      database
+        .add_record("oncall", (ago(120), ago(100)), false)
+        .unwrap();
+      database
+        .start_at("oncall".to_string(), false, Labels::default(), ago(60))
+        .unwrap();
+      assert_eq!(
+        database.archive("oncall", true).err(),
+        Some(ArchiveError::Running("Oncall".to_string()))
+      );
+      database.stop().unwrap();
+      assert!(database.archive("oncall", true).unwrap().archived());
+      assert_eq!(
+        database.archive("oncall", true).err(),
+        Some(ArchiveError::Archived("Oncall".to_string()))
+      );
+      assert!(database
+        .start_on("oncall".to_string(), false, Labels::default())
+        .is_err());
+      // Rewrites the WAL from the records left
+      assert_eq!(database.prune(ago(110), false).unwrap(), 1);
+    }
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      assert_eq!(database.skipped(), 0);
+      let project = database.project("oncall").unwrap();
+      assert!(project.archived());
+      assert_eq!(project.records().len(), 1);
+      assert!(!database.archive("oncall", false).unwrap().archived());
+      database
+        .start_on("oncall".to_string(), false, Labels::default())
+        .unwrap();
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_tells_why_projects_cant_be_deleted() {
+    let location = env::temp_dir().join("timeknightTest_tells_why_projects_cant_be_deleted");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Oncall".to_string()).unwrap();
+      database
+        .start_on("oncall".to_string(), false, Labels::default())
+        .unwrap();
+      assert_eq!(
+        database.remove_project("oncall".to_string(), false).err(),
+        Some(DeleteError::Running("Oncall".to_string()))
+      );
+      assert_eq!(
+        database.remove_project("Oncal".to_string(), false).err(),
+        Some(DeleteError::UnknownProject("Oncal".to_string()))
+      );
+      database.stop().unwrap();
+      assert!(database.remove_project("oncall".to_string(), false).is_ok());
+      assert!(database.project("oncall").is_none());
+      database.add_project("Review".to_string()).unwrap();
+      database
+        .start_on("review".to_string(), false, Labels::default())
+        .unwrap();
+      let review = database.remove_project("review".to_string(), true).unwrap();
+      assert!(!review.in_flight());
+      assert!(database.current_project().is_none());
+      assert!(database.last_stopped().is_none());
+    }
+    {
+      let database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      assert!(database.list_projects().is_empty());
+      assert_eq!(database.skipped, 0);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_new_records_get_project_defaults() {
+    let location = env::temp_dir().join("timeknightTest_new_records_get_project_defaults");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    let internal = Labels {
+      billable: Some(false),
+      tags: Some(vec!["internal".to_string()]),
+    };
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("standup".to_string()).unwrap();
+      database.add_project("review".to_string()).unwrap();
+      database.set_defaults("Standup", internal.clone());
+      database.set_defaults("review", internal);
+      database
+        .start_on("standup".to_string(), true, Labels::default())
+        .unwrap();
+      let billed = Labels {
+        billable: Some(true),
+        tags: None,
+      };
+      database
+        .start_on("review".to_string(), true, billed)
+        .unwrap();
+    }
+    {
+      let database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      let standup = database
+        .project("standup")
+        .unwrap()
+        .records()
+        .last()
+        .unwrap();
+      assert!(!standup.is_billable());
+      assert_eq!(standup.tags(), ["internal"]);
+      let review = database
+        .project("review")
+        .unwrap()
+        .records()
+        .last()
+        .unwrap();
+      assert!(review.is_billable());
+      assert_eq!(review.tags(), ["internal"]);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_importing_again_adds_nothing() {
+    let location = env::temp_dir().join("timeknightTest_importing_again_adds_nothing");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let at = |rfc3339| DateTime::parse_from_rfc3339(rfc3339).unwrap();
+      let record = |start, end| {
+        let mut record = Record::started_on(at(start));
+        record.end_at(at(end)).unwrap();
+        record
+      };
+      let records = || {
+        vec![
+          (
+            "Backend".to_string(),
+            record("2022-03-14T09:00:00Z", "2022-03-14T10:00:00Z"),
+          ),
+          (
+            "backend".to_string(),
+            record("2022-03-14T09:00:00Z", "2022-03-14T10:00:00Z"),
+          ),
+          (
+            "backend".to_string(),
+            record("2022-03-14T09:30:00Z", "2022-03-14T11:00:00Z"),
+          ),
+          (
+            "Frontend".to_string(),
+            Record::started_on(at("2022-03-14T12:00:00Z")),
+          ),
+        ]
+      };
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      let imported = database.import(records()).unwrap();
+      assert_eq!(
+        (imported.added, imported.created, imported.duplicates),
+        (1, 1, 1)
+      );
+      assert_eq!((imported.overlapping, imported.in_flight), (1, 1));
+      drop(database);
+
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      let imported = database.import(records()).unwrap();
+      assert_eq!((imported.added, imported.duplicates), (0, 2));
+      assert_eq!(database.project("backend").unwrap().records().count(), 1);
+
+      let unprintable = vec![(
+        "Ops\n".to_string(),
+        record("2022-03-15T09:00:00Z", "2022-03-15T10:00:00Z"),
+      )];
+      assert!(matches!(
+        database.import(unprintable),
+        Err(AmendError::Unprintable)
+      ));
+      assert!(database.project("ops\n").is_none());
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_amendments_note_records() {
+    let location = env::temp_dir().join("timeknightTest_amendments_note_records");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    let now = Local.timestamp(Local::now().timestamp(), 0);
+    let ago = |minutes| DateTime::<FixedOffset>::from(now - chrono::Duration::minutes(minutes));
+    let note = |note: &str| Amendment::Note {
+      project: "oncall".to_string(),
+      start: ago(120),
+      note: note.to_string(),
+    };
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Oncall".to_string()).unwrap();
+      database
+        .add_record("oncall", (ago(120), ago(60)), false)
+        .unwrap();
+      assert!(matches!(
+        database.amend(vec![note("paged\tfixed")]),
+        Err(AmendError::Unprintable)
+      ));
+      assert_eq!(database.amend(vec![note("paged")]).unwrap(), 1);
+    }
+    {
+      let database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      let oncall = database.project("oncall").unwrap();
+      assert_eq!(oncall.records().last().unwrap().notes(), ["paged"]);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_favorites_fit_their_slots() {
+    let location = env::temp_dir().join("timeknightTest_favorites_fit_their_slots");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      for slot in 1..=FAVORITE_SLOTS + 1 {
+        database.add_project(format!("Project{}", slot)).unwrap();
+      }
+      for slot in 1..=FAVORITE_SLOTS {
+        assert_eq!(database.add_favorite(&format!("project{}", slot)), Ok(slot));
+      }
+      assert_eq!(database.add_favorite("project1"), Ok(1));
+      let last = format!("project{}", FAVORITE_SLOTS + 1);
+      assert_eq!(database.add_favorite(&last), Err(FavoriteError::Full));
+      assert_eq!(
+        database.add_favorite("nope"),
+        Err(FavoriteError::UnknownProject("nope".to_string()))
+      );
+      database.remove_favorite("project1").unwrap();
+      assert_eq!(database.add_favorite(&last), Ok(FAVORITE_SLOTS));
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_windows_include_records_running_into_them() {
+    let location = env::temp_dir().join("timeknightTest_windows_include_records_running_into_them");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    let at = |rfc3339| DateTime::parse_from_rfc3339(rfc3339).unwrap();
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Backend".to_string()).unwrap();
+      database.add_project("Nightly".to_string()).unwrap();
+      database
+        .add_record(
+          "backend",
+          (at("2022-03-01T09:00:00Z"), at("2022-03-01T10:00:00Z")),
+          false,
+        )
+        .unwrap();
+      database
+        .start_at(
+          "backend".to_string(),
+          false,
+          Labels::default(),
+          at("2022-03-10T09:00:00Z"),
+        )
+        .unwrap();
+      database
+        .start_at(
+          "nightly".to_string(),
+          true,
+          Labels::default(),
+          at("2022-03-10T22:00:00Z"),
+        )
+        .unwrap();
+      database
+        .stop_as_of(Some("backend"), at("2022-03-14T12:00:00Z"), None)
+        .unwrap();
     }
+    let day = NaiveDate::from_ymd(2022, 3, 14);
+    let records = Database::records(location.as_path(), day..=day).unwrap();
+    let starts: Vec<(&str, DateTime<FixedOffset>)> = records
+      .iter()
+      .map(|(name, record)| (name.as_str(), record.start()))
+      .collect();
+    assert_eq!(
+      starts,
+      [
+        ("Backend", at("2022-03-10T09:00:00Z")),
+        ("Nightly", at("2022-03-10T22:00:00Z")),
+      ]
+    );
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
   }
-  Ok(database)
 }
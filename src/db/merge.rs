@@ -0,0 +1,191 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::db::storage::Action;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// Follows the device markers of a log, numbering every other entry. Devices continue the
+/// sequence of whatever they've seen last, so the numbers order entries causally across copies of
+/// the log, whatever their wall-clocks say.
+#[derive(Debug, Default)]
+pub struct Sequencer {
+  device: Option<String>,
+  next: u64,
+}
+
+impl Sequencer {
+  /// The sequence number and device of `action`, or `None` for markers, which only move the
+  /// sequencer along
+  pub fn observe(&mut self, action: &Action) -> Option<(u64, Option<String>)> {
+    match action {
+      Action::Device { id, seq } => {
+        self.device = Some(id.clone());
+        self.next = *seq;
+        None
+      }
+      // Seals and groups are only ever about the copy of the log they're in
+      Action::Seal { .. } | Action::Begin { .. } => None,
+      _ => {
+        self.next += 1;
+        Some((self.next - 1, self.device.clone()))
+      }
+    }
+  }
+
+  pub fn device(&self) -> Option<&str> {
+    self.device.as_deref()
+  }
+
+  /// The sequence number the next entry gets
+  pub fn next(&self) -> u64 {
+    self.next
+  }
+}
+
+#[derive(Debug)]
+pub enum MergeError {
+  /// The other copy couldn't be read
+  Unreadable,
+  /// Both copies hold different entries under the same sequence number and device
+  Conflict(String),
+  /// The merged log wouldn't replay, every problem as reported by `verify`
+  Problems(Vec<String>),
+  /// The merged log couldn't be written
+  Storage,
+}
+
+impl Display for MergeError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      MergeError::Unreadable => write!(f, "the log couldn't be read"),
+      MergeError::Conflict(conflict) => write!(f, "{}", conflict),
+      MergeError::Problems(problems) => write!(f, "{}", problems.join(", ")),
+      MergeError::Storage => write!(f, "the merged log couldn't be written"),
+    }
+  }
+}
+
+/// Merges copies of the same log, which may have diverged, into one: entries are ordered by their
+/// sequence number, then device, those present in more than one copy are kept once.
+pub fn merge(logs: Vec<Vec<Action>>) -> Result<Vec<Action>, MergeError> {
+  let mut entries: BTreeMap<(u64, Option<String>), Action> = BTreeMap::new();
+  for log in logs {
+    let mut sequencer = Sequencer::default();
+    for action in log {
+      if let Some(id) = sequencer.observe(&action) {
+        match entries.get(&id) {
+          Some(existing) if Vec::<u8>::from(existing) != Vec::<u8>::from(&action) => {
+            return Err(MergeError::Conflict(format!(
+              "entry {} from {} differs between copies",
+              id.0,
+              id.1.as_deref().unwrap_or("an unknown device"),
+            )));
+          }
+          Some(_) => {}
+          None => {
+            entries.insert(id, action);
+          }
+        }
+      }
+    }
+  }
+
+  let mut merged = Vec::with_capacity(entries.len());
+  let mut sequencer = Sequencer::default();
+  for ((seq, device), action) in entries {
+    if seq != sequencer.next() || device.as_deref() != sequencer.device() {
+      match device {
+        Some(id) => {
+          let marker = Action::Device { id, seq };
+          sequencer.observe(&marker);
+          merged.push(marker);
+        }
+        // Only ever found at the very start of logs, from before entries were attributed
+        None => {
+          return Err(MergeError::Conflict(format!(
+            "entry {} isn't attributed to any device and can't be ordered",
+            seq
+          )))
+        }
+      }
+    }
+    sequencer.observe(&action);
+    merged.push(action);
+  }
+  Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::db::merge::{merge, MergeError};
+  use crate::db::storage::Action;
+
+  fn add(name: &str) -> Action {
+    Action::ProjectAdd {
+      name: name.to_string(),
+    }
+  }
+
+  fn device(id: &str, seq: u64) -> Action {
+    Action::Device {
+      id: id.to_string(),
+      seq,
+    }
+  }
+
+  fn names(log: &[Action]) -> Vec<String> {
+    log
+      .iter()
+      .map(|action| match action {
+        Action::ProjectAdd { name } => name.clone(),
+        Action::Device { id, seq } => format!("{}@{}", id, seq),
+        _ => unreachable!("Only adds and markers"),
+      })
+      .collect()
+  }
+
+  #[test]
+  fn diverged_copies_merge_deterministically() {
+    let common = || vec![device("laptop", 0), add("a"), add("b")];
+    let mut laptop = common();
+    laptop.extend([add("c"), add("d")]);
+    let mut desktop = common();
+    desktop.extend([device("desktop", 2), add("e")]);
+
+    let merged = merge(vec![laptop, desktop]).expect("No conflict");
+    assert_eq!(
+      names(&merged),
+      vec!["laptop@0", "a", "b", "desktop@2", "e", "laptop@2", "c", "d"],
+    );
+    let mut laptop = common();
+    laptop.extend([add("c"), add("d")]);
+    let mut desktop = common();
+    desktop.extend([device("desktop", 2), add("e")]);
+    let swapped = merge(vec![desktop, laptop]).expect("No conflict");
+    assert_eq!(names(&merged), names(&swapped));
+  }
+
+  #[test]
+  fn same_entry_differing_is_a_conflict() {
+    let laptop = vec![device("laptop", 0), add("a")];
+    let other = vec![device("laptop", 0), add("b")];
+    assert!(matches!(
+      merge(vec![laptop, other]),
+      Err(MergeError::Conflict(_))
+    ));
+  }
+}
@@ -15,6 +15,14 @@
  */
 
 mod database;
+mod merge;
 mod storage;
+mod verify;
 
-pub use database::Database;
+pub use database::{
+  AmendError, Amendment, ArchiveError, BackdateError, Database, DeleteError, FavoriteError,
+  Imported, Load, FAVORITE_SLOTS,
+};
+pub use merge::MergeError;
+pub use storage::{DryRun, ProjectTotals, WAL_FORMAT};
+pub use verify::{audit, verify, Audit, Verification};
@@ -14,60 +14,465 @@
  * limitations under the License.
  */
 
-use crate::core::{Project, Record};
-use crate::db::database::{ProjectKey, SomeDbError};
-use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use crate::core::{Context, Expense, Labels, Project, Record};
+use crate::db::database::ProjectKey;
+use crate::db::storage::chain::hex;
+use crate::zone::instant;
 use std::borrow::Cow;
 use std::collections::btree_map::Entry;
+use std::fmt::{Display, Formatter};
 
-#[derive(Debug)]
+/// The version of how entries are encoded, to be bumped along any change an older build couldn't
+/// replay, e.g. a new kind of entry
+pub const WAL_FORMAT: u32 = 4;
+
+/// An entry of the WAL. Times are instants, `ts` seconds since the epoch in UTC, along with the
+/// offset they were recorded at, `tz` seconds west of UTC, which [`instant`] reads them back from.
+#[derive(Clone, Debug)]
 pub enum Action {
-  ProjectAdd { name: String },
-  ProjectDel { key: ProjectKey },
-  RecordStart { key: ProjectKey, ts: i64, tz: i32 },
-  RecordStop { ts: i64, tz: i32 },
+  ProjectAdd {
+    name: String,
+  },
+  ProjectDel {
+    key: ProjectKey,
+  },
+  /// Archives `key`, or unarchives it
+  ProjectArchive {
+    key: ProjectKey,
+    archived: bool,
+  },
+  RecordStart {
+    key: ProjectKey,
+    ts: i64,
+    tz: i32,
+  },
+  /// Stops `key`, or the latest project started when there is none
+  RecordStop {
+    ts: i64,
+    tz: i32,
+    key: Option<ProjectKey>,
+  },
+  /// Adds a completed record to `key`, from `ts` to `end_ts`, wherever it falls in its history
+  RecordAdd {
+    key: ProjectKey,
+    ts: i64,
+    tz: i32,
+    end_ts: i64,
+    end_tz: i32,
+  },
+  /// Discards the record `key` has in flight, as if it was never started
+  RecordCancel {
+    key: ProjectKey,
+  },
+  /// Labels the record `key` has in flight
+  RecordLabel {
+    key: ProjectKey,
+    billable: bool,
+    tags: Vec<String>,
+  },
+  /// Notes where the record `key` has in flight was started from
+  RecordContext {
+    key: ProjectKey,
+    context: Context,
+  },
+  /// Notes what was being done on the record `key` has in flight
+  RecordNote {
+    key: ProjectKey,
+    note: String,
+  },
+  /// Marks the record `key` has in flight as stopped for running past the hard stop, along with
+  /// the stop that follows
+  RecordHardStop {
+    key: ProjectKey,
+  },
+  /// Money spent on `key`, in cents, at `ts`
+  Expense {
+    key: ProjectKey,
+    ts: i64,
+    tz: i32,
+    cents: i64,
+    description: String,
+  },
+  /// Attributes the entries that follow, up until the next one, to the device `id`, numbering them
+  /// from `seq` onwards
+  Device {
+    id: String,
+    seq: u64,
+  },
+  /// Seals the entries since the previous seal, see [`Chain`](crate::db::storage::Chain)
+  Seal {
+    hash: [u8; 32],
+  },
+  /// Groups the `count` entries that follow, which only ever replay all together
+  Begin {
+    count: u32,
+  },
+}
+
+/// Why an action can't be applied to the project it is about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidAction {
+  /// Adds a project that already exists
+  AlreadyExists(ProjectKey),
+  /// Is about a project that doesn't exist
+  UnknownProject(ProjectKey),
+  /// Deletes a project while it is running
+  DeletesRunning(ProjectKey),
+  /// Archives a project while it is running
+  ArchivesRunning(ProjectKey),
+  /// Starts a project that is archived
+  Archived(ProjectKey),
+  /// Starts a record before the project's latest one started
+  StartsBeforeLatest(ProjectKey),
+  /// Stops a project that isn't running
+  NotRunning(ProjectKey),
+  /// Stops a record before it started
+  StopsBeforeStart(ProjectKey),
+  /// Adds a record over another one of the project
+  Overlapping(ProjectKey),
+  /// Isn't about any project, e.g. attributes entries to a device
+  NotApplicable,
+  /// Holds text with control characters, e.g. a newline in a note, which the WAL's newline
+  /// delimited, tab separated entries can't hold
+  Unprintable,
+}
+
+impl Display for InvalidAction {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      InvalidAction::AlreadyExists(key) => write!(f, "project '{}' already exists", key),
+      InvalidAction::UnknownProject(key) => write!(f, "no project '{}'", key),
+      InvalidAction::DeletesRunning(key) | InvalidAction::ArchivesRunning(key) => {
+        write!(f, "'{}' is running", key)
+      }
+      InvalidAction::Archived(key) => write!(f, "'{}' is archived", key),
+      InvalidAction::StartsBeforeLatest(key) => {
+        write!(f, "'{}' would start before its latest record", key)
+      }
+      InvalidAction::NotRunning(key) => write!(f, "'{}' isn't running", key),
+      InvalidAction::StopsBeforeStart(key) => write!(f, "'{}' would stop before it started", key),
+      InvalidAction::Overlapping(key) => write!(f, "'{}' already has a record then", key),
+      InvalidAction::NotApplicable => write!(f, "not about any project"),
+      InvalidAction::Unprintable => write!(f, "holds control characters"),
+    }
+  }
+}
+
+impl std::error::Error for InvalidAction {}
+
+impl Display for Action {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    const AT: &str = "%Y-%m-%d %H:%M:%S %:z";
+    match self {
+      Action::ProjectAdd { name } => write!(f, "add project '{}'", name),
+      Action::ProjectDel { key } => write!(f, "delete project '{}'", key),
+      Action::ProjectArchive {
+        key,
+        archived: true,
+      } => write!(f, "archive project '{}'", key),
+      Action::ProjectArchive {
+        key,
+        archived: false,
+      } => write!(f, "unarchive project '{}'", key),
+      Action::RecordStart { key, ts, tz } => {
+        write!(f, "start '{}' at {}", key, instant(*ts, *tz).format(AT))
+      }
+      Action::RecordStop {
+        ts,
+        tz,
+        key: Some(key),
+      } => {
+        write!(f, "stop '{}' at {}", key, instant(*ts, *tz).format(AT))
+      }
+      Action::RecordStop { ts, tz, key: None } => {
+        write!(
+          f,
+          "stop the latest started at {}",
+          instant(*ts, *tz).format(AT)
+        )
+      }
+      Action::RecordCancel { key } => write!(f, "cancel what '{}' has in flight", key),
+      Action::RecordHardStop { key } => write!(f, "hard stop what '{}' has in flight", key),
+      Action::RecordAdd {
+        key,
+        ts,
+        tz,
+        end_ts,
+        end_tz,
+      } => write!(
+        f,
+        "add a record to '{}' from {} to {}",
+        key,
+        instant(*ts, *tz).format(AT),
+        instant(*end_ts, *end_tz).format(AT)
+      ),
+      Action::Expense {
+        key,
+        ts,
+        tz,
+        cents,
+        description,
+      } => write!(
+        f,
+        "spend {:.2} on '{}' at {}: {}",
+        *cents as f64 / 100.0,
+        key,
+        instant(*ts, *tz).format(AT),
+        description
+      ),
+      Action::RecordLabel {
+        key,
+        billable,
+        tags,
+      } => {
+        let billable = if *billable {
+          "billable"
+        } else {
+          "non-billable"
+        };
+        match tags.is_empty() {
+          true => write!(f, "label '{}' as {}", key, billable),
+          false => write!(f, "label '{}' as {}: {}", key, billable, tags.join(", ")),
+        }
+      }
+      Action::RecordNote { key, note } => write!(f, "note '{}' on '{}'", note, key),
+      Action::RecordContext { key, context } => {
+        let unknown = || "?".to_string();
+        write!(
+          f,
+          "note '{}' was started by {} on {}, branch {}, in {}",
+          key,
+          context.user.clone().unwrap_or_else(unknown),
+          context.host.clone().unwrap_or_else(unknown),
+          context.branch.clone().unwrap_or_else(unknown),
+          context.cwd.clone().unwrap_or_else(unknown),
+        )
+      }
+      Action::Device { id, seq } => write!(f, "attribute entries from #{} to {}", seq, id),
+      Action::Seal { hash } => write!(f, "seal the entries before as {}", hex(hash)),
+      Action::Begin { count } => write!(f, "group the {} entries after", count),
+    }
+  }
 }
 
 impl Action {
+  /// Checks that the action makes sense for `project`, the one it is about if it exists, before
+  /// it gets recorded or replayed
+  pub fn validate(&self, key: &ProjectKey, project: Option<&Project>) -> Result<(), InvalidAction> {
+    let latest = project.and_then(|p| p.records().last());
+    match self {
+      _ if !self.is_printable() => Err(InvalidAction::Unprintable),
+      Action::ProjectAdd { .. } if project.is_some() => {
+        Err(InvalidAction::AlreadyExists(key.clone()))
+      }
+      Action::ProjectAdd { .. } => Ok(()),
+      Action::Device { .. } | Action::Seal { .. } | Action::Begin { .. } => {
+        Err(InvalidAction::NotApplicable)
+      }
+      _ if project.is_none() => Err(InvalidAction::UnknownProject(key.clone())),
+      Action::ProjectDel { .. } if project.is_some_and(|p| p.in_flight()) => {
+        Err(InvalidAction::DeletesRunning(key.clone()))
+      }
+      Action::ProjectArchive { archived: true, .. } if project.is_some_and(|p| p.in_flight()) => {
+        Err(InvalidAction::ArchivesRunning(key.clone()))
+      }
+      Action::RecordStart { .. } if project.is_some_and(|p| p.archived()) => {
+        Err(InvalidAction::Archived(key.clone()))
+      }
+      Action::RecordStart { ts, .. } if latest.is_some_and(|r| *ts < r.start().timestamp()) => {
+        Err(InvalidAction::StartsBeforeLatest(key.clone()))
+      }
+      Action::RecordAdd { ts, end_ts, .. } if end_ts < ts => {
+        Err(InvalidAction::StopsBeforeStart(key.clone()))
+      }
+      Action::RecordAdd { ts, end_ts, .. }
+        if project.is_some_and(|p| {
+          p.records().any(|r| {
+            let end = r.end().map(|end| end.timestamp());
+            r.start().timestamp() < *end_ts && end.is_none_or(|end| end > *ts)
+          })
+        }) =>
+      {
+        Err(InvalidAction::Overlapping(key.clone()))
+      }
+      Action::RecordStop { ts, .. } => match latest.filter(|r| r.is_on_going()) {
+        None => Err(InvalidAction::NotRunning(key.clone())),
+        Some(r) if *ts < r.start().timestamp() => Err(InvalidAction::StopsBeforeStart(key.clone())),
+        Some(_) => Ok(()),
+      },
+      Action::RecordCancel { .. }
+      | Action::RecordLabel { .. }
+      | Action::RecordContext { .. }
+      | Action::RecordNote { .. }
+      | Action::RecordHardStop { .. }
+        if !project.is_some_and(|p| p.in_flight()) =>
+      {
+        Err(InvalidAction::NotRunning(key.clone()))
+      }
+      Action::ProjectDel { .. }
+      | Action::ProjectArchive { .. }
+      | Action::RecordStart { .. }
+      | Action::RecordAdd { .. }
+      | Action::RecordCancel { .. }
+      | Action::RecordLabel { .. }
+      | Action::RecordContext { .. }
+      | Action::RecordNote { .. }
+      | Action::RecordHardStop { .. }
+      | Action::Expense { .. } => Ok(()),
+    }
+  }
+
+  /// Applies the action to the project it is about, once [validated](Action::validate)
   pub fn apply<'a, 'b: 'a>(
     self,
     entry: Entry<'b, ProjectKey, Project>,
-  ) -> Result<Cow<'a, Project>, SomeDbError> {
+  ) -> Result<Cow<'a, Project>, InvalidAction> {
+    let project = match &entry {
+      Entry::Occupied(e) => Some(e.get()),
+      Entry::Vacant(_) => None,
+    };
+    self.validate(entry.key(), project)?;
+    match (self, entry) {
+      (Action::ProjectAdd { name }, Entry::Vacant(e)) => {
+        Ok(Cow::Borrowed(e.insert(Project::new(name))))
+      }
+      (Action::ProjectDel { .. }, Entry::Occupied(e)) => Ok(Cow::Owned(e.remove())),
+      (Action::ProjectArchive { archived, .. }, Entry::Occupied(mut e)) => {
+        e.get_mut().archive(archived);
+        Ok(Cow::Borrowed(e.into_mut()))
+      }
+      (Action::RecordStart { ts, tz, .. }, Entry::Occupied(mut e)) => {
+        e.get_mut()
+          .add_record(Record::started_on(instant(ts, tz)))
+          .map_err(|_| InvalidAction::StartsBeforeLatest(e.key().clone()))?;
+        Ok(Cow::Borrowed(e.into_mut()))
+      }
+      (Action::RecordStop { ts, tz, .. }, Entry::Occupied(mut e)) => {
+        e.get_mut()
+          .end_at(instant(ts, tz))
+          .map_err(|_| InvalidAction::StopsBeforeStart(e.key().clone()))?;
+        Ok(Cow::Borrowed(e.into_mut()))
+      }
+      (
+        Action::RecordAdd {
+          ts,
+          tz,
+          end_ts,
+          end_tz,
+          ..
+        },
+        Entry::Occupied(mut e),
+      ) => {
+        let mut record = Record::started_on(instant(ts, tz));
+        record
+          .end_at(instant(end_ts, end_tz))
+          .map_err(|_| InvalidAction::StopsBeforeStart(e.key().clone()))?;
+        e.get_mut()
+          .insert_record(record)
+          .map_err(|_| InvalidAction::Overlapping(e.key().clone()))?;
+        Ok(Cow::Borrowed(e.into_mut()))
+      }
+      (Action::RecordCancel { .. }, Entry::Occupied(mut e)) => {
+        e.get_mut().cancel();
+        Ok(Cow::Borrowed(e.into_mut()))
+      }
+      (Action::RecordLabel { billable, tags, .. }, Entry::Occupied(mut e)) => {
+        e.get_mut().label(&Labels {
+          billable: Some(billable),
+          tags: Some(tags),
+        });
+        Ok(Cow::Borrowed(e.into_mut()))
+      }
+      (Action::RecordContext { context, .. }, Entry::Occupied(mut e)) => {
+        e.get_mut().set_context(context);
+        Ok(Cow::Borrowed(e.into_mut()))
+      }
+      (Action::RecordNote { note, .. }, Entry::Occupied(mut e)) => {
+        e.get_mut().note(note);
+        Ok(Cow::Borrowed(e.into_mut()))
+      }
+      (Action::RecordHardStop { .. }, Entry::Occupied(mut e)) => {
+        e.get_mut().hard_stop();
+        Ok(Cow::Borrowed(e.into_mut()))
+      }
+      (
+        Action::Expense {
+          ts,
+          tz,
+          cents,
+          description,
+          ..
+        },
+        Entry::Occupied(mut e),
+      ) => {
+        e.get_mut()
+          .add_expense(Expense::new(instant(ts, tz), cents, description));
+        Ok(Cow::Borrowed(e.into_mut()))
+      }
+      _ => unreachable!("validated above"),
+    }
+  }
+
+  /// Whether none of the text the action holds, e.g. project names, tags or notes, has control
+  /// characters
+  pub fn is_printable(&self) -> bool {
+    let text: Vec<&str> = match self {
+      Action::ProjectAdd { name } => vec![name],
+      Action::RecordLabel { tags, .. } => tags.iter().map(String::as_str).collect(),
+      Action::RecordContext { context, .. } => {
+        [&context.host, &context.branch, &context.cwd, &context.user]
+          .into_iter()
+          .flatten()
+          .map(String::as_str)
+          .collect()
+      }
+      Action::RecordNote { note, .. } => vec![note],
+      Action::Expense { description, .. } => vec![description],
+      _ => Vec::new(),
+    };
+    !text.iter().any(|text| text.contains(char::is_control))
+  }
+
+  /// The project the action is about, if it names one
+  pub fn key(&self) -> Option<ProjectKey> {
     match self {
-      Action::ProjectAdd { name } => match entry {
-        Entry::Vacant(e) => Ok(Cow::Borrowed(e.insert(Project::new(name)))),
-        Entry::Occupied(_) => Err(SomeDbError),
-      },
-      Action::ProjectDel { key: _ } => match entry {
-        Entry::Occupied(e) => Ok(Cow::Owned(e.remove())),
-        Entry::Vacant(_) => Err(SomeDbError),
-      },
-      Action::RecordStart { key: _, ts, tz } => match entry {
-        Entry::Occupied(mut e) => {
-          let utc = Utc.timestamp(ts, 0);
-          let offset = FixedOffset::from_offset(&FixedOffset::west(tz));
-          let start: DateTime<FixedOffset> = utc.with_timezone(&offset);
-          e.get_mut()
-            .add_record(Record::started_on(start))
-            .expect("Replay start failed");
-          Ok(Cow::Borrowed(e.into_mut()))
-        }
-        Entry::Vacant(_) => Err(SomeDbError),
-      },
-      Action::RecordStop { ts, tz } => match entry {
-        Entry::Occupied(mut e) => {
-          let utc = Utc.timestamp(ts, 0);
-          let offset = FixedOffset::from_offset(&FixedOffset::west(tz));
-          let end: DateTime<FixedOffset> = utc.with_timezone(&offset);
-          e.get_mut().end_at(end).expect("Replay end failed");
-          Ok(Cow::Borrowed(e.into_mut()))
-        }
-        Entry::Vacant(_) => Err(SomeDbError),
-      },
+      Action::ProjectAdd { name } => Some(ProjectKey::new(name)),
+      Action::ProjectDel { key }
+      | Action::ProjectArchive { key, .. }
+      | Action::RecordStart { key, .. }
+      | Action::RecordAdd { key, .. }
+      | Action::RecordCancel { key }
+      | Action::RecordLabel { key, .. }
+      | Action::RecordContext { key, .. }
+      | Action::RecordNote { key, .. }
+      | Action::RecordHardStop { key }
+      | Action::Expense { key, .. } => Some(key.clone()),
+      Action::RecordStop { key, .. } => key.clone(),
+      Action::Device { .. } | Action::Seal { .. } | Action::Begin { .. } => None,
+    }
+  }
+
+  /// The minimal length of an entry starting with `tag`, delimiter excluded. Anything shorter means
+  /// the delimiter was actually part of the entry's binary payload.
+  pub fn min_len(tag: u8) -> usize {
+    match tag {
+      125 | 124 => 13,
+      123 => 9,
+      122 => 23,
+      121 => 4,
+      120 | 116 => 3,
+      119 => 33,
+      117 => 25,
+      115 => 5,
+      114 => 2,
+      _ => 1,
     }
   }
 
   pub fn from_bytes(data: &[u8]) -> Result<(Option<ProjectKey>, Action), ()> {
+    if data.is_empty() || data.len() < Self::min_len(data[0]) {
+      return Err(());
+    }
     match data[0] {
       127 => {
         let name = String::from_utf8_lossy(&data[1..]).to_string();
@@ -86,7 +491,123 @@ impl Action {
       124 => {
         let ts = i64::from_le_bytes(data[1..9].try_into().expect("Wrong math!"));
         let tz = i32::from_le_bytes(data[9..13].try_into().expect("Wrong math!"));
-        Ok((None, Action::RecordStop { ts, tz }))
+        let key = match data.len() {
+          13 => None,
+          _ => Some(ProjectKey::raw(
+            String::from_utf8_lossy(&data[13..]).to_string(),
+          )),
+        };
+        Ok((key.clone(), Action::RecordStop { ts, tz, key }))
+      }
+      117 => {
+        let ts = i64::from_le_bytes(data[1..9].try_into().expect("Wrong math!"));
+        let tz = i32::from_le_bytes(data[9..13].try_into().expect("Wrong math!"));
+        let end_ts = i64::from_le_bytes(data[13..21].try_into().expect("Wrong math!"));
+        let end_tz = i32::from_le_bytes(data[21..25].try_into().expect("Wrong math!"));
+        let key = ProjectKey::raw(String::from_utf8_lossy(&data[25..]).to_string());
+        let add = Action::RecordAdd {
+          key: key.clone(),
+          ts,
+          tz,
+          end_ts,
+          end_tz,
+        };
+        Ok((Some(key), add))
+      }
+      118 => {
+        let key = ProjectKey::raw(String::from_utf8_lossy(&data[1..]).to_string());
+        Ok((Some(key.clone()), Action::RecordCancel { key }))
+      }
+      113 => {
+        let key = ProjectKey::raw(String::from_utf8_lossy(&data[1..]).to_string());
+        Ok((Some(key.clone()), Action::RecordHardStop { key }))
+      }
+      114 => {
+        let archived = data[1] != 0;
+        let key = ProjectKey::raw(String::from_utf8_lossy(&data[2..]).to_string());
+        let archive = Action::ProjectArchive {
+          key: key.clone(),
+          archived,
+        };
+        Ok((Some(key), archive))
+      }
+      123 => {
+        let seq = u64::from_le_bytes(data[1..9].try_into().expect("Wrong math!"));
+        let id = String::from_utf8_lossy(&data[9..]).to_string();
+        Ok((None, Action::Device { id, seq }))
+      }
+      119 if data.len() == 33 => {
+        let hash = data[1..33].try_into().expect("Wrong math!");
+        Ok((None, Action::Seal { hash }))
+      }
+      115 if data.len() == 5 => {
+        let count = u32::from_le_bytes(data[1..5].try_into().expect("Wrong math!"));
+        Ok((None, Action::Begin { count }))
+      }
+      122 => {
+        let ts = i64::from_le_bytes(data[1..9].try_into().expect("Wrong math!"));
+        let tz = i32::from_le_bytes(data[9..13].try_into().expect("Wrong math!"));
+        let cents = i64::from_le_bytes(data[13..21].try_into().expect("Wrong math!"));
+        let key_len = u16::from_le_bytes(data[21..23].try_into().expect("Wrong math!")) as usize;
+        if data.len() < 23 + key_len {
+          return Err(());
+        }
+        let key = ProjectKey::raw(String::from_utf8_lossy(&data[23..23 + key_len]).to_string());
+        let description = String::from_utf8_lossy(&data[23 + key_len..]).to_string();
+        let expense = Action::Expense {
+          key: key.clone(),
+          ts,
+          tz,
+          cents,
+          description,
+        };
+        Ok((Some(key), expense))
+      }
+      121 => {
+        let billable = data[1] != 0;
+        let key_len = u16::from_le_bytes(data[2..4].try_into().expect("Wrong math!")) as usize;
+        if data.len() < 4 + key_len {
+          return Err(());
+        }
+        let key = ProjectKey::raw(String::from_utf8_lossy(&data[4..4 + key_len]).to_string());
+        let tags = String::from_utf8_lossy(&data[4 + key_len..])
+          .split('\t')
+          .filter(|tag| !tag.is_empty())
+          .map(str::to_string)
+          .collect();
+        let label = Action::RecordLabel {
+          key: key.clone(),
+          billable,
+          tags,
+        };
+        Ok((Some(key), label))
+      }
+      120 => {
+        let key_len = u16::from_le_bytes(data[1..3].try_into().expect("Wrong math!")) as usize;
+        if data.len() < 3 + key_len {
+          return Err(());
+        }
+        let key = ProjectKey::raw(String::from_utf8_lossy(&data[3..3 + key_len]).to_string());
+        let context = String::from_utf8_lossy(&data[3 + key_len..]).to_string();
+        let mut fields = context
+          .split('\0')
+          .map(|field| Some(field.to_string()).filter(|field| !field.is_empty()));
+        let context = Context {
+          host: fields.next().flatten(),
+          branch: fields.next().flatten(),
+          cwd: fields.next().flatten(),
+          user: fields.next().flatten(),
+        };
+        Ok((Some(key.clone()), Action::RecordContext { key, context }))
+      }
+      116 => {
+        let key_len = u16::from_le_bytes(data[1..3].try_into().expect("Wrong math!")) as usize;
+        if data.len() < 3 + key_len {
+          return Err(());
+        }
+        let key = ProjectKey::raw(String::from_utf8_lossy(&data[3..3 + key_len]).to_string());
+        let note = String::from_utf8_lossy(&data[3 + key_len..]).to_string();
+        Ok((Some(key.clone()), Action::RecordNote { key, note }))
       }
       _ => Err(()),
     }
@@ -112,6 +633,49 @@ impl From<&Action> for Vec<u8> {
         buffer.push(b'\n');
         buffer
       }
+      Action::ProjectArchive { key, archived } => {
+        let raw = key.as_bytes();
+        let mut buffer = Vec::with_capacity(raw.len() + 3);
+        buffer.push(114);
+        buffer.push(u8::from(*archived));
+        buffer.extend_from_slice(raw);
+        buffer.push(b'\n');
+        buffer
+      }
+      Action::RecordAdd {
+        key,
+        ts,
+        tz,
+        end_ts,
+        end_tz,
+      } => {
+        let raw = key.as_bytes();
+        let mut buffer = Vec::with_capacity(raw.len() + 26);
+        buffer.push(117);
+        buffer.extend_from_slice(&ts.to_le_bytes());
+        buffer.extend_from_slice(&tz.to_le_bytes());
+        buffer.extend_from_slice(&end_ts.to_le_bytes());
+        buffer.extend_from_slice(&end_tz.to_le_bytes());
+        buffer.extend_from_slice(raw);
+        buffer.push(b'\n');
+        buffer
+      }
+      Action::RecordCancel { key } => {
+        let raw = key.as_bytes();
+        let mut buffer = Vec::with_capacity(raw.len() + 2);
+        buffer.push(118);
+        buffer.extend_from_slice(raw);
+        buffer.push(b'\n');
+        buffer
+      }
+      Action::RecordHardStop { key } => {
+        let raw = key.as_bytes();
+        let mut buffer = Vec::with_capacity(raw.len() + 2);
+        buffer.push(113);
+        buffer.extend_from_slice(raw);
+        buffer.push(b'\n');
+        buffer
+      }
       Action::RecordStart { key, ts, tz } => {
         let raw = key.as_bytes();
         let mut buffer = Vec::with_capacity(raw.len() + 14);
@@ -122,11 +686,98 @@ impl From<&Action> for Vec<u8> {
         buffer.push(b'\n');
         buffer
       }
-      Action::RecordStop { ts, tz } => {
-        let mut buffer = Vec::with_capacity(14);
+      Action::Device { id, seq } => {
+        let raw = id.as_bytes();
+        let mut buffer = Vec::with_capacity(raw.len() + 10);
+        buffer.push(123);
+        buffer.extend_from_slice(&seq.to_le_bytes());
+        buffer.extend_from_slice(raw);
+        buffer.push(b'\n');
+        buffer
+      }
+      Action::Seal { hash } => {
+        let mut buffer = Vec::with_capacity(34);
+        buffer.push(119);
+        buffer.extend_from_slice(hash);
+        buffer.push(b'\n');
+        buffer
+      }
+      Action::Begin { count } => {
+        let mut buffer = Vec::with_capacity(6);
+        buffer.push(115);
+        buffer.extend_from_slice(&count.to_le_bytes());
+        buffer.push(b'\n');
+        buffer
+      }
+      // The binary fields all come first, within `min_len`, as they may contain delimiters
+      Action::Expense {
+        key,
+        ts,
+        tz,
+        cents,
+        description,
+      } => {
+        let raw = key.as_bytes();
+        let mut buffer = Vec::with_capacity(raw.len() + description.len() + 24);
+        buffer.push(122);
+        buffer.extend_from_slice(&ts.to_le_bytes());
+        buffer.extend_from_slice(&tz.to_le_bytes());
+        buffer.extend_from_slice(&cents.to_le_bytes());
+        buffer.extend_from_slice(&(raw.len() as u16).to_le_bytes());
+        buffer.extend_from_slice(raw);
+        buffer.extend_from_slice(description.as_bytes());
+        buffer.push(b'\n');
+        buffer
+      }
+      // Tags are tab separated, after the key
+      Action::RecordLabel {
+        key,
+        billable,
+        tags,
+      } => {
+        let raw = key.as_bytes();
+        let tags = tags.join("\t");
+        let mut buffer = Vec::with_capacity(raw.len() + tags.len() + 5);
+        buffer.push(121);
+        buffer.push(*billable as u8);
+        buffer.extend_from_slice(&(raw.len() as u16).to_le_bytes());
+        buffer.extend_from_slice(raw);
+        buffer.extend_from_slice(tags.as_bytes());
+        buffer.push(b'\n');
+        buffer
+      }
+      // Fields are NUL separated, after the key, as paths can't contain any. The user came last,
+      // entries without one only having three.
+      Action::RecordContext { key, context } => {
+        let raw = key.as_bytes();
+        let context = [&context.host, &context.branch, &context.cwd, &context.user]
+          .map(|field| field.as_deref().unwrap_or_default())
+          .join("\0");
+        let mut buffer = Vec::with_capacity(raw.len() + context.len() + 4);
+        buffer.push(120);
+        buffer.extend_from_slice(&(raw.len() as u16).to_le_bytes());
+        buffer.extend_from_slice(raw);
+        buffer.extend_from_slice(context.as_bytes());
+        buffer.push(b'\n');
+        buffer
+      }
+      Action::RecordNote { key, note } => {
+        let raw = key.as_bytes();
+        let mut buffer = Vec::with_capacity(raw.len() + note.len() + 4);
+        buffer.push(116);
+        buffer.extend_from_slice(&(raw.len() as u16).to_le_bytes());
+        buffer.extend_from_slice(raw);
+        buffer.extend_from_slice(note.as_bytes());
+        buffer.push(b'\n');
+        buffer
+      }
+      Action::RecordStop { ts, tz, key } => {
+        let raw = key.as_ref().map(|key| key.as_bytes()).unwrap_or_default();
+        let mut buffer = Vec::with_capacity(raw.len() + 14);
         buffer.push(124);
         buffer.extend_from_slice(&ts.to_le_bytes());
         buffer.extend_from_slice(&tz.to_le_bytes());
+        buffer.extend_from_slice(raw);
         buffer.push(b'\n');
         buffer
       }
@@ -136,9 +787,11 @@ impl From<&Action> for Vec<u8> {
 
 #[cfg(test)]
 mod tests {
+  use crate::core::{Context, Project, Record};
   use crate::db::database::ProjectKey;
-  use crate::db::storage::Action;
-  use chrono::DateTime;
+  use crate::db::storage::{Action, InvalidAction};
+  use chrono::{DateTime, TimeZone, Utc};
+  use std::collections::BTreeMap;
 
   #[test]
   fn record_start_serializes_alright() {
@@ -163,7 +816,297 @@ mod tests {
         assert_eq!(ts, 1648417054);
         assert_eq!(tz, 14400);
       }
-      _ => assert!(false),
+      _ => unreachable!("Expected a RecordStart"),
+    }
+  }
+
+  #[test]
+  fn record_stop_only_carries_a_key_when_needed() {
+    let keyless: Vec<u8> = (&Action::RecordStop {
+      ts: 1648417054,
+      tz: 14400,
+      key: None,
+    })
+      .into();
+    assert_eq!(keyless.len(), 14);
+    match Action::from_bytes(&keyless[..keyless.len() - 1]).unwrap() {
+      (None, Action::RecordStop { ts, key: None, .. }) => assert_eq!(ts, 1648417054),
+      _ => unreachable!("Expected a key-less RecordStop"),
+    }
+
+    let keyed: Vec<u8> = (&Action::RecordStop {
+      ts: 1648417054,
+      tz: 14400,
+      key: Some(ProjectKey::new("oncall")),
+    })
+      .into();
+    assert_eq!(keyed.len(), 20);
+    match Action::from_bytes(&keyed[..keyed.len() - 1]).unwrap() {
+      (Some(key), Action::RecordStop { key: Some(own), .. }) => {
+        assert_eq!(key, ProjectKey::new("oncall"));
+        assert_eq!(own, key);
+      }
+      _ => unreachable!("Expected a keyed RecordStop"),
+    }
+  }
+
+  #[test]
+  fn expense_roundtrips() {
+    let expense = Action::Expense {
+      key: ProjectKey::new("oncall"),
+      ts: 1648417054,
+      tz: 14400,
+      cents: 10 * 256 + 10,
+      description: "train ticket".to_string(),
+    };
+    let buffer: Vec<u8> = (&expense).into();
+    assert_eq!(buffer.len(), buffer.capacity());
+    match Action::from_bytes(&buffer[..buffer.len() - 1]).unwrap() {
+      (
+        Some(key),
+        Action::Expense {
+          cents, description, ..
+        },
+      ) => {
+        assert_eq!(key, ProjectKey::new("oncall"));
+        assert_eq!(cents, 2570);
+        assert_eq!(description, "train ticket");
+      }
+      _ => unreachable!("Expected an Expense"),
+    }
+  }
+
+  #[test]
+  fn hard_stops_roundtrip() {
+    let hard_stop = Action::RecordHardStop {
+      key: ProjectKey::new("oncall"),
+    };
+    let buffer: Vec<u8> = (&hard_stop).into();
+    assert_eq!(buffer.len(), buffer.capacity());
+    match Action::from_bytes(&buffer[..buffer.len() - 1]).unwrap() {
+      (Some(key), Action::RecordHardStop { .. }) => assert_eq!(key, ProjectKey::new("oncall")),
+      _ => unreachable!("Expected a RecordHardStop"),
+    }
+  }
+
+  #[test]
+  fn archives_roundtrip() {
+    for archived in [true, false] {
+      let archive = Action::ProjectArchive {
+        key: ProjectKey::new("oncall"),
+        archived,
+      };
+      let buffer: Vec<u8> = (&archive).into();
+      assert_eq!(buffer.len(), buffer.capacity());
+      match Action::from_bytes(&buffer[..buffer.len() - 1]).unwrap() {
+        (Some(key), Action::ProjectArchive { archived: read, .. }) => {
+          assert_eq!(key, ProjectKey::new("oncall"));
+          assert_eq!(read, archived);
+        }
+        _ => unreachable!("Expected a ProjectArchive"),
+      }
     }
   }
+
+  #[test]
+  fn label_roundtrips() {
+    let label = Action::RecordLabel {
+      key: ProjectKey::new("oncall"),
+      billable: false,
+      tags: vec!["internal".to_string(), "on call".to_string()],
+    };
+    let buffer: Vec<u8> = (&label).into();
+    assert_eq!(buffer.len(), buffer.capacity());
+    match Action::from_bytes(&buffer[..buffer.len() - 1]).unwrap() {
+      (Some(key), Action::RecordLabel { billable, tags, .. }) => {
+        assert_eq!(key, ProjectKey::new("oncall"));
+        assert!(!billable);
+        assert_eq!(tags, vec!["internal", "on call"]);
+      }
+      _ => unreachable!("Expected a RecordLabel"),
+    }
+  }
+
+  #[test]
+  fn context_roundtrips() {
+    let context = Context {
+      host: Some("laptop".to_string()),
+      branch: None,
+      cwd: Some("/home/me/src/backend".to_string()),
+      user: Some("alice".to_string()),
+    };
+    let action = Action::RecordContext {
+      key: ProjectKey::new("backend"),
+      context: context.clone(),
+    };
+    let buffer: Vec<u8> = (&action).into();
+    assert_eq!(buffer.len(), buffer.capacity());
+    match Action::from_bytes(&buffer[..buffer.len() - 1]).unwrap() {
+      (Some(key), Action::RecordContext { context: read, .. }) => {
+        assert_eq!(key, ProjectKey::new("backend"));
+        assert_eq!(read, context);
+      }
+      _ => unreachable!("Expected a RecordContext"),
+    }
+    // As noted before users were
+    let mut buffer = vec![120, 7, 0];
+    buffer.extend_from_slice(b"backendlaptop\0main\0/home/me");
+    match Action::from_bytes(&buffer).unwrap() {
+      (_, Action::RecordContext { context, .. }) => {
+        assert_eq!(context.cwd.as_deref(), Some("/home/me"));
+        assert_eq!(context.user, None);
+      }
+      _ => unreachable!("Expected a RecordContext"),
+    }
+  }
+
+  #[test]
+  fn notes_roundtrip() {
+    let action = Action::RecordNote {
+      key: ProjectKey::new("backend"),
+      note: "pairing with Bob, on the flaky test".to_string(),
+    };
+    let buffer: Vec<u8> = (&action).into();
+    assert_eq!(buffer.len(), buffer.capacity());
+    match Action::from_bytes(&buffer[..buffer.len() - 1]).unwrap() {
+      (Some(key), Action::RecordNote { note, .. }) => {
+        assert_eq!(key, ProjectKey::new("backend"));
+        assert_eq!(note, "pairing with Bob, on the flaky test");
+      }
+      _ => unreachable!("Expected a RecordNote"),
+    }
+  }
+
+  #[test]
+  fn added_records_roundtrip() {
+    let add = Action::RecordAdd {
+      key: ProjectKey::new("oncall"),
+      ts: 1648417054,
+      tz: 14400,
+      end_ts: 1648417054 + 10 * 256 + 10,
+      end_tz: 14400,
+    };
+    let buffer: Vec<u8> = (&add).into();
+    assert_eq!(buffer.len(), buffer.capacity());
+    match Action::from_bytes(&buffer[..buffer.len() - 1]).unwrap() {
+      (Some(key), Action::RecordAdd { ts, end_ts, .. }) => {
+        assert_eq!(key, ProjectKey::new("oncall"));
+        assert_eq!(end_ts - ts, 2570);
+      }
+      _ => unreachable!("Expected a RecordAdd"),
+    }
+  }
+
+  #[test]
+  fn validates_before_applying() {
+    let key = ProjectKey::new("a");
+    let start = |ts| Action::RecordStart {
+      key: key.clone(),
+      ts,
+      tz: 0,
+    };
+    let stop = |ts| Action::RecordStop {
+      ts,
+      tz: 0,
+      key: None,
+    };
+    let mut project = Project::new("a".to_string());
+    project
+      .add_record(Record::started_on(Utc.timestamp(20, 0).into()))
+      .unwrap();
+    let running = Some(&project);
+
+    assert_eq!(
+      start(10).validate(&key, None),
+      Err(InvalidAction::UnknownProject(key.clone()))
+    );
+    assert_eq!(
+      start(10).validate(&key, running),
+      Err(InvalidAction::StartsBeforeLatest(key.clone()))
+    );
+    assert_eq!(start(20).validate(&key, running), Ok(()));
+    assert_eq!(
+      stop(10).validate(&key, running),
+      Err(InvalidAction::StopsBeforeStart(key.clone()))
+    );
+    assert_eq!(stop(20).validate(&key, running), Ok(()));
+    assert_eq!(
+      Action::ProjectDel { key: key.clone() }.validate(&key, running),
+      Err(InvalidAction::DeletesRunning(key.clone()))
+    );
+    assert_eq!(
+      Action::ProjectAdd {
+        name: "A".to_string()
+      }
+      .validate(&key, running),
+      Err(InvalidAction::AlreadyExists(key.clone()))
+    );
+
+    let add = |ts, end_ts| Action::RecordAdd {
+      key: key.clone(),
+      ts,
+      tz: 0,
+      end_ts,
+      end_tz: 0,
+    };
+    assert_eq!(add(5, 15).validate(&key, running), Ok(()));
+    assert_eq!(
+      add(15, 25).validate(&key, running),
+      Err(InvalidAction::Overlapping(key.clone()))
+    );
+    assert_eq!(
+      add(15, 5).validate(&key, running),
+      Err(InvalidAction::StopsBeforeStart(key.clone()))
+    );
+
+    let archive = |archived| Action::ProjectArchive {
+      key: key.clone(),
+      archived,
+    };
+    assert_eq!(
+      archive(true).validate(&key, running),
+      Err(InvalidAction::ArchivesRunning(key.clone()))
+    );
+    let mut archived = Project::new("a".to_string());
+    archived.archive(true);
+    assert_eq!(
+      start(10).validate(&key, Some(&archived)),
+      Err(InvalidAction::Archived(key.clone()))
+    );
+    assert_eq!(archive(false).validate(&key, Some(&archived)), Ok(()));
+
+    let note = |note: &str| Action::RecordNote {
+      key: key.clone(),
+      note: note.to_string(),
+    };
+    assert_eq!(note("paged, fixed").validate(&key, running), Ok(()));
+    assert_eq!(
+      note("paged\nfixed").validate(&key, running),
+      Err(InvalidAction::Unprintable)
+    );
+    let label = Action::RecordLabel {
+      key: key.clone(),
+      billable: true,
+      tags: vec!["on\tcall".to_string()],
+    };
+    assert_eq!(
+      label.validate(&key, running),
+      Err(InvalidAction::Unprintable)
+    );
+    assert_eq!(
+      Action::ProjectAdd {
+        name: "b\r".to_string()
+      }
+      .validate(&ProjectKey::new("b\r"), None),
+      Err(InvalidAction::Unprintable)
+    );
+
+    let mut projects = BTreeMap::new();
+    projects.insert(key.clone(), project);
+    stop(25).apply(projects.entry(key.clone())).unwrap();
+    assert_eq!(
+      stop(30).apply(projects.entry(key.clone())).err(),
+      Some(InvalidAction::NotRunning(key))
+    );
+  }
 }
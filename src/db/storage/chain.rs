@@ -0,0 +1,122 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::db::storage::Action;
+use sha2::{Digest, Sha256};
+
+/// A SHA-256 digest
+pub type Hash = [u8; 32];
+
+/// The hash chain over the WAL's entries: every seal hashes the one before it along with all the
+/// entries in between, so that changing any of them, or any seal, breaks every seal after it.
+#[derive(Debug, Default, Clone)]
+pub struct Chain {
+  /// The latest seal, all zeros before the first one
+  head: Hash,
+  /// The entries since the latest seal, as they're written
+  pending: Vec<u8>,
+  unsealed: usize,
+}
+
+impl Chain {
+  /// Follows `action`, as replayed from the WAL. Seals are checked against what they cover,
+  /// failing when it isn't what they sealed, the chain then carrying on from them.
+  pub fn observe(&mut self, action: &Action) -> Result<(), ()> {
+    match action {
+      Action::Seal { hash } => {
+        let expected = self.digest();
+        self.advance(*hash);
+        match expected == *hash {
+          true => Ok(()),
+          false => Err(()),
+        }
+      }
+      action => {
+        self.pending.extend(Vec::<u8>::from(action));
+        self.unsealed += 1;
+        Ok(())
+      }
+    }
+  }
+
+  /// The seal to write after `appended`, the entries about to be written, moving the chain to it
+  pub fn seal(&mut self, appended: &[u8]) -> Action {
+    self.pending.extend_from_slice(appended);
+    let hash = self.digest();
+    self.advance(hash);
+    Action::Seal { hash }
+  }
+
+  /// How many entries came after the latest seal
+  pub fn unsealed(&self) -> usize {
+    self.unsealed
+  }
+
+  fn digest(&self) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(self.head);
+    hasher.update(&self.pending);
+    hasher.finalize().into()
+  }
+
+  fn advance(&mut self, hash: Hash) {
+    self.head = hash;
+    self.pending.clear();
+    self.unsealed = 0;
+  }
+}
+
+/// `hash` in hexadecimal
+pub fn hex(hash: &Hash) -> String {
+  hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::db::storage::chain::Chain;
+  use crate::db::storage::Action;
+
+  fn add(name: &str) -> Action {
+    Action::ProjectAdd {
+      name: name.to_string(),
+    }
+  }
+
+  #[test]
+  fn seals_break_on_tampering() {
+    let mut writer = Chain::default();
+    let mut log = vec![add("foo")];
+    let seal = writer.seal(&Vec::<u8>::from(&log[0]));
+    log.push(seal);
+    log.push(add("bar"));
+    let seal = writer.seal(&Vec::<u8>::from(&log[2]));
+    log.push(seal);
+
+    let mut reader = Chain::default();
+    assert!(log.iter().all(|action| reader.observe(action).is_ok()));
+    assert_eq!(reader.unsealed(), 0);
+    assert_eq!(reader.seal(&[]).to_string(), writer.seal(&[]).to_string());
+
+    log[0] = add("fool");
+    let mut reader = Chain::default();
+    let checked: Vec<bool> = log.iter().map(|a| reader.observe(a).is_ok()).collect();
+    assert_eq!(checked, vec![true, false, true, true]);
+
+    let mut unsealed = Chain::default();
+    unsealed.observe(&add("foo")).unwrap();
+    assert_eq!(unsealed.unsealed(), 1);
+  }
+}
@@ -15,8 +15,10 @@
  */
 
 use crate::db::database::ProjectKey;
-use crate::db::storage::Action;
-use std::fs::{remove_file, File, OpenOptions};
+use crate::db::storage::{Action, Chain, Index, ProjectTotals, Session, State};
+use crate::zone;
+use std::collections::VecDeque;
+use std::fs::{metadata, read, read_dir, remove_file, rename, write, File, OpenOptions};
 use std::io;
 use std::io::{BufRead, ErrorKind, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
@@ -24,10 +26,36 @@ use std::path::{Path, PathBuf};
 pub struct FsStorage {
   location: PathBuf,
   wal: File,
+  read_only: bool,
+  /// The device appending, and whether the latest entries of the WAL are already attributed to it
+  device: Option<(String, bool)>,
+  /// The sequence number of the next entry appended
+  next_seq: u64,
+  /// What would have been written, when only pretending to
+  dry_run: Option<DryRun>,
+  /// The hash chain everything appended gets sealed onto, when auditing
+  chain: Option<Chain>,
+  /// Whether the end of the WAL is known to be intact, so that appends don't need to check again
+  intact: bool,
+}
+
+/// What a dry run would have written to the WAL
+#[derive(Default, Debug)]
+pub struct DryRun {
+  /// How many entries the WAL would have been rewritten to, if it would have
+  pub rewritten: Option<usize>,
+  /// The actions that would have been appended, in order
+  pub appended: Vec<Action>,
 }
 
 const LOCK_FILE: &str = ".lock";
 const WAL_FILE: &str = "entries.wal";
+const STATE_FILE: &str = "current.state";
+const INDEX_FILE: &str = "daily.idx";
+const INVOICES_FILE: &str = "invoices";
+const FAVORITES_FILE: &str = "favorites";
+const HEARTBEAT_FILE: &str = "heartbeat";
+const GENERATION_FILE: &str = "generation";
 
 impl FsStorage {
   pub fn new(location: &Path) -> Result<Self, ErrorKind> {
@@ -44,7 +72,6 @@ impl FsStorage {
     {
       Ok(_) => match OpenOptions::new()
         .read(true)
-        .write(true)
         .create(true)
         .append(true)
         .open(location.join(WAL_FILE))
@@ -52,6 +79,12 @@ impl FsStorage {
         Ok(wal) => Ok(FsStorage {
           location: location.to_path_buf(),
           wal,
+          read_only: false,
+          device: None,
+          next_seq: 0,
+          dry_run: None,
+          chain: None,
+          intact: false,
         }),
         Err(err) => Err(err.kind()),
       },
@@ -59,32 +92,448 @@ impl FsStorage {
     }
   }
 
+  /// Opens the storage without taking the lock, for commands that only ever read, e.g. `prompt`.
+  /// Any attempt to record an action through it fails.
+  pub fn read_only(location: &Path) -> Result<Self, ErrorKind> {
+    if !location.is_dir() {
+      return Err(ErrorKind::InvalidInput);
+    }
+
+    match OpenOptions::new().read(true).open(location.join(WAL_FILE)) {
+      Ok(wal) => Ok(FsStorage {
+        location: location.to_path_buf(),
+        wal,
+        read_only: true,
+        device: None,
+        next_seq: 0,
+        dry_run: None,
+        chain: None,
+        intact: false,
+      }),
+      Err(err) => Err(err.kind()),
+    }
+  }
+
+  /// Attributes everything appended from now on to `device`, numbered from `next_seq`.
+  /// `attributed` tells whether the latest entries of the WAL already are, sparing a new marker.
+  pub fn attribute_to(&mut self, device: String, attributed: bool, next_seq: u64) {
+    self.device = Some((device, attributed));
+    self.next_seq = next_seq;
+  }
+
+  /// Seals everything appended from now on onto the hash chain of the WAL, which gets read to
+  /// find where it's at, whether it's intact or not being for `verify --audit` to tell
+  pub fn seal_appends(&mut self) -> Result<(), ()> {
+    let mut chain = Chain::default();
+    for entry in self.replay_actions() {
+      let (_, action) = entry?;
+      let _ = chain.observe(&action);
+    }
+    self.chain = Some(chain);
+    Ok(())
+  }
+
+  /// `buffer`, sealed if appends are, along with the chain moved past it, to keep once written
+  fn sealed(&self, mut buffer: Vec<u8>) -> (Vec<u8>, Option<Chain>) {
+    let mut chain = self.chain.clone();
+    if let Some(chain) = &mut chain {
+      let seal = chain.seal(&buffer);
+      buffer.extend(Vec::<u8>::from(&seal));
+    }
+    (buffer, chain)
+  }
+
+  /// Stops writing to the WAL, keeping track of what would have been written instead
+  pub fn dry_run(&mut self) {
+    self.dry_run.get_or_insert_with(DryRun::default);
+  }
+
+  /// What would have been written so far, if this is a dry run
+  pub fn pending(&self) -> Option<&DryRun> {
+    self.dry_run.as_ref()
+  }
+
+  /// The marker to append ahead of new entries, if they aren't attributed to this device already
+  fn attribution(&self) -> Vec<u8> {
+    match &self.device {
+      Some((id, false)) => (&Action::Device {
+        id: id.clone(),
+        seq: self.next_seq,
+      })
+        .into(),
+      _ => Vec::new(),
+    }
+  }
+
+  fn attributed(&mut self, appended: usize) {
+    if let Some((_, attributed)) = &mut self.device {
+      *attributed = true;
+    }
+    self.next_seq += appended as u64;
+  }
+
+  /// Cuts off whatever a torn write left at the end of the WAL, be it an entry cut short or a
+  /// group missing some of its entries, for appends not to get glued onto it. Only checked once.
+  fn drop_torn_tail(&mut self) -> Result<(), ()> {
+    if self.intact {
+      return Ok(());
+    }
+    let mut log = ReplayLog::new(&mut self.wal);
+    log.by_ref().for_each(drop);
+    let intact = log.intact;
+    if self.len() > intact {
+      self.wal.set_len(intact).map_err(|_| ())?;
+    }
+    self.intact = true;
+    Ok(())
+  }
+
   pub fn record_action(&mut self, action: Action) -> Result<Action, ()> {
-    let buffer: Vec<u8> = (&action).into();
+    if self.read_only {
+      return Err(());
+    }
+    if let Some(dry_run) = &mut self.dry_run {
+      dry_run.appended.push(action.clone());
+      return Ok(action);
+    }
+    self.drop_torn_tail()?;
+    let mut buffer = self.attribution();
+    buffer.extend(Vec::<u8>::from(&action));
+    let (buffer, chain) = self.sealed(buffer);
     match self.wal.write_all(&buffer) {
       Ok(_) => match self.wal.flush() {
-        Ok(_) => Ok(action),
+        Ok(_) => {
+          self.chain = chain;
+          self.attributed(1);
+          Ok(action)
+        }
         Err(_) => Err(()),
       },
       Err(_) => Err(()),
     }
   }
 
-  pub fn replay_actions(&mut self) -> impl Iterator<Item = (Option<ProjectKey>, Action)> + '_ {
+  /// Every entry of the WAL, in order, yielding an error for any that can't be read
+  pub fn replay_actions(
+    &mut self,
+  ) -> impl Iterator<Item = Result<(Option<ProjectKey>, Action), ()>> + '_ {
     ReplayLog::new(&mut self.wal)
   }
 
+  /// Copies of the WAL left behind by sync tools on conflicts, e.g. Dropbox's
+  /// `entries (conflicted copy).wal` or Syncthing's `entries.sync-conflict-<date>.wal`
+  pub fn conflicted_copies(location: &Path) -> Vec<PathBuf> {
+    let (stem, extension) = WAL_FILE.split_once('.').expect("Has an extension");
+    let mut copies: Vec<PathBuf> = match read_dir(location) {
+      Ok(entries) => entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+          let name = entry.file_name().to_string_lossy().to_string();
+          name != WAL_FILE
+            && name.starts_with(stem)
+            && name.ends_with(extension)
+            && name.contains("conflict")
+        })
+        .map(|entry| entry.path())
+        .collect(),
+      Err(_) => Vec::new(),
+    };
+    copies.sort();
+    copies
+  }
+
+  /// Every entry of the log at `path`, e.g. another copy of the WAL, failing if any can't be read
+  pub fn read_log(path: &Path) -> Result<Vec<Action>, ()> {
+    let mut file = File::open(path).map_err(|_| ())?;
+    ReplayLog::new(&mut file)
+      .map(|entry| entry.map(|(_, action)| action))
+      .collect()
+  }
+
+  /// Appends all actions with a single write, only syncing to disk once all of them are in.
+  /// Meant for anything appending more than one action at a time, e.g. switching or importing.
+  /// More than one get grouped, so that a write torn halfway through replays none of them.
+  pub fn record_actions(&mut self, actions: Vec<Action>) -> Result<Vec<Action>, ()> {
+    if self.read_only {
+      return Err(());
+    }
+    if let Some(dry_run) = &mut self.dry_run {
+      dry_run.appended.extend(actions.iter().cloned());
+      return Ok(actions);
+    }
+    self.drop_torn_tail()?;
+    let mut buffer = self.attribution();
+    if actions.len() > 1 {
+      let count = actions.len() as u32;
+      buffer.extend(Vec::<u8>::from(&Action::Begin { count }));
+    }
+    buffer.extend(actions.iter().flat_map(Vec::<u8>::from));
+    let (buffer, chain) = self.sealed(buffer);
+    match self.wal.write_all(&buffer) {
+      Ok(_) => match self.wal.sync_data() {
+        Ok(_) => {
+          self.chain = chain;
+          self.attributed(actions.len());
+          Ok(actions)
+        }
+        Err(_) => Err(()),
+      },
+      Err(_) => Err(()),
+    }
+  }
+
+  /// Atomically replaces the whole WAL with `actions`: they're written and synced to a temporary
+  /// file first, which is then renamed over the WAL. When auditing, the chain starts over from
+  /// them, sealed anew. The generation is bumped last, see [`FsStorage::generation`].
+  pub fn rewrite(&mut self, actions: &[Action]) -> Result<(), ()> {
+    if self.read_only {
+      return Err(());
+    }
+    if let Some(dry_run) = &mut self.dry_run {
+      dry_run.rewritten = Some(actions.len());
+      dry_run.appended.clear();
+      return Ok(());
+    }
+    let tmp = self.location.join(format!("{}.tmp", WAL_FILE));
+    let buffer: Vec<u8> = actions.iter().flat_map(Vec::<u8>::from).collect();
+    let chain = self.chain.as_ref().map(|_| Chain::default());
+    let (buffer, chain) = match chain {
+      Some(mut chain) => {
+        let seal = Vec::<u8>::from(&chain.seal(&buffer));
+        ([buffer, seal].concat(), Some(chain))
+      }
+      None => (buffer, None),
+    };
+    let written = File::create(&tmp)
+      .and_then(|mut file| file.write_all(&buffer).and_then(|_| file.sync_all()))
+      .and_then(|_| rename(&tmp, self.location.join(WAL_FILE)));
+    if written.is_err() {
+      let _ = remove_file(tmp);
+      return Err(());
+    }
+    self.chain = chain;
+    self.intact = true;
+    // The caches are only stamped with the WAL's size, which the rewritten one may well share
+    let _ = remove_file(self.location.join(STATE_FILE));
+    let _ = remove_file(self.location.join(INDEX_FILE));
+    // Only once nothing from before is left, for readers to tell they may have seen some of it
+    let _ = Self::write_generation(&self.location, Self::generation(&self.location) + 1);
+    // The snapshot carries no attribution, whatever comes next needs one again
+    if let Some((_, attributed)) = &mut self.device {
+      *attributed = false;
+    }
+    match OpenOptions::new()
+      .read(true)
+      .append(true)
+      .open(self.location.join(WAL_FILE))
+    {
+      Ok(wal) => {
+        self.wal = wal;
+        Ok(())
+      }
+      Err(_) => Err(()),
+    }
+  }
+
+  /// How many times the WAL at `location` was rewritten. Readers not taking the lock check it's
+  /// unchanged once done, as whatever they read across files could otherwise straddle a rewrite.
+  pub fn generation(location: &Path) -> u64 {
+    match read(location.join(GENERATION_FILE)) {
+      Ok(data) => String::from_utf8_lossy(&data).trim().parse().unwrap_or(0),
+      Err(_) => 0,
+    }
+  }
+
+  fn write_generation(location: &Path, generation: u64) -> io::Result<()> {
+    let tmp = location.join(format!("{}.tmp", GENERATION_FILE));
+    write(&tmp, generation.to_string())?;
+    rename(tmp, location.join(GENERATION_FILE))
+  }
+
+  /// Caches what's currently running and what ran last, stamped with the current WAL size
+  pub fn write_state(&mut self, running: Vec<Session>, last: Option<Session>) -> Result<(), ()> {
+    if self.read_only {
+      return Err(());
+    }
+    let wal_len = match self.wal.metadata() {
+      Ok(meta) => meta.len(),
+      Err(_) => return Err(()),
+    };
+    let buffer: Vec<u8> = (&State {
+      wal_len,
+      running,
+      last,
+    })
+      .into();
+    let tmp = self.location.join(format!("{}.tmp", STATE_FILE));
+    match write(&tmp, buffer) {
+      Ok(_) => rename(tmp, self.location.join(STATE_FILE)).map_err(|_| ()),
+      Err(_) => Err(()),
+    }
+  }
+
+  /// Reads the cached state, if present and still matching the WAL. Doesn't need nor take the lock.
+  pub fn read_state(location: &Path) -> Option<State> {
+    let wal_len = metadata(location.join(WAL_FILE)).ok()?.len();
+    let data = read(location.join(STATE_FILE)).ok()?;
+    match State::from_bytes(&data) {
+      Ok(state) if state.wal_len == wal_len => Some(state),
+      _ => None,
+    }
+  }
+
+  /// Persists the daily aggregates, stamped with the current WAL size
+  pub fn write_index(&mut self, projects: Vec<ProjectTotals>) -> Result<(), ()> {
+    if self.read_only {
+      return Err(());
+    }
+    let wal_len = match self.wal.metadata() {
+      Ok(meta) => meta.len(),
+      Err(_) => return Err(()),
+    };
+    let zone = zone::current().to_string();
+    let buffer: Vec<u8> = (&Index {
+      wal_len,
+      zone,
+      projects,
+    })
+      .into();
+    let tmp = self.location.join(format!("{}.tmp", INDEX_FILE));
+    match write(&tmp, buffer) {
+      Ok(_) => rename(tmp, self.location.join(INDEX_FILE)).map_err(|_| ()),
+      Err(_) => Err(()),
+    }
+  }
+
+  /// Reads the daily aggregates, if present and still matching the WAL and the zone records are
+  /// read in
+  pub fn read_index(location: &Path) -> Option<Index> {
+    let wal_len = metadata(location.join(WAL_FILE)).ok()?.len();
+    let data = read(location.join(INDEX_FILE)).ok()?;
+    match Index::from_bytes(&data) {
+      Ok(index) if index.wal_len == wal_len && index.zone == zone::current().to_string() => {
+        Some(index)
+      }
+      _ => None,
+    }
+  }
+
+  /// Stamps the time the machine was last known to be up. Doesn't need nor take the lock.
+  pub fn write_heartbeat(location: &Path, ts: i64) -> io::Result<()> {
+    let tmp = location.join(format!("{}.tmp", HEARTBEAT_FILE));
+    write(&tmp, ts.to_string())?;
+    rename(tmp, location.join(HEARTBEAT_FILE))
+  }
+
+  pub fn read_heartbeat(location: &Path) -> Option<i64> {
+    let data = read(location.join(HEARTBEAT_FILE)).ok()?;
+    String::from_utf8_lossy(&data).trim().parse().ok()
+  }
+
+  /// The favorite projects, in slot order. They're kept one per line, to be reordered by hand.
+  pub fn read_favorites(location: &Path) -> Vec<String> {
+    match read(location.join(FAVORITES_FILE)) {
+      Ok(data) => String::from_utf8_lossy(&data)
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect(),
+      Err(_) => Vec::new(),
+    }
+  }
+
+  pub fn write_favorites(&mut self, favorites: &[String]) -> Result<(), ()> {
+    if self.read_only {
+      return Err(());
+    }
+    if self.dry_run.is_some() {
+      return Ok(());
+    }
+    let content: String = favorites.iter().map(|name| format!("{}\n", name)).collect();
+    let tmp = self.location.join(format!("{}.tmp", FAVORITES_FILE));
+    match write(&tmp, content) {
+      Ok(_) => rename(tmp, self.location.join(FAVORITES_FILE)).map_err(|_| ()),
+      Err(_) => Err(()),
+    }
+  }
+
+  /// Records an invoice issued to `client` at `issued`, returning its number: one more than the
+  /// last one issued. Invoices are kept one per line, tab separated, so that the sequence can be
+  /// started at any number by adding a line by hand.
+  pub fn issue_invoice(&mut self, client: &str, issued: i64) -> Result<u64, ()> {
+    if self.read_only {
+      return Err(());
+    }
+    let path = self.location.join(INVOICES_FILE);
+    let last = match read(&path) {
+      Ok(data) => String::from_utf8_lossy(&data)
+        .lines()
+        .filter_map(|line| line.split('\t').next()?.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0),
+      Err(err) if err.kind() == ErrorKind::NotFound => 0,
+      Err(_) => return Err(()),
+    };
+    let number = last + 1;
+    let mut file = OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(path)
+      .map_err(|_| ())?;
+    writeln!(file, "{}\t{}\t{}", number, issued, client).map_err(|_| ())?;
+    file.sync_all().map_err(|_| ())?;
+    Ok(number)
+  }
+
+  /// The size of the WAL, in bytes
+  pub fn len(&self) -> u64 {
+    match self.wal.metadata() {
+      Ok(meta) => meta.len(),
+      Err(_) => 0,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    match self.wal.metadata() {
+      Ok(meta) => meta.len() == 0,
+      Err(_) => false,
+    }
+  }
+
+  pub fn is_read_only(&self) -> bool {
+    self.read_only
+  }
+
+  pub fn is_dry_run(&self) -> bool {
+    self.dry_run.is_some()
+  }
+
+  pub fn location(&self) -> &Path {
+    self.location.as_path()
+  }
+
   #[cfg(test)]
   pub fn delete(&mut self) {
+    let _ = remove_file(self.location.join(STATE_FILE));
+    let _ = remove_file(self.location.join(INDEX_FILE));
+    let _ = remove_file(self.location.join(INVOICES_FILE));
+    let _ = remove_file(self.location.join(FAVORITES_FILE));
+    let _ = remove_file(self.location.join(HEARTBEAT_FILE));
+    let _ = remove_file(self.location.join(GENERATION_FILE));
     let path = self.location.join(WAL_FILE);
-    remove_file(path.clone()).expect(&format!("Couldn't delete our db at {}", path.display()));
+    remove_file(path.clone())
+      .unwrap_or_else(|_| panic!("Couldn't delete our db at {}", path.display()));
   }
 
-  fn lock_file(location: &Path) -> PathBuf {
+  pub fn lock_file(location: &Path) -> PathBuf {
     location.join(LOCK_FILE)
   }
 
   fn close(&mut self) -> Result<(), io::Error> {
+    if self.read_only {
+      return Ok(());
+    }
     remove_file(Self::lock_file(self.location.as_path()))
   }
 }
@@ -100,9 +549,25 @@ impl Drop for FsStorage {
   }
 }
 
+type Entry = Result<(Option<ProjectKey>, Action), ()>;
+
 struct ReplayLog<'a> {
   reader: io::BufReader<&'a mut File>,
   buffer: Vec<u8>,
+  /// The rest of a group read ahead, as it only replays once all of its entries are in
+  pending: VecDeque<Entry>,
+  /// How many bytes were read so far
+  read: u64,
+  /// How many bytes of what was read are whole entries and groups, i.e. not torn
+  intact: u64,
+}
+
+/// A line of the WAL, as read
+enum Line {
+  Entry(Entry),
+  /// Cut short by the end of the log
+  Torn,
+  End,
 }
 
 const REPLAY_LOG_BUFFER_SIZE: usize = 1024;
@@ -113,31 +578,81 @@ impl<'a> ReplayLog<'a> {
     ReplayLog {
       reader: io::BufReader::new(wal),
       buffer: Vec::with_capacity(REPLAY_LOG_BUFFER_SIZE),
+      pending: VecDeque::new(),
+      read: 0,
+      intact: 0,
     }
   }
+
+  fn read_line(&mut self) -> Line {
+    self.buffer.clear();
+    loop {
+      let read = self.reader.read_until(b'\n', &mut self.buffer);
+      if let Ok(read) = read {
+        self.read += read as u64;
+      }
+      match read {
+        Ok(0) if self.buffer.is_empty() => return Line::End,
+        Ok(0) => return Line::Torn,
+        Ok(_) if self.buffer.last() != Some(&b'\n') => return Line::Torn,
+        Ok(_) => {
+          // A delimiter within the fixed size part of an entry is payload, keep reading
+          if self.buffer.len() > Action::min_len(self.buffer[0]) {
+            break;
+          }
+        }
+        Err(e) => panic!("Failed reading wal log: {}", e),
+      }
+    }
+    let data = self.buffer.as_slice();
+    Line::Entry(Action::from_bytes(&data[..data.len() - 1]))
+  }
 }
 
 impl<'a> Iterator for ReplayLog<'a> {
-  type Item = (Option<ProjectKey>, Action);
+  type Item = Result<(Option<ProjectKey>, Action), ()>;
 
   fn next(&mut self) -> Option<Self::Item> {
-    self.buffer.clear();
-    match self.reader.read_until(b'\n', &mut self.buffer) {
-      Ok(0) => None,
-      Ok(size) => {
-        let data = self.buffer.as_slice();
-        Some(Action::from_bytes(&data[..size - 1]).unwrap())
+    if let Some(entry) = self.pending.pop_front() {
+      return Some(entry);
+    }
+    let mut entry = match self.read_line() {
+      Line::Entry(entry) => entry,
+      Line::Torn => return Some(Err(())),
+      Line::End => return None,
+    };
+    'groups: while let Ok((_, Action::Begin { count })) = &entry {
+      let count = *count as usize;
+      let mut group = VecDeque::with_capacity(count + 1);
+      group.push_back(entry);
+      while group.len() <= count {
+        match self.read_line() {
+          // Another group before this one is complete, it was torn and then appended to
+          Line::Entry(next @ Ok((_, Action::Begin { .. }))) => {
+            entry = next;
+            continue 'groups;
+          }
+          Line::Entry(next) => group.push_back(next),
+          // The log ends halfway through the group, none of which was ever applied
+          Line::Torn | Line::End => return None,
+        }
       }
-      Err(e) => panic!("Failed reading wal log: {}", e),
+      self.intact = self.read;
+      self.pending = group;
+      return self.pending.pop_front();
     }
+    self.intact = self.read;
+    Some(entry)
   }
 }
 
 #[cfg(test)]
 mod tests {
+  use crate::db::database::ProjectKey;
   use crate::db::storage::fs::FsStorage;
+  use crate::db::storage::Action;
   use std::env;
-  use std::fs::{create_dir, remove_dir};
+  use std::fs::{create_dir, read, remove_dir, remove_file, write};
   use std::io::ErrorKind;
   use std::io::ErrorKind::InvalidInput;
   use std::path::Path;
@@ -165,4 +680,361 @@ mod tests {
     }
     remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
   }
+
+  #[test]
+  fn test_state_goes_stale() {
+    let location = env::temp_dir().join("timeknightTest_state_goes_stale");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      assert_eq!(FsStorage::read_state(location.as_path()), None);
+      storage
+        .write_state(Vec::new(), None)
+        .expect("Failed writing state");
+      assert!(FsStorage::read_state(location.as_path()).is_some());
+      storage
+        .record_action(Action::RecordStop {
+          ts: 0,
+          tz: 0,
+          key: None,
+        })
+        .expect("Failed recording");
+      assert_eq!(FsStorage::read_state(location.as_path()), None);
+      storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_numbers_invoices_in_sequence() {
+    let location = env::temp_dir().join("timeknightTest_numbers_invoices_in_sequence");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      assert_eq!(storage.issue_invoice("Acme", 0), Ok(1));
+      assert_eq!(storage.issue_invoice("Globex", 0), Ok(2));
+      write(location.join("invoices"), "100\t0\tAcme\n").expect("Failed editing");
+      assert_eq!(storage.issue_invoice("Acme", 0), Ok(101));
+      storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_batch_replays_in_order() {
+    let location = env::temp_dir().join("timeknightTest_batch_replays_in_order");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      let actions = storage
+        .record_actions(vec![
+          Action::ProjectAdd {
+            name: "first".to_string(),
+          },
+          Action::ProjectAdd {
+            name: "second".to_string(),
+          },
+        ])
+        .expect("Failed recording");
+      assert_eq!(actions.len(), 2);
+      let names: Vec<String> = storage
+        .replay_actions()
+        .map(|entry| match entry {
+          Ok((_, Action::Begin { count })) => format!("{} more", count),
+          Ok((_, Action::ProjectAdd { name })) => name,
+          _ => unreachable!("Only added projects"),
+        })
+        .collect();
+      assert_eq!(names, vec!["2 more", "first", "second"]);
+      storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_rewrites_bump_the_generation() {
+    let location = env::temp_dir().join("timeknightTest_rewrites_bump_the_generation");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let add = |name: &str| Action::ProjectAdd {
+        name: name.to_string(),
+      };
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      assert_eq!(FsStorage::generation(location.as_path()), 0);
+      storage
+        .record_action(add("first"))
+        .expect("Failed recording");
+      assert_eq!(FsStorage::generation(location.as_path()), 0);
+      storage.rewrite(&[add("second")]).expect("Failed rewriting");
+      storage.rewrite(&[add("third")]).expect("Failed rewriting");
+      assert_eq!(FsStorage::generation(location.as_path()), 2);
+      storage.dry_run();
+      storage.rewrite(&[add("fourth")]).expect("Failed rewriting");
+      assert_eq!(FsStorage::generation(location.as_path()), 2);
+      storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_drops_torn_groups() {
+    let location = env::temp_dir().join("timeknightTest_drops_torn_groups");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let add = |name: &str| Action::ProjectAdd {
+        name: name.to_string(),
+      };
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      storage
+        .record_action(add("first"))
+        .expect("Failed recording");
+      storage
+        .record_actions(vec![add("second"), add("third")])
+        .expect("Failed recording");
+      let names = |storage: &mut FsStorage| -> Vec<String> {
+        storage
+          .replay_actions()
+          .map(|entry| match entry {
+            Ok((_, Action::ProjectAdd { name })) => name,
+            Ok((_, Action::Begin { .. })) => "begin".to_string(),
+            _ => unreachable!("Only added projects"),
+          })
+          .collect()
+      };
+      assert_eq!(
+        names(&mut storage),
+        vec!["first", "begin", "second", "third"]
+      );
+      let wal = read(location.join("entries.wal")).unwrap();
+      drop(storage);
+      // Cut within the last entry, then right after the one before it
+      for cut in [
+        wal.len() - 3,
+        wal.len() - Vec::<u8>::from(&add("third")).len(),
+      ] {
+        write(location.join("entries.wal"), &wal[..cut]).expect("Failed tearing");
+        let mut storage = FsStorage::new(location.as_path()).expect("Failed reopening");
+        assert_eq!(names(&mut storage), vec!["first"]);
+        // Appended to after being torn, the torn group still never replays, nor takes any in
+        storage
+          .record_action(add("fourth"))
+          .expect("Failed recording");
+        storage
+          .record_actions(vec![add("fifth"), add("sixth")])
+          .expect("Failed recording");
+        assert_eq!(
+          names(&mut storage),
+          vec!["first", "fourth", "begin", "fifth", "sixth"]
+        );
+        storage.delete();
+      }
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_attributes_once_per_device() {
+    let location = env::temp_dir().join("timeknightTest_attributes_once_per_device");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      storage.attribute_to("laptop".to_string(), false, 0);
+      for name in ["first", "second"] {
+        storage
+          .record_action(Action::ProjectAdd {
+            name: name.to_string(),
+          })
+          .expect("Failed recording");
+      }
+      storage.attribute_to("desktop".to_string(), false, 2);
+      storage
+        .record_actions(vec![Action::ProjectDel {
+          key: ProjectKey::new("first"),
+        }])
+        .expect("Failed recording");
+      let entries: Vec<String> = storage
+        .replay_actions()
+        .map(|entry| match entry {
+          Ok((_, Action::Device { id, seq })) => format!("{}@{}", id, seq),
+          Ok((Some(key), _)) => key.to_string(),
+          _ => unreachable!("Only attributed project entries"),
+        })
+        .collect();
+      assert_eq!(
+        entries,
+        vec!["laptop@0", "first", "second", "desktop@2", "first"]
+      );
+      storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_finds_conflicted_copies() {
+    let location = env::temp_dir().join("timeknightTest_finds_conflicted_copies");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      let copies = [
+        "entries (conflicted copy 2022-03-27).wal",
+        "entries.sync-conflict-20220327-173734-ABCDEFG.wal",
+      ];
+      for copy in copies.iter().chain(["entries.wal.tmp", "notes.wal"].iter()) {
+        write(location.join(copy), b"").expect("Failed writing copy");
+      }
+      let found = FsStorage::conflicted_copies(location.as_path());
+      assert_eq!(
+        found,
+        copies.iter().map(|c| location.join(c)).collect::<Vec<_>>()
+      );
+      for copy in copies.iter().chain(["entries.wal.tmp", "notes.wal"].iter()) {
+        remove_file(location.join(copy)).expect("Failed removing copy");
+      }
+      storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_replays_delimiters_within_payload() {
+    let location = env::temp_dir().join("timeknightTest_replays_delimiters_within_payload");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      // 0x0a, i.e. '\n', all over the timestamp
+      let ts = 0x0a0a0a0a;
+      storage
+        .record_action(Action::RecordStop {
+          ts,
+          tz: 10,
+          key: None,
+        })
+        .expect("Failed recording");
+      let entries: Vec<_> = storage.replay_actions().collect();
+      assert_eq!(entries.len(), 1);
+      match &entries[0] {
+        Ok((None, Action::RecordStop { ts: read, .. })) => assert_eq!(*read, ts),
+        _ => unreachable!("Expected a RecordStop"),
+      }
+      storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_read_only_doesnt_lock() {
+    let location = env::temp_dir().join("timeknightTest_read_only_doesnt_lock");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut working_storage =
+        FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      {
+        let mut reader = FsStorage::read_only(location.as_path()).expect("Failed opening reader");
+        assert!(reader
+          .record_action(Action::RecordStop {
+            ts: 0,
+            tz: 0,
+            key: None,
+          })
+          .is_err());
+      }
+      assert!(location.join(".lock").exists());
+      working_storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_seals_what_it_appends() {
+    let location = env::temp_dir().join("timeknightTest_seals_what_it_appends");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let add = |name: &str| Action::ProjectAdd {
+        name: name.to_string(),
+      };
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      storage.record_action(add("foo")).expect("Failed recording");
+      storage.seal_appends().expect("Failed following the chain");
+      storage.record_action(add("bar")).expect("Failed recording");
+      storage
+        .record_actions(vec![add("baz"), add("qux")])
+        .expect("Failed recording");
+      let audit = crate::db::audit(location.as_path()).expect("Failed auditing");
+      assert!(audit.broken.is_empty());
+      assert_eq!((audit.seals.len(), audit.unsealed), (2, 0));
+
+      storage.rewrite(&[add("foo")]).expect("Failed rewriting");
+      let audit = crate::db::audit(location.as_path()).expect("Failed auditing");
+      assert!(audit.broken.is_empty());
+      assert_eq!(audit.seals.len(), 1);
+
+      // Renaming foo to fol, behind the seal's back
+      let wal = location.join(super::WAL_FILE);
+      let mut bytes = read(&wal).unwrap();
+      bytes[3] = b'l';
+      write(&wal, bytes).unwrap();
+      let audit = crate::db::audit(location.as_path()).expect("Failed auditing");
+      assert_eq!(audit.broken, vec![2]);
+      storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_dry_run_writes_nothing() {
+    let location = env::temp_dir().join("timeknightTest_dry_run_writes_nothing");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      storage.dry_run();
+      let add = Action::ProjectAdd {
+        name: "dry".to_string(),
+      };
+      storage
+        .record_action(add.clone())
+        .expect("Failed recording");
+      storage.record_actions(vec![add]).expect("Failed recording");
+      assert!(storage.is_empty());
+      assert_eq!(storage.pending().unwrap().appended.len(), 2);
+      storage.rewrite(&[]).expect("Failed rewriting");
+      assert_eq!(storage.pending().unwrap().rewritten, Some(0));
+      assert!(storage.pending().unwrap().appended.is_empty());
+      storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_favorites_roundtrip() {
+    let location = env::temp_dir().join("timeknightTest_favorites_roundtrip");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      assert!(FsStorage::read_favorites(location.as_path()).is_empty());
+      let favorites = vec!["Backend".to_string(), "Oncall".to_string()];
+      storage.write_favorites(&favorites).expect("Failed writing");
+      write(location.join("favorites"), "Backend\n\n  Oncall \n").expect("Failed editing");
+      assert_eq!(FsStorage::read_favorites(location.as_path()), favorites);
+      storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+
+  #[test]
+  fn test_heartbeat_roundtrip() {
+    let location = env::temp_dir().join("timeknightTest_heartbeat_roundtrip");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut storage = FsStorage::new(location.as_path()).expect("Failed creating Storage");
+      assert_eq!(FsStorage::read_heartbeat(location.as_path()), None);
+      FsStorage::write_heartbeat(location.as_path(), 1_600_000_000).expect("Failed beating");
+      FsStorage::write_heartbeat(location.as_path(), 1_600_000_300).expect("Failed beating");
+      assert_eq!(
+        FsStorage::read_heartbeat(location.as_path()),
+        Some(1_600_000_300)
+      );
+      storage.delete();
+    }
+    remove_dir(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
 }
@@ -0,0 +1,178 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use chrono::{Datelike, NaiveDate};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Per project, per day aggregated durations of all completed records, so that reports don't need
+/// to replay the whole WAL. Like [`crate::db::storage::State`], it is stale once `wal_len` doesn't
+/// match the WAL's size anymore, but also once records are read in another `zone` than the one
+/// they were bucketed into days in.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Index {
+  pub wal_len: u64,
+  pub zone: String,
+  pub projects: Vec<ProjectTotals>,
+}
+
+/// Seconds tracked on a project, keyed by the local day the records started on
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProjectTotals {
+  pub name: String,
+  pub days: BTreeMap<NaiveDate, u64>,
+  /// Whether the project is archived, which reports leave out unless told otherwise
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "std::ops::Not::not")
+  )]
+  pub archived: bool,
+}
+
+impl ProjectTotals {
+  pub fn new(name: String) -> Self {
+    ProjectTotals {
+      name,
+      days: BTreeMap::new(),
+      archived: false,
+    }
+  }
+
+  pub fn add(&mut self, day: NaiveDate, secs: u64) {
+    *self.days.entry(day).or_insert(0) += secs;
+  }
+}
+
+struct Reader<'a> {
+  data: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, len: usize) -> Result<&'a [u8], ()> {
+    if self.pos + len > self.data.len() {
+      return Err(());
+    }
+    let slice = &self.data[self.pos..self.pos + len];
+    self.pos += len;
+    Ok(slice)
+  }
+
+  fn u32(&mut self) -> Result<u32, ()> {
+    Ok(u32::from_le_bytes(
+      self.take(4)?.try_into().expect("Wrong math!"),
+    ))
+  }
+
+  fn i32(&mut self) -> Result<i32, ()> {
+    Ok(i32::from_le_bytes(
+      self.take(4)?.try_into().expect("Wrong math!"),
+    ))
+  }
+
+  fn u64(&mut self) -> Result<u64, ()> {
+    Ok(u64::from_le_bytes(
+      self.take(8)?.try_into().expect("Wrong math!"),
+    ))
+  }
+}
+
+impl Index {
+  pub fn from_bytes(data: &[u8]) -> Result<Index, ()> {
+    let mut reader = Reader { data, pos: 0 };
+    let wal_len = reader.u64()?;
+    let count = reader.u32()?;
+    let mut projects = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+      let len = reader.u32()? as usize;
+      let mut project = ProjectTotals::new(String::from_utf8_lossy(reader.take(len)?).to_string());
+      for _ in 0..reader.u32()? {
+        let day = NaiveDate::from_num_days_from_ce_opt(reader.i32()?).ok_or(())?;
+        project.days.insert(day, reader.u64()?);
+      }
+      projects.push(project);
+    }
+    // Indices from before zones bucketed records in the offset they were recorded at
+    let zone = match reader.pos < data.len() {
+      true => {
+        let len = reader.u32()? as usize;
+        String::from_utf8_lossy(reader.take(len)?).to_string()
+      }
+      false => "recorded".to_string(),
+    };
+    // Nor were projects archived before, each now has a flag telling whether it is
+    if reader.pos < data.len() {
+      for project in projects.iter_mut() {
+        project.archived = reader.take(1)?[0] != 0;
+      }
+    }
+    Ok(Index {
+      wal_len,
+      zone,
+      projects,
+    })
+  }
+}
+
+impl From<&Index> for Vec<u8> {
+  fn from(index: &Index) -> Self {
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(&index.wal_len.to_le_bytes());
+    buffer.extend_from_slice(&(index.projects.len() as u32).to_le_bytes());
+    for project in &index.projects {
+      let raw = project.name.as_bytes();
+      buffer.extend_from_slice(&(raw.len() as u32).to_le_bytes());
+      buffer.extend_from_slice(raw);
+      buffer.extend_from_slice(&(project.days.len() as u32).to_le_bytes());
+      for (day, secs) in &project.days {
+        buffer.extend_from_slice(&day.num_days_from_ce().to_le_bytes());
+        buffer.extend_from_slice(&secs.to_le_bytes());
+      }
+    }
+    buffer.extend_from_slice(&(index.zone.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(index.zone.as_bytes());
+    buffer.extend(index.projects.iter().map(|p| u8::from(p.archived)));
+    buffer
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::db::storage::index::{Index, ProjectTotals};
+  use chrono::NaiveDate;
+
+  #[test]
+  fn index_roundtrips() {
+    let mut busy = ProjectTotals::new("busy".to_string());
+    busy.add(NaiveDate::from_ymd(2022, 3, 27), 60);
+    busy.add(NaiveDate::from_ymd(2022, 3, 27), 30);
+    busy.add(NaiveDate::from_ymd(2022, 3, 28), 3600);
+    let mut idle = ProjectTotals::new("idle".to_string());
+    idle.archived = true;
+    let index = Index {
+      wal_len: 1024,
+      zone: "+05:30".to_string(),
+      projects: vec![busy, idle],
+    };
+    let buffer: Vec<u8> = (&index).into();
+    let read = Index::from_bytes(&buffer).unwrap();
+    assert_eq!(read.projects[0].days[&NaiveDate::from_ymd(2022, 3, 27)], 90);
+    assert_eq!(read, index);
+    assert!(Index::from_bytes(&buffer[..buffer.len() - 1]).is_err());
+  }
+}
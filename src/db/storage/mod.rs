@@ -15,7 +15,13 @@
  */
 
 mod action;
+mod chain;
 mod fs;
+mod index;
+mod state;
 
-pub use action::Action;
-pub use fs::FsStorage;
+pub use action::{Action, InvalidAction, WAL_FORMAT};
+pub use chain::{hex, Chain};
+pub use fs::{DryRun, FsStorage};
+pub use index::{Index, ProjectTotals};
+pub use state::{Session, State};
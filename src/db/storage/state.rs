@@ -0,0 +1,154 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/// What's currently being tracked, and what was last, cached beside the WAL so that `status` and
+/// `prompt` don't need to replay the whole log. `wal_len` is the size of the WAL the state was
+/// derived from, a state whose `wal_len` doesn't match the WAL anymore is stale.
+#[derive(Debug, PartialEq, Eq)]
+pub struct State {
+  pub wal_len: u64,
+  /// In the order they were started
+  pub running: Vec<Session>,
+  pub last: Option<Session>,
+}
+
+/// A record of a project: its start and, unless still running, its end
+#[derive(Debug, PartialEq, Eq)]
+pub struct Session {
+  pub name: String,
+  pub ts: i64,
+  pub tz: i32,
+  pub end: Option<(i64, i32)>,
+}
+
+const LAST: u8 = 1;
+
+impl State {
+  pub fn from_bytes(data: &[u8]) -> Result<State, ()> {
+    if data.len() < 13 {
+      return Err(());
+    }
+    let flags = data[0];
+    let wal_len = u64::from_le_bytes(data[1..9].try_into().expect("Wrong math!"));
+    let count = u32::from_le_bytes(data[9..13].try_into().expect("Wrong math!"));
+    let mut rest = &data[13..];
+    let running = (0..count)
+      .map(|_| Session::read(&mut rest))
+      .collect::<Result<Vec<Session>, ()>>()?;
+    let last = match flags & LAST {
+      0 => None,
+      _ => Some(Session::read(&mut rest)?),
+    };
+    Ok(State {
+      wal_len,
+      running,
+      last,
+    })
+  }
+}
+
+impl Session {
+  fn read(data: &mut &[u8]) -> Result<Session, ()> {
+    if data.len() < 29 {
+      return Err(());
+    }
+    let ts = i64::from_le_bytes(data[0..8].try_into().expect("Wrong math!"));
+    let tz = i32::from_le_bytes(data[8..12].try_into().expect("Wrong math!"));
+    let end_ts = i64::from_le_bytes(data[12..20].try_into().expect("Wrong math!"));
+    let end_tz = i32::from_le_bytes(data[20..24].try_into().expect("Wrong math!"));
+    let len = u32::from_le_bytes(data[25..29].try_into().expect("Wrong math!")) as usize;
+    if data.len() < 29 + len {
+      return Err(());
+    }
+    let name = String::from_utf8_lossy(&data[29..29 + len]).to_string();
+    let end = match data[24] {
+      0 => None,
+      _ => Some((end_ts, end_tz)),
+    };
+    *data = &data[29 + len..];
+    Ok(Session { name, ts, tz, end })
+  }
+
+  fn write(&self, buffer: &mut Vec<u8>) {
+    let raw = self.name.as_bytes();
+    let (end_ts, end_tz) = self.end.unwrap_or((0, 0));
+    buffer.extend_from_slice(&self.ts.to_le_bytes());
+    buffer.extend_from_slice(&self.tz.to_le_bytes());
+    buffer.extend_from_slice(&end_ts.to_le_bytes());
+    buffer.extend_from_slice(&end_tz.to_le_bytes());
+    buffer.push(self.end.is_some() as u8);
+    buffer.extend_from_slice(&(raw.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(raw);
+  }
+}
+
+impl From<&State> for Vec<u8> {
+  fn from(state: &State) -> Self {
+    let mut buffer = Vec::with_capacity(13);
+    buffer.push(match state.last {
+      Some(_) => LAST,
+      None => 0,
+    });
+    buffer.extend_from_slice(&state.wal_len.to_le_bytes());
+    buffer.extend_from_slice(&(state.running.len() as u32).to_le_bytes());
+    state.running.iter().for_each(|s| s.write(&mut buffer));
+    state.last.iter().for_each(|s| s.write(&mut buffer));
+    buffer
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::db::storage::state::{Session, State};
+
+  #[test]
+  fn state_roundtrips() {
+    let running = State {
+      wal_len: 42,
+      running: vec![
+        Session {
+          name: "oncall".to_string(),
+          ts: 1648400000,
+          tz: 14400,
+          end: None,
+        },
+        Session {
+          name: "ourName".to_string(),
+          ts: 1648417054,
+          tz: 14400,
+          end: None,
+        },
+      ],
+      last: Some(Session {
+        name: "previous".to_string(),
+        ts: 1648410000,
+        tz: 14400,
+        end: Some((1648417054, 14400)),
+      }),
+    };
+    let buffer: Vec<u8> = (&running).into();
+    assert_eq!(State::from_bytes(&buffer), Ok(running));
+
+    let idle = State {
+      wal_len: 7,
+      running: Vec::new(),
+      last: None,
+    };
+    let buffer: Vec<u8> = (&idle).into();
+    assert_eq!(State::from_bytes(&buffer), Ok(idle));
+    assert!(State::from_bytes(&buffer[..4]).is_err());
+  }
+}
@@ -0,0 +1,333 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::db::database::ProjectKey;
+use crate::db::storage::{hex, Action, Chain, FsStorage};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// The outcome of checking a WAL: what it contains and everything that would go wrong replaying it
+#[derive(Debug, Default)]
+pub struct Verification {
+  pub entries: usize,
+  pub projects: usize,
+  pub records: usize,
+  /// How many entries each device appended, for the entries attributed to one
+  pub devices: BTreeMap<String, usize>,
+  pub problems: Vec<String>,
+}
+
+/// Reads the whole WAL, without taking the lock nor ever modifying it, checking that every entry
+/// can be decoded and that they make sense in sequence.
+pub fn verify(location: &Path) -> Result<Verification, ErrorKind> {
+  let mut storage = FsStorage::read_only(location)?;
+  Ok(check(storage.replay_actions()))
+}
+
+/// The outcome of following the WAL's hash chain
+#[derive(Debug, Default)]
+pub struct Audit {
+  /// Every seal, in hexadecimal, in order
+  pub seals: Vec<String>,
+  /// The entries that aren't what was sealed, or can't be decoded, numbered as by `verify`
+  pub broken: Vec<usize>,
+  /// How many entries came after the latest seal, appended without auditing
+  pub unsealed: usize,
+}
+
+/// Follows the hash chain of the WAL, without taking the lock, checking that every seal matches
+/// the entries it covers
+pub fn audit(location: &Path) -> Result<Audit, ErrorKind> {
+  let mut storage = FsStorage::read_only(location)?;
+  let mut audit = Audit::default();
+  let mut chain = Chain::default();
+  for (n, entry) in storage.replay_actions().enumerate() {
+    match entry {
+      Ok((_, action)) => {
+        if chain.observe(&action).is_err() {
+          audit.broken.push(n + 1);
+        }
+        if let Action::Seal { hash } = action {
+          audit.seals.push(hex(&hash));
+        }
+      }
+      Err(_) => audit.broken.push(n + 1),
+    }
+  }
+  audit.unsealed = chain.unsealed();
+  Ok(audit)
+}
+
+pub(crate) fn check(
+  entries: impl Iterator<Item = Result<(Option<ProjectKey>, Action), ()>>,
+) -> Verification {
+  let mut verification = Verification::default();
+  // Every live project, with the start of its latest record
+  let mut projects: BTreeMap<ProjectKey, Option<i64>> = BTreeMap::new();
+  // Every record in flight, in the order they were started
+  let mut running: Vec<(ProjectKey, i64)> = Vec::new();
+  // The start of the record before the one in flight, for a cancel to go back to
+  let mut previous: BTreeMap<ProjectKey, Option<i64>> = BTreeMap::new();
+  let mut archived: BTreeSet<ProjectKey> = BTreeSet::new();
+  let mut device: Option<String> = None;
+
+  for (n, entry) in entries.enumerate() {
+    let n = n + 1;
+    verification.entries = n;
+    if let Ok((_, Action::Device { id, .. })) = &entry {
+      device = Some(id.clone());
+      continue;
+    }
+    if let Ok((_, Action::Seal { .. } | Action::Begin { .. })) = &entry {
+      continue;
+    }
+    if let Some(device) = &device {
+      *verification.devices.entry(device.clone()).or_default() += 1;
+    }
+    let mut problem = |p: String| verification.problems.push(format!("entry #{}: {}", n, p));
+    match entry {
+      Err(_) => problem("can't be decoded".to_string()),
+      Ok((Some(key), Action::ProjectAdd { name })) => {
+        if projects.insert(key, None).is_some() {
+          problem(format!("adds project '{}', which already exists", name));
+        }
+      }
+      Ok((Some(key), Action::ProjectDel { .. })) => {
+        if projects.remove(&key).is_none() {
+          problem(format!("deletes unknown project '{}'", key));
+        }
+        if running.iter().any(|(k, _)| k == &key) {
+          problem(format!("deletes project '{}' while it's running", key));
+          running.retain(|(k, _)| k != &key);
+        }
+        archived.remove(&key);
+      }
+      Ok((Some(key), Action::ProjectArchive { archived: true, .. })) => {
+        if !projects.contains_key(&key) {
+          problem(format!("archives unknown project '{}'", key));
+        } else if running.iter().any(|(k, _)| k == &key) {
+          problem(format!("archives project '{}' while it's running", key));
+        } else {
+          archived.insert(key);
+        }
+      }
+      Ok((
+        Some(key),
+        Action::ProjectArchive {
+          archived: false, ..
+        },
+      )) => {
+        if !projects.contains_key(&key) {
+          problem(format!("unarchives unknown project '{}'", key));
+        }
+        archived.remove(&key);
+      }
+      Ok((Some(key), Action::RecordStart { ts, .. })) => {
+        match projects.get_mut(&key) {
+          None => problem(format!("starts unknown project '{}'", key)),
+          Some(_) if archived.contains(&key) => {
+            problem(format!("starts archived project '{}'", key))
+          }
+          Some(last) => {
+            if last.is_some_and(|last| ts < last) {
+              problem(format!("starts '{}' before its previous record", key));
+            }
+            previous.insert(key.clone(), *last);
+            *last = Some(ts);
+          }
+        }
+        verification.records += 1;
+        running.retain(|(k, _)| k != &key);
+        running.push((key, ts));
+      }
+      Ok((key, Action::RecordStop { ts, .. })) => {
+        let position = match &key {
+          Some(key) => running.iter().position(|(k, _)| k == key),
+          None => running.len().checked_sub(1),
+        };
+        match (position.map(|p| running.remove(p)), key) {
+          (None, Some(key)) => problem(format!("stops '{}' while it isn't running", key)),
+          (None, None) => problem("stops while nothing is running".to_string()),
+          (Some((key, start)), _) if ts < start => {
+            problem(format!("stops '{}' before it started", key))
+          }
+          (Some(_), _) => {}
+        }
+      }
+      Ok((Some(key), Action::RecordAdd { ts, end_ts, .. })) => {
+        match projects.get_mut(&key) {
+          None => problem(format!("adds a record to unknown project '{}'", key)),
+          Some(last) => *last = (*last).max(Some(ts)),
+        }
+        if end_ts < ts {
+          problem(format!(
+            "adds a record to '{}' ending before it started",
+            key
+          ));
+        }
+        verification.records += 1;
+      }
+      Ok((Some(key), Action::RecordCancel { .. })) => {
+        match running.iter().position(|(k, _)| k == &key) {
+          None => problem(format!("cancels '{}' while it isn't running", key)),
+          Some(position) => {
+            running.remove(position);
+            verification.records -= 1;
+            if let (Some(last), Some(start)) = (projects.get_mut(&key), previous.remove(&key)) {
+              *last = start;
+            }
+          }
+        }
+      }
+      Ok((Some(key), Action::RecordLabel { .. })) => {
+        if !running.iter().any(|(k, _)| k == &key) {
+          problem(format!("labels '{}' while it isn't running", key));
+        }
+      }
+      Ok((Some(key), Action::RecordContext { .. })) => {
+        if !running.iter().any(|(k, _)| k == &key) {
+          problem(format!(
+            "notes where '{}' started while it isn't running",
+            key
+          ));
+        }
+      }
+      Ok((Some(key), Action::RecordNote { .. })) => {
+        if !running.iter().any(|(k, _)| k == &key) {
+          problem(format!("notes on '{}' while it isn't running", key));
+        }
+      }
+      Ok((Some(key), Action::RecordHardStop { .. })) => {
+        if !running.iter().any(|(k, _)| k == &key) {
+          problem(format!("hard stops '{}' while it isn't running", key));
+        }
+      }
+      Ok((Some(key), Action::Expense { .. })) => {
+        if !projects.contains_key(&key) {
+          problem(format!("spends on unknown project '{}'", key));
+        }
+      }
+      Ok((_, action)) => problem(format!("unexpected {:?}", action)),
+    }
+  }
+  verification.projects = projects.len();
+  verification
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::db::database::ProjectKey;
+  use crate::db::storage::Action;
+  use crate::db::verify::check;
+
+  fn start(key: &str, ts: i64) -> Result<(Option<ProjectKey>, Action), ()> {
+    let key = ProjectKey::new(key);
+    Ok((Some(key.clone()), Action::RecordStart { key, ts, tz: 0 }))
+  }
+
+  fn stop(key: Option<&str>, ts: i64) -> Result<(Option<ProjectKey>, Action), ()> {
+    let key = key.map(ProjectKey::new);
+    Ok((key.clone(), Action::RecordStop { ts, tz: 0, key }))
+  }
+
+  fn cancel(key: &str) -> Result<(Option<ProjectKey>, Action), ()> {
+    let key = ProjectKey::new(key);
+    Ok((Some(key.clone()), Action::RecordCancel { key }))
+  }
+
+  fn add(name: &str) -> Result<(Option<ProjectKey>, Action), ()> {
+    let name = name.to_string();
+    Ok((Some(ProjectKey::new(&name)), Action::ProjectAdd { name }))
+  }
+
+  #[test]
+  fn sane_log_verifies() {
+    let log = vec![
+      add("a"),
+      add("b"),
+      start("a", 10),
+      stop(None, 20),
+      start("b", 20),
+      start("a", 30),
+      stop(Some("b"), 40),
+      stop(None, 50),
+    ];
+    let verification = check(log.into_iter());
+    assert_eq!(verification.entries, 8);
+    assert_eq!(verification.projects, 2);
+    assert_eq!(verification.records, 3);
+    assert!(verification.problems.is_empty());
+  }
+
+  #[test]
+  fn cancels_leave_no_record() {
+    let log = vec![
+      add("a"),
+      start("a", 10),
+      stop(None, 20),
+      start("a", 40),
+      cancel("a"),
+      start("a", 30),
+      stop(None, 35),
+      cancel("a"),
+    ];
+    let verification = check(log.into_iter());
+    assert_eq!(verification.records, 2);
+    assert_eq!(verification.problems.len(), 1);
+    assert!(verification.problems[0].starts_with("entry #8"));
+  }
+
+  #[test]
+  fn archived_projects_dont_start() {
+    let archive = |key: &str, archived| {
+      let key = ProjectKey::new(key);
+      Ok((Some(key.clone()), Action::ProjectArchive { key, archived }))
+    };
+    let log = vec![
+      add("a"),
+      start("a", 10),
+      archive("a", true),
+      stop(None, 20),
+      archive("a", true),
+      start("a", 30),
+      archive("a", false),
+      start("a", 40),
+    ];
+    let verification = check(log.into_iter());
+    assert_eq!(verification.problems.len(), 2);
+    assert!(verification.problems[0].starts_with("entry #3"));
+    assert!(verification.problems[1].starts_with("entry #6"));
+  }
+
+  #[test]
+  fn broken_log_reports_every_problem() {
+    let log = vec![
+      add("a"),
+      stop(None, 5),
+      start("nope", 10),
+      start("a", 10),
+      stop(None, 9),
+      stop(Some("a"), 20),
+      Err(()),
+    ];
+    let verification = check(log.into_iter());
+    assert_eq!(verification.problems.len(), 5);
+    assert!(verification.problems[0].starts_with("entry #2"));
+    assert!(verification.problems[3].contains("isn't running"));
+    assert!(verification.problems[4].starts_with("entry #7"));
+  }
+}
@@ -0,0 +1,153 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::db::Database;
+use crate::theme;
+use std::fmt::Display;
+use std::io;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// A failure as reported to the user: what went wrong, why, and what can be done about it
+pub struct Diagnostic {
+  message: String,
+  causes: Vec<String>,
+  hint: Option<String>,
+}
+
+impl Diagnostic {
+  pub fn new(message: impl Display) -> Self {
+    Diagnostic {
+      message: message.to_string(),
+      causes: Vec::new(),
+      hint: None,
+    }
+  }
+
+  pub fn caused_by(mut self, cause: impl Display) -> Self {
+    self.causes.push(cause.to_string());
+    self
+  }
+
+  pub fn hint(mut self, hint: impl Display) -> Self {
+    self.hint = Some(hint.to_string());
+    self
+  }
+
+  /// Explains why the database at `location` couldn't be used
+  pub fn storage(location: &Path, err: ErrorKind) -> Self {
+    let diagnostic = Diagnostic::new(format!("Couldn't access storage at {}", location.display()));
+    match err {
+      ErrorKind::InvalidInput => diagnostic
+        .caused_by(format!(
+          "{} doesn't appear to be a directory",
+          location.display()
+        ))
+        .hint("move whatever is there out of the way, it'll be initialized on the next run"),
+      ErrorKind::AlreadyExists => diagnostic
+        .caused_by(format!(
+          "the lock {} is held",
+          Database::lock_file(location).display()
+        ))
+        .hint(format!(
+          "another instance holds the lock — run with --wait or remove {} if no process is running",
+          Database::lock_file(location).display()
+        )),
+      ErrorKind::NotFound => diagnostic
+        .caused_by("there is no database there yet")
+        .hint("run any other command, e.g. `timek status`, to initialize it"),
+      ErrorKind::PermissionDenied => diagnostic
+        .caused_by(io::Error::from(err))
+        .hint(format!("check the permissions on {}", location.display())),
+      ErrorKind::InvalidData => diagnostic
+        .caused_by("the log contains entries that can't be replayed")
+        .hint("run `timek verify` to find the offending entries"),
+      ErrorKind::Interrupted => diagnostic
+        .caused_by("the log kept being rewritten while reading it")
+        .hint("try again once whatever is pruning or amending records is done"),
+      _ => diagnostic.caused_by(io::Error::from(err)),
+    }
+  }
+
+  pub fn report(&self) {
+    eprintln!("{} {}", theme::failure("FAIL").bold(), self.message);
+    self
+      .causes
+      .iter()
+      .for_each(|cause| eprintln!("  {} {}", theme::failure("caused by:"), cause));
+    if let Some(hint) = &self.hint {
+      eprintln!("  {} {}", theme::info("hint:"), hint);
+    }
+  }
+
+  /// Reports and exits with a failure status
+  pub fn exit(&self) -> ! {
+    self.report();
+    std::process::exit(1);
+  }
+}
+
+/// The candidate closest to `name`, if any is close enough to probably be a typo of it
+pub fn did_you_mean<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+  let name = name.to_lowercase();
+  let threshold = (name.chars().count() / 3).max(2);
+  candidates
+    .map(|candidate| (levenshtein(&name, &candidate.to_lowercase()), candidate))
+    .filter(|(distance, _)| *distance <= threshold)
+    .min_by_key(|(distance, _)| *distance)
+    .map(|(_, candidate)| candidate)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut previous: Vec<usize> = (0..=b.len()).collect();
+  for (i, ca) in a.chars().enumerate() {
+    let mut current = vec![i + 1; b.len() + 1];
+    for (j, cb) in b.iter().enumerate() {
+      let substitution = previous[j] + usize::from(ca != *cb);
+      current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+    }
+    previous = current;
+  }
+  previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::diagnostic::{did_you_mean, levenshtein};
+
+  #[test]
+  fn levenshtein_distances() {
+    assert_eq!(levenshtein("", ""), 0);
+    assert_eq!(levenshtein("kitten", "sitting"), 3);
+    assert_eq!(levenshtein("backend", "backend"), 0);
+    assert_eq!(levenshtein("", "abc"), 3);
+  }
+
+  #[test]
+  fn suggests_closest_candidate() {
+    let projects = ["Backend", "Frontend", "Ops"];
+    assert_eq!(
+      did_you_mean("bakend", projects.into_iter()),
+      Some("Backend")
+    );
+    assert_eq!(
+      did_you_mean("frontnd", projects.into_iter()),
+      Some("Frontend")
+    );
+    assert_eq!(did_you_mean("marketing", projects.into_iter()), None);
+  }
+}
@@ -0,0 +1,196 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::core::{Project, Record};
+use chrono::{DateTime, FixedOffset};
+use std::time::Duration;
+
+/// How much rewriting the WAL needs to save for it to be worth it, in bytes
+const WORTH_COMPACTING: u64 = 64 * 1024;
+
+/// How the database is doing, to tell when maintenance is worth it
+pub struct Health {
+  /// The size of the WAL, in bytes
+  pub wal_len: u64,
+  /// How many entries the WAL holds, as numbered by `verify`
+  pub entries: usize,
+  /// How long replaying the whole WAL takes
+  pub replay: Duration,
+  pub projects: usize,
+  pub records: usize,
+  /// The size of the WAL rewritten from what it replays into, in bytes
+  pub compacted_len: u64,
+}
+
+impl Health {
+  /// How many bytes rewriting the WAL would save
+  pub fn savings(&self) -> u64 {
+    self.wal_len.saturating_sub(self.compacted_len)
+  }
+
+  /// Whether rewriting the WAL would save a good share of it, and enough bytes to matter
+  pub fn worth_compacting(&self) -> bool {
+    self.savings() >= WORTH_COMPACTING && self.savings() * 4 >= self.wal_len
+  }
+}
+
+/// `bytes`, in the largest binary unit they amount to at least one of, e.g. 1.5 KiB
+pub fn size(bytes: u64) -> String {
+  let mut size = bytes as f64;
+  for unit in ["B", "KiB", "MiB"] {
+    if size < 1024.0 {
+      return match unit {
+        "B" => format!("{} B", bytes),
+        unit => format!("{:.1} {}", size, unit),
+      };
+    }
+    size /= 1024.0;
+  }
+  format!("{:.1} GiB", size)
+}
+
+/// A record that looks impossible on the wall clock, though it isn't in time: only the clock's
+/// offset changing, e.g. when travelling or on a DST change, explains it
+pub struct ClockJump {
+  pub project: String,
+  pub start: DateTime<FixedOffset>,
+  pub reason: String,
+}
+
+/// The records of `projects` that seem to end before they start, or to start before the record
+/// preceding them ended, when read off the wall clock at their own offsets
+pub fn clock_jumps(projects: &[&Project]) -> Vec<ClockJump> {
+  let mut records: Vec<(&str, &Record)> = projects
+    .iter()
+    .flat_map(|p| p.records().map(move |r| (p.name(), r)))
+    .collect();
+  records.sort_by_key(|(_, record)| record.start());
+  let wall = |at: DateTime<FixedOffset>| at.naive_local();
+  let describe = |at: DateTime<FixedOffset>| at.format("%Y-%m-%d %H:%M %:z").to_string();
+  let mut jumps = Vec::new();
+  let mut previous: Option<(&str, DateTime<FixedOffset>)> = None;
+  for (name, record) in records {
+    let start = record.start();
+    if let Some((before, end)) = previous.filter(|(_, end)| *end <= start) {
+      if wall(start) < wall(end) {
+        jumps.push(ClockJump {
+          project: name.to_string(),
+          start,
+          reason: format!(
+            "starts at {}, before '{}' ended at {}",
+            describe(start),
+            before,
+            describe(end)
+          ),
+        });
+      }
+    }
+    if let Some(end) = record.end() {
+      if wall(end) < wall(start) {
+        jumps.push(ClockJump {
+          project: name.to_string(),
+          start,
+          reason: format!(
+            "starts at {}, after it ends at {}",
+            describe(start),
+            describe(end)
+          ),
+        });
+      }
+      // Concurrent records are fine, only what follows the latest end could look off
+      if previous.is_none_or(|(_, latest)| end > latest) {
+        previous = Some((name, end));
+      }
+    }
+  }
+  jumps
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::core::{Project, Record};
+  use crate::doctor::{clock_jumps, size, Health};
+  use chrono::DateTime;
+  use std::time::Duration;
+
+  #[test]
+  fn tells_when_compacting_is_worth_it() {
+    let health = |wal_len, compacted_len| Health {
+      wal_len,
+      entries: 0,
+      replay: Duration::ZERO,
+      projects: 0,
+      records: 0,
+      compacted_len,
+    };
+    assert!(health(1024 * 1024, 512 * 1024).worth_compacting());
+    // A good share, yet only a few bytes
+    assert!(!health(4096, 1024).worth_compacting());
+    // Enough bytes, yet only a sliver of it
+    assert!(!health(10 * 1024 * 1024, 9 * 1024 * 1024).worth_compacting());
+    assert_eq!(health(100, 200).savings(), 0);
+    assert_eq!(size(512), "512 B");
+    assert_eq!(size(1536), "1.5 KiB");
+    assert_eq!(size(3 * 1024 * 1024), "3.0 MiB");
+    assert_eq!(size(2048 * 1024 * 1024), "2.0 GiB");
+  }
+
+  #[test]
+  fn finds_what_only_clock_changes_explain() {
+    let at = |rfc3339| DateTime::parse_from_rfc3339(rfc3339).unwrap();
+    let record = |start, end| {
+      let mut record = Record::started_on(at(start));
+      record.end_at(at(end)).unwrap();
+      record
+    };
+    let mut home = Project::new("home".to_string());
+    home
+      .add_record(record(
+        "2022-03-14T10:00:00+02:00",
+        "2022-03-14T11:00:00+02:00",
+      ))
+      .unwrap();
+    let mut travel = Project::new("travel".to_string());
+    // An hour after home ended, but at 10:30 on the wall clock
+    travel
+      .add_record(record(
+        "2022-03-14T10:30:00+01:00",
+        "2022-03-14T11:30:00+01:00",
+      ))
+      .unwrap();
+    // Ends 30 minutes after it started, at 00:15 on the wall clock
+    travel
+      .add_record(record(
+        "2022-03-15T00:45:00+01:00",
+        "2022-03-15T00:15:00+00:00",
+      ))
+      .unwrap();
+    let jumps = clock_jumps(&[&home, &travel]);
+    assert_eq!(jumps.len(), 2);
+    assert_eq!(jumps[0].project, "travel");
+    assert!(jumps[0].reason.contains("before 'home' ended"));
+    assert!(jumps[1].reason.contains("after it ends"));
+
+    let mut steady = Project::new("steady".to_string());
+    steady
+      .add_record(record(
+        "2022-03-14T12:00:00+02:00",
+        "2022-03-14T13:00:00+02:00",
+      ))
+      .unwrap();
+    assert!(clock_jumps(&[&home, &steady]).is_empty());
+  }
+}
@@ -0,0 +1,171 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! `timek editor-server`: JSON-RPC 2.0 over stdin and stdout, one message per line, for editor
+//! plugins to drive timeknight through a single long lived process.
+//!
+//! Methods:
+//! - `status`: what's running and what ran last, cheap enough to poll
+//! - `projects`: the names of all projects
+//! - `start`, `{"project": name}`: starts tracking it, stopping whatever is running
+//! - `stop`: stops whatever is running
+//! - `hint`, `{"path": file}`: the project the file being edited belongs to, if any
+
+use crate::config::Config;
+use crate::core::Record;
+use crate::tracker::{Tracker, TrackerError};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Failures of the tracker itself, e.g. an unknown project
+const TRACKER_ERROR: i64 = -32000;
+
+/// Answers every request read from `input` on `output`, until `input` is closed
+pub fn serve(
+  tracker: &Tracker,
+  config: &Config,
+  input: impl BufRead,
+  mut output: impl Write,
+) -> io::Result<()> {
+  for line in input.lines() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let response = match serde_json::from_str::<Value>(&line) {
+      Ok(request) => respond(tracker, config, &request),
+      Err(err) => Some(error(Value::Null, PARSE_ERROR, err.to_string())),
+    };
+    // Notifications, i.e. requests without an id, get no response
+    if let Some(response) = response {
+      writeln!(output, "{}", response)?;
+      output.flush()?;
+    }
+  }
+  Ok(())
+}
+
+fn respond(tracker: &Tracker, config: &Config, request: &Value) -> Option<Value> {
+  let id = request.get("id").cloned();
+  let method = request.get("method").and_then(Value::as_str);
+  let params = request.get("params").cloned().unwrap_or(Value::Null);
+  let param = |name: &str| params.get(name).and_then(Value::as_str);
+  let result = match method {
+    None => Err((INVALID_REQUEST, "no method".to_string())),
+    Some("status") => tracker.status().map_err(failed).map(|status| {
+      json!({
+        "running": status.running.iter().map(session).collect::<Vec<_>>(),
+        "last": status.last.as_ref().map(session),
+      })
+    }),
+    Some("projects") => tracker.projects().map_err(failed).map(|names| json!(names)),
+    Some("start") => match param("project") {
+      Some(name) => tracker
+        .start(name)
+        .map_err(failed)
+        .map(|started| session(&started)),
+      None => Err((INVALID_PARAMS, "needs a project".to_string())),
+    },
+    Some("stop") => tracker
+      .stop()
+      .map_err(failed)
+      .map(|stopped| json!(stopped.iter().map(session).collect::<Vec<_>>())),
+    Some("hint") => match param("path") {
+      Some(path) => Ok(json!({ "project": config.project_at(Path::new(path)) })),
+      None => Err((INVALID_PARAMS, "needs a path".to_string())),
+    },
+    Some(method) => Err((METHOD_NOT_FOUND, format!("no method {}", method))),
+  };
+  let id = id?;
+  Some(match result {
+    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+    Err((code, message)) => error(id, code, message),
+  })
+}
+
+fn failed(err: TrackerError) -> (i64, String) {
+  (TRACKER_ERROR, err.to_string())
+}
+
+fn error(id: Value, code: i64, message: String) -> Value {
+  json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn session((project, record): &(String, Record)) -> Value {
+  json!({
+    "project": project,
+    "start": record.start().to_rfc3339(),
+    "end": record.end().map(|end| end.to_rfc3339()),
+    "seconds": record.duration().as_secs(),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::Config;
+  use crate::db::{Database, Load};
+  use crate::editor::serve;
+  use crate::tracker::Tracker;
+  use serde_json::Value;
+  use std::env;
+  use std::fs::{create_dir, remove_dir_all};
+
+  #[test]
+  fn test_answers_each_request() {
+    let location = env::temp_dir().join("timeknightTest_answers_each_request");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Plugin".to_string()).unwrap();
+    }
+    let input = [
+      r#"{"jsonrpc": "2.0", "id": 1, "method": "start", "params": {"project": "plugin"}}"#,
+      r#"{"jsonrpc": "2.0", "method": "status"}"#,
+      r#"{"jsonrpc": "2.0", "id": 2, "method": "status"}"#,
+      r#"{"jsonrpc": "2.0", "id": 3, "method": "hint", "params": {"path": "/tmp/x.rs"}}"#,
+      r#"{"jsonrpc": "2.0", "id": 4, "method": "start"}"#,
+      r#"{"jsonrpc": "2.0", "id": 5, "method": "stop"}"#,
+      r#"{"jsonrpc": "2.0", "id": 6, "method": "stop"}"#,
+      r#"{"jsonrpc": "2.0", "id": 7, "method": "rewind"}"#,
+      "not json",
+    ]
+    .join("\n");
+    let mut output = Vec::new();
+    let tracker = Tracker::new(location.as_path());
+    serve(&tracker, &Config::default(), input.as_bytes(), &mut output).unwrap();
+    let responses: Vec<Value> = String::from_utf8(output)
+      .unwrap()
+      .lines()
+      .map(|line| serde_json::from_str(line).unwrap())
+      .collect();
+    assert_eq!(responses.len(), 8);
+    assert_eq!(responses[0]["result"]["project"], "Plugin");
+    assert_eq!(responses[1]["id"], 2);
+    assert_eq!(responses[1]["result"]["running"][0]["project"], "Plugin");
+    assert_eq!(responses[2]["result"]["project"], Value::Null);
+    assert_eq!(responses[3]["error"]["code"], -32602);
+    assert_eq!(responses[4]["result"][0]["project"], "Plugin");
+    assert_eq!(responses[5]["error"]["message"], "nothing is running");
+    assert_eq!(responses[6]["error"]["code"], -32601);
+    assert_eq!(responses[7]["error"]["code"], -32700);
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+}
@@ -0,0 +1,337 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::core::Project;
+use crate::db::ProjectTotals;
+use chrono::{Datelike, NaiveDate};
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook, XlsxError};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::io;
+use std::io::Write;
+use std::ops::RangeInclusive;
+
+/// Writes every record as a `project,start,end` CSV line, `end` being empty for the record in
+/// flight. With `anonymize`, project names are replaced by their [`pseudonym`].
+pub fn write_csv(out: &mut impl Write, projects: &[&Project], anonymize: bool) -> io::Result<()> {
+  writeln!(out, "project,start,end")?;
+  for project in projects {
+    let name = match anonymize {
+      true => pseudonym(project.name()),
+      false => project.name().to_string(),
+    };
+    for record in project.records() {
+      writeln!(
+        out,
+        "{},{},{}",
+        csv_field(&name),
+        record.start().to_rfc3339(),
+        record.end().map(|end| end.to_rfc3339()).unwrap_or_default(),
+      )?;
+    }
+  }
+  Ok(())
+}
+
+/// A line of the jsonl export: the record, as the core serializes it, along with its project
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct Line<'a> {
+  project: &'a str,
+  #[serde(flatten)]
+  record: &'a crate::core::Record,
+  seconds: Option<i64>,
+  cursor: Option<i64>,
+}
+
+/// Writes every record as a JSON object on its own line, for `jq` or incremental ingestion. With
+/// `anonymize`, project names, tags and notes are replaced by pseudonyms, and the context the
+/// records were tracked in is left out. Each
+/// line carries a `cursor`, the unix timestamp the record ended at: passing the greatest one seen
+/// as `since` on the next export only yields the records completed after it. The record in flight
+/// has no cursor yet, so it is left out of incremental exports.
+#[cfg(feature = "serde")]
+pub fn write_jsonl(
+  out: &mut impl Write,
+  projects: &[&Project],
+  anonymize: bool,
+  since: Option<i64>,
+) -> io::Result<()> {
+  for project in projects {
+    let name = match anonymize {
+      true => pseudonym(project.name()),
+      false => project.name().to_string(),
+    };
+    for record in project.records() {
+      let anonymized;
+      let record = match anonymize {
+        true => {
+          anonymized = record.anonymized(pseudonym_as);
+          &anonymized
+        }
+        false => record,
+      };
+      let end = record.end();
+      let cursor = end.map(|end| end.timestamp());
+      if let Some(since) = since {
+        if cursor.is_none_or(|cursor| cursor <= since) {
+          continue;
+        }
+      }
+      let line = Line {
+        project: &name,
+        record,
+        seconds: end.map(|end| (end - record.start()).num_seconds()),
+        cursor,
+      };
+      writeln!(out, "{}", serde_json::to_string(&line)?)?;
+    }
+  }
+  Ok(())
+}
+
+/// A workbook with a summary sheet of every project's total over `days`, followed by a sheet per
+/// project with its daily totals. Durations are Excel time values, so that they can be summed.
+pub fn xlsx_report(
+  title: &str,
+  projects: &[ProjectTotals],
+  days: RangeInclusive<NaiveDate>,
+) -> Result<Workbook, XlsxError> {
+  let bold = Format::new().set_bold();
+  let date = Format::new().set_num_format("yyyy-mm-dd");
+  let duration = Format::new().set_num_format("[h]:mm");
+  let as_days = |secs: u64| secs as f64 / 86400.0;
+
+  let mut workbook = Workbook::new();
+  let mut names = vec!["Summary".to_string()];
+  let summary = workbook.add_worksheet().set_name("Summary")?;
+  summary.write_string_with_format(0, 0, title, &bold)?;
+  summary.write_string_with_format(2, 0, "Project", &bold)?;
+  summary.write_string_with_format(2, 1, "Duration", &bold)?;
+  summary.set_column_width(0, 30)?;
+  for (row, project) in (3..).zip(projects) {
+    let total = project.days.range(days.clone()).map(|(_, secs)| secs).sum();
+    summary.write_string(row, 0, &project.name)?;
+    summary.write_number_with_format(row, 1, as_days(total), &duration)?;
+  }
+
+  for project in projects {
+    let name = sheet_name(&project.name, &names);
+    let sheet = workbook.add_worksheet();
+    // Excel reserves a few names, sheets keep their default one then
+    if sheet.set_name(&name).is_ok() {
+      names.push(name);
+    }
+    sheet.write_string_with_format(0, 0, "Day", &bold)?;
+    sheet.write_string_with_format(0, 1, "Duration", &bold)?;
+    sheet.set_column_width(0, 12)?;
+    for (row, (day, secs)) in (1..).zip(project.days.range(days.clone())) {
+      let day = ExcelDateTime::from_ymd(day.year() as u16, day.month() as u8, day.day() as u8)?;
+      sheet.write_date_with_format(row, 0, &day, &date)?;
+      sheet.write_number_with_format(row, 1, as_days(*secs), &duration)?;
+    }
+  }
+  Ok(workbook)
+}
+
+/// `name` made fit for a sheet: at most 31 characters, none of `[]:*?/\`, and unique amongst
+/// `taken`, which Excel compares case-insensitively.
+fn sheet_name(name: &str, taken: &[String]) -> String {
+  let clean: String = name
+    .chars()
+    .map(|c| match c {
+      '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+      c => c,
+    })
+    .take(31)
+    .collect();
+  let clean = clean.trim_matches('\'').to_string();
+  let is_taken = |candidate: &str| {
+    taken
+      .iter()
+      .any(|t| t.to_lowercase() == candidate.to_lowercase())
+  };
+  let mut candidate = clean.clone();
+  for n in 2.. {
+    if !candidate.is_empty() && !is_taken(&candidate) {
+      break;
+    }
+    let suffix = format!(" ({})", n);
+    let keep = 31 - suffix.len();
+    candidate = clean.chars().take(keep).collect::<String>() + &suffix;
+  }
+  candidate
+}
+
+/// A stable replacement for `name`: the same project always gets the same pseudonym, on any
+/// machine and across versions, regardless of its casing.
+pub fn pseudonym(name: &str) -> String {
+  pseudonym_as("project", name)
+}
+
+/// A stable replacement for `value`, telling it's a `kind` of value, e.g. a note
+fn pseudonym_as(kind: &str, value: &str) -> String {
+  // FNV-1a, as std's hashers aren't guaranteed to be stable
+  let hash = value
+    .to_lowercase()
+    .bytes()
+    .fold(0xcbf29ce484222325_u64, |hash, byte| {
+      (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+  format!("{}-{:08x}", kind, hash as u32)
+}
+
+pub(crate) fn csv_field(value: &str) -> String {
+  if value.contains([',', '"', '\n']) {
+    format!("\"{}\"", value.replace('"', "\"\""))
+  } else {
+    value.to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::core::{Project, Record};
+  use crate::db::ProjectTotals;
+  use crate::export::{pseudonym, sheet_name, write_csv, xlsx_report};
+  use chrono::{DateTime, NaiveDate};
+
+  #[test]
+  fn pseudonyms_are_stable() {
+    assert_eq!(pseudonym("Client X"), pseudonym("client x"));
+    assert_ne!(pseudonym("Client X"), pseudonym("Client Y"));
+    assert_eq!(pseudonym("backend"), "project-c402eb8f");
+  }
+
+  #[test]
+  fn anonymized_csv_keeps_timings() {
+    let mut project = Project::new("Acme, Inc.".to_string());
+    let start = DateTime::parse_from_rfc3339("2022-03-27T09:00:00-04:00").unwrap();
+    let mut record = Record::started_on(start);
+    record
+      .end_at(DateTime::parse_from_rfc3339("2022-03-27T10:00:00-04:00").unwrap())
+      .unwrap();
+    project.add_record(record).unwrap();
+
+    let mut out = Vec::new();
+    write_csv(&mut out, &[&project], false).unwrap();
+    let plain = String::from_utf8(out).unwrap();
+    assert!(plain.contains("\"Acme, Inc.\",2022-03-27T09:00:00-04:00,2022-03-27T10:00:00-04:00"));
+
+    let mut out = Vec::new();
+    write_csv(&mut out, &[&project], true).unwrap();
+    let anonymized = String::from_utf8(out).unwrap();
+    assert!(!anonymized.contains("Acme"));
+    assert_eq!(
+      anonymized.lines().nth(1).unwrap(),
+      format!(
+        "{},2022-03-27T09:00:00-04:00,2022-03-27T10:00:00-04:00",
+        pseudonym("Acme, Inc.")
+      )
+    );
+  }
+
+  #[test]
+  #[cfg(feature = "serde")]
+  fn jsonl_resumes_from_cursor() {
+    use crate::export::write_jsonl;
+
+    let mut project = Project::new("backend".to_string());
+    for hour in ["09", "11"] {
+      let start = format!("2022-03-27T{}:00:00-04:00", hour);
+      let mut record = Record::started_on(DateTime::parse_from_rfc3339(&start).unwrap());
+      record
+        .end_at(DateTime::parse_from_rfc3339(&start.replace(":00:00", ":30:00")).unwrap())
+        .unwrap();
+      project.add_record(record).unwrap();
+    }
+
+    let mut out = Vec::new();
+    write_jsonl(&mut out, &[&project], false, None).unwrap();
+    let all = String::from_utf8(out).unwrap();
+    assert_eq!(all.lines().count(), 2);
+    let first: serde_json::Value = serde_json::from_str(all.lines().next().unwrap()).unwrap();
+    assert_eq!(first["project"], "backend");
+    assert_eq!(first["seconds"], 1800);
+
+    let mut out = Vec::new();
+    let cursor = first["cursor"].as_i64().unwrap();
+    write_jsonl(&mut out, &[&project], false, Some(cursor)).unwrap();
+    let since = String::from_utf8(out).unwrap();
+    assert_eq!(since.lines().count(), 1);
+    assert!(since.contains("\"start\":\"2022-03-27T11:00:00-04:00\""));
+  }
+
+  #[test]
+  #[cfg(feature = "serde")]
+  fn anonymized_jsonl_leaves_details_out() {
+    use crate::core::{Context, Labels};
+    use crate::export::write_jsonl;
+
+    let mut project = Project::new("Acme".to_string());
+    let start = DateTime::parse_from_rfc3339("2022-03-27T09:00:00-04:00").unwrap();
+    let mut record = Record::started_on(start);
+    record.note("call with the CFO".to_string());
+    record.label(&Labels {
+      billable: None,
+      tags: Some(vec!["acme-sales".to_string()]),
+    });
+    record.set_context(Context {
+      host: Some("acme-laptop".to_string()),
+      branch: Some("acme/invoicing".to_string()),
+      cwd: Some("/home/jane/acme".to_string()),
+      user: Some("jane".to_string()),
+    });
+    project.add_record(record).unwrap();
+
+    let mut out = Vec::new();
+    write_jsonl(&mut out, &[&project], true, None).unwrap();
+    let anonymized = String::from_utf8(out).unwrap();
+    assert!(!anonymized.to_lowercase().contains("acme"));
+    assert!(!anonymized.contains("CFO"));
+    assert!(!anonymized.contains("jane"));
+    let line: serde_json::Value = serde_json::from_str(anonymized.trim()).unwrap();
+    assert!(line["notes"][0].as_str().unwrap().starts_with("note-"));
+    assert!(line["tags"][0].as_str().unwrap().starts_with("tag-"));
+    assert!(line.get("context").is_none());
+  }
+
+  #[test]
+  fn sheet_names_are_valid_and_unique() {
+    let taken = vec!["Summary".to_string()];
+    assert_eq!(sheet_name("client/backend", &taken), "client_backend");
+    assert_eq!(sheet_name("summary", &taken), "summary (2)");
+    assert_eq!(sheet_name(&"x".repeat(40), &taken).len(), 31);
+    let taken = vec!["x".repeat(31)];
+    assert_eq!(
+      sheet_name(&"X".repeat(40), &taken),
+      format!("{} (2)", "X".repeat(27))
+    );
+  }
+
+  #[test]
+  fn xlsx_report_has_a_sheet_per_project() {
+    let day = NaiveDate::from_ymd(2022, 3, 27);
+    let mut backend = ProjectTotals::new("backend".to_string());
+    backend.add(day, 3600);
+    let mut history = ProjectTotals::new("History".to_string());
+    history.add(day, 60);
+    let mut workbook = xlsx_report("Time report", &[backend, history], day..=day).unwrap();
+    assert_eq!(workbook.worksheets().len(), 3);
+    assert_eq!(workbook.worksheets()[1].name(), "backend");
+    assert!(workbook.save_to_buffer().unwrap().starts_with(b"PK"));
+  }
+}
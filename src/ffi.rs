@@ -0,0 +1,208 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A C API over the [`Tracker`], for native apps, e.g. menu bar ones, to embed timeknight rather
+//! than shelling out to it. Functions return `TIMEK_OK` or a negative error code, or a JSON
+//! string to be released with `timek_string_free`. See `include/timeknight.h`.
+
+use crate::core::Record;
+use crate::report::Period;
+use crate::tracker::{Tracker, TrackerError};
+use serde_json::{json, Value};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::path::Path;
+use std::ptr;
+
+pub const TIMEK_OK: c_int = 0;
+pub const TIMEK_UNKNOWN_PROJECT: c_int = -1;
+pub const TIMEK_NOT_RUNNING: c_int = -2;
+pub const TIMEK_STORAGE: c_int = -3;
+pub const TIMEK_UNWRITABLE: c_int = -4;
+pub const TIMEK_INVALID_ARGUMENT: c_int = -5;
+pub const TIMEK_ARCHIVED: c_int = -6;
+
+fn code(err: TrackerError) -> c_int {
+  match err {
+    TrackerError::UnknownProject(_) => TIMEK_UNKNOWN_PROJECT,
+    TrackerError::Archived(_) => TIMEK_ARCHIVED,
+    TrackerError::NotRunning => TIMEK_NOT_RUNNING,
+    TrackerError::Storage(_) => TIMEK_STORAGE,
+    TrackerError::Unwritable => TIMEK_UNWRITABLE,
+  }
+}
+
+/// The string `raw` points to, if it's valid UTF-8
+unsafe fn string<'a>(raw: *const c_char) -> Option<&'a str> {
+  match raw.is_null() {
+    true => None,
+    false => CStr::from_ptr(raw).to_str().ok(),
+  }
+}
+
+fn to_c(value: Value) -> *mut c_char {
+  match CString::new(value.to_string()) {
+    Ok(json) => json.into_raw(),
+    Err(_) => ptr::null_mut(),
+  }
+}
+
+fn session((project, record): &(String, Record)) -> Value {
+  json!({
+    "project": project,
+    "start": record.start(),
+    "end": record.end(),
+    "seconds": record.duration().as_secs(),
+  })
+}
+
+/// Creates a tracker for the database at `location`, e.g. `~/.timeknight`. Returns null if
+/// `location` isn't a valid path.
+///
+/// # Safety
+/// `location` must be a null-terminated string. The tracker is released by `timek_tracker_free`.
+#[no_mangle]
+pub unsafe extern "C" fn timek_tracker_new(location: *const c_char) -> *mut Tracker {
+  match string(location) {
+    Some(location) => Box::into_raw(Box::new(Tracker::new(Path::new(location)))),
+    None => ptr::null_mut(),
+  }
+}
+
+/// Releases a tracker created by `timek_tracker_new`
+///
+/// # Safety
+/// `tracker` must come from `timek_tracker_new`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn timek_tracker_free(tracker: *mut Tracker) {
+  if !tracker.is_null() {
+    drop(Box::from_raw(tracker));
+  }
+}
+
+/// Starts tracking the project `name`, stopping whatever is running
+///
+/// # Safety
+/// `tracker` must come from `timek_tracker_new`, `name` must be a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn timek_start(tracker: *const Tracker, name: *const c_char) -> c_int {
+  match (tracker.as_ref(), string(name)) {
+    (Some(tracker), Some(name)) => tracker.start(name).map_or_else(code, |_| TIMEK_OK),
+    _ => TIMEK_INVALID_ARGUMENT,
+  }
+}
+
+/// Stops everything that's running
+///
+/// # Safety
+/// `tracker` must come from `timek_tracker_new`.
+#[no_mangle]
+pub unsafe extern "C" fn timek_stop(tracker: *const Tracker) -> c_int {
+  match tracker.as_ref() {
+    Some(tracker) => tracker.stop().map_or_else(code, |_| TIMEK_OK),
+    None => TIMEK_INVALID_ARGUMENT,
+  }
+}
+
+/// What's running and what ran last, as JSON: `{"running": [...], "last": ...}`, each session
+/// with its `project`, `start`, `end` and `seconds`. Returns null on failure.
+///
+/// # Safety
+/// `tracker` must come from `timek_tracker_new`. The string is released by `timek_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn timek_status_json(tracker: *const Tracker) -> *mut c_char {
+  match tracker.as_ref().map(|tracker| tracker.status()) {
+    Some(Ok(status)) => to_c(json!({
+      "running": status.running.iter().map(session).collect::<Vec<_>>(),
+      "last": status.last.as_ref().map(session),
+    })),
+    _ => ptr::null_mut(),
+  }
+}
+
+/// The time tracked per project over `period`, e.g. `week` or `2022-03-01..2022-03-15`, as a
+/// JSON array of `{"project", "day", "seconds"}`. Returns null on failure.
+///
+/// # Safety
+/// `tracker` must come from `timek_tracker_new`, `period` must be a null-terminated string. The
+/// string is released by `timek_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn timek_report_json(
+  tracker: *const Tracker,
+  period: *const c_char,
+) -> *mut c_char {
+  let period = match string(period).map(str::parse::<Period>) {
+    Some(Ok(period)) => period,
+    _ => return ptr::null_mut(),
+  };
+  match tracker.as_ref().map(|tracker| tracker.report(&period)) {
+    Some(Ok(rows)) => to_c(json!(rows)),
+    _ => ptr::null_mut(),
+  }
+}
+
+/// Releases a string returned by any of the functions above
+///
+/// # Safety
+/// `string` must come from this API, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn timek_string_free(string: *mut c_char) {
+  if !string.is_null() {
+    drop(CString::from_raw(string));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::db::{Database, Load};
+  use crate::ffi::*;
+  use std::env;
+  use std::ffi::{CStr, CString};
+  use std::fs::{create_dir, remove_dir_all};
+
+  #[test]
+  fn test_tracks_through_c() {
+    let location = env::temp_dir().join("timeknightTest_tracks_through_c");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("native".to_string()).unwrap();
+    }
+    let c = |s: &str| CString::new(s).unwrap();
+    unsafe {
+      let tracker = timek_tracker_new(c(location.to_str().unwrap()).as_ptr());
+      assert!(!tracker.is_null());
+      assert_eq!(
+        timek_start(tracker, c("nope").as_ptr()),
+        TIMEK_UNKNOWN_PROJECT
+      );
+      assert_eq!(timek_start(tracker, c("native").as_ptr()), TIMEK_OK);
+      let status = timek_status_json(tracker);
+      let json = CStr::from_ptr(status).to_str().unwrap().to_string();
+      timek_string_free(status);
+      assert!(json.starts_with(r#"{"last":null,"running":[{"end":null,"project":"native""#));
+      assert_eq!(timek_stop(tracker), TIMEK_OK);
+      assert_eq!(timek_stop(tracker), TIMEK_NOT_RUNNING);
+      assert!(timek_report_json(tracker, c("someday").as_ptr()).is_null());
+      let report = timek_report_json(tracker, c("today").as_ptr());
+      let json = CStr::from_ptr(report).to_str().unwrap().to_string();
+      timek_string_free(report);
+      assert!(json.starts_with(r#"[{"day":null,"project":"native","seconds":"#));
+      timek_tracker_free(tracker);
+    }
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+}
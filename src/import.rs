@@ -0,0 +1,404 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::config::{parse_duration, Config};
+use crate::core::Record;
+use chrono::DateTime;
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::io::BufRead;
+
+/// Why a line of a file being imported couldn't be read
+#[derive(Debug)]
+pub struct ImportError {
+  /// The line, counting from 1
+  pub line: usize,
+  pub reason: String,
+}
+
+impl Display for ImportError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "line {}: {}", self.line, self.reason)
+  }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Reads records as [`write_csv`](crate::export::write_csv) writes them, `project,start,end`
+/// lines after a header, times in RFC 3339. Records without an end are read as in flight.
+pub fn read_csv(input: impl BufRead) -> Result<Vec<(String, Record)>, ImportError> {
+  let mut records = Vec::new();
+  for (n, line) in input.lines().enumerate() {
+    let error = |reason: String| ImportError {
+      line: n + 1,
+      reason,
+    };
+    let line = line.map_err(|err| error(err.to_string()))?;
+    if n == 0 && line.trim() == "project,start,end" || line.trim().is_empty() {
+      continue;
+    }
+    let fields = csv_fields(&line).ok_or_else(|| error("unbalanced quotes".to_string()))?;
+    let (project, start, end) = match fields.as_slice() {
+      [project, start, end] => (project, start, end),
+      _ => return Err(error(format!("expected 3 fields, not {}", fields.len()))),
+    };
+    let at = |raw: &str| {
+      DateTime::parse_from_rfc3339(raw).map_err(|_| error(format!("'{}' isn't a time", raw)))
+    };
+    let mut record = Record::started_on(at(start)?);
+    if !end.is_empty() {
+      record
+        .end_at(at(end)?)
+        .map_err(|_| error("ends before it starts".to_string()))?;
+    }
+    records.push((project.to_string(), record));
+  }
+  Ok(records)
+}
+
+/// Reads records as [`write_jsonl`](crate::export::write_jsonl) writes them, one JSON object per
+/// line, labels and context included
+#[cfg(feature = "serde")]
+pub fn read_jsonl(input: impl BufRead) -> Result<Vec<(String, Record)>, ImportError> {
+  #[derive(serde::Deserialize)]
+  struct Line {
+    project: String,
+    #[serde(flatten)]
+    record: Record,
+  }
+
+  let mut records = Vec::new();
+  for (n, line) in input.lines().enumerate() {
+    let error = |reason: String| ImportError {
+      line: n + 1,
+      reason,
+    };
+    let line = line.map_err(|err| error(err.to_string()))?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let line: Line = serde_json::from_str(&line).map_err(|err| error(err.to_string()))?;
+    if line
+      .record
+      .end()
+      .is_some_and(|end| end < line.record.start())
+    {
+      return Err(error("ends before it starts".to_string()));
+    }
+    records.push((line.project, line.record));
+  }
+  Ok(records)
+}
+
+/// A project to create, along with what it's to be configured with
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectImport {
+  #[serde(skip)]
+  pub name: String,
+  /// The client it's billed to
+  pub client: Option<String>,
+  pub rate: Option<f64>,
+  pub tags: Vec<String>,
+  /// As configured, e.g. "120h"
+  pub budget: Option<String>,
+}
+
+/// Reads projects from `name,client,rate,tags,budget` lines, after a header naming the columns,
+/// in any order. Only the name is required, tags are separated by semicolons.
+pub fn read_projects_csv(input: impl BufRead) -> Result<Vec<ProjectImport>, ImportError> {
+  let mut columns: Vec<String> = Vec::new();
+  let mut projects = Vec::new();
+  for (n, line) in input.lines().enumerate() {
+    let error = |reason: String| ImportError {
+      line: n + 1,
+      reason,
+    };
+    let line = line.map_err(|err| error(err.to_string()))?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let fields = csv_fields(&line).ok_or_else(|| error("unbalanced quotes".to_string()))?;
+    if n == 0 {
+      columns = fields
+        .iter()
+        .map(|field| field.trim().to_lowercase())
+        .collect();
+      if !columns.iter().any(|column| column == "name") {
+        return Err(error("expected a header with a name column".to_string()));
+      }
+      continue;
+    }
+    if fields.len() != columns.len() {
+      return Err(error(format!(
+        "expected {} fields, not {}",
+        columns.len(),
+        fields.len()
+      )));
+    }
+    let mut project = ProjectImport::default();
+    for (column, field) in columns.iter().zip(fields) {
+      let field = field.trim().to_string();
+      if field.is_empty() {
+        continue;
+      }
+      match column.as_str() {
+        "name" => project.name = field,
+        "client" => project.client = Some(field),
+        "rate" => match field.parse() {
+          Ok(rate) => project.rate = Some(rate),
+          Err(_) => return Err(error(format!("'{}' isn't a rate", field))),
+        },
+        "tags" => project.tags = field.split(';').map(|tag| tag.trim().to_string()).collect(),
+        "budget" => project.budget = Some(field),
+        column => return Err(error(format!("unknown column '{}'", column))),
+      }
+    }
+    checked(&project).map_err(error)?;
+    projects.push(project);
+  }
+  Ok(projects)
+}
+
+/// Reads projects from tables laid out as in the configuration, e.g.
+///
+/// ```toml
+/// [projects.Backend]
+/// client = "Acme"
+/// rate = 100.0
+/// tags = ["dev"]
+/// budget = "120h"
+/// ```
+pub fn read_projects_toml(content: &str) -> Result<Vec<ProjectImport>, ImportError> {
+  #[derive(Deserialize)]
+  #[serde(deny_unknown_fields)]
+  struct File {
+    projects: BTreeMap<String, ProjectImport>,
+  }
+  let file: File = toml::from_str(content).map_err(|err| ImportError {
+    line: err.line_col().map_or(1, |(line, _)| line + 1),
+    reason: err.to_string(),
+  })?;
+  let mut projects = Vec::new();
+  for (name, mut project) in file.projects {
+    project.name = name;
+    checked(&project).map_err(|reason| ImportError { line: 1, reason })?;
+    projects.push(project);
+  }
+  Ok(projects)
+}
+
+fn checked(project: &ProjectImport) -> Result<(), String> {
+  if project.name.trim().is_empty() {
+    return Err("a project needs a name".to_string());
+  }
+  match &project.budget {
+    Some(budget) if parse_duration(budget).is_none() => Err(format!(
+      "invalid budget '{}', expected e.g. 120h or 1h30m",
+      budget
+    )),
+    _ => Ok(()),
+  }
+}
+
+/// The settings of imported `projects`, to be appended to the configuration
+#[derive(Debug, Default, PartialEq)]
+pub struct Settings {
+  /// The tables to append, in TOML
+  pub toml: String,
+  /// What couldn't be configured, as it already is
+  pub left_out: Vec<String>,
+}
+
+/// Configures `projects` as imported, but for the ones `config` already has settings for. Clients
+/// not configured yet get a table of their own, billing their projects.
+pub fn settings(projects: &[ProjectImport], config: &Config) -> Settings {
+  let quoted = |raw: &str| toml::Value::String(raw.to_string()).to_string();
+  let key = |raw: &str| match raw
+    .chars()
+    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+  {
+    true => raw.to_string(),
+    false => quoted(raw),
+  };
+  let mut settings = Settings::default();
+  let mut clients: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+  for project in projects {
+    if let Some(client) = &project.client {
+      match config.client(client) {
+        Some((_, configured)) if configured.bills(&project.name) => {}
+        Some((client, _)) => settings.left_out.push(format!(
+          "'{}' for client '{}', configured already",
+          project.name, client
+        )),
+        None => clients.entry(client).or_default().push(&project.name),
+      }
+    }
+    if project.rate.is_none() && project.tags.is_empty() && project.budget.is_none() {
+      continue;
+    }
+    if config.project(&project.name).is_some() {
+      settings.left_out.push(format!(
+        "the settings of '{}', configured already",
+        project.name
+      ));
+      continue;
+    }
+    settings
+      .toml
+      .push_str(&format!("[projects.{}]\n", key(&project.name)));
+    if let Some(rate) = project.rate {
+      settings
+        .toml
+        .push_str(&format!("rate = {}\n", toml::Value::Float(rate)));
+    }
+    if !project.tags.is_empty() {
+      let tags: Vec<String> = project.tags.iter().map(|tag| quoted(tag)).collect();
+      settings
+        .toml
+        .push_str(&format!("tags = [{}]\n", tags.join(", ")));
+    }
+    if let Some(budget) = &project.budget {
+      settings
+        .toml
+        .push_str(&format!("budget = {}\n", quoted(budget)));
+    }
+    settings.toml.push('\n');
+  }
+  for (client, projects) in clients {
+    let projects: Vec<String> = projects.iter().map(|project| quoted(project)).collect();
+    settings.toml.push_str(&format!(
+      "[clients.{}]\nprojects = [{}]\n\n",
+      key(client),
+      projects.join(", ")
+    ));
+  }
+  settings.toml.truncate(settings.toml.trim_end().len());
+  if !settings.toml.is_empty() {
+    settings.toml.push('\n');
+  }
+  settings
+}
+
+/// What a completed record is identified by when importing: its project, regardless of casing,
+/// and the instants it started and ended at. Offsets don't matter, for the same instant to be the
+/// same record wherever it was exported.
+pub fn fingerprint(project: &str, record: &Record) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  project.to_lowercase().hash(&mut hasher);
+  record.start().timestamp().hash(&mut hasher);
+  record.end().map(|end| end.timestamp()).hash(&mut hasher);
+  hasher.finish()
+}
+
+/// The fields of a CSV line, unquoted, or `None` when a quote isn't closed
+fn csv_fields(line: &str) -> Option<Vec<String>> {
+  let mut fields = Vec::new();
+  let mut field = String::new();
+  let mut quoted = false;
+  let mut chars = line.chars().peekable();
+  while let Some(c) = chars.next() {
+    match (c, quoted) {
+      ('"', true) if chars.peek() == Some(&'"') => {
+        chars.next();
+        field.push('"');
+      }
+      ('"', _) => quoted = !quoted,
+      (',', false) => fields.push(std::mem::take(&mut field)),
+      (c, _) => field.push(c),
+    }
+  }
+  if quoted {
+    return None;
+  }
+  fields.push(field);
+  Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::Config;
+  use crate::core::{Project, Record};
+  use crate::export::write_csv;
+  use crate::import::{fingerprint, read_csv, read_projects_csv, read_projects_toml, settings};
+  use chrono::DateTime;
+
+  #[test]
+  fn reads_what_is_exported() {
+    let at = |rfc3339| DateTime::parse_from_rfc3339(rfc3339).unwrap();
+    let mut project = Project::new("Client \"X\", backend".to_string());
+    let mut record = Record::started_on(at("2022-03-14T09:00:00-04:00"));
+    record.end_at(at("2022-03-14T10:30:00-04:00")).unwrap();
+    project.add_record(record).unwrap();
+    project
+      .add_record(Record::started_on(at("2022-03-14T11:00:00-04:00")))
+      .unwrap();
+    let mut out = Vec::new();
+    write_csv(&mut out, &[&project], false).unwrap();
+
+    let records = read_csv(out.as_slice()).unwrap();
+    assert_eq!(records.len(), 2);
+    let (name, first) = &records[0];
+    assert_eq!(name, project.name());
+    assert_eq!(first.end(), Some(at("2022-03-14T10:30:00-04:00")));
+    assert!(records[1].1.is_on_going());
+
+    let mut moved = Record::started_on(at("2022-03-14T13:00:00Z"));
+    moved.end_at(at("2022-03-14T14:30:00Z")).unwrap();
+    assert_eq!(
+      fingerprint(&name.to_uppercase(), first),
+      fingerprint(name, &moved)
+    );
+    match read_csv("project,start,end\nfoo,yesterday,\n".as_bytes()) {
+      Err(error) => assert_eq!(error.line, 2),
+      Ok(_) => unreachable!("yesterday isn't a time"),
+    }
+  }
+
+  #[test]
+  fn reads_projects_and_configures_them() {
+    let csv = "name,client,tags,budget\nBackend,Acme,dev;api,120h\n\"Ops, misc\",Globex,,\n";
+    let projects = read_projects_csv(csv.as_bytes()).unwrap();
+    assert_eq!(projects.len(), 2);
+    assert_eq!(projects[0].tags, vec!["dev", "api"]);
+    assert_eq!(projects[1].name, "Ops, misc");
+    assert_eq!(projects[1].budget, None);
+    let toml =
+      "[projects.Backend]\nclient = \"Acme\"\ntags = [\"dev\", \"api\"]\nbudget = \"120h\"\n\n\
+                [projects.\"Ops, misc\"]\nclient = \"Globex\"\n";
+    assert_eq!(read_projects_toml(toml).unwrap(), projects);
+    match read_projects_csv("name,rate\nBackend,lots\n".as_bytes()) {
+      Err(error) => assert_eq!(error.line, 2),
+      Ok(_) => unreachable!("lots isn't a rate"),
+    }
+    assert!(read_projects_toml("[projects.Backend]\nbudget = \"lots\"").is_err());
+
+    let config: Config = toml::from_str("[clients.globex]\nprojects = [\"Ops\"]").unwrap();
+    let settings = settings(&projects, &config);
+    assert_eq!(
+      settings.toml,
+      "[projects.Backend]\ntags = [\"dev\", \"api\"]\nbudget = \"120h\"\n\n\
+       [clients.Acme]\nprojects = [\"Backend\"]\n"
+    );
+    assert_eq!(
+      settings.left_out,
+      vec!["'Ops, misc' for client 'globex', configured already"]
+    );
+  }
+}
@@ -0,0 +1,183 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::config::{ClientConfig, Config};
+use crate::core::Project;
+use crate::db::ProjectTotals;
+use chrono::NaiveDate;
+use std::ops::RangeInclusive;
+
+/// What a client owes for the time billed to it over a period
+pub struct Invoice {
+  /// None for drafts, which don't use up a number
+  pub number: Option<u64>,
+  pub date: NaiveDate,
+  pub client: String,
+  pub currency: Option<String>,
+  pub period: RangeInclusive<NaiveDate>,
+  pub lines: Vec<Line>,
+  pub expenses: Vec<ExpenseLine>,
+  /// As a percentage
+  pub tax_rate: Option<f64>,
+}
+
+pub struct Line {
+  pub project: String,
+  pub seconds: u64,
+  pub rate: f64,
+}
+
+pub struct ExpenseLine {
+  pub project: String,
+  pub date: NaiveDate,
+  pub description: String,
+  pub cents: i64,
+}
+
+impl ExpenseLine {
+  pub fn amount(&self) -> f64 {
+    self.cents as f64 / 100.0
+  }
+}
+
+impl Line {
+  pub fn amount(&self) -> f64 {
+    cents(self.seconds as f64 / 3600.0 * self.rate)
+  }
+}
+
+impl Invoice {
+  /// A line per project billed to `client` over `period`, from `projects` as billed, and one per
+  /// expense made on them. Fails with the name of the first project worked on that has no rate.
+  pub fn new(
+    date: NaiveDate,
+    (name, client): (&str, &ClientConfig),
+    config: &Config,
+    projects: &[ProjectTotals],
+    expenses: &[Project],
+    period: RangeInclusive<NaiveDate>,
+  ) -> Result<Self, String> {
+    let mut lines = Vec::new();
+    for project in projects {
+      let seconds = project.days.range(period.clone()).map(|(_, s)| s).sum();
+      if seconds == 0 {
+        continue;
+      }
+      match config.rate(&project.name) {
+        Some(rate) => lines.push(Line {
+          project: project.name.clone(),
+          seconds,
+          rate,
+        }),
+        None => return Err(project.name.clone()),
+      }
+    }
+    let mut expenses: Vec<ExpenseLine> = expenses
+      .iter()
+      .filter(|project| client.bills(project.name()))
+      .flat_map(|project| {
+        project
+          .expenses()
+          .map(|expense| (config.timezone.day(expense.at()), expense))
+          .filter(|(date, _)| period.contains(date))
+          .map(|(date, expense)| ExpenseLine {
+            project: project.name().to_string(),
+            date,
+            description: expense.description().to_string(),
+            cents: expense.cents(),
+          })
+      })
+      .collect();
+    expenses.sort_by_key(|expense| expense.date);
+    Ok(Invoice {
+      number: None,
+      date,
+      client: name.to_string(),
+      currency: client.currency.clone(),
+      period,
+      lines,
+      expenses,
+      tax_rate: client.tax,
+    })
+  }
+
+  pub fn subtotal(&self) -> f64 {
+    let spent: f64 = self.expenses.iter().map(ExpenseLine::amount).sum();
+    cents(self.lines.iter().map(Line::amount).sum::<f64>() + spent)
+  }
+
+  pub fn tax(&self) -> f64 {
+    cents(self.subtotal() * self.tax_rate.unwrap_or(0.0) / 100.0)
+  }
+
+  pub fn total(&self) -> f64 {
+    cents(self.subtotal() + self.tax())
+  }
+}
+
+fn cents(amount: f64) -> f64 {
+  (amount * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::Config;
+  use crate::core::{Expense, Project};
+  use crate::db::ProjectTotals;
+  use crate::invoice::Invoice;
+  use chrono::{DateTime, NaiveDate};
+
+  #[test]
+  fn taxes_the_billed_time() {
+    let config: Config = toml::from_str(
+      "timezone = \"recorded\"\n\
+       [clients.Acme]\n\
+       projects = [\"backend\", \"frontend\"]\n\
+       rate = 100.0\n\
+       tax = 20.0\n\
+       currency = \"EUR\"\n\
+       [projects.frontend]\n\
+       rate = 80.0\n",
+    )
+    .expect("valid");
+    let day = NaiveDate::from_ymd(2022, 3, 1);
+    let mut backend = ProjectTotals::new("backend".to_string());
+    backend.add(day, 5400);
+    let mut frontend = ProjectTotals::new("frontend".to_string());
+    frontend.add(day, 1200);
+    let idle = ProjectTotals::new("idle".to_string());
+
+    let mut spent = Project::new("backend".to_string());
+    let at = DateTime::parse_from_rfc3339("2022-03-01T09:00:00-04:00").unwrap();
+    spent.add_expense(Expense::new(at, 3450, "train ticket".to_string()));
+
+    let acme = config.client("acme").expect("configured");
+    let projects = [backend, frontend, idle];
+    let invoice = Invoice::new(day, acme, &config, &projects, &[spent], day..=day).expect("rated");
+    assert_eq!(invoice.lines.len(), 2);
+    assert_eq!(invoice.lines[1].amount(), 26.67);
+    assert_eq!(invoice.expenses.len(), 1);
+    assert_eq!(invoice.subtotal(), 211.17);
+    assert_eq!(invoice.tax(), 42.23);
+    assert_eq!(invoice.total(), 253.4);
+    assert_eq!(invoice.currency.as_deref(), Some("EUR"));
+
+    let mut unrated = ProjectTotals::new("unrated".to_string());
+    unrated.add(day, 60);
+    let failed = Invoice::new(day, acme, &config, &[unrated], &[], day..=day);
+    assert_eq!(failed.err().as_deref(), Some("unrated"));
+  }
+}
@@ -0,0 +1,37 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+pub mod config;
+pub mod core;
+pub mod db;
+pub mod diagnostic;
+pub mod doctor;
+pub mod editor;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod import;
+pub mod invoice;
+pub mod locale;
+pub mod messages;
+pub mod report;
+pub mod selftest;
+pub mod stats;
+pub mod template;
+pub mod theme;
+pub mod timesheet;
+pub mod tracker;
+pub mod zone;
@@ -0,0 +1,493 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::t;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Languages reports can be written in, set with `language` in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+  #[default]
+  En,
+  De,
+  Es,
+  Fr,
+  It,
+  Nl,
+}
+
+/// The words a language needs to spell out dates and durations
+struct Words {
+  /// The default date pattern, as strftime
+  date: &'static str,
+  nothing: &'static str,
+  /// Singular and plural, for hours, minutes and seconds
+  units: [(&'static str, &'static str); 3],
+  weekdays: [&'static str; 7],
+  months: [&'static str; 12],
+}
+
+const EN: Words = Words {
+  date: "%Y-%m-%d",
+  nothing: "None",
+  units: [
+    ("hour", "hours"),
+    ("minute", "minutes"),
+    ("second", "seconds"),
+  ],
+  weekdays: [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+  ],
+  months: [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+  ],
+};
+
+const DE: Words = Words {
+  date: "%d.%m.%Y",
+  nothing: "Keine",
+  units: [
+    ("Stunde", "Stunden"),
+    ("Minute", "Minuten"),
+    ("Sekunde", "Sekunden"),
+  ],
+  weekdays: [
+    "Montag",
+    "Dienstag",
+    "Mittwoch",
+    "Donnerstag",
+    "Freitag",
+    "Samstag",
+    "Sonntag",
+  ],
+  months: [
+    "Januar",
+    "Februar",
+    "März",
+    "April",
+    "Mai",
+    "Juni",
+    "Juli",
+    "August",
+    "September",
+    "Oktober",
+    "November",
+    "Dezember",
+  ],
+};
+
+const ES: Words = Words {
+  date: "%d/%m/%Y",
+  nothing: "Nada",
+  units: [
+    ("hora", "horas"),
+    ("minuto", "minutos"),
+    ("segundo", "segundos"),
+  ],
+  weekdays: [
+    "lunes",
+    "martes",
+    "miércoles",
+    "jueves",
+    "viernes",
+    "sábado",
+    "domingo",
+  ],
+  months: [
+    "enero",
+    "febrero",
+    "marzo",
+    "abril",
+    "mayo",
+    "junio",
+    "julio",
+    "agosto",
+    "septiembre",
+    "octubre",
+    "noviembre",
+    "diciembre",
+  ],
+};
+
+const FR: Words = Words {
+  date: "%d/%m/%Y",
+  nothing: "Aucune",
+  units: [
+    ("heure", "heures"),
+    ("minute", "minutes"),
+    ("seconde", "secondes"),
+  ],
+  weekdays: [
+    "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+  ],
+  months: [
+    "janvier",
+    "février",
+    "mars",
+    "avril",
+    "mai",
+    "juin",
+    "juillet",
+    "août",
+    "septembre",
+    "octobre",
+    "novembre",
+    "décembre",
+  ],
+};
+
+const IT: Words = Words {
+  date: "%d/%m/%Y",
+  nothing: "Nessuna",
+  units: [("ora", "ore"), ("minuto", "minuti"), ("secondo", "secondi")],
+  weekdays: [
+    "lunedì",
+    "martedì",
+    "mercoledì",
+    "giovedì",
+    "venerdì",
+    "sabato",
+    "domenica",
+  ],
+  months: [
+    "gennaio",
+    "febbraio",
+    "marzo",
+    "aprile",
+    "maggio",
+    "giugno",
+    "luglio",
+    "agosto",
+    "settembre",
+    "ottobre",
+    "novembre",
+    "dicembre",
+  ],
+};
+
+const NL: Words = Words {
+  date: "%d-%m-%Y",
+  nothing: "Geen",
+  units: [
+    ("uur", "uur"),
+    ("minuut", "minuten"),
+    ("seconde", "seconden"),
+  ],
+  weekdays: [
+    "maandag",
+    "dinsdag",
+    "woensdag",
+    "donderdag",
+    "vrijdag",
+    "zaterdag",
+    "zondag",
+  ],
+  months: [
+    "januari",
+    "februari",
+    "maart",
+    "april",
+    "mei",
+    "juni",
+    "juli",
+    "augustus",
+    "september",
+    "oktober",
+    "november",
+    "december",
+  ],
+};
+
+static CURRENT: OnceLock<Locale> = OnceLock::new();
+
+const DEFAULT: Locale = Locale {
+  language: Language::En,
+  date_format: None,
+  precision: Precision::Seconds,
+};
+
+/// Sets the locale all output is spelled out in, once, as soon as the configuration is loaded
+pub fn set(locale: Locale) {
+  let _ = CURRENT.set(locale);
+}
+
+/// The locale output is spelled out in, English until [`set`]
+pub fn current() -> &'static Locale {
+  CURRENT.get().unwrap_or(&DEFAULT)
+}
+
+/// The smallest unit durations are spelled out in, see `--precision`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+  Minutes,
+  #[default]
+  Seconds,
+}
+
+/// How dates and durations are spelled out
+#[derive(Debug, Clone, Default)]
+pub struct Locale {
+  language: Language,
+  date_format: Option<String>,
+  precision: Precision,
+}
+
+impl Locale {
+  pub fn new(language: Language, date_format: Option<String>) -> Self {
+    Self {
+      language,
+      date_format,
+      precision: Precision::default(),
+    }
+  }
+
+  pub fn with_precision(self, precision: Precision) -> Self {
+    Self { precision, ..self }
+  }
+
+  pub fn language(&self) -> Language {
+    self.language
+  }
+
+  fn words(&self) -> &'static Words {
+    match self.language {
+      Language::En => &EN,
+      Language::De => &DE,
+      Language::Es => &ES,
+      Language::Fr => &FR,
+      Language::It => &IT,
+      Language::Nl => &NL,
+    }
+  }
+
+  /// A day, as per the configured `date-format`, or the language's usual numeric one
+  pub fn date(&self, day: NaiveDate) -> String {
+    let pattern = self.date_format.as_deref().unwrap_or(self.words().date);
+    self.format(day, pattern)
+  }
+
+  /// Formats `day` as chrono would, but with week days and months in the report's language.
+  /// Abbreviations are the first three letters of the name, as they are in English.
+  pub fn format(&self, day: NaiveDate, pattern: &str) -> String {
+    let words = self.words();
+    let weekday = words.weekdays[day.weekday().num_days_from_monday() as usize];
+    let month = words.months[day.month0() as usize];
+    let mut translated = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+      if c != '%' {
+        translated.push(c);
+        continue;
+      }
+      match chars.next() {
+        Some('a') => translated.push_str(&escape(&abbreviate(weekday))),
+        Some('A') => translated.push_str(&escape(weekday)),
+        Some('b') | Some('h') => translated.push_str(&escape(&abbreviate(month))),
+        Some('B') => translated.push_str(&escape(month)),
+        Some(other) => {
+          translated.push('%');
+          translated.push(other);
+        }
+        None => translated.push('%'),
+      }
+    }
+    day.format(&translated).to_string()
+  }
+
+  /// A duration spelled out unit by unit, skipping those at zero, down to the precision's
+  pub fn duration(&self, duration: Duration) -> String {
+    let words = self.words();
+    let secs = duration.as_secs();
+    let units = [secs / 3600, (secs / 60) % 60, secs % 60];
+    let shown = match self.precision {
+      // Less than a minute still reads better in seconds than as nothing at all
+      Precision::Minutes if secs >= 60 => 2,
+      _ => 3,
+    };
+    let parts: Vec<String> = units[..shown]
+      .iter()
+      .zip(words.units)
+      .filter(|(count, _)| **count > 0)
+      .map(|(count, (one, many))| format!("{} {}", count, if *count == 1 { one } else { many }))
+      .collect();
+    match parts.is_empty() {
+      true => words.nothing.to_string(),
+      false => parts.join(" "),
+    }
+  }
+}
+
+/// When `at` was, relative to `now`, then in absolute terms: "25 minutes ago at 14:32",
+/// "yesterday at 09:10" or "3 days ago, on Mon Mar  7 at 17:45". The year shows once it differs.
+pub fn relative(at: NaiveDateTime, now: NaiveDateTime) -> String {
+  let time = at.format("%H:%M");
+  let elapsed = (now - at).max(chrono::Duration::zero());
+  let days = (now.date() - at.date()).num_days();
+  let ago = match days {
+    _ if elapsed.num_minutes() < 1 => t!("just-now"),
+    0 => match (elapsed.num_hours(), elapsed.num_minutes()) {
+      (0, 1) => t!("minutes-ago-one"),
+      (0, minutes) => t!("minutes-ago", count = minutes),
+      (1, _) => t!("hours-ago-one"),
+      (hours, _) => t!("hours-ago", count = hours),
+    },
+    1 => t!("yesterday"),
+    2..=13 => t!("days-ago", count = days),
+    14..=59 => t!("weeks-ago", count = days / 7),
+    60..=364 => t!("months-ago", count = days / 30),
+    365..=729 => t!("years-ago-one"),
+    _ => t!("years-ago", count = days / 365),
+  };
+  if days < 2 {
+    return t!("relative-at", ago = ago, time = time);
+  }
+  let pattern = match at.year() == now.year() {
+    true => "%a %b %e",
+    false => "%a %b %e %Y",
+  };
+  let date = Locale::new(current().language, None).format(at.date(), pattern);
+  t!("relative-on", ago = ago, date = date, time = time)
+}
+
+fn abbreviate(name: &str) -> String {
+  name.chars().take(3).collect()
+}
+
+/// Keeps chrono from reading a translated name as specifiers
+fn escape(name: &str) -> String {
+  name.replace('%', "%%")
+}
+
+/// Whether chrono understands every specifier in `pattern`
+pub fn valid_pattern(pattern: &str) -> bool {
+  StrftimeItems::new(pattern).all(|item| !matches!(item, Item::Error))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::locale::{relative, valid_pattern, Language, Locale, Precision};
+  use chrono::NaiveDate;
+  use std::time::Duration;
+
+  #[test]
+  fn translates_names_and_units() {
+    let day = NaiveDate::from_ymd(2022, 3, 7);
+    let english = Locale::default();
+    assert_eq!(english.date(day), "2022-03-07");
+    assert_eq!(english.format(day, "%a %b %e"), "Mon Mar  7");
+    assert_eq!(english.duration(Duration::from_secs(7200)), "2 hours");
+
+    let german = Locale::new(Language::De, None);
+    assert_eq!(german.date(day), "07.03.2022");
+    assert_eq!(german.format(day, "%A, %e. %B"), "Montag,  7. März");
+    assert_eq!(
+      german.duration(Duration::from_secs(3660)),
+      "1 Stunde 1 Minute"
+    );
+    assert_eq!(german.duration(Duration::from_secs(7200)), "2 Stunden");
+    assert_eq!(german.duration(Duration::from_secs(0)), "Keine");
+
+    let french = Locale::new(Language::Fr, Some("%a %d %b".to_string()));
+    assert_eq!(french.date(day), "lun 07 mar");
+    assert_eq!(
+      french.duration(Duration::from_secs(95)),
+      "1 minute 35 secondes"
+    );
+  }
+
+  #[test]
+  fn spells_every_unit_out() {
+    let english = Locale::default();
+    let spelled = |secs| english.duration(Duration::from_secs(secs));
+    assert_eq!(spelled(0), "None");
+    assert_eq!(spelled(1), "1 second");
+    assert_eq!(spelled(42), "42 seconds");
+    assert_eq!(spelled(60), "1 minute");
+    assert_eq!(spelled(61), "1 minute 1 second");
+    assert_eq!(spelled(125), "2 minutes 5 seconds");
+    assert_eq!(spelled(3600), "1 hour");
+    assert_eq!(spelled(3601), "1 hour 1 second");
+    assert_eq!(spelled(3660), "1 hour 1 minute");
+    assert_eq!(spelled(3661), "1 hour 1 minute 1 second");
+    assert_eq!(
+      spelled(2 * 3600 + 59 * 60 + 59),
+      "2 hours 59 minutes 59 seconds"
+    );
+    assert_eq!(spelled(30 * 3600), "30 hours");
+
+    let minutes = Locale::default().with_precision(Precision::Minutes);
+    let spelled = |secs| minutes.duration(Duration::from_secs(secs));
+    assert_eq!(spelled(0), "None");
+    assert_eq!(spelled(42), "42 seconds");
+    assert_eq!(spelled(61), "1 minute");
+    assert_eq!(spelled(3601), "1 hour");
+    assert_eq!(spelled(3661), "1 hour 1 minute");
+  }
+
+  #[test]
+  fn relative_then_absolute() {
+    let now = NaiveDate::from_ymd(2022, 3, 9).and_hms(15, 0, 0);
+    let at = |y, m, d, h, min| NaiveDate::from_ymd(y, m, d).and_hms(h, min, 0);
+    assert_eq!(relative(now, now), "just now at 15:00");
+    assert_eq!(relative(at(2022, 3, 9, 16, 0), now), "just now at 16:00");
+    assert_eq!(
+      relative(at(2022, 3, 9, 14, 59), now),
+      "a minute ago at 14:59"
+    );
+    assert_eq!(
+      relative(at(2022, 3, 9, 14, 35), now),
+      "25 minutes ago at 14:35"
+    );
+    assert_eq!(relative(at(2022, 3, 9, 9, 10), now), "5 hours ago at 09:10");
+    assert_eq!(relative(at(2022, 3, 8, 23, 50), now), "yesterday at 23:50");
+    assert_eq!(
+      relative(at(2022, 3, 6, 17, 45), now),
+      "3 days ago, on Sun Mar  6 at 17:45"
+    );
+    assert_eq!(
+      relative(at(2021, 12, 1, 8, 0), now),
+      "3 months ago, on Wed Dec  1 2021 at 08:00"
+    );
+  }
+
+  #[test]
+  fn validates_patterns() {
+    assert!(valid_pattern("%d/%m/%Y"));
+    assert!(!valid_pattern("%Q"));
+  }
+}
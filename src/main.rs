@@ -14,402 +14,3491 @@
  * limitations under the License.
  */
 
-pub mod core;
-pub mod db;
-
-use db::Database;
+use config::Config;
+use db::{
+  Amendment, ArchiveError, BackdateError, Database, DeleteError, DryRun, FavoriteError, Load,
+  MergeError, ProjectTotals, FAVORITE_SLOTS, WAL_FORMAT,
+};
+use diagnostic::Diagnostic;
+use invoice::{ExpenseLine, Invoice};
+use locale::{Locale, Precision};
+use report::{Criterion, Period, ReportRenderer};
 use std::fs;
+use std::io;
+use timesheet::Timesheet;
 
-use crate::core::Project;
-use chrono::{DateTime, Datelike, Local};
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveTime, Offset, TimeZone, Utc};
 use clap::{arg, App, AppSettings, ArgMatches};
 use console::{style, Term};
-use itertools::Itertools;
-use std::io::ErrorKind;
+use std::collections::BTreeMap;
+use std::io::{ErrorKind, Write};
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use timeknight::core::{Expense, Labels, Project, Record};
+use timeknight::tracker::Tracker;
+use timeknight::{
+  config, db, diagnostic, doctor, editor, export, import, invoice, locale, report, selftest, stats,
+  t, template, theme, timesheet, zone,
+};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const DEFAULT_DIRECTORY: &str = ".timeknight";
 
+const DEFAULT_PROMPT: &str = "{project} {elapsed}";
+
+const LOCK_WAIT: Duration = Duration::from_secs(10);
+
 fn main() {
-  let matches = App::new("timeknight")
+  let mut app = App::new("timeknight")
     .about("Traces where all that time goes...")
-    .setting(AppSettings::SubcommandRequiredElseHelp)
+    .setting(AppSettings::ArgRequiredElseHelp)
     .version(VERSION)
+    .disable_version_flag(true)
+    .arg(arg!(-V --version "Print version information"))
+    .arg(
+      arg!(--json "With --version, prints it as JSON along with the database's details")
+        .requires("version"),
+    )
+    .arg(
+      arg!(--wait "Waits for the lock to be released, should another instance hold it")
+        .global(true),
+    )
+    .arg(
+      arg!(--precision <UNIT> "Smallest unit durations are spelled out in")
+        .possible_values(["minutes", "seconds"])
+        .default_value("seconds")
+        .required(false)
+        .global(true),
+    )
+    .arg(
+      arg!(--"dry-run" "Shows what would be written to the log, without writing anything").global(true),
+    )
     .subcommand(
       App::new("project")
         .about("Project management")
         .subcommand(
           App::new("add")
             .arg(arg!(<NAME> "The project name to create"))
+            .arg(arg!(--"if-missing" "Quietly leaves the project be, should it exist already"))
             .setting(AppSettings::ArgRequiredElseHelp),
         )
         .subcommand(
           App::new("del")
             .arg(arg!(<NAME> "The project name to delete"))
+            .arg(arg!(--"stop-first" "Stops the project first, should it be running"))
+            .setting(AppSettings::ArgRequiredElseHelp),
+        )
+        .subcommand(
+          App::new("archive")
+            .about("Keeps a finished project out of the way, along with its history")
+            .arg(arg!(<NAME> "The project name to archive"))
+            .after_help(
+              "Archived projects are left out of `project list` and reports, unless with --all, \
+               and can't be started until unarchived.",
+            )
+            .setting(AppSettings::ArgRequiredElseHelp),
+        )
+        .subcommand(
+          App::new("unarchive")
+            .about("Brings an archived project back")
+            .arg(arg!(<NAME> "The project name to unarchive"))
+            .setting(AppSettings::ArgRequiredElseHelp),
+        )
+        .subcommand(App::new("list").arg(arg!(--all "Lists the archived projects too")))
+        .subcommand(
+          App::new("import")
+            .about("Creates many projects at once, along with their settings")
+            .arg(arg!(<FILE> "The .toml or .csv file listing them"))
+            .arg(
+              arg!(--"format" <FORMAT> "Format of the file, when its extension doesn't tell")
+                .required(false)
+                .possible_values(["toml", "csv"]),
+            )
+            .after_help(
+              "Projects are listed as in the configuration, e.g.:\n\n    \
+               [projects.Backend]\n    \
+               client = \"Acme\"\n    \
+               rate = 100.0\n    \
+               tags = [\"dev\"]\n    \
+               budget = \"120h\"\n\n\
+               or as CSV, e.g. name,client,rate,tags,budget with tags separated by semicolons. \
+               Projects are all created within a single append, their settings are then added \
+               to config.toml, but for the projects and clients it already configures.",
+            )
+            .setting(AppSettings::ArgRequiredElseHelp),
+        )
+        .subcommand(
+          App::new("info")
+            .about("Summarizes a single project")
+            .arg(arg!(<NAME> "The project name to summarize"))
+            .setting(AppSettings::ArgRequiredElseHelp),
+        )
+        .subcommand(
+          App::new("forecast")
+            .about("Estimates when the project's budget runs out, at the pace it's spent at")
+            .arg(arg!(<NAME> "The project with a budget configured"))
+            .arg(
+              arg!(--"deadline" <DAY> "The day the budget is to be spent by, e.g. 2022-06-30")
+                .required(false)
+                .validator(parse_day),
+            )
+            .after_help(
+              "The budget, and possibly the deadline, are configured per project:\n\n    \
+               [projects.Backend]\n    \
+               budget = \"120h\"\n    \
+               deadline = \"2022-06-30\"",
+            )
             .setting(AppSettings::ArgRequiredElseHelp),
         )
-        .subcommand(App::new("list"))
         .setting(AppSettings::ArgRequiredElseHelp),
     )
     .subcommand(
       App::new("start")
         .about("Starts tracking time for a project")
-        .arg(arg!(<NAME> "the project's name to start tracking time for"))
+        .arg(
+          arg!(<NAME> "the project's name to start tracking time for")
+            .long_help(
+              "the project's name to start tracking time for, or . for the one of the current \
+               directory: the project configured for it, or the one named after its git repository",
+            ),
+        )
+        .arg(arg!(--concurrent "Keeps whatever else is running going"))
+        .arg(
+          arg!(--at <TIME> "Starts as of then instead, e.g. 09:30 or \"15 minutes ago\"")
+            .long_help(
+              "Starts as of then instead, e.g. 09:30, \"2022-03-14 09:30\" or \"15 minutes ago\", \
+               for when tracking was forgotten. What gets stopped is stopped then too, and nothing \
+               can have been tracked since.",
+            )
+            .required(false)
+            .validator(|at| zone::parse_instant(at, &Local, Utc::now())),
+        )
+        .arg(arg!(--billable "Bills the record, whatever the project's default"))
+        .arg(
+          arg!(--"non-billable" "Doesn't bill the record, whatever the project's default")
+            .conflicts_with("billable"),
+        )
+        .arg(
+          arg!(--tag <TAG> "Tags the record, instead of with the project's default tags")
+            .required(false)
+            .multiple_occurrences(true)
+            .validator(parse_tag),
+        )
+        .arg(arg!(--unarchive "Unarchives the project first, should it be archived"))
+        .setting(AppSettings::ArgRequiredElseHelp),
+    )
+    .subcommand(
+      App::new("fav")
+        .about("Manages the favorite projects, started by their slot, e.g. timek 1")
+        .subcommand(
+          App::new("add")
+            .arg(arg!(<NAME> "The project to add to the next slot"))
+            .setting(AppSettings::ArgRequiredElseHelp),
+        )
+        .subcommand(
+          App::new("del")
+            .arg(arg!(<NAME> "The project to remove, the ones after it moving up a slot"))
+            .setting(AppSettings::ArgRequiredElseHelp),
+        )
+        .subcommand(App::new("list"))
+        .setting(AppSettings::ArgRequiredElseHelp),
+    )
+    .subcommands((1..=FAVORITE_SLOTS).map(|slot| {
+      App::new(slot.to_string())
+        .about("Starts the favorite project in that slot")
+        .hide(true)
+    }))
+    .subcommand(
+      App::new("recent")
+        .about("Lists the projects most recently stopped, to be resumed by their number")
+        .arg(
+          arg!(--"count" <COUNT> "How many to list")
+            .required(false)
+            .validator(|count| count.parse::<usize>())
+            .default_value("5"),
+        ),
+    )
+    .subcommand(
+      App::new("resume")
+        .about("Starts one of the projects most recently stopped again, as numbered by recent")
+        .arg(arg!(<NUMBER> "Its number in the recent list").validator(|n| n.parse::<usize>()))
+        .setting(AppSettings::ArgRequiredElseHelp),
+    )
+    .subcommand(
+      App::new("toggle")
+        .about("Starts a project, stops it if it's running, switches to it if another one is")
+        .arg(arg!(<NAME> "the project's name to toggle"))
+        .setting(AppSettings::ArgRequiredElseHelp),
+    )
+    .subcommand(
+      App::new("expense")
+        .about("Records money spent on a project, to be billed with the time tracked on it")
+        .arg(arg!(<PROJECT> "The project the money was spent on"))
+        .arg(arg!(<AMOUNT> "How much was spent, e.g. 34.50"))
+        .arg(arg!(<DESCRIPTION> "What it was spent on"))
+        .setting(AppSettings::ArgRequiredElseHelp),
+    )
+    .subcommand(
+      App::new("stop")
+        .about("Stops tracking time")
+        .arg(arg!([NAME] "Only stops this project, failing if it isn't the one running"))
+        .arg(
+          arg!(-m --message <NOTE> "Notes what was done, on the latest started")
+            .required(false)
+            .validator(parse_note),
+        )
+        .arg(
+          arg!(--at <TIME> "Stops as of then instead, e.g. 17:00 or \"15 minutes ago\"")
+            .required(false)
+            .validator(|at| zone::parse_instant(at, &Local, Utc::now())),
+        ),
+    )
+    .subcommand(
+      App::new("add")
+        .about("Adds a record after the fact, e.g. for work tracked on paper")
+        .arg(arg!(<NAME> "The project the time was spent on"))
+        .arg(
+          arg!(--from <TIME> "When it started, e.g. 09:30 or \"2022-03-14 09:30\"")
+            .validator(|at| zone::parse_instant(at, &Local, Utc::now())),
+        )
+        .arg(
+          arg!(--to <TIME> "When it ended, e.g. 12:00 or \"15 minutes ago\"")
+            .validator(|at| zone::parse_instant(at, &Local, Utc::now())),
+        )
+        .arg(arg!(--concurrent "Only keeps it from overlapping the project's own records"))
         .setting(AppSettings::ArgRequiredElseHelp),
     )
-    .subcommand(App::new("stop").about("Stops tracking time"))
+    .subcommand(
+      App::new("cancel")
+        .about("Discards what's being tracked, as if it was never started")
+        .arg(arg!([NAME] "Only cancels this project, the latest started otherwise")),
+    )
+    .subcommand(
+      App::new("note")
+        .about("Notes what's being done, on what's being tracked")
+        .arg(arg!(<NOTE> "What's being done, e.g. \"pairing with Bob\"").validator(parse_note))
+        .arg(
+          arg!(--on <NAME> "Notes it on this project, the latest started otherwise")
+            .required(false),
+        )
+        .setting(AppSettings::ArgRequiredElseHelp),
+    )
+    .subcommand(
+      App::new("log")
+        .about("Lists the records of a period, along with what was noted on them")
+        .arg(
+          arg!(<PERIOD> "Period to list the records of")
+            .required(false)
+            .possible_values(["today", "yesterday", "week", "lastweek", "month", "lastmonth"])
+            .default_value("today"),
+        ),
+    )
     .subcommand(App::new("status").about("Displays current status"))
+    .subcommand(
+      App::new("prompt")
+        .about("Compact status, meant to be embedded in shell prompts")
+        .arg(
+          arg!(--"format" <TEMPLATE> "Template to render, using {project} and {elapsed}")
+            .required(false)
+            .default_value(DEFAULT_PROMPT),
+        ),
+    )
+    .subcommand(
+      App::new("nag")
+        .about("Notifies when nothing's been tracked for a while during work hours")
+        .after_help(
+          "Needs work-hours and nag-after to be configured, and is meant to be run from cron, \
+           e.g. every 10 minutes:\n\n    */10 * * * * timek nag",
+        ),
+    )
+    .subcommand(
+      App::new("heartbeat")
+        .about("Notes the machine is up while something runs, to stop it there if it goes down")
+        .after_help(
+          "Needs heartbeat to be configured to how often it runs, and is meant to be run from \
+           cron, e.g. every 5 minutes:\n\n    \
+           heartbeat = \"5m\"\n\n    \
+           */5 * * * * timek heartbeat",
+        ),
+    )
+    .subcommand(
+      App::new("stats")
+        .about("Shows how fragmented the time tracked was")
+        .arg(
+          arg!(<PERIOD> "Period to produce the stats for")
+            .required(false)
+            .possible_values(["today", "yesterday", "week", "lastweek", "month", "lastmonth"])
+            .default_value("week"),
+        )
+        .arg(
+          arg!(--"by" <GROUPING>)
+            .required(false)
+            .possible_values(["day", "week"])
+            .default_value("day"),
+        )
+        .after_help(
+          "Switches count the sessions following one on another project. Focus is the share of \
+           the time tracked in sessions of 25 minutes or more.",
+        ),
+    )
+    .subcommand(
+      App::new("today").about("Shows what was tracked today, and how it stands against the target"),
+    )
+    .subcommand(
+      App::new("week")
+        .about("Shows what was tracked each day this week, and how it stands against the target"),
+    )
+    .subcommand(
+      App::new("trends")
+        .about("Draws the weekly totals of a project, or of all of them, over the past weeks")
+        .arg(arg!(<PROJECT> "The project to draw the trend of, or all"))
+        .arg(
+          arg!(--"weeks" <WEEKS> "How many weeks to look back, the current one included")
+            .required(false)
+            .default_value("12"),
+        )
+        .setting(AppSettings::ArgRequiredElseHelp),
+    )
+    .subcommand(
+      App::new("streak")
+        .about("Shows how many work days in a row the daily target was met")
+        .after_help(
+          "The target is configured, along with whether to celebrate reaching it, e.g.:\n\n    \
+           daily-target = \"6h\"\n    \
+           celebrate = true\n\n\
+           Only the days of the work hours count, Monday through Friday unless configured otherwise.",
+        ),
+    )
+    .subcommand(
+      App::new("review")
+        .about("Walks through the records of a day, offering to adjust, reassign, note or delete them")
+        .arg(
+          arg!([DAY] "The day to review: today, yesterday or a date, e.g. 2022-03-14")
+            .validator(parse_day)
+            .default_value("today"),
+        ),
+    )
+    .subcommand(
+      App::new("retag")
+        .about("Moves the records of a period over to another project, e.g. once it got split")
+        .arg(arg!(--"from-project" <NAME> "The project the records are on"))
+        .arg(arg!(--"to-project" <NAME> "The project to move them to"))
+        .arg(
+          arg!(--"period" <PERIOD> "Period of the records to move, by the day they started")
+            .long_help(
+              "Period of the records to move, by the day they started: ever, today, yesterday, \
+               week, lastweek, month, lastmonth, or a range of days, e.g. 2022-03-01..2022-03-15",
+            )
+            .required(false)
+            .validator(|period| period.parse::<Period>())
+            .default_value("ever"),
+        ),
+    )
+    .subcommand(
+      App::new("verify")
+        .about("Checks the integrity of the database, changing nothing")
+        .arg(arg!(--audit "Also checks that the entries are what was sealed, see audit in config"))
+        .arg(
+          arg!(--"seal" <SEAL> "Also checks the log still holds that seal, as audited before")
+            .required(false)
+            .requires("audit"),
+        )
+        .after_help(
+          "With audit = true in config.toml, every entry appended gets sealed onto a hash chain, \
+           that changing any entry afterwards breaks. Amending or deleting records rewrites the \
+           log, starting the chain over: note the latest seal after such changes, for later \
+           audits to check it is still there with --seal.",
+        ),
+    )
+    .subcommand(
+      App::new("selftest")
+        .about("Checks this install works, on a throwaway database")
+        .long_about(
+          "Checks this install works on this platform: creates a throwaway database, tracks, \
+           cancels, replays, reports and compacts records in it, and tells how each step went. \
+           The user's database is left alone.",
+        ),
+    )
+    .subcommand(
+      App::new("doctor")
+        .about("Tells how the database is doing, and looks for what may be off in its records")
+        .long_about(
+          "Tells how the database is doing: the size of its log, how long replaying it takes, \
+           what it holds and how much rewriting it would save, e.g. when pruning.",
+        )
+        .subcommand(
+          App::new("timezones")
+            .about("Finds records only a change of the clock's offset explains, e.g. travelling")
+            .arg(arg!(--normalize "Expresses every record in this machine's timezone instead"))
+            .after_help(
+              "Records are stored as instants, along with the offset they were tracked at, \
+               which is what reports go by. Normalizing keeps the instants, only changing the \
+               offsets to the ones of this machine's timezone at the time.",
+            ),
+        ),
+    )
+    .subcommand(
+      App::new("editor-server")
+        .about("Serves editor plugins JSON-RPC over stdin and stdout, one message per line")
+        .after_help(
+          "Methods are status, projects, start {\"project\"}, stop, and hint {\"path\"}, which \
+           tells the project a file belongs to as per the directories configured, e.g.:\n\n    \
+           [projects.Backend]\n    \
+           directories = [\"~/src/backend\"]",
+        ),
+    )
+    .subcommand(
+      App::new("merge")
+        .about("Merges another copy of the log, e.g. one that diverged on another machine")
+        .arg(arg!(<FILE> "The other copy of entries.wal"))
+        .setting(AppSettings::ArgRequiredElseHelp),
+    )
+    .subcommand(
+      App::new("export")
+        .about("Exports all records, as CSV or one JSON object per line")
+        .arg(
+          arg!(<FORMAT> "Format to export the records in")
+            .required(false)
+            .possible_values(["csv", "jsonl"])
+            .default_value("csv"),
+        )
+        .arg(arg!(--anonymize "Replaces project names with stable pseudonyms"))
+        .arg(arg!(--"out" <PATH> "Writes the export to a file instead").required(false))
+        .arg(
+          arg!(--since <CURSOR> "Only exports records completed after this cursor, jsonl only")
+            .required(false),
+        )
+        .after_help(
+          "Each jsonl line carries the cursor to resume from, so that only new records get \
+           exported next time, e.g.:\n\n    \
+           timek export jsonl --since $(cat cursor) | tee -a records.jsonl | \
+           jq -s 'map(.cursor) | max' > cursor",
+        ),
+    )
+    .subcommand(
+      App::new("import")
+        .about("Imports records, as exported, skipping the ones already tracked")
+        .arg(arg!(<FILE> "The file to import, .jsonl for jsonl, CSV otherwise"))
+        .arg(
+          arg!(--"format" <FORMAT> "Format of the file, when its extension doesn't tell")
+            .required(false)
+            .possible_values(["csv", "jsonl"]),
+        )
+        .after_help(
+          "Records are the same when their project, start and end are, so importing the same \
+           file again adds nothing. Projects missing are created, records in flight are skipped.",
+        )
+        .setting(AppSettings::ArgRequiredElseHelp),
+    )
+    .subcommand(
+      App::new("prune")
+        .about("Drops old records, for good")
+        .arg(arg!(--"older-than" <AGE> "Age of the records to prune, e.g. 90d, 12w, 6m or 3y"))
+        .arg(arg!(--aggregate "Keeps monthly totals of the pruned records")),
+    )
     .subcommand(
       App::new("report")
         .about("Reports")
         .arg(
           arg!(<PERIOD> "Period to produce the report for")
+            .long_help(
+              "Period to produce the report for: ever, today, yesterday, week, lastweek, month, \
+               lastmonth, or a range of days, e.g. 2022-03-01..2022-03-15",
+            )
             .required(false)
-            .possible_values([
-              "ever",
-              "today",
-              "yesterday",
-              "week",
-              "lastweek",
-              "month",
-              "lastmonth",
-            ])
+            .validator(|period| period.parse::<Period>())
             .default_value("ever"),
         )
         .arg(
           arg!(--"by" <GROUPING>)
             .possible_values(["day"])
             .required(false),
+        )
+        .arg(
+          arg!(--"format" <FORMAT> "Format of the report, xlsx needs to be written to a file")
+            .long_help(
+              "Format of the report, xlsx needs to be written to a file. Reports written to a \
+               file are otherwise rendered as per its extension, .html, .csv or .json, Markdown \
+               for anything else, and mailed ones as Markdown, unless a format is given.",
+            )
+            .required(false)
+            .possible_values(["table", "csv", "json", "markdown", "html", "xlsx"])
+            .default_value("table"),
+        )
+        .arg(
+          arg!(--"max-width" <COLUMNS> "How wide the table gets at most, the terminal's otherwise")
+            .required(false)
+            .validator(|columns| columns.parse::<usize>()),
+        )
+        .arg(
+          arg!(--"out" <PATH> "Writes the report to a file, as HTML for .html, Markdown otherwise")
+            .aliases(&["output", "write"])
+            .required(false),
+        )
+        .arg(
+          arg!(--"email" <ADDRESS> "Mails the report, in Markdown, using the local sendmail")
+            .required(false),
+        )
+        .arg(
+          arg!(--"template" <FILE> "Renders the report with a Handlebars template instead")
+            .required(false),
+        )
+        .arg(
+          arg!(--"client" <NAME> "Only reports the projects billed to that client, as billed")
+            .required(false),
+        )
+        .arg(
+          arg!(--"where" <CONDITION> "Only reports the records matching, e.g. host=laptop")
+            .long_help(
+              "Only reports the records matching, e.g. host=laptop: on host (or device), branch, \
+               dir or user, as noted with capture-context or user, on tag, or on billable=yes or \
+               no. Can be given multiple times, records then have to match them all.",
+            )
+            .required(false)
+            .multiple_occurrences(true)
+            .validator(|condition| condition.parse::<Criterion>()),
+        )
+        .arg(
+          arg!(--tag <TAG> "Only reports the records tagged so, the same as --where tag=TAG")
+            .required(false)
+            .multiple_occurrences(true)
+            .validator(parse_tag),
+        )
+        .arg(arg!(--all "Reports on the archived projects too"))
+        .arg(
+          arg!(--"per-day-average" "Ends the report with the average tracked per day and workday")
+            .long_help(
+              "Ends the report with the average tracked per day, and per workday: all that was \
+               tracked, days off included, over the days work is expected on, as per the work \
+               hours and holidays configured. Ever is averaged from the first day tracked, and \
+               periods up to today.",
+            ),
+        )
+        .after_help(
+          "With --out or --email nothing is printed, making it fit to be run from cron, e.g. \
+           for a summary of the past week every Monday morning:\n\n    \
+           0 8 * * 1 timek report lastweek --email me@example.com",
+        ),
+    )
+    .subcommand(
+      App::new("timesheet")
+        .about("Lists the time tracked each day, e.g. to hand in at the end of the month")
+        .arg(
+          arg!(--"period" <PERIOD> "Period to produce the timesheet for")
+            .required(false)
+            .possible_values(["week", "lastweek", "month", "lastmonth"])
+            .default_value("month"),
+        )
+        .arg(
+          arg!(--"format" <FORMAT> "Format of the timesheet, pdf needs to be written to a file")
+            .required(false)
+            .possible_values(["table", "pdf"])
+            .default_value("table"),
+        )
+        .arg(arg!(--"out" <PATH> "Writes the timesheet to a file").required(false))
+        .arg(
+          arg!(--"template" <FILE> "Renders the timesheet with a Handlebars template instead")
+            .required(false),
+        )
+        .arg(
+          arg!(--"client" <NAME> "Only lists the projects billed to that client, as billed")
+            .required(false),
+        )
+        .after_help(
+          "The employee and client on the timesheet come from the configuration, e.g.:\n\n    \
+           [timesheet]\n    \
+           employee = \"Jane Doe\"\n    \
+           client = \"Acme, Inc.\"",
         ),
     )
-    .get_matches();
+    .subcommand(
+      App::new("invoice")
+        .about("Invoices a client for the time billed to it")
+        .arg(arg!(<CLIENT> "The client to invoice, as configured"))
+        .arg(
+          arg!(--"period" <PERIOD> "Period to invoice")
+            .required(false)
+            .possible_values(["week", "lastweek", "month", "lastmonth"])
+            .default_value("lastmonth"),
+        )
+        .arg(arg!(--draft "Leaves the invoice unnumbered, not using up a number"))
+        .arg(
+          arg!(--"template" <FILE> "Renders the invoice with a Handlebars template instead")
+            .required(false),
+        )
+        .arg(arg!(--"out" <PATH> "Writes the invoice to a file").required(false))
+        .setting(AppSettings::ArgRequiredElseHelp)
+        .after_help(
+          "Rates, currencies and taxes are configured per client, e.g.:\n\n    \
+           [clients.Acme]\n    \
+           projects = [\"backend\", \"frontend\"]\n    \
+           rate = 100.0\n    \
+           currency = \"EUR\"\n    \
+           tax = 20.0\n\n\
+           Invoices are numbered in sequence, as recorded in the invoices file of the database.",
+        ),
+    );
+  let matches = app.get_matches_mut();
 
   let location = db_location();
-  init_if_needed(&location);
 
-  match Database::open(location.as_path()) {
-    Ok(mut database) => handle_command(matches, &mut database),
-    Err(err) => match err {
-      ErrorKind::InvalidInput => {
-        eprintln!(
-          "{} Location {} doesn't appear to be a directory!",
-          style("FAIL").red().bold(),
-          location.display(),
-        )
-      }
-      _ => {
-        eprintln!(
-          "{} Couldn't access storage: {}",
-          style("FAIL").red().bold(),
-          location.display(),
-        )
-      }
-    },
+  if matches.is_present("version") {
+    print_version(&location, matches.is_present("json"));
+    return;
+  }
+  if matches.subcommand().is_none() {
+    app
+      .error(
+        clap::ErrorKind::MissingSubcommand,
+        "A subcommand is required, see --help",
+      )
+      .exit();
   }
-}
 
-fn handle_command(matches: ArgMatches, database: &mut Database) {
-  match matches.subcommand() {
-    Some(("project", sub_matches)) => match sub_matches.subcommand() {
-      Some(("add", sub_matches)) => {
-        let project = sub_matches.value_of("NAME").expect("required");
-        match database.add_project(project.to_string()) {
-          Ok(project) => {
-            println!(
-              "{} project '{}'",
-              style("Created").green().bold(),
-              project.name(),
-            );
-          }
-          Err(_) => {
-            println!(
-              "{} to create project '{}'",
-              style("Failed").red().bold(),
-              project,
-            );
-          }
-        }
+  if let Some(("prompt", sub_matches)) = matches.subcommand() {
+    let template = sub_matches.value_of("format").expect("defaulted");
+    if let Ok(running) = Database::running(location.as_path()) {
+      let prompts: Vec<String> = running
+        .iter()
+        .map(|(name, record)| render_prompt(template, name, record))
+        .collect();
+      print!("{}", prompts.join(", "));
+    }
+    return;
+  }
+
+  // Leaves the user's database alone, whether there is one or not
+  if matches.subcommand_name() == Some("selftest") {
+    selftest();
+    return;
+  }
+
+  init_if_needed(&location);
+
+  let config = match Config::load(&location) {
+    Ok(config) => config,
+    Err(err) => Diagnostic::new("Couldn't load the configuration")
+      .caused_by(err)
+      .hint(format!(
+        "fix or remove {}",
+        Config::file(&location).display()
+      ))
+      .exit(),
+  };
+  let precision = match matches.value_of("precision") {
+    Some("minutes") => Precision::Minutes,
+    _ => Precision::Seconds,
+  };
+  locale::set(config.locale().with_precision(precision));
+  zone::set(config.timezone);
+  theme::set(config.theme.clone());
+  // Speaks JSON-RPC on stdout, nothing else may print to it
+  if matches.subcommand_name() == Some("editor-server") {
+    let tracker = Tracker::new(&location);
+    if let Err(err) = editor::serve(&tracker, &config, io::stdin().lock(), io::stdout()) {
+      Diagnostic::new(t!("editor-server-failed"))
+        .caused_by(err)
+        .exit();
+    }
+    return;
+  }
+  let dry_run = matches.is_present("dry-run");
+  if let Some(every) = config.heartbeat.filter(|_| !dry_run) {
+    if matches.subcommand_name() != Some("heartbeat") {
+      check_heartbeat(&location, every, matches.is_present("wait"));
+    }
+  }
+  if let Some(at) = config.hard_stop.filter(|_| !dry_run) {
+    enforce_hard_stop(&location, at, matches.is_present("wait"));
+  }
+  if config.synced && !dry_run {
+    merge_conflicted_copies(&location, matches.is_present("wait"));
+  }
+  if !matches!(matches.subcommand_name(), Some("stop") | Some("toggle")) {
+    remind(&location, &config);
+  }
+
+  match matches.subcommand() {
+    Some(("status", _sub_matches)) => {
+      match Database::running(location.as_path()) {
+        Ok(running) if !running.is_empty() => {
+          for (name, record) in running {
+            println!(
+              "{}",
+              t!(
+                "working-on",
+                name = theme::success(name).bold(),
+                duration = theme::success(display_duration(record.duration())),
+              )
+            );
+          }
+        }
+        Ok(_) => {
+          println!("{}", t!("idle"));
+          if let Ok(Some((name, record))) = Database::last_session(location.as_path()) {
+            if let Some(end) = record.end() {
+              let when = locale::relative(
+                end.with_timezone(&Local).naive_local(),
+                Local::now().naive_local(),
+              );
+              println!(
+                "{}",
+                t!(
+                  "last-worked-on",
+                  name = style(name).bold(),
+                  duration = display_duration(record.duration()),
+                  when = when,
+                )
+              );
+            }
+          }
+        }
+        Err(err) => storage_failure(&location, err),
       }
-      Some(("del", sub_matches)) => {
+      if let Ok((total, projects)) =
+        Database::day_total(location.as_path(), Local::today().naive_local())
+      {
+        if projects > 0 {
+          let total = style(display_duration(total)).bold();
+          match projects {
+            1 => println!("{}", t!("today-total-one", duration = total)),
+            count => println!("{}", t!("today-total", duration = total, count = count)),
+          }
+        }
+      }
+    }
+    Some(("nag", _sub_matches)) => nag(&location, &config),
+    Some(("heartbeat", _)) => {
+      if matches!(Database::running(&location), Ok(running) if !running.is_empty()) {
+        if let Err(err) = Database::beat(&location) {
+          Diagnostic::storage(&location, err).exit();
+        }
+      }
+    }
+    Some(("verify", sub_matches)) => match db::verify(location.as_path()) {
+      Ok(verification) => {
+        println!(
+          "{}",
+          t!(
+            "entries-verified",
+            verified = style(t!("verified")).bold(),
+            entries = verification.entries,
+            projects = verification.projects,
+            records = verification.records
+          )
+        );
+        verification.devices.iter().for_each(|(device, entries)| {
+          println!(
+            "  {}",
+            t!("entries-from-device", count = entries, device = device)
+          )
+        });
+        let mut problems = verification.problems;
+        if sub_matches.is_present("audit") {
+          match db::audit(location.as_path()) {
+            Ok(audit) => problems.extend(print_audit(&audit, sub_matches.value_of("seal"))),
+            Err(err) => return storage_failure(&location, err),
+          }
+        }
+        if problems.is_empty() {
+          println!("{}", theme::success(t!("no-problems")).bold());
+        } else {
+          problems
+            .iter()
+            .for_each(|p| println!("{} {}", theme::failure(t!("problem")).bold(), p));
+          std::process::exit(1);
+        }
+      }
+      Err(err) => storage_failure(&location, err),
+    },
+    Some(("project", sub_matches)) if sub_matches.subcommand_name() == Some("info") => {
+      let name = sub_matches
+        .subcommand_matches("info")
+        .and_then(|m| m.value_of("NAME"))
+        .expect("required");
+      match Database::open_read_only(location.as_path(), Load::Full) {
+        Ok(database) => match database.project(name) {
+          Some(project) => print_project_info(project),
+          None => unknown_project(&database, name).exit(),
+        },
+        Err(err) => storage_failure(&location, err),
+      }
+    }
+    Some(("project", sub_matches)) if sub_matches.subcommand_name() == Some("forecast") => {
+      let sub_matches = sub_matches.subcommand_matches("forecast").expect("matched");
+      let name = sub_matches.value_of("NAME").expect("required");
+      let database = match Database::open_read_only(location.as_path(), Load::Full) {
+        Ok(database) => database,
+        Err(err) => return storage_failure(&location, err),
+      };
+      let project = match database.project(name) {
+        Some(project) => project,
+        None => unknown_project(&database, name).exit(),
+      };
+      let settings = config.project(project.name());
+      let budget = match settings.and_then(|p| p.budget) {
+        Some(budget) => budget,
+        None => Diagnostic::new(t!("no-budget", name = project.name()))
+          .hint(t!("configure-budget", name = project.name()))
+          .exit(),
+      };
+      let deadline = match sub_matches.value_of("deadline") {
+        Some(day) => Some(parse_day(day).expect("validated")),
+        None => settings.and_then(|p| p.deadline),
+      };
+      let mut daily = BTreeMap::new();
+      for record in project.records() {
+        *daily
+          .entry(zone::current().day(record.start()))
+          .or_default() += record.duration().as_secs();
+      }
+      let today = Local::today().naive_local();
+      let forecast = stats::forecast(&daily, budget.as_secs(), today, deadline);
+      print_forecast(project.name(), budget, deadline, &forecast);
+    }
+    Some(("report", sub_matches)) => {
+      let period = parse_period(sub_matches.value_of("PERIOD").expect("defaulted"));
+      let today = Local::today().naive_local();
+      let days = period.bounds(today);
+      let mut criteria: Vec<Criterion> = sub_matches.values_of_t("where").unwrap_or_default();
+      if let Some(tags) = sub_matches.values_of("tag") {
+        criteria.extend(tags.map(|tag| Criterion::Tag(tag.to_string())));
+      }
+      let totals = match criteria.is_empty() {
+        true => Database::daily_totals(location.as_path(), period.days(today)),
+        false => Database::daily_totals_where(location.as_path(), period.days(today), |record| {
+          criteria.iter().all(|criterion| criterion.matches(record))
+        }),
+      };
+      match totals {
+        Ok(mut projects) => {
+          if !sub_matches.is_present("all") {
+            projects.retain(|p| !p.archived);
+          }
+          projects.sort_by_key(|p| p.name.to_lowercase());
+          let locale = locale::current();
+          let mut title = report::title(&period, today, locale);
+          if let Some(client) = sub_matches.value_of("client") {
+            let (client, billed) =
+              billed_to(&config, client, projects).unwrap_or_else(|diagnostic| diagnostic.exit());
+            title = format!("{}, billed to {}", title, client);
+            projects = billed;
+          }
+          // Only client reports and templates show expenses
+          let expenses =
+            match sub_matches.is_present("client") || sub_matches.is_present("template") {
+              true => match Database::expenses(location.as_path(), days.clone()) {
+                Ok(mut expenses) => {
+                  expenses.retain(|e| projects.iter().any(|p| p.name == e.name()));
+                  expenses
+                }
+                Err(err) => return storage_failure(&location, err),
+              },
+              false => Vec::new(),
+            };
+          let by_day = sub_matches.value_of("by").is_some();
+          let rows = report::rows(&projects, &days, by_day);
+          let report = report::Report {
+            title: &title,
+            period: &period,
+            days: period.days(today),
+            rows: &rows,
+            locale,
+          };
+          // Files and mails only default to a table in Markdown
+          let explicit = match sub_matches.occurrences_of("format") {
+            0 => None,
+            _ => sub_matches.value_of("format"),
+          };
+          let render = |format: &str| match report::renderer(format) {
+            Some(renderer) => renderer.render(&report),
+            None => Diagnostic::new(t!("unrenderable-report", format = format)).exit(),
+          };
+          let templated = sub_matches.value_of("template").map(|template| {
+            let days = days.clone();
+            let context = template::report_context(&title, &config, &projects, &expenses, days);
+            apply_template(template, &context).unwrap_or_else(|diagnostic| diagnostic.exit())
+          });
+          let to_stdout = !sub_matches.is_present("out") && !sub_matches.is_present("email");
+          if let Some(rendered) = templated.as_ref().filter(|_| to_stdout) {
+            print!("{}", rendered);
+          } else if let Some((path, rendered)) = sub_matches.value_of("out").zip(templated.as_ref())
+          {
+            write_out(path, &t!("the-report"), rendered.as_str());
+          } else if explicit == Some("xlsx") {
+            let path = match sub_matches.value_of("out") {
+              Some(path) => path,
+              None => Diagnostic::new(t!("xlsx-needs-file"))
+                .hint(t!("add-out-report"))
+                .exit(),
+            };
+            let saved = export::xlsx_report(&title, &projects, days.clone())
+              .and_then(|mut workbook| workbook.save(path));
+            if let Err(err) = saved {
+              Diagnostic::new(t!("write-failed", what = t!("the-report"), path = path))
+                .caused_by(err)
+                .exit();
+            }
+          } else if let Some(path) = sub_matches.value_of("out") {
+            let extension = Path::new(path).extension().and_then(|e| e.to_str());
+            let rendered = match (explicit, extension) {
+              (Some(format), _) => render(format),
+              (None, Some("html")) | (None, Some("htm")) => render("html"),
+              (None, Some("csv")) => render("csv"),
+              (None, Some("json")) => render("json"),
+              (None, _) => render("markdown"),
+            };
+            write_out(path, &t!("the-report"), rendered.as_str());
+          }
+          if let Some(address) = sub_matches.value_of("email") {
+            let body = templated
+              .clone()
+              .unwrap_or_else(|| render(explicit.unwrap_or("markdown")));
+            if let Err(err) = send_report(address, &title, &body) {
+              Diagnostic::new(t!("mail-failed", address = address))
+                .caused_by(err)
+                .hint(t!("sendmail-hint"))
+                .exit();
+            }
+          }
+          let format = sub_matches.value_of("format").expect("defaulted");
+          // Anything but the table is meant for other programs, which only get the report
+          if to_stdout && templated.is_none() {
+            let rendered = match (format, sub_matches.value_of("max-width")) {
+              ("table", Some(columns)) => report::Table {
+                max_width: Some(columns.parse().expect("validated")),
+              }
+              .render(&report),
+              ("table", None) => report::Table::for_terminal().render(&report),
+              (format, _) => render(format),
+            };
+            print!("{}", rendered);
+          }
+          if to_stdout && templated.is_none() && format == "table" {
+            if sub_matches.is_present("per-day-average") {
+              let first = projects.iter().filter_map(|p| p.days.keys().next()).min();
+              let from = first.map_or(*days.start(), |first| *days.start().max(first));
+              let averages = report::averages(&rows, from..=*days.end().min(&today), |day| {
+                config.is_workday(day)
+              });
+              print_averages(&averages);
+            }
+            print_expenses(&expenses);
+          }
+        }
+        Err(err) => storage_failure(&location, err),
+      }
+    }
+    Some(("timesheet", sub_matches)) => {
+      let period = parse_period(sub_matches.value_of("period").expect("defaulted"));
+      let today = Local::today().naive_local();
+      let days = period.bounds(today);
+      match Database::daily_totals(location.as_path(), Some(days.clone())) {
+        Ok(projects) => {
+          let locale = locale::current();
+          let title = report::title(&period, today, locale).replace("Time report", "Timesheet");
+          let sheet = match sub_matches.value_of("client") {
+            Some(client) => {
+              let (client, billed) =
+                billed_to(&config, client, projects).unwrap_or_else(|diagnostic| diagnostic.exit());
+              let mut sheet = Timesheet::new(title, &config.timesheet, &billed, days);
+              sheet.client = Some(client.to_string());
+              sheet
+            }
+            None => Timesheet::new(title, &config.timesheet, &projects, days),
+          };
+          let rendered = match sub_matches.value_of("format") {
+            _ if sub_matches.is_present("template") => {
+              let template = sub_matches.value_of("template").expect("present");
+              let context = template::timesheet_context(&sheet, &config);
+              match apply_template(template, &context) {
+                Ok(rendered) => rendered.into_bytes(),
+                Err(diagnostic) => diagnostic.exit(),
+              }
+            }
+            Some("pdf") if sub_matches.is_present("out") => sheet.to_pdf(locale),
+            Some("pdf") => Diagnostic::new(t!("pdf-needs-file"))
+              .hint(t!("add-out-timesheet"))
+              .exit(),
+            _ => timesheet_table(&sheet, locale).into_bytes(),
+          };
+          match sub_matches.value_of("out") {
+            Some(path) => {
+              write_out(path, &t!("the-timesheet"), rendered);
+            }
+            None => print!("{}", String::from_utf8_lossy(&rendered)),
+          }
+        }
+        Err(err) => storage_failure(&location, err),
+      }
+    }
+    Some(("stats", sub_matches)) => {
+      let period = parse_period(sub_matches.value_of("PERIOD").expect("defaulted"));
+      let days = period.bounds(Local::today().naive_local());
+      match Database::records(location.as_path(), days.clone()) {
+        Ok(records) => {
+          let by_week = sub_matches.value_of("by") == Some("week");
+          // Records started before the period are accounted for on its first day
+          let group = |record: &Record| {
+            let day = zone::current().day(record.start()).max(*days.start());
+            match by_week {
+              true => day - chrono::Duration::days(day.weekday().num_days_from_monday() as i64),
+              false => day,
+            }
+          };
+          let mut groups: BTreeMap<NaiveDate, Vec<(&str, u64)>> = BTreeMap::new();
+          for (name, record) in &records {
+            groups
+              .entry(group(record))
+              .or_default()
+              .push((name, record.duration().as_secs()));
+          }
+          let mut rows: Vec<(String, stats::Focus)> = groups
+            .iter()
+            .map(|(day, group)| {
+              let label = match by_week {
+                true => t!("week-of", date = day.format("%b %e")),
+                false => day.format("%a %b %e").to_string(),
+              };
+              (label, stats::focus(group.iter().copied()))
+            })
+            .collect();
+          let all = records
+            .iter()
+            .map(|(name, record)| (name.as_str(), record.duration().as_secs()));
+          rows.push((t!("overall"), stats::focus(all)));
+          print_stats(&rows);
+        }
+        Err(err) => storage_failure(&location, err),
+      }
+    }
+    Some(("log", sub_matches)) => {
+      let period = parse_period(sub_matches.value_of("PERIOD").expect("defaulted"));
+      let days = period.bounds(Local::today().naive_local());
+      match Database::records(location.as_path(), days) {
+        Ok(records) => print_log(&records),
+        Err(err) => storage_failure(&location, err),
+      }
+    }
+    Some(("today", _)) => {
+      let now = Local::now();
+      let today = now.date().naive_local();
+      match Database::daily_totals(location.as_path(), Some(today..=today)) {
+        Ok(mut projects) => {
+          projects.sort_by_key(|p| p.name.to_lowercase());
+          let tracked: Vec<(&str, u64)> = projects
+            .iter()
+            .filter_map(|p| p.days.get(&today).map(|secs| (p.name.as_str(), *secs)))
+            .filter(|(_, secs)| *secs > 0)
+            .collect();
+          println!("{}", style(now.format("Today, %a %b %e")).bold());
+          let width = tracked
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+          for (name, secs) in &tracked {
+            println!(
+              "  {: <width$}  {: >6}",
+              name,
+              compact_duration(Duration::from_secs(*secs)),
+              width = width,
+            );
+          }
+          let total = tracked.iter().map(|(_, secs)| secs).sum();
+          println!("{}", day_progress(&config, today, total));
+        }
+        Err(err) => storage_failure(&location, err),
+      }
+    }
+    Some(("week", _)) => {
+      let today = Local::today().naive_local();
+      let monday = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+      let sunday = monday + chrono::Duration::days(6);
+      match Database::daily_totals(location.as_path(), Some(monday..=today)) {
+        Ok(projects) => {
+          let daily = stats::daily(&projects);
+          for day in monday.iter_days().take_while(|day| *day <= sunday) {
+            let total = daily.get(&day).copied().unwrap_or_default();
+            println!("{}", day_progress(&config, day, total));
+          }
+        }
+        Err(err) => storage_failure(&location, err),
+      }
+    }
+    Some(("trends", sub_matches)) => {
+      let name = sub_matches.value_of("PROJECT").expect("required");
+      let weeks = match sub_matches
+        .value_of("weeks")
+        .expect("defaulted")
+        .parse::<u32>()
+      {
+        Ok(weeks) if weeks > 0 => weeks,
+        _ => Diagnostic::new(t!(
+          "invalid-weeks",
+          weeks = sub_matches.value_of("weeks").expect("defaulted")
+        ))
+        .exit(),
+      };
+      let today = Local::today().naive_local();
+      let from = today - chrono::Duration::weeks(weeks as i64);
+      let mut projects = match Database::daily_totals(location.as_path(), Some(from..=today)) {
+        Ok(projects) => projects,
+        Err(err) => return storage_failure(&location, err),
+      };
+      projects.sort_by_key(|p| p.name.to_lowercase());
+      let mut trends: Vec<(String, Vec<u64>)> = match name {
+        "all" => projects
+          .iter()
+          .map(|p| (p.name.clone(), stats::weekly(&p.days, weeks, today)))
+          .filter(|(_, totals)| totals.iter().any(|secs| *secs > 0))
+          .collect(),
+        name => match projects
+          .iter()
+          .find(|p| p.name.to_lowercase() == name.to_lowercase())
+        {
+          Some(p) => vec![(p.name.clone(), stats::weekly(&p.days, weeks, today))],
+          None => {
+            let diagnostic = Diagnostic::new(t!("unknown-project", name = name));
+            match diagnostic::did_you_mean(name, projects.iter().map(|p| p.name.as_str())) {
+              Some(close) => diagnostic.hint(t!("did-you-mean", name = close)),
+              None => diagnostic.hint(t!("trends-of-all")),
+            }
+            .exit()
+          }
+        },
+      };
+      if name == "all" {
+        trends.push((
+          t!("all-projects"),
+          stats::weekly(&stats::daily(&projects), weeks, today),
+        ));
+      }
+      let width = trends.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+      for (name, totals) in trends {
+        println!(
+          "{: >width$}  {}  {}",
+          name,
+          stats::sparkline(&totals),
+          t!(
+            "this-week",
+            duration = format!(
+              "{: >7}",
+              compact_duration(Duration::from_secs(*totals.last().unwrap_or(&0)))
+            )
+          ),
+          width = width,
+        );
+      }
+    }
+    Some(("streak", _)) => {
+      let target = match config.daily_target {
+        Some(target) => target,
+        None => Diagnostic::new(t!("no-daily-target"))
+          .hint(t!(
+            "set-daily-target",
+            file = Config::file(&location).display()
+          ))
+          .exit(),
+      };
+      match Database::daily_totals(location.as_path(), None) {
+        Ok(projects) => {
+          let daily = stats::daily(&projects);
+          let today = Local::today().naive_local();
+          let streak = stats::streak(&daily, target.as_secs(), &config.workdays(), today);
+          let days = |n: u32| match n {
+            1 => t!("day-count-one"),
+            n => t!("day-count", count = n),
+          };
+          println!(
+            "{}",
+            t!(
+              "streak-summary",
+              streak = theme::success(t!("streak")).bold(),
+              current = days(streak.current),
+              longest = days(streak.longest)
+            )
+          );
+          let tracked = daily.get(&today).copied().unwrap_or_default();
+          match streak.met_today {
+            true => println!(
+              "{}",
+              t!("target-met-today", duration = display_duration(target))
+            ),
+            false => println!(
+              "{}",
+              t!(
+                "to-go-today",
+                duration = display_duration(Duration::from_secs(target.as_secs() - tracked))
+              )
+            ),
+          }
+        }
+        Err(err) => storage_failure(&location, err),
+      }
+    }
+    Some(("invoice", sub_matches)) => {
+      let client = sub_matches.value_of("CLIENT").expect("required");
+      let period = parse_period(sub_matches.value_of("period").expect("defaulted"));
+      let days = period.bounds(Local::today().naive_local());
+      let projects = match Database::daily_totals(location.as_path(), Some(days.clone())) {
+        Ok(projects) => projects,
+        Err(err) => return storage_failure(&location, err),
+      };
+      let (client, billed) =
+        billed_to(&config, client, projects).unwrap_or_else(|diagnostic| diagnostic.exit());
+      let client = config.client(client).expect("known");
+      let today = Local::today().naive_local();
+      let expenses = match Database::expenses(location.as_path(), days.clone()) {
+        Ok(expenses) => expenses,
+        Err(err) => return storage_failure(&location, err),
+      };
+      let mut invoice = match Invoice::new(today, client, &config, &billed, &expenses, days) {
+        Ok(invoice) => invoice,
+        Err(project) => Diagnostic::new(t!("no-rate", name = project))
+          .hint(t!("set-rate"))
+          .exit(),
+      };
+      let template = sub_matches.value_of("template");
+      // Rendered once as a draft first, not to use up a number on a broken template
+      if let Some(template) = template {
+        let context = template::invoice_context(&invoice);
+        apply_template(template, &context).unwrap_or_else(|diagnostic| diagnostic.exit());
+      }
+      if !sub_matches.is_present("draft") {
+        let issued = match open(
+          location.as_path(),
+          Load::Headers,
+          matches.is_present("wait"),
+        ) {
+          Ok(mut database) => database.issue_invoice(&invoice.client),
+          Err(err) => return storage_failure(&location, err),
+        };
+        match issued {
+          Ok(number) => invoice.number = Some(number),
+          Err(_) => Diagnostic::new(t!("invoice-unnumbered")).exit(),
+        }
+      }
+      let rendered = match template {
+        Some(template) => {
+          let context = template::invoice_context(&invoice);
+          apply_template(template, &context).unwrap_or_else(|diagnostic| diagnostic.exit())
+        }
+        None => invoice_text(&invoice),
+      };
+      match sub_matches.value_of("out") {
+        Some(path) => {
+          write_out(path, &t!("the-invoice"), rendered);
+        }
+        None => print!("{}", rendered),
+      }
+    }
+    _ => match open(
+      location.as_path(),
+      loading_strategy(&matches),
+      matches.is_present("wait"),
+    ) {
+      Ok(mut database) => {
+        let stopping = matches!(matches.subcommand_name(), Some("stop") | Some("toggle"));
+        if dry_run {
+          database.dry_run();
+        }
+        for (name, defaults) in config.defaults() {
+          database.set_defaults(name, defaults);
+        }
+        if let Some(context) = config.context() {
+          database.capture_context(context);
+        }
+        let result = match config.audit && database.seal_appends().is_err() {
+          true => Err(Diagnostic::new(t!("seal-failed")).hint(t!("audit-hint"))),
+          false => handle_command(matches, &mut database, &config),
+        };
+        if let Some(pending) = database.pending() {
+          print_dry_run(pending);
+        }
+        // Releases the lock before possibly exiting
+        drop(database);
+        if let Err(diagnostic) = result {
+          diagnostic.exit();
+        }
+        if stopping && config.celebrate && !dry_run {
+          celebrate(&location, &config);
+        }
+      }
+      Err(err) => storage_failure(&location, err),
+    },
+  }
+}
+
+/// Summarizes the log's hash chain, returning what's wrong with it, `seal` included if it's
+/// nowhere to be found
+fn print_audit(audit: &db::Audit, seal: Option<&str>) -> Vec<String> {
+  let mut problems: Vec<String> = audit
+    .broken
+    .iter()
+    .map(|n| t!("entry-not-sealed", number = n))
+    .collect();
+  match audit.seals.last() {
+    Some(head) => println!("  {}", t!("seals", count = audit.seals.len(), head = head)),
+    None => println!("  {}", theme::muted(t!("nothing-sealed"))),
+  }
+  if audit.unsealed > 0 && !audit.seals.is_empty() {
+    println!(
+      "  {}",
+      theme::warning(t!("unsealed-entries", count = audit.unsealed))
+    );
+  }
+  if let Some(seal) = seal.filter(|seal| !audit.seals.iter().any(|s| s == seal)) {
+    problems.push(t!("seal-missing", seal = seal));
+  }
+  problems
+}
+
+fn selftest() {
+  let location = std::env::temp_dir().join(format!("timeknight-selftest-{}", std::process::id()));
+  if let Err(err) = fs::create_dir_all(&location) {
+    Diagnostic::storage(&location, err.kind()).exit();
+  }
+  let steps = selftest::run(&location);
+  for step in &steps {
+    match &step.outcome {
+      Ok(()) => println!("{} {}", theme::success("ok").bold(), step.name),
+      Err(err) => println!("{} {}: {}", theme::failure("FAILED").bold(), step.name, err),
+    }
+  }
+  match steps.iter().find(|step| step.outcome.is_err()) {
+    None => {
+      let _ = fs::remove_dir_all(&location);
+      println!("{}", theme::success("All good").bold());
+    }
+    Some(step) => Diagnostic::new(format!("The self test failed to {}", step.name))
+      .hint(format!(
+        "the database is left in {}, for a bug report",
+        location.display()
+      ))
+      .exit(),
+  }
+}
+
+/// Tells what a dry run would have written to the log
+fn print_dry_run(pending: &DryRun) {
+  let heading = theme::warning(t!("dry-run")).bold();
+  match (pending.rewritten, pending.appended.is_empty()) {
+    (None, true) => println!("{}", t!("would-write-nothing", dry_run = heading)),
+    (Some(entries), _) => println!(
+      "{}",
+      t!("would-rewrite", dry_run = heading, count = entries)
+    ),
+    (None, false) => println!("{}", t!("would-append", dry_run = heading)),
+  }
+  for action in &pending.appended {
+    println!("  {}", action);
+  }
+}
+
+/// Cheers when the record just stopped is the one that reached the daily target
+fn celebrate(location: &Path, config: &Config) {
+  let target = match config.daily_target {
+    Some(target) => target,
+    None => return,
+  };
+  let today = Local::today().naive_local();
+  let (tracked, last) = match (
+    Database::day_total(location, today),
+    Database::last_session(location),
+  ) {
+    (Ok((tracked, _)), Ok(Some((_, last)))) if zone::current().day(last.start()) == today => {
+      (tracked, last)
+    }
+    _ => return,
+  };
+  if tracked < target || tracked - last.duration() >= target {
+    return;
+  }
+  let streak = match Database::daily_totals(location, None) {
+    Ok(projects) => {
+      let daily = stats::daily(&projects);
+      stats::streak(&daily, target.as_secs(), &config.workdays(), today).current
+    }
+    Err(_) => return,
+  };
+  let reached = theme::success(t!("reached-target")).bold();
+  match streak {
+    0 | 1 => println!(
+      "{}",
+      t!(
+        "target-reached",
+        reached = reached,
+        duration = display_duration(target)
+      )
+    ),
+    n => println!(
+      "{}",
+      t!(
+        "target-reached-streak",
+        reached = reached,
+        duration = display_duration(target),
+        count = n
+      )
+    ),
+  }
+}
+
+fn loading_strategy(matches: &ArgMatches) -> Load {
+  match matches.subcommand_name() {
+    Some("start") | Some("stop") | Some("toggle") | Some("project") | Some("expense")
+    | Some("fav") | Some("recent") | Some("resume") | Some("note") => Load::Headers,
+    Some(slot) if slot.parse::<usize>().is_ok() => Load::Headers,
+    _ => Load::Full,
+  }
+}
+
+/// Stops whatever ran past the hard stop, only taking the lock when there is anything to stop
+fn enforce_hard_stop(location: &Path, at: NaiveTime, wait: bool) {
+  let now = Local::now();
+  match Database::running(location) {
+    Ok(running) if running.iter().any(|(_, record)| record.next(at) < now) => {}
+    // Failing to read is for the command itself to report
+    _ => return,
+  }
+  match open(location, Load::Headers, wait) {
+    Ok(mut database) => match database.hard_stop(at) {
+      Ok(projects) => projects.iter().for_each(|project| {
+        let record = project.records().last().unwrap();
+        println!(
+          "{} {}",
+          theme::warning(t!("note-label")).bold(),
+          t!(
+            "ran-past-hard-stop",
+            name = style(project.name()).bold(),
+            at = at.format("%H:%M"),
+            duration = display_duration(record.duration())
+          ),
+        );
+      }),
+      Err(_) => Diagnostic::new(t!("hard-stop-failed"))
+        .caused_by(t!("storage-unwritable"))
+        .report(),
+    },
+    Err(err) => Diagnostic::storage(location, err).report(),
+  }
+}
+
+/// Offers to stop what's running at the last heartbeat, if three were missed since: the machine
+/// was off then, e.g. it rebooted. Only takes the lock if accepted.
+fn check_heartbeat(location: &Path, every: Duration, wait: bool) {
+  let beat = match Database::last_beat(location) {
+    Some(beat) => beat,
+    None => return,
+  };
+  let missed = Local::now()
+    .signed_duration_since(beat)
+    .to_std()
+    .unwrap_or_default();
+  let running = match Database::running(location) {
+    Ok(running) => running,
+    Err(_) => return,
+  };
+  if missed < every * 3 || !running.iter().any(|(_, record)| record.start() <= beat) {
+    return;
+  }
+  println!(
+    "{}",
+    t!(
+      "missed-heartbeats",
+      note = theme::warning(t!("note-label")).bold(),
+      when = locale::relative(beat.naive_local(), Local::now().naive_local())
+    )
+  );
+  let term = Term::stdout();
+  if !term.is_term() {
+    return;
+  }
+  println!("{}", t!("stop-at-heartbeat"));
+  if !matches!(term.read_char(), Ok('y') | Ok('Y')) {
+    // Kept running on purpose, no need to ask again
+    let _ = Database::beat(location);
+    return;
+  }
+  match open(location, Load::Headers, wait) {
+    Ok(mut database) => match database.stop_at(beat.with_timezone(beat.offset())) {
+      Ok(projects) => projects.iter().for_each(print_stopped),
+      Err(_) => Diagnostic::new(t!("heartbeat-stop-failed"))
+        .caused_by(t!("storage-unwritable"))
+        .report(),
+    },
+    Err(err) => Diagnostic::storage(location, err).report(),
+  }
+}
+
+/// Merges the copies of the log a sync tool left behind, setting them aside once merged. Only takes
+/// the lock when there are any.
+fn merge_conflicted_copies(location: &Path, wait: bool) {
+  let copies = Database::conflicted_copies(location);
+  if copies.is_empty() {
+    return;
+  }
+  let mut database = match open(location, Load::Full, wait) {
+    Ok(database) => database,
+    Err(err) => return Diagnostic::storage(location, err).report(),
+  };
+  for copy in copies {
+    let result = database.merge(&copy).map(|merged| {
+      let mut aside = copy.clone().into_os_string();
+      aside.push(".merged");
+      (merged, fs::rename(&copy, aside))
+    });
+    match result {
+      Ok((merged, Ok(_))) => println!(
+        "{}",
+        t!(
+          "copy-merged",
+          note = theme::warning(t!("note-label")).bold(),
+          count = merged,
+          file = copy.display()
+        )
+      ),
+      Ok((_, Err(err))) => Diagnostic::new(t!("set-aside-failed", file = copy.display()))
+        .caused_by(err)
+        .hint(t!("remove-merged-copy"))
+        .report(),
+      Err(err) => Diagnostic::new(t!("merge-failed", file = copy.display()))
+        .caused_by(err)
+        .hint(t!("fix-or-remove-copy"))
+        .report(),
+    }
+  }
+}
+
+/// Nudges about records running for longer than configured, on stderr to not mess with any output
+fn remind(location: &Path, config: &Config) {
+  if let Ok(running) = Database::running(location) {
+    for (name, record) in running {
+      if config
+        .remind_after(&name)
+        .is_some_and(|after| record.duration() >= after)
+      {
+        eprintln!(
+          "{}",
+          t!(
+            "still-working-on",
+            reminder = theme::info(t!("reminder")).bold(),
+            name = style(&name).bold(),
+            duration = display_duration(record.duration())
+          )
+        );
+      }
+    }
+  }
+}
+
+fn nag(location: &Path, config: &Config) {
+  let (hours, after) = match (&config.work_hours, config.nag_after) {
+    (Some(hours), Some(after)) => (hours, after),
+    _ => Diagnostic::new(t!("nothing-to-nag"))
+      .caused_by(t!("nag-not-configured"))
+      .hint(t!("set-them-in", file = Config::file(location).display()))
+      .exit(),
+  };
+  let now = Local::now();
+  let started = match hours.started(&now) {
+    Some(started) => started,
+    None => return,
+  };
+  match Database::running(location) {
+    Ok(running) if running.is_empty() => {}
+    Ok(_) => return,
+    Err(err) => storage_failure(location, err),
+  }
+  let idle_since = match Database::last_session(location) {
+    Ok(Some((_, record))) => record
+      .end()
+      .map(|end| end.with_timezone(&Local).max(started))
+      .unwrap_or(started),
+    _ => started,
+  };
+  let idle = (now - idle_since).to_std().unwrap_or_default();
+  if idle >= after {
+    notify(&t!("forgot-timer", duration = display_duration(idle)));
+  }
+}
+
+/// Shows a desktop notification, falling back to printing it
+fn notify(message: &str) {
+  let sent = match cfg!(target_os = "macos") {
+    true => Command::new("osascript")
+      .arg("-e")
+      .arg(format!(
+        "display notification {:?} with title \"timeknight\"",
+        message
+      ))
+      .status(),
+    false => Command::new("notify-send")
+      .arg("timeknight")
+      .arg(message)
+      .status(),
+  };
+  if !sent.is_ok_and(|status| status.success()) {
+    println!("{}", message);
+  }
+}
+
+fn print_health(location: &Path, health: &doctor::Health) {
+  println!("{} {}", style(t!("database")).bold(), location.display());
+  println!(
+    "  {:<10} {}",
+    t!("log-label"),
+    t!(
+      "log-size",
+      size = doctor::size(health.wal_len),
+      count = health.entries
+    )
+  );
+  println!("  {:<10} {:.1?}", t!("replay-label"), health.replay);
+  println!("  {:<10} {}", t!("projects-label"), health.projects);
+  println!("  {:<10} {}", t!("records-column"), health.records);
+  let share = match health.wal_len {
+    0 => 0,
+    len => health.savings() * 100 / len,
+  };
+  println!(
+    "  {:<10} {}",
+    t!("compacted-label"),
+    t!(
+      "compacted-size",
+      size = doctor::size(health.compacted_len),
+      saving = doctor::size(health.savings()),
+      share = share
+    )
+  );
+  match health.worth_compacting() {
+    true => println!(
+      "{}",
+      t!(
+        "log-mostly-stale",
+        worth = theme::warning(t!("worth-compacting")).bold()
+      )
+    ),
+    false => println!("{}", theme::success(t!("no-maintenance")).bold()),
+  }
+}
+
+fn storage_failure(location: &Path, err: ErrorKind) {
+  Diagnostic::storage(location, err).exit()
+}
+
+/// Takes the lock, waiting for whoever holds it to be done with it when `wait` is set. Whatever
+/// gets recorded is attributed to this device.
+fn open(location: &Path, load: Load, wait: bool) -> Result<Database, ErrorKind> {
+  let deadline = Instant::now() + LOCK_WAIT;
+  loop {
+    match Database::open(location, load.clone()) {
+      Err(ErrorKind::AlreadyExists) if wait && Instant::now() < deadline => {
+        thread::sleep(Duration::from_millis(100))
+      }
+      Ok(mut database) => {
+        // Attribution is best effort, entries are fine without
+        if let Ok(device) = config::device_id() {
+          database.attribute_to(device);
+        }
+        if database.skipped() > 0 {
+          eprintln!(
+            "{}",
+            t!(
+              "skipped-entries",
+              warning = theme::warning(t!("warning-label")).bold(),
+              count = database.skipped()
+            )
+          );
+        }
+        return Ok(database);
+      }
+      result => return result,
+    }
+  }
+}
+
+fn handle_command(
+  matches: ArgMatches,
+  database: &mut Database,
+  config: &Config,
+) -> Result<(), Diagnostic> {
+  match matches.subcommand() {
+    Some(("project", sub_matches)) => match sub_matches.subcommand() {
+      Some(("add", sub_matches)) => {
         let project = sub_matches.value_of("NAME").expect("required");
-        match database.remove_project(project.to_string()) {
+        if sub_matches.is_present("if-missing") && database.project(project).is_some() {
+          return Ok(());
+        }
+        match database.add_project(project.to_string()) {
           Ok(project) => {
             println!(
-              "{} project '{}'",
-              style("Deleted").green().bold(),
-              project.name(),
+              "{}",
+              t!(
+                "project-created",
+                created = theme::success(t!("created")).bold(),
+                name = project.name(),
+              )
             );
           }
           Err(_) => {
             println!(
-              "{} to delete project '{}'",
-              style("Failed").red().bold(),
-              project,
+              "{}",
+              t!(
+                "project-not-created",
+                failed = theme::failure(t!("failed")).bold(),
+                name = project,
+              )
             );
           }
         }
       }
-      Some(("list", _)) => {
-        let projects = database.list_projects();
+      Some(("del", sub_matches)) => {
+        let project = sub_matches.value_of("NAME").expect("required");
+        let stop_first = sub_matches.is_present("stop-first");
+        match database.remove_project(project.to_string(), stop_first) {
+          Ok(project) => {
+            println!(
+              "{}",
+              t!(
+                "project-deleted",
+                deleted = theme::success(t!("deleted")).bold(),
+                name = project.name(),
+              )
+            );
+          }
+          Err(DeleteError::UnknownProject(_)) => {
+            return Err(unknown_project(database, project));
+          }
+          Err(DeleteError::Running(name)) => {
+            return Err(
+              Diagnostic::new(t!("delete-failed", name = &name))
+                .caused_by(t!("still-running", name = &name))
+                .hint(t!("stop-first", name = &name)),
+            );
+          }
+          Err(DeleteError::Storage) => {
+            return Err(
+              Diagnostic::new(t!("delete-failed", name = project))
+                .caused_by(t!("storage-unwritable")),
+            );
+          }
+        }
+      }
+      Some(("import", sub_matches)) => {
+        let file = sub_matches.value_of("FILE").expect("required");
+        let csv = match sub_matches.value_of("format") {
+          Some(format) => format == "csv",
+          None => file.ends_with(".csv"),
+        };
+        let failed = || Diagnostic::new(t!("import-failed", file = file));
+        let content = fs::read_to_string(file).map_err(|err| failed().caused_by(err))?;
+        let projects = match csv {
+          true => import::read_projects_csv(content.as_bytes()),
+          false => import::read_projects_toml(&content),
+        }
+        .map_err(|err| failed().caused_by(err).hint(t!("nothing-was-imported")))?;
+        let names = projects.iter().map(|p| p.name.clone()).collect();
+        let created = database.add_projects(names).map_err(|_| {
+          failed()
+            .caused_by(t!("storage-unwritable"))
+            .hint(t!("nothing-was-imported"))
+        })?;
+        for name in &created {
+          println!(
+            "{}",
+            t!(
+              "project-created",
+              created = theme::success(t!("created")).bold(),
+              name = name,
+            )
+          );
+        }
+        let existing = projects.len() - created.len();
+        if existing > 0 {
+          println!("  {}", t!("already-existed", count = existing));
+        }
+        let settings = import::settings(&projects, config);
+        for project in &settings.left_out {
+          println!("  {}", t!("left-out", name = project));
+        }
+        if settings.toml.is_empty() || database.pending().is_some() {
+          return Ok(());
+        }
+        let location = database.location();
+        match Config::append(location, &settings.toml) {
+          Ok(_) => println!(
+            "{}",
+            t!(
+              "settings-added",
+              added = theme::success(t!("added")).bold(),
+              file = Config::file(location).display()
+            )
+          ),
+          Err(err) => {
+            return Err(
+              Diagnostic::new(t!("configure-imported-failed"))
+                .caused_by(err)
+                .hint(t!(
+                  "add-settings-yourself",
+                  file = Config::file(location).display(),
+                  settings = settings.toml
+                )),
+            )
+          }
+        }
+      }
+      Some(("archive", sub_matches)) => {
+        let name = sub_matches.value_of("NAME").expect("required");
+        archive(database, name, true)?;
+      }
+      Some(("unarchive", sub_matches)) => {
+        let name = sub_matches.value_of("NAME").expect("required");
+        archive(database, name, false)?;
+      }
+      Some(("list", sub_matches)) => {
+        let all = sub_matches.is_present("all");
+        let projects: Vec<&Project> = database
+          .list_projects()
+          .into_iter()
+          .filter(|p| all || !p.archived())
+          .collect();
         if projects.is_empty() {
           println!(
-            "{} use 'add' to create one",
-            style("No projects").yellow().bold(),
+            "{}",
+            t!(
+              "no-projects-hint",
+              none = theme::warning(t!("no-projects")).bold(),
+            )
           );
         }
-        projects.iter().for_each(|p| println!("{}", p.name()));
+        projects.iter().for_each(|p| match p.archived() {
+          true => println!(
+            "{} {}",
+            p.name(),
+            theme::muted(format!("({})", t!("archived").to_lowercase()))
+          ),
+          false => println!("{}", p.name()),
+        });
       }
       _ => unreachable!("clap should ensure we don't get here"),
     },
     Some(("start", sub_matches)) => {
+      let name = match sub_matches.value_of("NAME").expect("required") {
+        "." => match project_here(config, database) {
+          Some(name) => name,
+          None => pick_project(database)?,
+        },
+        name => name.to_string(),
+      };
+      let name = name.as_str();
+      if database.project(name).is_none() {
+        offer_to_create(database, name)?;
+      }
+      if sub_matches.is_present("unarchive") && database.project(name).is_some_and(|p| p.archived())
+      {
+        archive(database, name, false)?;
+      }
+      let concurrent = sub_matches.is_present("concurrent");
+      let labels = Labels {
+        billable: match (
+          sub_matches.is_present("billable"),
+          sub_matches.is_present("non-billable"),
+        ) {
+          (true, _) => Some(true),
+          (_, true) => Some(false),
+          _ => None,
+        },
+        tags: sub_matches
+          .values_of("tag")
+          .map(|tags| tags.map(str::to_string).collect()),
+      };
+      match sub_matches.value_of("at") {
+        Some(at) => {
+          let at = zone::parse_instant(at, &Local, Utc::now()).expect("validated");
+          start_at(database, name, concurrent, labels, at)?;
+        }
+        None => start(database, name, concurrent, labels)?,
+      }
+    }
+    Some(("fav", sub_matches)) => match sub_matches.subcommand() {
+      Some(("add", sub_matches)) => {
+        let name = sub_matches.value_of("NAME").expect("required");
+        let name = match database.project(name) {
+          Some(project) => project.name().to_string(),
+          None => return Err(unknown_project(database, name)),
+        };
+        match database.add_favorite(&name) {
+          Ok(slot) => println!(
+            "{}",
+            t!(
+              "favorite-added",
+              favorite = theme::success(t!("favorite")).bold(),
+              name = name,
+              slot = slot,
+            )
+          ),
+          Err(FavoriteError::UnknownProject(_)) => return Err(unknown_project(database, &name)),
+          Err(FavoriteError::Full) => {
+            return Err(
+              Diagnostic::new(t!("favorite-failed", name = name))
+                .caused_by(t!("favorites-full", count = FAVORITE_SLOTS))
+                .hint(t!("remove-a-favorite")),
+            )
+          }
+          Err(FavoriteError::Storage) => {
+            return Err(
+              Diagnostic::new(t!("favorite-failed", name = name))
+                .caused_by(t!("storage-unwritable")),
+            )
+          }
+        }
+      }
+      Some(("del", sub_matches)) => {
+        let name = sub_matches.value_of("NAME").expect("required");
+        if database.remove_favorite(name).is_err() {
+          return Err(Diagnostic::new(t!("not-a-favorite", name = name)));
+        }
+        println!(
+          "{}",
+          t!(
+            "favorite-removed",
+            removed = theme::success(t!("removed")).bold(),
+            name = name,
+          )
+        );
+      }
+      Some(("list", _)) => {
+        let favorites = database.favorites();
+        if favorites.is_empty() {
+          println!("{}", t!("no-favorites"));
+        }
+        for (slot, name) in favorites.iter().enumerate() {
+          println!("  {}. {}", slot + 1, name);
+        }
+      }
+      _ => unreachable!("clap should ensure we don't get here"),
+    },
+    Some((slot, _)) if slot.parse::<usize>().is_ok() => {
+      let favorites = database.favorites();
+      match favorites.get(slot.parse::<usize>().expect("checked") - 1) {
+        Some(name) if database.project(name).is_some() => {
+          start(database, name, false, Labels::default())?
+        }
+        Some(name) => return Err(unknown_project(database, name)),
+        None => {
+          return Err(
+            Diagnostic::new(t!("no-favorite-in-slot", slot = slot)).hint(t!("add-a-favorite")),
+          )
+        }
+      }
+    }
+    Some(("recent", sub_matches)) => {
+      let count: usize = sub_matches.value_of_t("count").expect("validated");
+      let recent = database.recent();
+      if recent.is_empty() {
+        println!("{}", t!("nothing-stopped"));
+      }
+      let now = Local::now().naive_local();
+      for (i, project) in recent.iter().take(count).enumerate() {
+        let end = project
+          .records()
+          .last()
+          .and_then(Record::end)
+          .expect("stopped");
+        println!(
+          "  {}",
+          t!(
+            "recently-stopped",
+            number = i + 1,
+            name = format!("{:<20}", project.name()),
+            when = locale::relative(end.with_timezone(&Local).naive_local(), now),
+          )
+        );
+      }
+    }
+    Some(("resume", sub_matches)) => {
+      let number: usize = sub_matches.value_of_t("NUMBER").expect("validated");
+      let name = match number
+        .checked_sub(1)
+        .and_then(|i| database.recent().get(i).copied())
+      {
+        Some(project) => project.name().to_string(),
+        None => {
+          return Err(
+            Diagnostic::new(t!("nothing-numbered", number = number)).hint(t!("see-recent")),
+          )
+        }
+      };
+      start(database, &name, false, Labels::default())?;
+    }
+    Some(("toggle", sub_matches)) => {
+      let name = sub_matches.value_of("NAME").expect("required");
+      match database.project(name) {
+        None => {
+          offer_to_create(database, name)?;
+          start(database, name, false, Labels::default())?;
+        }
+        Some(project) if project.in_flight() => match database.stop_on(name) {
+          Ok(project) => print_stopped(&project),
+          Err(_) => {
+            return Err(
+              Diagnostic::new(t!("stop-failed", name = name)).caused_by(t!("storage-unwritable")),
+            )
+          }
+        },
+        Some(_) => start(database, name, false, Labels::default())?,
+      }
+    }
+    Some(("expense", sub_matches)) => {
+      let name = sub_matches.value_of("PROJECT").expect("required");
+      let amount = sub_matches.value_of("AMOUNT").expect("required");
+      let description = sub_matches.value_of("DESCRIPTION").expect("required");
+      let cents = match Expense::parse_amount(amount) {
+        Some(cents) => cents,
+        None => {
+          return Err(
+            Diagnostic::new(t!("invalid-amount", amount = amount)).hint(t!("amount-currency")),
+          )
+        }
+      };
+      if database.project(name).is_none() {
+        return Err(unknown_project(database, name));
+      }
+      match database.add_expense(name, cents, description) {
+        Ok(project) => println!(
+          "{}",
+          t!(
+            "expense-recorded",
+            spent = theme::success(t!("spent")).bold(),
+            amount = format!("{:.2}", cents as f64 / 100.0),
+            name = project.name(),
+          )
+        ),
+        Err(_) => {
+          return Err(
+            Diagnostic::new(t!("expense-failed", name = name)).caused_by(t!("storage-unwritable")),
+          )
+        }
+      }
+    }
+    Some(("stop", sub_matches)) => {
+      let name = sub_matches.value_of("NAME");
+      if let Some(name) = name {
+        if database.project(name).is_none() {
+          return Err(unknown_project(database, name));
+        }
+        if !database.project(name).is_some_and(|p| p.in_flight()) {
+          return Err(not_running(database, name));
+        }
+      }
+      let note = sub_matches.value_of("message").map(str::to_string);
+      let noted = match name {
+        Some(name) => database.project(name),
+        None => database.current_project(),
+      }
+      .map(|p| p.name().to_string());
+      let at = sub_matches.value_of("at");
+      let stopped = match (name, at) {
+        // Notes go along with the stop, so that a stop failing leaves none behind
+        (_, at) if (at.is_some() || note.is_some()) && !database.running_projects().is_empty() => {
+          let at = match at {
+            Some(at) => zone::parse_instant(at, &Local, Utc::now()).expect("validated"),
+            None => Local::now().into(),
+          };
+          let noting = note.is_some();
+          match database.stop_as_of(name, at, note) {
+            Ok(projects) => {
+              if let Some(name) = noted.filter(|_| noting) {
+                println!(
+                  "{}",
+                  t!(
+                    "note-added",
+                    noted = theme::success(t!("noted")).bold(),
+                    name = name,
+                  )
+                );
+              }
+              Ok(projects)
+            }
+            Err(err) => {
+              let name = match (&err, name, database.current_project()) {
+                (BackdateError::BeforeStart(name), _, _) => name.clone(),
+                (_, Some(name), _) => name.to_string(),
+                (_, None, project) => project.map(|p| p.name().to_string()).unwrap_or_default(),
+              };
+              return Err(Diagnostic::new(t!("stop-failed", name = name)).caused_by(err));
+            }
+          }
+        }
+        (Some(name), _) => database.stop_on(name).map(|p| vec![p.into_owned()]),
+        (None, _) => database.stop(),
+      };
+      match stopped {
+        Ok(projects) => projects.iter().for_each(print_stopped),
+        Err(_) => {
+          println!(
+            "{}",
+            t!(
+              "nothing-to-stop",
+              none = theme::warning(t!("nothing-tracked")).bold(),
+            )
+          );
+        }
+      }
+    }
+    Some(("add", sub_matches)) => {
       let name = sub_matches.value_of("NAME").expect("required");
-      if database.start_on(name.to_string()).is_ok() {
+      let name = match database.project(name) {
+        Some(project) => project.name().to_string(),
+        None => return Err(unknown_project(database, name)),
+      };
+      let [from, to] = ["from", "to"].map(|at| {
+        let at = sub_matches.value_of(at).expect("required");
+        zone::parse_instant(at, &Local, Utc::now()).expect("validated")
+      });
+      let concurrent = sub_matches.is_present("concurrent");
+      match database.add_record(&name, (from, to), concurrent) {
+        Ok(_) => println!(
+          "{}",
+          t!(
+            "record-added",
+            added = theme::success(t!("added")).bold(),
+            duration = display_duration((to - from).to_std().unwrap_or_default()),
+            name = name,
+            when = locale::relative(
+              from.with_timezone(&Local).naive_local(),
+              Local::now().naive_local()
+            ),
+          )
+        ),
+        Err(BackdateError::Storage) => {
+          return Err(
+            Diagnostic::new(t!("add-failed", name = &name)).caused_by(t!("storage-unwritable")),
+          )
+        }
+        Err(err) => return Err(Diagnostic::new(t!("add-failed", name = &name)).caused_by(err)),
+      }
+    }
+    Some(("note", sub_matches)) => {
+      let name = sub_matches.value_of("on");
+      if let Some(name) = name {
+        match database.project(name) {
+          None => return Err(unknown_project(database, name)),
+          Some(project) if !project.in_flight() => return Err(not_running(database, name)),
+          Some(_) => {}
+        }
+      }
+      if database.running_projects().is_empty() {
         println!(
-          "{} tracking time on '{}'",
-          style("Started").green().bold(),
-          name,
+          "{}",
+          t!(
+            "nothing-to-note",
+            none = theme::warning(t!("nothing-tracked")).bold(),
+          )
         );
+        return Ok(());
+      }
+      let note = sub_matches.value_of("NOTE").expect("required");
+      self::note(database, name, note)?;
+    }
+    Some(("cancel", sub_matches)) => {
+      let name = match sub_matches.value_of("NAME") {
+        Some(name) => match database.project(name) {
+          None => return Err(unknown_project(database, name)),
+          Some(project) if !project.in_flight() => return Err(not_running(database, name)),
+          Some(project) => project.name().to_string(),
+        },
+        None => match database.current_project() {
+          Some(project) => project.name().to_string(),
+          None => {
+            println!(
+              "{}",
+              t!(
+                "nothing-to-cancel",
+                none = theme::warning(t!("nothing-tracked")).bold(),
+              )
+            );
+            return Ok(());
+          }
+        },
+      };
+      match database.cancel(Some(&name)) {
+        Ok(record) => println!(
+          "{}",
+          t!(
+            "tracking-cancelled",
+            cancelled = theme::warning(t!("cancelled")).bold(),
+            name = theme::warning(&name).bold(),
+            duration = display_duration(record.duration()),
+          )
+        ),
+        Err(_) => {
+          return Err(
+            Diagnostic::new(t!("cancel-failed", name = &name)).caused_by(t!("storage-unwritable")),
+          )
+        }
+      }
+    }
+    Some(("export", sub_matches)) => {
+      let projects = database.list_projects();
+      let anonymize = sub_matches.is_present("anonymize");
+      let since = match sub_matches.value_of("since").map(str::parse::<i64>) {
+        None => None,
+        Some(Ok(cursor)) => Some(cursor),
+        Some(Err(_)) => {
+          return Err(
+            Diagnostic::new(t!(
+              "invalid-cursor",
+              cursor = sub_matches.value_of("since").expect("present")
+            ))
+            .hint(t!("use-last-cursor")),
+          )
+        }
+      };
+      let mut out: Box<dyn Write> = match sub_matches.value_of("out") {
+        Some(path) => match fs::File::create(path) {
+          Ok(file) => Box::new(io::BufWriter::new(file)),
+          Err(err) => {
+            return Err(
+              Diagnostic::new(t!("write-failed", what = t!("the-export"), path = path))
+                .caused_by(err),
+            )
+          }
+        },
+        None => Box::new(io::stdout().lock()),
+      };
+      let written = match sub_matches.value_of("FORMAT").expect("defaulted") {
+        #[cfg(feature = "serde")]
+        "jsonl" => export::write_jsonl(&mut out, &projects, anonymize, since),
+        #[cfg(not(feature = "serde"))]
+        "jsonl" => return Err(Diagnostic::new(t!("no-jsonl-exports"))),
+        _ if since.is_some() => return Err(Diagnostic::new(t!("since-needs-jsonl"))),
+        _ => export::write_csv(&mut out, &projects, anonymize),
+      }
+      .and_then(|_| out.flush());
+      match written {
+        Err(err) if err.kind() != ErrorKind::BrokenPipe => {
+          Diagnostic::new(t!("export-failed")).caused_by(err).report()
+        }
+        _ => {}
       }
     }
-    Some(("stop", _sub_matches)) => match database.stop() {
-      Ok(project) => {
-        println!(
-          "{} tracking on {} - {} recorded",
-          style("Stopped").green().bold(),
-          style(project.name()).green().bold(),
-          style(display_duration(
-            project.records().last().unwrap().duration()
-          ))
-          .green(),
+    Some(("doctor", sub_matches)) => match sub_matches.subcommand() {
+      None => {
+        let location = database.location().to_path_buf();
+        let replaying = Instant::now();
+        let replayed = match Database::open_read_only(location.as_path(), Load::Full) {
+          Ok(replayed) => replayed,
+          Err(err) => return Err(Diagnostic::storage(&location, err)),
+        };
+        let replay = replaying.elapsed();
+        let entries = match db::verify(location.as_path()) {
+          Ok(verification) => verification.entries,
+          Err(err) => return Err(Diagnostic::storage(&location, err)),
+        };
+        let projects = replayed.list_projects();
+        print_health(
+          &location,
+          &doctor::Health {
+            wal_len: replayed.wal_len(),
+            entries,
+            replay,
+            projects: projects.len(),
+            records: projects.iter().map(|p| p.records().len()).sum(),
+            compacted_len: replayed.compacted_len(),
+          },
         );
       }
-      Err(_) => {
+      Some(("timezones", sub_matches)) if sub_matches.is_present("normalize") => {
+        let local =
+          |at: DateTime<FixedOffset>| Local.offset_from_utc_datetime(&at.naive_utc()).fix();
+        match database.reoffset(local) {
+          Ok(changed) => println!(
+            "{}",
+            t!(
+              "records-normalized",
+              normalized = theme::success(t!("normalized")).bold(),
+              count = changed
+            )
+          ),
+          Err(err) => {
+            return Err(
+              Diagnostic::new(t!("normalize-failed"))
+                .caused_by(err)
+                .hint(t!("nothing-was-changed")),
+            )
+          }
+        }
+      }
+      Some(("timezones", _)) => {
+        let projects = database.list_projects();
+        let mut offsets: Vec<FixedOffset> = projects
+          .iter()
+          .flat_map(|p| p.records())
+          .flat_map(|r| [Some(r.start()), r.end()])
+          .flatten()
+          .map(|at| *at.offset())
+          .collect();
+        offsets.sort_by_key(|offset| offset.local_minus_utc());
+        offsets.dedup();
+        let offsets: Vec<String> = offsets.iter().map(|offset| offset.to_string()).collect();
         println!(
-          "{} to be stopped",
-          style("No tracked project").yellow().bold(),
+          "{}",
+          t!(
+            "tracked-offsets",
+            count = offsets.len(),
+            offsets = offsets.join(", ")
+          )
         );
+        let jumps = doctor::clock_jumps(&projects);
+        for jump in &jumps {
+          println!(
+            "{} '{}' {}",
+            theme::warning(t!("clock-jump")).bold(),
+            jump.project,
+            jump.reason
+          );
+        }
+        match jumps.is_empty() {
+          true => println!("{}", theme::success(t!("no-clock-jumps")).bold()),
+          false => println!("{}", t!("clock-jumps-found", count = jumps.len())),
+        }
       }
+      _ => unreachable!("clap should ensure we don't get here"),
     },
-    Some(("status", _sub_matches)) => match database.current_project() {
-      None => println!("Nothing going on!"),
-      Some(project) => {
-        let r = project.records().last().unwrap();
-        if r.is_on_going() {
+    Some(("import", sub_matches)) => {
+      let file = sub_matches.value_of("FILE").expect("required");
+      let jsonl = match sub_matches.value_of("format") {
+        Some(format) => format == "jsonl",
+        None => file.ends_with(".jsonl") || file.ends_with(".json"),
+      };
+      let input = match fs::File::open(file) {
+        Ok(input) => io::BufReader::new(input),
+        Err(err) => return Err(Diagnostic::new(t!("read-failed", file = file)).caused_by(err)),
+      };
+      let records = match jsonl {
+        #[cfg(feature = "serde")]
+        true => import::read_jsonl(input),
+        #[cfg(not(feature = "serde"))]
+        true => return Err(Diagnostic::new(t!("no-jsonl-imports"))),
+        false => import::read_csv(input),
+      };
+      let records = records.map_err(|err| {
+        Diagnostic::new(t!("import-failed", file = file))
+          .caused_by(err)
+          .hint(t!("nothing-was-imported"))
+      })?;
+      match database.import(records) {
+        Ok(imported) => {
           println!(
-            "Working on {} for {}",
-            style(project.name()).green().bold(),
-            style(display_duration(r.duration())).green(),
+            "{}",
+            t!(
+              "records-imported",
+              imported = theme::success(t!("imported")).bold(),
+              count = imported.added,
+              file = file
+            )
           );
+          if imported.created > 0 {
+            println!("  {}", t!("projects-created", count = imported.created));
+          }
+          let skipped = [
+            (
+              imported.duplicates,
+              t!("skipped-already-tracked", count = imported.duplicates),
+            ),
+            (
+              imported.overlapping,
+              t!("skipped-overlapping", count = imported.overlapping),
+            ),
+            (
+              imported.in_flight,
+              t!("skipped-in-flight", count = imported.in_flight),
+            ),
+          ];
+          for (_, skipped) in skipped.iter().filter(|(count, _)| *count > 0) {
+            println!("  {}", skipped);
+          }
+        }
+        Err(err) => {
+          return Err(
+            Diagnostic::new(t!("import-failed", file = file))
+              .caused_by(err)
+              .hint(t!("nothing-was-imported")),
+          )
         }
       }
-    },
-    Some(("report", sub_matches)) => {
-      let mut projects = database.list_projects();
-      projects.sort_by_key(|p| p.name().to_lowercase());
+    }
+    Some(("merge", sub_matches)) => {
+      let file = sub_matches.value_of("FILE").expect("required");
+      match database.merge(Path::new(file)) {
+        Ok(merged) => println!(
+          "{}",
+          t!(
+            "entries-merged",
+            merged = theme::success(t!("merged")).bold(),
+            count = merged,
+            file = file
+          )
+        ),
+        Err(MergeError::Problems(problems)) => {
+          let diagnostic = Diagnostic::new(t!("refusing-to-merge", file = file));
+          return Err(
+            problems
+              .iter()
+              .fold(diagnostic, |d, problem| d.caused_by(problem))
+              .hint(t!("merge-would-not-replay")),
+          );
+        }
+        Err(err) => {
+          return Err(Diagnostic::new(t!("merge-failed", file = file)).caused_by(err));
+        }
+      }
+    }
+    Some(("retag", sub_matches)) => {
+      let from = sub_matches.value_of("from-project").expect("required");
+      let to = sub_matches.value_of("to-project").expect("required");
+      let period = parse_period(sub_matches.value_of("period").expect("defaulted"));
+      let days = period.bounds(Local::today().naive_local());
+      let (from, to) = match (database.project(from), database.project(to)) {
+        (Some(from), Some(to)) => (from.name().to_string(), to.name().to_string()),
+        (None, _) => return Err(unknown_project(database, from)),
+        (_, None) => return Err(unknown_project(database, to)),
+      };
+      // The record in flight, if any, stays where it is
+      let amendments: Vec<Amendment> = database
+        .project(&from)
+        .expect("found above")
+        .records()
+        .filter(|r| !r.is_on_going() && days.contains(&zone::current().day(r.start())))
+        .map(|r| Amendment::Reassign {
+          project: from.clone(),
+          start: r.start(),
+          to: to.clone(),
+        })
+        .collect();
+      match database.amend(amendments) {
+        Ok(moved) => println!(
+          "{}",
+          t!(
+            "records-moved",
+            moved = theme::success(t!("moved")).bold(),
+            count = moved,
+            from = style(&from).bold(),
+            to = style(&to).bold()
+          )
+        ),
+        Err(err) => {
+          return Err(
+            Diagnostic::new(t!("move-failed", name = from))
+              .caused_by(err)
+              .hint(t!("nothing-was-changed")),
+          )
+        }
+      }
+    }
+    Some(("review", sub_matches)) => {
+      let day = sub_matches.value_of("DAY").expect("defaulted");
+      review(database, parse_day(day).expect("validated"))?;
+    }
+    Some(("prune", sub_matches)) => {
+      let age = sub_matches.value_of("older-than").expect("required");
+      let age = match parse_age(age) {
+        Some(age) => age,
+        None => return Err(Diagnostic::new(t!("invalid-age", age = age)).hint(t!("age-examples"))),
+      };
       let now = Local::now();
-      let period = sub_matches.value_of("PERIOD").unwrap();
-      let lines = build_report(&projects, now, period, sub_matches.value_of("by").is_some());
-      print_report(lines);
+      let before = now.with_timezone(now.offset()) - age;
+      match database.prune(before, sub_matches.is_present("aggregate")) {
+        Ok(pruned) => println!(
+          "{}",
+          t!(
+            "records-pruned",
+            pruned = theme::success(t!("pruned")).bold(),
+            count = pruned,
+            date = before.date().naive_local()
+          )
+        ),
+        Err(_) => {
+          return Err(
+            Diagnostic::new(t!("prune-failed"))
+              .caused_by(t!("storage-unwritable"))
+              .hint(t!("nothing-was-changed")),
+          )
+        }
+      }
     }
     _ => unreachable!("clap should ensure we don't get here"),
   }
+  Ok(())
 }
 
-fn build_report(
-  projects: &[&Project],
-  now: DateTime<Local>,
-  period: &str,
-  by_day: bool,
-) -> Vec<(String, String, String)> {
-  let tz = now.offset();
-  let (start, end) = match period {
-    "ever" => {
-      let min = chrono::MIN_DATE;
-      let max = chrono::MAX_DATE;
-      (min.with_timezone(tz), max.with_timezone(tz))
-    }
-    "today" => {
-      let today = now.with_timezone(now.offset());
-      (today.date(), today.date())
-    }
-    "yesterday" => {
-      let yesterday = now.with_timezone(now.offset()) - chrono::Duration::days(1);
-      (yesterday.date(), yesterday.date())
-    }
-    "week" => {
-      let off = now.weekday().num_days_from_monday();
-      let today = now.with_timezone(now.offset());
-      let start = today - chrono::Duration::days(off as i64);
-      (start.date(), today.date())
-    }
-    "lastweek" => {
-      let off = now.weekday().num_days_from_monday();
-      let start = now - chrono::Duration::days(off as i64 + 7);
-      let end = now - chrono::Duration::days(off as i64 + 1);
-      (
-        start.with_timezone(start.offset()).date(),
-        end.with_timezone(end.offset()).date(),
-      )
+/// Walks through the records started on `day`, asking what to do with each, then makes all the
+/// amendments at once
+fn review(database: &mut Database, day: NaiveDate) -> Result<(), Diagnostic> {
+  let term = Term::stdout();
+  if !term.is_term() {
+    return Err(Diagnostic::new(t!("review-needs-terminal")).hint(t!("run-interactively")));
+  }
+  let mut records: Vec<(String, Record)> = database
+    .list_projects()
+    .iter()
+    .flat_map(|p| {
+      p.records()
+        .filter(|r| zone::current().day(r.start()) == day)
+        .map(|r| (p.name().to_string(), r.clone()))
+    })
+    .collect();
+  records.sort_by_key(|(_, record)| record.start());
+  let date = locale::current().date(day);
+  if records.is_empty() {
+    println!("{}", t!("nothing-tracked-on", date = date));
+    return Ok(());
+  }
+  println!(
+    "{}",
+    t!(
+      "reviewing-records",
+      reviewing = theme::info(t!("reviewing")).bold(),
+      count = records.len(),
+      date = date,
+    )
+  );
+  let count = records.len();
+  let mut amendments = Vec::new();
+  for (i, (name, record)) in records.into_iter().enumerate() {
+    let end = match record.end() {
+      Some(end) => zone::current().at(end).format("%H:%M").to_string(),
+      None => t!("now"),
+    };
+    println!(
+      "[{}/{}] {} {} - {}, {}",
+      i + 1,
+      count,
+      style(&name).bold(),
+      zone::current().at(record.start()).format("%H:%M"),
+      end,
+      display_duration(record.duration()),
+    );
+    let end = match record.end() {
+      Some(end) => end,
+      None => {
+        println!("  {}", t!("left-running"));
+        continue;
+      }
+    };
+    let start = record.start();
+    print!("  {} ", t!("review-choices"));
+    let _ = io::stdout().flush();
+    let choice = term.read_char().unwrap_or('q');
+    println!("{}", choice);
+    match choice {
+      't' => {
+        let to = (
+          ask_time(&term, &t!("start-prompt"), start),
+          ask_time(&term, &t!("end-prompt"), end),
+        );
+        match to {
+          (Some(from), Some(to)) if to >= from => amendments.push(Amendment::Retime {
+            project: name,
+            start,
+            to: (from, to),
+          }),
+          (Some(_), Some(_)) => println!(
+            "  {}",
+            t!("kept-ends-before-start", kept = theme::warning(t!("kept")))
+          ),
+          _ => println!(
+            "  {}",
+            t!("kept-not-a-time", kept = theme::warning(t!("kept")))
+          ),
+        }
+      }
+      'r' => {
+        print!("  {} ", t!("project-prompt"));
+        let _ = io::stdout().flush();
+        let to = term.read_line().unwrap_or_default();
+        match database.project(to.trim()) {
+          Some(project) => amendments.push(Amendment::Reassign {
+            project: name,
+            start,
+            to: project.name().to_string(),
+          }),
+          None => println!(
+            "  {}",
+            t!(
+              "kept-unknown-project",
+              kept = theme::warning(t!("kept")),
+              name = to.trim(),
+            )
+          ),
+        }
+      }
+      'n' => {
+        print!("  {} ", t!("note-prompt"));
+        let _ = io::stdout().flush();
+        let note = term.read_line().unwrap_or_default();
+        match note.trim() {
+          "" => println!(
+            "  {}",
+            t!("kept-empty-note", kept = theme::warning(t!("kept")))
+          ),
+          note => amendments.push(Amendment::Note {
+            project: name,
+            start,
+            note: note.to_string(),
+          }),
+        }
+      }
+      'd' => amendments.push(Amendment::Delete {
+        project: name,
+        start,
+      }),
+      'q' => break,
+      _ => {}
     }
-
-    "month" => {
-      let start = now.date().with_day(1).unwrap();
-      let today = now.with_timezone(now.offset());
-      (start.with_timezone(start.offset()), today.date())
-    }
-    "lastmonth" => {
-      let start = now
-        .date()
-        .with_day(1)
-        .unwrap()
-        .with_month(now.month() - 1)
-        .unwrap();
-      let end = start.with_month(now.month()).unwrap() - chrono::Duration::days(1);
-      (
-        start.with_timezone(start.offset()),
-        end.with_timezone(start.offset()),
-      )
+  }
+  if amendments.is_empty() {
+    println!("{}", t!("nothing-changed"));
+    return Ok(());
+  }
+  match database.amend(amendments) {
+    Ok(amended) => {
+      println!(
+        "{}",
+        t!(
+          "records-amended",
+          amended = theme::success(t!("amended")).bold(),
+          count = amended,
+        )
+      );
+      Ok(())
     }
-    _ => unreachable!("clap should ensure we don't get here"),
+    Err(err) => Err(
+      Diagnostic::new(t!("amend-failed"))
+        .caused_by(err)
+        .hint(t!("nothing-was-changed")),
+    ),
+  }
+}
+
+/// Asks for a new time of day for `current`, keeping it when nothing is entered
+fn ask_time(
+  term: &Term,
+  label: &str,
+  current: DateTime<FixedOffset>,
+) -> Option<DateTime<FixedOffset>> {
+  let current = zone::current().at(current);
+  print!("  {} [{}]: ", label, current.format("%H:%M"));
+  let _ = io::stdout().flush();
+  let input = term.read_line().ok()?;
+  let input = input.trim();
+  if input.is_empty() {
+    return Some(current);
+  }
+  let time = NaiveTime::parse_from_str(input, "%H:%M")
+    .or_else(|_| NaiveTime::parse_from_str(input, "%H:%M:%S"))
+    .ok()?;
+  current.date().and_time(time)
+}
+
+/// The project the current directory belongs to: the one configured for it, or the one named
+/// after the git repository it's in
+fn project_here(config: &Config, database: &Database) -> Option<String> {
+  let here = std::env::current_dir().ok()?;
+  if let Some(name) = config.project_at(&here) {
+    return Some(name.to_string());
+  }
+  let repository = here.ancestors().find(|dir| dir.join(".git").exists())?;
+  let name = repository.file_name()?.to_string_lossy();
+  database.project(&name).map(|p| p.name().to_string())
+}
+
+/// Asks which project to pick when interactive, failing with how to map directories otherwise
+fn pick_project(database: &Database) -> Result<String, Diagnostic> {
+  let diagnostic = Diagnostic::new(t!("project-here-unknown")).hint(t!("map-directories"));
+  let term = Term::stdout();
+  let projects = database.list_projects();
+  if !term.is_term() || projects.is_empty() {
+    return Err(diagnostic);
+  }
+  for (i, project) in projects.iter().enumerate() {
+    println!("  {}. {}", i + 1, project.name());
+  }
+  print!("{} ", t!("which-one"));
+  let _ = io::stdout().flush();
+  let choice = term.read_line().unwrap_or_default();
+  let picked = match choice.trim().parse::<usize>() {
+    Ok(i) => i.checked_sub(1).and_then(|i| projects.get(i)).copied(),
+    Err(_) => database.project(choice.trim()),
   };
-  let lines: Vec<(String, String, String)> = if by_day {
-    projects
-      .iter()
-      .flat_map(|p| {
-        p.records()
-          .group_by(|r| r.start().date())
-          .into_iter()
-          .filter(|(day, _)| day >= &start && day <= &end)
-          .map(|(day, records)| {
-            (
-              p.name().to_string(),
-              format!("{}", day.naive_local()),
-              display_duration(
-                records
-                  .into_iter()
-                  .filter(|r| r.start().date() >= start && r.start().date() <= end)
-                  .map(|r| r.duration())
-                  .sum(),
-              ),
-            )
-          })
-          .collect::<Vec<(String, String, String)>>()
-      })
-      .collect()
-  } else {
-    projects
-      .iter()
-      .map(|p| {
-        (
-          p.name().to_string(),
-          period.to_string(),
-          display_duration(
-            p.records()
-              .filter(|r| {
-                r.start().date().naive_local() >= start.naive_local()
-                  && r.start().date().naive_local() <= end.naive_local()
-              })
-              .map(|r| r.duration())
-              .sum(),
+  picked.map(|p| p.name().to_string()).ok_or(diagnostic)
+}
+
+/// Offers to create `name` when interactive, failing with why it can't be started otherwise
+fn unknown_project(database: &Database, name: &str) -> Diagnostic {
+  let diagnostic = Diagnostic::new(t!("unknown-project", name = name));
+  let projects = database.list_projects();
+  match diagnostic::did_you_mean(name, projects.iter().map(|p| p.name())) {
+    Some(close) => diagnostic.hint(t!("did-you-mean", name = close)),
+    None => diagnostic,
+  }
+}
+
+/// Archives `name`, or unarchives it
+fn archive(database: &mut Database, name: &str, archived: bool) -> Result<(), Diagnostic> {
+  let failed = || match archived {
+    true => Diagnostic::new(t!("archive-failed", name = name)),
+    false => Diagnostic::new(t!("unarchive-failed", name = name)),
+  };
+  match database.archive(name, archived) {
+    Ok(project) => {
+      println!(
+        "{}",
+        match archived {
+          true => t!(
+            "project-archived",
+            archived = theme::success(t!("archived")).bold(),
+            name = project.name(),
+          ),
+          false => t!(
+            "project-unarchived",
+            unarchived = theme::success(t!("unarchived")).bold(),
+            name = project.name(),
+          ),
+        }
+      );
+      Ok(())
+    }
+    Err(ArchiveError::UnknownProject(_)) => Err(unknown_project(database, name)),
+    Err(ArchiveError::Running(name)) => Err(
+      failed()
+        .caused_by(t!("still-running", name = &name))
+        .hint(t!("stop-before-archiving", name = &name)),
+    ),
+    Err(ArchiveError::Storage) => Err(failed().caused_by(t!("storage-unwritable"))),
+    Err(err) => Err(failed().caused_by(err)),
+  }
+}
+
+/// Why `name` can't be started, if it's archived
+fn archived(database: &Database, name: &str) -> Option<Diagnostic> {
+  let project = database.project(name).filter(|p| p.archived())?;
+  Some(
+    Diagnostic::new(t!("start-failed", name = project.name()))
+      .caused_by(t!("is-archived", name = project.name()))
+      .hint(t!("unarchive-first", name = project.name())),
+  )
+}
+
+fn start(
+  database: &mut Database,
+  name: &str,
+  concurrent: bool,
+  labels: Labels,
+) -> Result<(), Diagnostic> {
+  if let Some(diagnostic) = archived(database, name) {
+    return Err(diagnostic);
+  }
+  match database.start_on(name.to_string(), concurrent, labels) {
+    Ok(project) => {
+      println!(
+        "{}",
+        t!(
+          "tracking-started",
+          started = theme::success(t!("started")).bold(),
+          name = project.name(),
+        )
+      );
+      Ok(())
+    }
+    Err(_) => {
+      Err(Diagnostic::new(t!("start-failed", name = name)).caused_by(t!("storage-unwritable")))
+    }
+  }
+}
+
+fn start_at(
+  database: &mut Database,
+  name: &str,
+  concurrent: bool,
+  labels: Labels,
+  at: DateTime<FixedOffset>,
+) -> Result<(), Diagnostic> {
+  if let Some(diagnostic) = archived(database, name) {
+    return Err(diagnostic);
+  }
+  match database.start_at(name.to_string(), concurrent, labels, at) {
+    Ok(project) => {
+      println!(
+        "{}",
+        t!(
+          "tracking-started-at",
+          started = theme::success(t!("started")).bold(),
+          name = project.name(),
+          when = locale::relative(
+            at.with_timezone(&Local).naive_local(),
+            Local::now().naive_local()
           ),
         )
-      })
-      .collect()
+      );
+      Ok(())
+    }
+    Err(BackdateError::Storage) => {
+      Err(Diagnostic::new(t!("start-failed", name = name)).caused_by(t!("storage-unwritable")))
+    }
+    Err(err) => Err(Diagnostic::new(t!("start-failed", name = name)).caused_by(err)),
+  }
+}
+
+/// Notes `note` on the record `name` has in flight, or the latest started's without one
+fn note(database: &mut Database, name: Option<&str>, note: &str) -> Result<(), Diagnostic> {
+  let name = match name {
+    Some(name) => name.to_string(),
+    None => database
+      .current_project()
+      .map(|p| p.name().to_string())
+      .unwrap_or_default(),
   };
-  lines
+  match database.note(Some(&name), note.to_string()) {
+    Ok(project) => {
+      println!(
+        "{}",
+        t!(
+          "note-added",
+          noted = theme::success(t!("noted")).bold(),
+          name = project.name(),
+        )
+      );
+      Ok(())
+    }
+    Err(_) => {
+      Err(Diagnostic::new(t!("note-failed", name = name)).caused_by(t!("storage-unwritable")))
+    }
+  }
 }
 
-fn print_report(lines: Vec<(String, String, String)>) {
-  let h1 = "Project";
-  let h2 = "Period";
-  let h3 = "Duration";
+fn print_stopped(project: &Project) {
+  println!(
+    "{}",
+    t!(
+      "tracking-stopped",
+      stopped = theme::success(t!("stopped")).bold(),
+      name = theme::success(project.name()).bold(),
+      duration = theme::success(display_duration(
+        project.records().last().unwrap().duration()
+      )),
+    )
+  );
+}
 
-  let (n_width, p_width, d_width) = lines
+fn not_running(database: &Database, name: &str) -> Diagnostic {
+  let diagnostic = Diagnostic::new(t!("not-running", name = name));
+  let running: Vec<String> = database
+    .running_projects()
     .iter()
-    .map(|(n, p, d)| (n.len(), p.len(), d.len()))
-    .fold((h1.len(), h2.len(), h3.len()), |(m1, m2, m3), (n, p, d)| {
-      (m1.max(n), m2.max(p), m3.max(d))
-    });
+    .map(|p| format!("'{}'", p.name()))
+    .collect();
+  match running.len() {
+    0 => diagnostic.hint(t!("nothing-running")),
+    1 => diagnostic.hint(t!("one-running", name = running[0])),
+    _ => diagnostic.hint(t!("many-running", names = running.join(", "))),
+  }
+}
+
+fn offer_to_create(database: &mut Database, name: &str) -> Result<(), Diagnostic> {
+  let diagnostic = unknown_project(database, name);
+  let term = Term::stdout();
+  if !term.is_term() {
+    return Err(diagnostic);
+  }
+  diagnostic.report();
+  println!("{}", t!("should-create", name = name));
+  match term.read_char() {
+    Ok('y') | Ok('Y') => match database.add_project(name.to_string()) {
+      Ok(project) => {
+        println!(
+          "{}",
+          t!(
+            "project-created",
+            created = theme::success(t!("created")).bold(),
+            name = project.name(),
+          )
+        );
+        Ok(())
+      }
+      Err(_) => Err(Diagnostic::new(t!(
+        "project-not-created",
+        failed = theme::failure(t!("failed")).bold(),
+        name = name
+      ))),
+    },
+    _ => Err(Diagnostic::new(t!("not-tracking", name = name))),
+  }
+}
+
+fn print_project_info(project: &Project) {
+  const RECENT: usize = 5;
+  let format = "%Y-%m-%d %H:%M";
+  let records: Vec<&Record> = project.records().collect();
+  let total: Duration = records.iter().map(|r| r.duration()).sum();
+  println!("{}", style(project.name()).bold());
+  println!("  {: <8} {}", t!("total-label"), display_duration(total));
+  println!("  {: <8} {}", t!("records-label"), records.len());
+  if let (Some(first), Some(last)) = (records.first(), records.last()) {
+    let now = Local::now().naive_local();
+    let local = |at: DateTime<FixedOffset>| at.with_timezone(&Local).naive_local();
+    println!(
+      "  {: <8} {}",
+      t!("first-label"),
+      locale::relative(local(first.start()), now)
+    );
+    match last.end() {
+      Some(end) => println!(
+        "  {: <8} {}",
+        t!("last-label"),
+        locale::relative(local(end), now)
+      ),
+      None => println!(
+        "  {: <8} {}",
+        t!("last-label"),
+        theme::success(t!("running-now"))
+      ),
+    }
+    println!("  {}", t!("recent-label"));
+    for record in records.iter().rev().take(RECENT).rev() {
+      let start = zone::current().at(record.start());
+      let end = match record.end().map(|end| zone::current().at(end)) {
+        Some(end) if end.date() == start.date() => end.format("%H:%M").to_string(),
+        Some(end) => end.format(format).to_string(),
+        None => t!("now"),
+      };
+      let context = record.context().map(|context| {
+        let mut parts = Vec::new();
+        parts.extend(context.user.as_ref().map(|user| t!("by-user", user = user)));
+        parts.extend(context.host.as_ref().map(|host| t!("on-host", host = host)));
+        parts.extend(
+          context
+            .branch
+            .as_ref()
+            .map(|branch| t!("on-branch", branch = branch)),
+        );
+        parts.extend(
+          context
+            .cwd
+            .as_ref()
+            .map(|cwd| t!("in-directory", directory = cwd)),
+        );
+        format!(" {}", theme::muted(parts.join(", ")))
+      });
+      println!(
+        "    {} - {:<5} {}{}",
+        start.format(format),
+        end,
+        display_duration(record.duration()),
+        context.unwrap_or_default(),
+      );
+    }
+  }
+}
+
+fn render_prompt(template: &str, name: &str, record: &Record) -> String {
+  template
+    .replace("{project}", name)
+    .replace("{elapsed}", &compact_duration(record.duration()))
+}
+
+/// Parses a single day: today, yesterday or a date, e.g. 2022-03-14
+fn parse_day(day: &str) -> Result<NaiveDate, String> {
+  let today = Local::today().naive_local();
+  match day {
+    "today" => Ok(today),
+    "yesterday" => Ok(today.pred()),
+    _ => NaiveDate::parse_from_str(day, "%Y-%m-%d")
+      .map_err(|_| format!("{} isn't today, yesterday nor a date like 2022-03-14", day)),
+  }
+}
+
+/// A note, anything printable on a single line
+fn parse_note(note: &str) -> Result<(), String> {
+  match note.trim().is_empty() || note.contains(char::is_control) {
+    true => Err("notes are a single line of text".to_string()),
+    false => Ok(()),
+  }
+}
+
+/// A tag, anything printable on a single line
+fn parse_tag(tag: &str) -> Result<(), String> {
+  match tag.trim().is_empty() || tag.contains(char::is_control) {
+    true => Err(format!("'{}' can't be a tag", tag.escape_debug())),
+    false => Ok(()),
+  }
+}
+
+/// Parses a period clap already validated, e.g. through its possible values
+fn parse_period(period: &str) -> Period {
+  period
+    .parse()
+    .expect("clap should ensure we don't get here")
+}
 
+fn print_forecast(
+  name: &str,
+  budget: Duration,
+  deadline: Option<NaiveDate>,
+  forecast: &stats::Forecast,
+) {
+  let secs = Duration::from_secs;
+  println!("{}", style(name).bold());
   println!(
-    "┏━{0:━>w1$}━┯━{0:━>w2$}━┯━{0:━^w3$}━┓",
-    "━",
-    w1 = n_width,
-    w2 = p_width,
-    w3 = d_width
+    "  {: <9} {}",
+    t!("budget-label"),
+    t!(
+      "budget-spent",
+      budget = display_duration(budget),
+      spent = display_duration(secs(forecast.spent))
+    )
   );
   println!(
-    "┃ {0: ^w1$} │ {1: ^w2$} │ {2: ^w3$} ┃",
-    h1,
-    h2,
-    h3,
-    w1 = n_width,
-    w2 = p_width,
-    w3 = d_width
+    "  {: <9} {}",
+    t!("pace-label"),
+    t!(
+      "weekly-pace",
+      duration = compact_duration(secs(forecast.weekly_burn)),
+      weeks = stats::BURN_WEEKS
+    )
   );
+  let runs_out = match forecast.runs_out {
+    Some(day) => t!(
+      "runs-out-on",
+      date = day.format("%a %b %e %Y"),
+      remaining = display_duration(secs(forecast.remaining))
+    ),
+    None if forecast.remaining == 0 => theme::failure(t!(
+      "ran-out",
+      over = display_duration(secs(forecast.spent) - budget)
+    ))
+    .to_string(),
+    None => theme::muted(t!("not-spent-lately")).to_string(),
+  };
+  println!("  {: <9} {}", t!("runs-out-label"), runs_out);
+  if let Some(deadline) = deadline {
+    let pace = match forecast.weekly_pace {
+      Some(pace) if pace > forecast.weekly_burn => {
+        theme::failure(t!("needs-a-week", duration = compact_duration(secs(pace))))
+      }
+      Some(pace) => theme::success(t!("needs-a-week", duration = compact_duration(secs(pace)))),
+      None => theme::muted(t!("deadline-passed")),
+    };
+    println!(
+      "  {: <9} {}, {}",
+      t!("deadline-label"),
+      deadline.format("%a %b %e %Y"),
+      pace
+    );
+  }
+}
+
+fn print_averages(averages: &report::Averages) {
+  let days = match averages.days {
+    1 => t!("day-count-one"),
+    count => t!("day-count", count = count),
+  };
+  let workdays = match averages.workdays {
+    1 => t!("workday-count-one"),
+    count => t!("workday-count", count = count),
+  };
+  let per_workday = match averages.per_workday {
+    Some(average) => t!(
+      "per-workday",
+      duration = compact_duration(average),
+      workdays = workdays
+    ),
+    None => t!("no-workday"),
+  };
   println!(
-    "┠─{0:─>w1$}─┼─{0:─>w2$}─┼─{0:─^w3$}─┨",
-    "─",
-    w1 = n_width,
-    w2 = p_width,
-    w3 = d_width
+    "{}",
+    t!(
+      "averaging",
+      duration = compact_duration(averages.per_day),
+      days = days,
+      per_workday = per_workday
+    )
   );
-  lines.iter().for_each(|(project, period, duration)| {
+}
+
+fn print_expenses(projects: &[Project]) {
+  let mut expenses: Vec<(&str, &Expense)> = projects
+    .iter()
+    .flat_map(|p| p.expenses().map(move |e| (p.name(), e)))
+    .collect();
+  if expenses.is_empty() {
+    return;
+  }
+  expenses.sort_by_key(|(_, e)| e.at());
+  println!("{}", t!("expenses"));
+  for (project, expense) in expenses {
     println!(
-      "┃ {0: >w1$} │ {1: ^w2$} │ {2: <w3$} ┃",
+      "  {}  {: >10.2}  {}, {}",
+      expense.at().format("%a %b %e"),
+      expense.amount(),
       project,
-      period,
-      duration,
-      w1 = n_width,
-      w2 = p_width,
-      w3 = d_width,
+      expense.description(),
     );
-  });
+  }
+}
+
+/// A line showing how the `tracked` seconds of `day` stand against its target, if it has one
+fn day_progress(config: &Config, day: NaiveDate, tracked: u64) -> String {
+  const WIDTH: usize = 20;
+  let label = day.format("%a %b %e");
+  let tracked_str = compact_duration(Duration::from_secs(tracked));
+  let target = match config.target_on(day) {
+    Some(target) => target.as_secs(),
+    None => {
+      let why = match (config.daily_target, config.holidays.contains(&day)) {
+        (None, _) => t!("no-daily-target-short"),
+        (_, true) => t!("holiday"),
+        _ => t!("day-off"),
+      };
+      return format!(
+        "{}  {}  {: >6}  {}",
+        label,
+        " ".repeat(WIDTH),
+        tracked_str,
+        theme::muted(why)
+      );
+    }
+  };
+  let now = Local::now();
+  let today = now.date().naive_local();
+  let elapsed = match day.cmp(&today) {
+    std::cmp::Ordering::Less => 1.0,
+    std::cmp::Ordering::Greater => 0.0,
+    std::cmp::Ordering::Equal => match &config.work_hours {
+      Some(hours) => hours.elapsed(&now),
+      None => {
+        let day = zone::Zone::Local.bounds(today);
+        let elapsed = now.signed_duration_since(day.start).num_seconds();
+        elapsed as f64 / (day.end - day.start).num_seconds() as f64
+      }
+    },
+  };
+  let bar = stats::bar(tracked, target, WIDTH);
+  let (bar, status) = match stats::progress(tracked, target, elapsed) {
+    stats::Progress::Done => (theme::success(bar), theme::success(t!("progress-done"))),
+    stats::Progress::OnTrack => (theme::warning(bar), theme::warning(t!("progress-on-track"))),
+    stats::Progress::Behind => (theme::failure(bar), theme::failure(t!("progress-behind"))),
+    stats::Progress::Upcoming => (theme::muted(bar), theme::muted(String::new())),
+  };
+  format!(
+    "{}  {}  {}  {}",
+    label,
+    bar,
+    t!(
+      "of-target",
+      tracked = format!("{: >6}", tracked_str),
+      target = compact_duration(Duration::from_secs(target))
+    ),
+    status
+  )
+}
+
+/// Every record under the day it started, with its times, duration, project and notes
+fn print_log(records: &[(String, Record)]) {
+  let zone = zone::current();
+  let width = records
+    .iter()
+    .map(|(name, _)| name.len())
+    .max()
+    .unwrap_or(0);
+  let mut day = None;
+  for (name, record) in records {
+    let start = zone.at(record.start());
+    if day != Some(start.naive_local().date()) {
+      day = Some(start.naive_local().date());
+      println!("{}", style(start.format("%a %b %e")).bold());
+    }
+    let end = match record.end() {
+      Some(end) => zone.at(end).format("%H:%M").to_string(),
+      None => "     ".to_string(),
+    };
+    let mut notes = record.notes().to_vec();
+    if record.is_hard_stopped() {
+      notes.push(t!("hard-stopped"));
+    }
+    println!(
+      "  {}-{}  {: >6}  {: <width$}  {}",
+      start.format("%H:%M"),
+      end,
+      compact_duration(record.duration()),
+      name,
+      theme::muted(notes.join("; ")),
+      width = width,
+    );
+  }
+}
+
+fn print_stats(rows: &[(String, stats::Focus)]) {
+  let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
   println!(
-    "┗━{0:━>w1$}━┷━{0:━>w2$}━┷━{0:━^w3$}━┛",
-    "━",
-    w1 = n_width,
-    w2 = p_width,
-    w3 = d_width
+    "{: <width$}  {: >8}  {: >8}  {: >8}  {: >8}  {: >5}",
+    "",
+    t!("tracked-column"),
+    t!("sessions-column"),
+    t!("switches-column"),
+    t!("median-column"),
+    t!("focus-column"),
+    width = width,
   );
+  for (label, focus) in rows {
+    println!(
+      "{: <width$}  {: >8}  {: >8}  {: >8}  {: >8}  {: >4}%",
+      label,
+      compact_duration(Duration::from_secs(focus.tracked)),
+      focus.sessions,
+      focus.switches,
+      compact_duration(Duration::from_secs(focus.median)),
+      focus.score(),
+      width = width,
+    );
+  }
+}
+
+/// The daily totals of the projects billed to `client`, rounded as the client is billed, along
+/// with the client's name as configured
+fn billed_to<'a>(
+  config: &'a Config,
+  client: &str,
+  projects: Vec<ProjectTotals>,
+) -> Result<(&'a str, Vec<ProjectTotals>), Diagnostic> {
+  let (name, client) = match config.client(client) {
+    Some(client) => client,
+    None => {
+      let diagnostic = Diagnostic::new(t!("unknown-client", name = client));
+      return Err(match diagnostic::did_you_mean(client, config.clients()) {
+        Some(close) => diagnostic.hint(t!("did-you-mean", name = close)),
+        None => diagnostic.hint(t!("clients-configured")),
+      });
+    }
+  };
+  let billed = projects
+    .into_iter()
+    .filter(|project| client.bills(&project.name))
+    .map(|mut project| {
+      project
+        .days
+        .values_mut()
+        .for_each(|secs| *secs = client.bill(*secs));
+      project
+    })
+    .collect();
+  Ok((name, billed))
+}
+
+/// Renders the template at `path`, see [`template::render`] for what's available to it
+fn apply_template(path: &str, context: &serde_json::Value) -> Result<String, Diagnostic> {
+  let template = fs::read_to_string(path)
+    .map_err(|err| Diagnostic::new(t!("read-failed", file = path)).caused_by(err))?;
+  template::render(Path::new(path), &template, context).map_err(|err| {
+    Diagnostic::new(t!("render-failed", file = path))
+      .caused_by(err)
+      .hint(t!("templates-are-handlebars"))
+  })
+}
+
+fn invoice_text(invoice: &Invoice) -> String {
+  let currency = invoice.currency.as_deref().unwrap_or("");
+  let money = |amount: f64| format!("{:.2} {}", amount, currency).trim_end().to_string();
+  let mut out = match invoice.number {
+    Some(number) => format!("Invoice #{}, {}\n", number, invoice.date),
+    None => format!("Draft invoice, {}\n", invoice.date),
+  };
+  out.push_str(&format!("To: {}\n", invoice.client));
+  out.push_str(&format!(
+    "For: {} to {}\n\n",
+    invoice.period.start(),
+    invoice.period.end()
+  ));
+  let label = |expense: &ExpenseLine| format!("{}, {}", expense.project, expense.description);
+  let width = invoice
+    .lines
+    .iter()
+    .map(|line| line.project.len())
+    .chain(invoice.expenses.iter().map(|expense| label(expense).len()))
+    .max()
+    .unwrap_or(0)
+    .max("Subtotal".len());
+  for line in &invoice.lines {
+    out.push_str(&format!(
+      "{: <width$}  {: >7} at {: >8}/h  {: >14}\n",
+      line.project,
+      timesheet::hours(line.seconds),
+      format!("{:.2}", line.rate),
+      money(line.amount()),
+      width = width,
+    ));
+  }
+  for expense in &invoice.expenses {
+    out.push_str(&format!(
+      "{: <width$}  {: >37}\n",
+      label(expense),
+      money(expense.amount()),
+      width = width,
+    ));
+  }
+  let total = |label: &str, amount: f64| {
+    format!(
+      "{: <width$}  {: >37}\n",
+      label,
+      money(amount),
+      width = width
+    )
+  };
+  out.push_str(&total("Subtotal", invoice.subtotal()));
+  if let Some(rate) = invoice.tax_rate {
+    out.push_str(&total(&format!("Tax {}%", rate), invoice.tax()));
+  }
+  out.push_str(&total("Total", invoice.total()));
+  out
+}
+
+fn timesheet_table(sheet: &Timesheet, locale: &Locale) -> String {
+  let mut out = format!("{}\n", sheet.title);
+  for (label, value) in [("Employee", &sheet.employee), ("Client", &sheet.client)] {
+    if let Some(value) = value {
+      out.push_str(&format!("{}: {}\n", label, value));
+    }
+  }
+  out.push('\n');
+  for day in &sheet.days {
+    let names: Vec<&str> = day.projects.iter().map(|(name, _)| name.as_str()).collect();
+    let total = match day.total() {
+      0 => String::new(),
+      secs => timesheet::hours(secs),
+    };
+    let line = format!(
+      "{}  {: >6}  {}",
+      locale.format(day.date, "%a %b %e"),
+      total,
+      names.join(", "),
+    );
+    out.push_str(line.trim_end());
+    out.push('\n');
+  }
+  out.push_str(&format!(
+    "{: >10}  {: >6}\n",
+    "Total",
+    timesheet::hours(sheet.total())
+  ));
+  out
+}
+
+/// Writes what was rendered to `path`, as the shell's redirection would but for the styling: text
+/// is written without the terminal's escape codes and ends with a newline, while binary formats,
+/// e.g. pdf, are written as is.
+fn write_out(path: &str, what: &str, rendered: impl Into<Vec<u8>>) {
+  let mut bytes = rendered.into();
+  if let Ok(text) = std::str::from_utf8(&bytes) {
+    let mut text = console::strip_ansi_codes(text).into_owned();
+    if !text.is_empty() && !text.ends_with('\n') {
+      text.push('\n');
+    }
+    bytes = text.into_bytes();
+  }
+  if let Err(err) = fs::write(path, bytes) {
+    Diagnostic::new(t!("write-failed", what = what, path = path))
+      .caused_by(err)
+      .exit();
+  }
+}
+
+/// Hands the report over to the local MTA, as `cron` would with a job's output
+fn send_report(address: &str, subject: &str, body: &str) -> io::Result<()> {
+  let mut sendmail = Command::new("sendmail")
+    .arg("-t")
+    .stdin(Stdio::piped())
+    .spawn()?;
+  if let Some(stdin) = sendmail.stdin.as_mut() {
+    write!(
+      stdin,
+      "To: {}\nSubject: {}\nContent-Type: text/plain; charset=utf-8\n\n{}",
+      address, subject, body
+    )?;
+  }
+  match sendmail.wait()? {
+    status if status.success() => Ok(()),
+    status => Err(io::Error::other(format!("sendmail exited with {}", status))),
+  }
 }
 
 fn display_duration(duration: Duration) -> String {
-  match (
-    duration.as_secs() % 60,
-    (duration.as_secs() / 60) % 60,
-    (duration.as_secs() / 60) / 60,
-  ) {
-    (0, 0, 0) => "None".to_string(),
-    (1, 0, 0) => "one second".to_string(),
-    (s, 0, 0) => format!("{s} seconds"),
-    (1, 1, 0) => "one minute one second".to_string(),
-    (s, 1, 0) => format!("one minute {s} second"),
-    (1, m, 0) => format!("{m} minutes one second"),
-    (s, m, 0) => format!("{m} minutes {s} seconds"),
-    (_, 0, 1) => "an hour".to_string(),
-    (_, 1, 1) => "an hour one minute".to_string(),
-    (_, m, 1) => format!("one hour {m} minute"),
-    (_, m, h) => format!("{h} hours {m} minutes"),
+  locale::current().duration(duration)
+}
+
+fn compact_duration(duration: Duration) -> String {
+  let secs = duration.as_secs();
+  match (secs / 3600, (secs / 60) % 60) {
+    (0, 0) => format!("{}s", secs),
+    (0, m) => format!("{m}m"),
+    (h, m) => format!("{h}h{m:02}m"),
+  }
+}
+
+/// Parses ages like `90d`, `12w`, `6m` or `3y`, where months are 30 days and years 365
+fn parse_age(age: &str) -> Option<chrono::Duration> {
+  let unit = age.chars().last()?;
+  let count: i64 = age[..age.len() - unit.len_utf8()].parse().ok()?;
+  match unit {
+    'd' => Some(chrono::Duration::days(count)),
+    'w' => Some(chrono::Duration::weeks(count)),
+    'm' => Some(chrono::Duration::days(count * 30)),
+    'y' => Some(chrono::Duration::days(count * 365)),
+    _ => None,
   }
 }
 
+/// Prints the version, or as JSON with what a bug report needs to know about the environment:
+/// where the database is, what its WAL holds and which features this build has
+fn print_version(location: &Path, json: bool) {
+  if !json {
+    println!("timeknight {}", VERSION);
+    return;
+  }
+  let features: Vec<&str> = [
+    ("serde", cfg!(feature = "serde")),
+    ("ffi", cfg!(feature = "ffi")),
+  ]
+  .into_iter()
+  .filter_map(|(feature, enabled)| enabled.then_some(feature))
+  .collect();
+  let entries = db::verify(location).ok().map(|v| v.entries);
+  let version = serde_json::json!({
+    "version": VERSION,
+    "database": location.display().to_string(),
+    "wal_format": WAL_FORMAT,
+    "entries": entries,
+    "features": features,
+  });
+  println!("{}", version);
+}
+
 fn db_location() -> PathBuf {
   dirs::home_dir()
     .get_or_insert_with(|| {
       eprintln!(
         "{} Could not find a home directory, falling back to current directory",
-        style("Ugh!").cyan(),
+        theme::info("Ugh!"),
       );
       match std::env::current_dir() {
         Ok(location) => location,
-        Err(err) => {
-          eprintln!("{}: {}", style("Can't access current directory").red(), err);
-          std::process::exit(1);
-        }
+        Err(err) => Diagnostic::new("Can't access current directory")
+          .caused_by(err)
+          .exit(),
       }
     })
     .join(DEFAULT_DIRECTORY)
@@ -419,7 +3508,7 @@ fn init_if_needed(location: &Path) {
   if !location.exists() {
     println!(
       "{} Looks like the environment wasn't ever set up...",
-      style("Welcome!").cyan(),
+      theme::info("Welcome!"),
     );
     println!("Should we initialize it in {} ?", location.display());
     match Term::stdout().read_char() {
@@ -427,21 +3516,16 @@ fn init_if_needed(location: &Path) {
         Ok(_) => {
           println!(
             "{} db... {}",
-            style("Initializing").green().bold(),
-            style("Done!").green(),
-          );
-        }
-        Err(err) => {
-          eprintln!(
-            "{} initializing db: {}",
-            style("Error").red().bold(),
-            style(format!("{}", err)).red().bold(),
+            theme::success("Initializing").bold(),
+            theme::success("Done!"),
           );
-          std::process::exit(1);
         }
+        Err(err) => Diagnostic::new(format!("Couldn't initialize {}", location.display()))
+          .caused_by(err)
+          .exit(),
       },
       _ => {
-        eprintln!("{} bye!", style("Aborting...").yellow());
+        eprintln!("{} bye!", theme::warning("Aborting..."));
         std::process::exit(1);
       }
     };
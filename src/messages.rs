@@ -0,0 +1,1148 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! User facing prose, looked up by key in the catalog of the configured [`Language`], so that
+//! commands don't hardcode it. Messages name their `{placeholders}`, letting translations order
+//! them as their grammar needs. Keys missing from a translation fall back to English.
+
+use crate::locale::{self, Language};
+use std::fmt::Display;
+
+const EN: &[(&str, &str)] = &[
+  ("created", "Created"),
+  ("deleted", "Deleted"),
+  ("archived", "Archived"),
+  ("unarchived", "Unarchived"),
+  ("failed", "Failed"),
+  ("started", "Started"),
+  ("stopped", "Stopped"),
+  ("cancelled", "Cancelled"),
+  ("added", "Added"),
+  ("noted", "Noted"),
+  ("spent", "Spent"),
+  ("project-created", "{created} project '{name}'"),
+  ("project-not-created", "{failed} to create project '{name}'"),
+  ("project-deleted", "{deleted} project '{name}'"),
+  ("delete-failed", "Couldn't delete project '{name}'"),
+  ("project-archived", "{archived} project '{name}'"),
+  ("project-unarchived", "{unarchived} project '{name}'"),
+  ("archive-failed", "Couldn't archive project '{name}'"),
+  ("unarchive-failed", "Couldn't unarchive project '{name}'"),
+  ("is-archived", "'{name}' is archived"),
+  (
+    "unarchive-first",
+    "unarchive it first, with `project unarchive {name}` or --unarchive",
+  ),
+  ("stop-before-archiving", "stop it first, with `stop {name}`"),
+  ("no-projects", "No projects"),
+  ("no-projects-hint", "{none} use 'add' to create one"),
+  ("should-create", "Should we create '{name}'? [y/N]"),
+  ("not-tracking", "Not tracking '{name}'"),
+  ("unknown-project", "No project named '{name}'"),
+  ("did-you-mean", "did you mean '{name}'?"),
+  ("tracking-started", "{started} tracking time on '{name}'"),
+  (
+    "tracking-started-at",
+    "{started} tracking time on '{name}', {when}",
+  ),
+  (
+    "tracking-stopped",
+    "{stopped} tracking on {name} - {duration} recorded",
+  ),
+  ("start-failed", "Couldn't start tracking '{name}'"),
+  ("stop-failed", "Couldn't stop tracking '{name}'"),
+  ("storage-unwritable", "the storage couldn't be written to"),
+  ("nothing-tracked", "No tracked project"),
+  ("nothing-to-stop", "{none} to be stopped"),
+  ("nothing-to-cancel", "{none} to be cancelled"),
+  (
+    "tracking-cancelled",
+    "{cancelled} tracking on {name} - {duration} discarded",
+  ),
+  ("cancel-failed", "Couldn't cancel tracking '{name}'"),
+  ("record-added", "{added} {duration} to '{name}', {when}"),
+  ("add-failed", "Couldn't add a record to '{name}'"),
+  ("note-added", "{noted} on '{name}'"),
+  ("nothing-to-note", "{none} to note on"),
+  ("note-failed", "Couldn't note on '{name}'"),
+  ("not-running", "'{name}' isn't running"),
+  ("still-running", "'{name}' is still being tracked"),
+  (
+    "stop-first",
+    "stop it first, with `stop {name}` or --stop-first",
+  ),
+  ("nothing-running", "nothing is being tracked"),
+  (
+    "one-running",
+    "{name} is, use `stop` without a name to stop it",
+  ),
+  ("many-running", "{names} are"),
+  ("expense-recorded", "{spent} {amount} on {name}"),
+  ("working-on", "Working on {name} for {duration}"),
+  ("idle", "Nothing going on!"),
+  (
+    "last-worked-on",
+    "Last worked on {name} for {duration}, stopped {when}",
+  ),
+  ("today-total-one", "Today: {duration} across 1 project"),
+  ("today-total", "Today: {duration} across {count} projects"),
+  ("just-now", "just now"),
+  ("minutes-ago-one", "a minute ago"),
+  ("minutes-ago", "{count} minutes ago"),
+  ("hours-ago-one", "an hour ago"),
+  ("hours-ago", "{count} hours ago"),
+  ("yesterday", "yesterday"),
+  ("days-ago", "{count} days ago"),
+  ("weeks-ago", "{count} weeks ago"),
+  ("months-ago", "{count} months ago"),
+  ("years-ago-one", "a year ago"),
+  ("years-ago", "{count} years ago"),
+  ("relative-at", "{ago} at {time}"),
+  ("relative-on", "{ago}, on {date} at {time}"),
+  ("nothing-tracked-on", "Nothing tracked on {date}"),
+  ("nothing-changed", "Nothing changed"),
+  (
+    "no-favorites",
+    "No favorites yet, add some with `timek fav add <NAME>`",
+  ),
+  ("nothing-stopped", "Nothing stopped yet"),
+  ("review-needs-terminal", "Reviewing needs a terminal"),
+  ("run-interactively", "run it interactively"),
+  ("reviewing", "Reviewing"),
+  ("reviewing-records", "{reviewing} {count} records on {date}"),
+  ("now", "now"),
+  ("left-running", "Still running, left as is"),
+  (
+    "review-choices",
+    "(k)eep, (t)imes, (r)eassign, (n)ote, (d)elete or (q)uit?",
+  ),
+  ("start-prompt", "Start"),
+  ("end-prompt", "End"),
+  ("project-prompt", "Project:"),
+  ("note-prompt", "Note:"),
+  ("kept", "Kept"),
+  (
+    "kept-ends-before-start",
+    "{kept}, it would end before it starts",
+  ),
+  ("kept-not-a-time", "{kept}, that isn't a time"),
+  ("kept-unknown-project", "{kept}, no project '{name}'"),
+  ("kept-empty-note", "{kept}, the note is empty"),
+  ("amended", "Amended"),
+  ("records-amended", "{amended} {count} records"),
+  ("amend-failed", "Couldn't amend the records"),
+  ("nothing-was-changed", "nothing was changed"),
+  ("favorite", "Favorite"),
+  ("removed", "Removed"),
+  (
+    "favorite-added",
+    "{favorite} '{name}' in slot {slot}, start it with `timek {slot}`",
+  ),
+  ("favorite-failed", "Couldn't add '{name}' to the favorites"),
+  ("favorites-full", "all {count} slots are taken"),
+  (
+    "remove-a-favorite",
+    "remove one first, with `timek fav del <NAME>`",
+  ),
+  ("not-a-favorite", "'{name}' isn't a favorite"),
+  ("favorite-removed", "{removed} '{name}' from the favorites"),
+  ("no-favorite-in-slot", "No favorite in slot {slot}"),
+  ("add-a-favorite", "add one with `timek fav add <NAME>`"),
+  ("recently-stopped", "{number}. {name} stopped {when}"),
+  ("nothing-numbered", "Nothing numbered {number} recently"),
+  ("see-recent", "see `timek recent` for the numbers"),
+  ("hard-stopped", "stopped at the hard stop"),
+  (
+    "ran-past-hard-stop",
+    "{name} was still running past {at}, stopped it then - {duration} recorded",
+  ),
+  (
+    "hard-stop-failed",
+    "Couldn't stop records running past the hard stop",
+  ),
+  ("note-label", "Note:"),
+  ("invalid-weeks", "{weeks} isn't a valid number of weeks"),
+  ("trends-of-all", "use all for the trends of every project"),
+  ("all-projects", "All"),
+  ("this-week", "{duration} this week"),
+  ("no-daily-target", "No daily target to keep a streak of"),
+  ("set-daily-target", "set one in {file}, e.g. daily-target = \"6h\""),
+  ("streak", "Streak"),
+  ("day-count-one", "1 day"),
+  ("day-count", "{count} days"),
+  ("streak-summary", "{streak} {current} in a row, longest streak {longest}"),
+  ("target-met-today", "Today's target of {duration} is met"),
+  ("to-go-today", "{duration} to go today to keep it going"),
+  ("read-failed", "Couldn't read {file}"),
+  ("no-jsonl-imports", "This build doesn't support jsonl imports"),
+  ("import-failed", "Couldn't import {file}"),
+  ("nothing-was-imported", "nothing was imported"),
+  ("imported", "Imported"),
+  ("records-imported", "{imported} {count} records from {file}"),
+  ("projects-created", "{count} projects created"),
+  ("skipped-already-tracked", "{count} skipped, already tracked"),
+  ("skipped-overlapping", "{count} skipped, overlapping others"),
+  ("skipped-in-flight", "{count} skipped, still in flight"),
+  ("merged", "Merged"),
+  ("entries-merged", "{merged} {count} new entries from {file}"),
+  ("refusing-to-merge", "Refusing to merge {file}"),
+  ("merge-would-not-replay", "nothing was changed, the merged log wouldn't replay"),
+  ("merge-failed", "Couldn't merge {file}"),
+  ("moved", "Moved"),
+  ("records-moved", "{moved} {count} records from {from} to {to}"),
+  ("move-failed", "Couldn't move the records of '{name}'"),
+  ("invalid-age", "{age} isn't a valid age"),
+  ("age-examples", "try something like 90d, 12w, 6m or 3y"),
+  ("pruned", "Pruned"),
+  ("records-pruned", "{pruned} {count} records started before {date}"),
+  ("prune-failed", "Couldn't prune records"),
+  ("no-budget", "{name} has no budget to forecast"),
+  ("configure-budget", "configure one, e.g. budget = \"120h\" under [projects.{name}]"),
+  ("budget-label", "Budget:"),
+  ("pace-label", "Pace:"),
+  ("runs-out-label", "Runs out:"),
+  ("deadline-label", "Deadline:"),
+  ("budget-spent", "{budget}, {spent} spent"),
+  ("weekly-pace", "{duration} a week, over the past {weeks} weeks"),
+  ("runs-out-on", "{date}, {remaining} left"),
+  ("ran-out", "already, {over} over"),
+  ("not-spent-lately", "not being spent lately"),
+  ("needs-a-week", "needs {duration} a week"),
+  ("deadline-passed", "passed"),
+  ("total-label", "Total:"),
+  ("records-label", "Records:"),
+  ("first-label", "First:"),
+  ("last-label", "Last:"),
+  ("recent-label", "Recent:"),
+  ("running-now", "running now"),
+  ("by-user", "by {user}"),
+  ("on-host", "on {host}"),
+  ("on-branch", "branch {branch}"),
+  ("in-directory", "in {directory}"),
+  ("workday-count-one", "1 workday"),
+  ("workday-count", "{count} workdays"),
+  ("per-workday", "{duration} per workday over {workdays}"),
+  ("no-workday", "no workday"),
+  ("averaging", "Averaging {duration} per day over {days}, {per_workday}"),
+  ("expenses", "Expenses:"),
+  ("no-daily-target-short", "no daily target"),
+  ("holiday", "holiday"),
+  ("day-off", "day off"),
+  ("progress-done", "done"),
+  ("progress-on-track", "on track"),
+  ("progress-behind", "behind"),
+  ("of-target", "{tracked} of {target}"),
+  ("editor-server-failed", "The editor server failed"),
+  ("verified", "Verified"),
+  ("entries-verified", "{verified} {entries} entries: {projects} projects, {records} records"),
+  ("entries-from-device", "{count} entries from {device}"),
+  ("no-problems", "No problems found"),
+  ("problem", "Problem"),
+  ("unrenderable-report", "The report can't be rendered as {format} here"),
+  ("the-report", "the report"),
+  ("the-timesheet", "the timesheet"),
+  ("the-invoice", "the invoice"),
+  ("write-failed", "Couldn't write {what} to {path}"),
+  ("xlsx-needs-file", "An xlsx report needs a file to be written to"),
+  ("add-out-report", "add --out report.xlsx"),
+  ("mail-failed", "Couldn't mail the report to {address}"),
+  ("sendmail-hint", "the report is handed to `sendmail -t`, is it installed and configured?"),
+  ("pdf-needs-file", "A pdf timesheet needs a file to be written to"),
+  ("add-out-timesheet", "add --out timesheet.pdf"),
+  ("week-of", "Week of {date}"),
+  ("overall", "Overall"),
+  ("no-rate", "No rate to bill '{name}' at"),
+  ("set-rate", "set its rate, or the client's, in config.toml"),
+  ("invoice-unnumbered", "Couldn't number the invoice"),
+  ("seal-failed", "Couldn't follow the log's hash chain to seal what gets appended"),
+  ("audit-hint", "timek verify --audit tells which entries are off"),
+  ("entry-not-sealed", "entry #{number}: isn't what was sealed"),
+  ("seals", "{count} seals, the latest {head}"),
+  ("nothing-sealed", "Nothing is sealed, set audit = true in config.toml for appends to be"),
+  ("unsealed-entries", "{count} entries after the latest seal were appended without auditing"),
+  ("seal-missing", "seal {seal} isn't in the log anymore"),
+  ("dry-run", "Dry run:"),
+  ("would-write-nothing", "{dry_run} nothing would be written"),
+  ("would-rewrite", "{dry_run} would rewrite the log as {count} entries"),
+  ("would-append", "{dry_run} would append"),
+  ("reached-target", "Reached the target"),
+  ("target-reached", "{reached} of {duration} for today!"),
+  ("target-reached-streak", "{reached} of {duration} for today, {count} days in a row!"),
+  ("missed-heartbeats", "{note} nothing was heard from this machine since {when}, it may have been off"),
+  ("stop-at-heartbeat", "Stop what was running then? (y/n)"),
+  ("heartbeat-stop-failed", "Couldn't stop records at the last heartbeat"),
+  ("copy-merged", "{note} merged {count} new entries from {file}"),
+  ("set-aside-failed", "Couldn't set {file} aside"),
+  ("remove-merged-copy", "it's been merged, remove it"),
+  ("fix-or-remove-copy", "fix it or remove it, then run `timek merge` on it if need be"),
+  ("reminder", "Reminder:"),
+  ("still-working-on", "{reminder} still working on {name}? {duration} so far"),
+  ("nothing-to-nag", "Nothing to nag about"),
+  ("nag-not-configured", "work-hours and nag-after aren't both configured"),
+  ("set-them-in", "set them in {file}"),
+  ("forgot-timer", "Nothing tracked for {duration}, forgot to start a timer?"),
+  ("database", "Database"),
+  ("log-label", "Log"),
+  ("replay-label", "Replay"),
+  ("projects-label", "Projects"),
+  ("records-column", "Records"),
+  ("compacted-label", "Compacted"),
+  ("log-size", "{size}, {count} entries"),
+  ("compacted-size", "{size}, saving {saving} ({share}%)"),
+  ("worth-compacting", "Worth compacting"),
+  ("log-mostly-stale", "{worth} a good part of the log no longer matters, `timek prune` rewrites it once it has records to drop"),
+  ("no-maintenance", "No maintenance needed"),
+  ("warning-label", "Warning:"),
+  ("skipped-entries", "{warning} skipped {count} entries that don't make sense, see `timek verify`"),
+  ("already-existed", "{count} already existed"),
+  ("left-out", "Left out {name}"),
+  ("settings-added", "{added} the settings in {file}"),
+  ("configure-imported-failed", "Couldn't configure the projects imported"),
+  ("add-settings-yourself", "add their settings to {file} yourself:\n\n{settings}"),
+  ("invalid-amount", "{amount} isn't a valid amount"),
+  ("amount-currency", "amounts are in the currency the project is billed in, e.g. 34.50"),
+  ("expense-failed", "Couldn't record the expense on '{name}'"),
+  ("invalid-cursor", "{cursor} isn't a valid cursor"),
+  ("use-last-cursor", "use the cursor of the last exported line"),
+  ("the-export", "the export"),
+  ("no-jsonl-exports", "This build doesn't support jsonl exports"),
+  ("since-needs-jsonl", "--since only applies to jsonl exports"),
+  ("export-failed", "Failed to export"),
+  ("normalized", "Normalized"),
+  ("records-normalized", "{normalized} {count} records to this machine's timezone"),
+  ("normalize-failed", "Couldn't normalize the records' timezones"),
+  ("tracked-offsets", "Records tracked at {count} offsets: {offsets}"),
+  ("clock-jump", "Clock jump"),
+  ("no-clock-jumps", "No clock jumps found"),
+  ("clock-jumps-found", "{count} records look off on the wall clock, `timek doctor timezones --normalize` expresses them all in this machine's timezone"),
+  ("project-here-unknown", "Couldn't tell the project of the current directory"),
+  ("map-directories", "list it in the directories of a project in config.toml, e.g. directories = [\"~/src/backend\"]"),
+  ("which-one", "Which one?"),
+  ("tracked-column", "Tracked"),
+  ("sessions-column", "Sessions"),
+  ("switches-column", "Switches"),
+  ("median-column", "Median"),
+  ("focus-column", "Focus"),
+  ("unknown-client", "No client named '{name}'"),
+  ("clients-configured", "clients are configured in config.toml, with the projects billed to them"),
+  ("render-failed", "Couldn't render {file}"),
+  ("templates-are-handlebars", "templates are Handlebars ones, e.g. {{#each projects}}{{name}} {{hours seconds}}{{/each}}"),
+];
+
+const DE: &[(&str, &str)] = &[
+  ("created", "Angelegt"),
+  ("deleted", "Gelöscht"),
+  ("archived", "Archiviert"),
+  ("unarchived", "Reaktiviert"),
+  ("failed", "Fehler"),
+  ("started", "Gestartet"),
+  ("stopped", "Gestoppt"),
+  ("cancelled", "Verworfen"),
+  ("added", "Nachgetragen"),
+  ("noted", "Notiert"),
+  ("spent", "Ausgegeben"),
+  ("project-created", "{created}: Projekt '{name}'"),
+  (
+    "project-not-created",
+    "{failed}: Projekt '{name}' konnte nicht angelegt werden",
+  ),
+  ("project-deleted", "{deleted}: Projekt '{name}'"),
+  (
+    "delete-failed",
+    "Projekt '{name}' konnte nicht gelöscht werden",
+  ),
+  ("project-archived", "{archived}: Projekt '{name}'"),
+  ("project-unarchived", "{unarchived}: Projekt '{name}'"),
+  (
+    "archive-failed",
+    "Projekt '{name}' konnte nicht archiviert werden",
+  ),
+  (
+    "unarchive-failed",
+    "Projekt '{name}' konnte nicht reaktiviert werden",
+  ),
+  ("is-archived", "'{name}' ist archiviert"),
+  (
+    "unarchive-first",
+    "zuerst mit `project unarchive {name}` oder --unarchive reaktivieren",
+  ),
+  ("stop-before-archiving", "zuerst mit `stop {name}` stoppen"),
+  ("no-projects", "Keine Projekte"),
+  ("no-projects-hint", "{none}, mit 'add' anlegen"),
+  ("should-create", "Soll '{name}' angelegt werden? [y/N]"),
+  ("not-tracking", "'{name}' wird nicht erfasst"),
+  ("unknown-project", "Kein Projekt namens '{name}'"),
+  ("did-you-mean", "war '{name}' gemeint?"),
+  ("tracking-started", "{started}: Zeiterfassung für '{name}'"),
+  (
+    "tracking-started-at",
+    "{started}: Zeiterfassung für '{name}', {when}",
+  ),
+  ("tracking-stopped", "{stopped}: {name} - {duration} erfasst"),
+  (
+    "start-failed",
+    "Zeiterfassung für '{name}' konnte nicht gestartet werden",
+  ),
+  (
+    "stop-failed",
+    "Zeiterfassung für '{name}' konnte nicht gestoppt werden",
+  ),
+  ("storage-unwritable", "der Speicher ist nicht beschreibbar"),
+  ("nothing-tracked", "Kein erfasstes Projekt"),
+  ("nothing-to-stop", "{none} zu stoppen"),
+  ("nothing-to-cancel", "{none} zu verwerfen"),
+  (
+    "tracking-cancelled",
+    "{cancelled}: {name} - {duration} nicht erfasst",
+  ),
+  (
+    "cancel-failed",
+    "Zeiterfassung für '{name}' konnte nicht verworfen werden",
+  ),
+  ("record-added", "{added}: {duration} für '{name}', {when}"),
+  (
+    "add-failed",
+    "Eintrag für '{name}' konnte nicht nachgetragen werden",
+  ),
+  ("note-added", "{noted}: zu '{name}'"),
+  ("nothing-to-note", "{none} für eine Notiz"),
+  (
+    "note-failed",
+    "Notiz zu '{name}' konnte nicht gespeichert werden",
+  ),
+  ("not-running", "'{name}' läuft nicht"),
+  ("still-running", "'{name}' wird noch erfasst"),
+  (
+    "stop-first",
+    "zuerst mit `stop {name}` oder --stop-first stoppen",
+  ),
+  ("nothing-running", "es wird nichts erfasst"),
+  ("one-running", "{name} läuft, `stop` ohne Namen stoppt es"),
+  ("many-running", "{names} laufen"),
+  ("expense-recorded", "{spent}: {amount} für {name}"),
+  ("working-on", "Arbeit an {name} seit {duration}"),
+  ("idle", "Nichts los!"),
+  (
+    "last-worked-on",
+    "Zuletzt {duration} an {name} gearbeitet, gestoppt {when}",
+  ),
+  ("today-total-one", "Heute: {duration} in 1 Projekt"),
+  ("today-total", "Heute: {duration} in {count} Projekten"),
+  ("just-now", "gerade eben"),
+  ("minutes-ago-one", "vor einer Minute"),
+  ("minutes-ago", "vor {count} Minuten"),
+  ("hours-ago-one", "vor einer Stunde"),
+  ("hours-ago", "vor {count} Stunden"),
+  ("yesterday", "gestern"),
+  ("days-ago", "vor {count} Tagen"),
+  ("weeks-ago", "vor {count} Wochen"),
+  ("months-ago", "vor {count} Monaten"),
+  ("years-ago-one", "vor einem Jahr"),
+  ("years-ago", "vor {count} Jahren"),
+  ("relative-at", "{ago} um {time}"),
+  ("relative-on", "{ago}, am {date} um {time}"),
+  ("nothing-tracked-on", "Am {date} wurde nichts erfasst"),
+  ("nothing-changed", "Nichts geändert"),
+  (
+    "no-favorites",
+    "Noch keine Favoriten, mit `timek fav add <NAME>` hinzufügen",
+  ),
+  ("nothing-stopped", "Noch nichts gestoppt"),
+  (
+    "review-needs-terminal",
+    "Die Durchsicht braucht ein Terminal",
+  ),
+  ("run-interactively", "interaktiv ausführen"),
+  ("reviewing", "Durchsicht"),
+  (
+    "reviewing-records",
+    "{reviewing} von {count} Einträgen am {date}",
+  ),
+  ("now", "jetzt"),
+  ("left-running", "Läuft noch, bleibt wie es ist"),
+  (
+    "review-choices",
+    "(k) behalten, (t) Zeiten, (r) neu zuordnen, (n) Notiz, (d) löschen oder (q) beenden?",
+  ),
+  ("start-prompt", "Beginn"),
+  ("end-prompt", "Ende"),
+  ("project-prompt", "Projekt:"),
+  ("note-prompt", "Notiz:"),
+  ("kept", "Behalten"),
+  (
+    "kept-ends-before-start",
+    "{kept}, es würde enden, bevor es beginnt",
+  ),
+  ("kept-not-a-time", "{kept}, das ist keine Uhrzeit"),
+  ("kept-unknown-project", "{kept}, kein Projekt '{name}'"),
+  ("kept-empty-note", "{kept}, die Notiz ist leer"),
+  ("amended", "Geändert"),
+  ("records-amended", "{amended}: {count} Einträge"),
+  ("amend-failed", "Die Einträge konnten nicht geändert werden"),
+  ("nothing-was-changed", "es wurde nichts geändert"),
+  ("favorite", "Favorit"),
+  ("removed", "Entfernt"),
+  (
+    "favorite-added",
+    "{favorite} '{name}' auf Platz {slot}, starten mit `timek {slot}`",
+  ),
+  (
+    "favorite-failed",
+    "'{name}' konnte nicht zu den Favoriten hinzugefügt werden",
+  ),
+  ("favorites-full", "alle {count} Plätze sind belegt"),
+  (
+    "remove-a-favorite",
+    "zuerst einen mit `timek fav del <NAME>` entfernen",
+  ),
+  ("not-a-favorite", "'{name}' ist kein Favorit"),
+  ("favorite-removed", "{removed}: '{name}' aus den Favoriten"),
+  ("no-favorite-in-slot", "Kein Favorit auf Platz {slot}"),
+  (
+    "add-a-favorite",
+    "einen mit `timek fav add <NAME>` hinzufügen",
+  ),
+  ("recently-stopped", "{number}. {name} gestoppt {when}"),
+  ("nothing-numbered", "Zuletzt nichts mit der Nummer {number}"),
+  ("see-recent", "die Nummern zeigt `timek recent`"),
+  ("hard-stopped", "am harten Stopp beendet"),
+  (
+    "ran-past-hard-stop",
+    "{name} lief noch nach {at}, wurde dann beendet - {duration} erfasst",
+  ),
+  (
+    "hard-stop-failed",
+    "Einträge, die über den harten Stopp hinaus laufen, konnten nicht beendet werden",
+  ),
+  ("note-label", "Hinweis:"),
+  ("invalid-weeks", "{weeks} ist keine gültige Anzahl Wochen"),
+  ("trends-of-all", "all zeigt die Trends aller Projekte"),
+  ("all-projects", "Alle"),
+  ("this-week", "{duration} diese Woche"),
+  ("no-daily-target", "Kein Tagesziel, für das eine Serie zählen könnte"),
+  ("set-daily-target", "in {file} festlegen, z. B. daily-target = \"6h\""),
+  ("streak", "Serie"),
+  ("day-count-one", "1 Tag"),
+  ("day-count", "{count} Tage"),
+  ("streak-summary", "{streak}: {current} in Folge, längste Serie {longest}"),
+  ("target-met-today", "Das heutige Ziel von {duration} ist erreicht"),
+  ("to-go-today", "noch {duration} heute, um die Serie zu halten"),
+  ("read-failed", "{file} konnte nicht gelesen werden"),
+  ("no-jsonl-imports", "Dieser Build unterstützt keine jsonl-Importe"),
+  ("import-failed", "{file} konnte nicht importiert werden"),
+  ("nothing-was-imported", "es wurde nichts importiert"),
+  ("imported", "Importiert"),
+  ("records-imported", "{imported}: {count} Einträge aus {file}"),
+  ("projects-created", "{count} Projekte angelegt"),
+  ("skipped-already-tracked", "{count} übersprungen, bereits erfasst"),
+  ("skipped-overlapping", "{count} übersprungen, überschneiden sich mit anderen"),
+  ("skipped-in-flight", "{count} übersprungen, laufen noch"),
+  ("merged", "Zusammengeführt"),
+  ("entries-merged", "{merged}: {count} neue Einträge aus {file}"),
+  ("refusing-to-merge", "{file} wird nicht zusammengeführt"),
+  ("merge-would-not-replay", "es wurde nichts geändert, das zusammengeführte Log ließe sich nicht abspielen"),
+  ("merge-failed", "{file} konnte nicht zusammengeführt werden"),
+  ("moved", "Verschoben"),
+  ("records-moved", "{moved}: {count} Einträge von {from} nach {to}"),
+  ("move-failed", "Die Einträge von '{name}' konnten nicht verschoben werden"),
+  ("invalid-age", "{age} ist kein gültiges Alter"),
+  ("age-examples", "z. B. 90d, 12w, 6m oder 3y"),
+  ("pruned", "Bereinigt"),
+  ("records-pruned", "{pruned}: {count} vor dem {date} begonnene Einträge"),
+  ("prune-failed", "Einträge konnten nicht bereinigt werden"),
+  ("no-budget", "{name} hat kein Budget für eine Prognose"),
+  ("configure-budget", "eines konfigurieren, z. B. budget = \"120h\" unter [projects.{name}]"),
+  ("budget-label", "Budget:"),
+  ("pace-label", "Tempo:"),
+  ("runs-out-label", "Aufgebraucht:"),
+  ("deadline-label", "Frist:"),
+  ("budget-spent", "{budget}, {spent} verbraucht"),
+  ("weekly-pace", "{duration} pro Woche, über die letzten {weeks} Wochen"),
+  ("runs-out-on", "{date}, {remaining} übrig"),
+  ("ran-out", "bereits, {over} darüber"),
+  ("not-spent-lately", "wird derzeit nicht verbraucht"),
+  ("needs-a-week", "braucht {duration} pro Woche"),
+  ("deadline-passed", "verstrichen"),
+  ("total-label", "Gesamt:"),
+  ("records-label", "Einträge:"),
+  ("first-label", "Erster:"),
+  ("last-label", "Letzter:"),
+  ("recent-label", "Zuletzt:"),
+  ("running-now", "läuft gerade"),
+  ("by-user", "von {user}"),
+  ("on-host", "auf {host}"),
+  ("on-branch", "Branch {branch}"),
+  ("in-directory", "in {directory}"),
+  ("workday-count-one", "1 Arbeitstag"),
+  ("workday-count", "{count} Arbeitstage"),
+  ("per-workday", "{duration} pro Arbeitstag über {workdays}"),
+  ("no-workday", "kein Arbeitstag"),
+  ("averaging", "Durchschnittlich {duration} pro Tag über {days}, {per_workday}"),
+  ("expenses", "Ausgaben:"),
+  ("no-daily-target-short", "kein Tagesziel"),
+  ("holiday", "Feiertag"),
+  ("day-off", "freier Tag"),
+  ("progress-done", "erledigt"),
+  ("progress-on-track", "im Plan"),
+  ("progress-behind", "im Rückstand"),
+  ("of-target", "{tracked} von {target}"),
+  ("editor-server-failed", "Der Editor-Server ist fehlgeschlagen"),
+  ("verified", "Geprüft"),
+  ("entries-verified", "{verified}: {entries} Logeinträge: {projects} Projekte, {records} Einträge"),
+  ("entries-from-device", "{count} Logeinträge von {device}"),
+  ("no-problems", "Keine Probleme gefunden"),
+  ("problem", "Problem"),
+  ("unrenderable-report", "Der Bericht kann hier nicht als {format} ausgegeben werden"),
+  ("the-report", "der Bericht"),
+  ("the-timesheet", "der Stundenzettel"),
+  ("the-invoice", "die Rechnung"),
+  ("write-failed", "{what} konnte nicht nach {path} geschrieben werden"),
+  ("xlsx-needs-file", "Ein xlsx-Bericht muss in eine Datei geschrieben werden"),
+  ("add-out-report", "--out report.xlsx angeben"),
+  ("mail-failed", "Der Bericht konnte nicht an {address} gesendet werden"),
+  ("sendmail-hint", "der Bericht wird an `sendmail -t` übergeben, ist es installiert und eingerichtet?"),
+  ("pdf-needs-file", "Ein pdf-Stundenzettel muss in eine Datei geschrieben werden"),
+  ("add-out-timesheet", "--out timesheet.pdf angeben"),
+  ("week-of", "Woche vom {date}"),
+  ("overall", "Insgesamt"),
+  ("no-rate", "Kein Satz, um '{name}' abzurechnen"),
+  ("set-rate", "seinen Satz, oder den des Kunden, in config.toml festlegen"),
+  ("invoice-unnumbered", "Die Rechnung konnte nicht nummeriert werden"),
+  ("seal-failed", "Der Hash-Kette des Logs konnte nicht gefolgt werden, um Angehängtes zu versiegeln"),
+  ("audit-hint", "timek verify --audit zeigt, welche Einträge abweichen"),
+  ("entry-not-sealed", "Eintrag #{number}: entspricht nicht dem Versiegelten"),
+  ("seals", "{count} Siegel, das neueste {head}"),
+  ("nothing-sealed", "Nichts ist versiegelt, audit = true in config.toml versiegelt Angehängtes"),
+  ("unsealed-entries", "{count} Einträge nach dem neuesten Siegel wurden ohne Prüfung angehängt"),
+  ("seal-missing", "Siegel {seal} ist nicht mehr im Log"),
+  ("dry-run", "Probelauf:"),
+  ("would-write-nothing", "{dry_run} es würde nichts geschrieben"),
+  ("would-rewrite", "{dry_run} würde das Log als {count} Einträge neu schreiben"),
+  ("would-append", "{dry_run} würde anhängen"),
+  ("reached-target", "Ziel erreicht"),
+  ("target-reached", "{reached}: {duration} für heute!"),
+  ("target-reached-streak", "{reached}: {duration} für heute, {count} Tage in Folge!"),
+  ("missed-heartbeats", "{note} von diesem Rechner kam seit {when} nichts, vielleicht war er aus"),
+  ("stop-at-heartbeat", "Beenden, was damals lief? (y/n)"),
+  ("heartbeat-stop-failed", "Einträge konnten nicht beim letzten Lebenszeichen beendet werden"),
+  ("copy-merged", "{note} {count} neue Logeinträge aus {file} zusammengeführt"),
+  ("set-aside-failed", "{file} konnte nicht beiseitegelegt werden"),
+  ("remove-merged-copy", "sie wurde zusammengeführt, bitte entfernen"),
+  ("fix-or-remove-copy", "reparieren oder entfernen, dann bei Bedarf `timek merge` darauf ausführen"),
+  ("reminder", "Erinnerung:"),
+  ("still-working-on", "{reminder} noch an {name}? bisher {duration}"),
+  ("nothing-to-nag", "Nichts zu erinnern"),
+  ("nag-not-configured", "work-hours und nag-after sind nicht beide konfiguriert"),
+  ("set-them-in", "in {file} festlegen"),
+  ("forgot-timer", "Seit {duration} nichts erfasst, vergessen, einen Timer zu starten?"),
+  ("database", "Datenbank"),
+  ("log-label", "Log"),
+  ("replay-label", "Abspielen"),
+  ("projects-label", "Projekte"),
+  ("records-column", "Einträge"),
+  ("compacted-label", "Verdichtet"),
+  ("log-size", "{size}, {count} Logeinträge"),
+  ("compacted-size", "{size}, spart {saving} ({share}%)"),
+  ("worth-compacting", "Verdichten lohnt sich"),
+  ("log-mostly-stale", "{worth} ein guter Teil des Logs ist überholt, `timek prune` schreibt es neu, sobald es Einträge zu verwerfen gibt"),
+  ("no-maintenance", "Keine Wartung nötig"),
+  ("warning-label", "Warnung:"),
+  ("skipped-entries", "{warning} {count} unsinnige Logeinträge übersprungen, siehe `timek verify`"),
+  ("already-existed", "{count} gab es bereits"),
+  ("left-out", "{name} ausgelassen"),
+  ("settings-added", "{added}: die Einstellungen in {file}"),
+  ("configure-imported-failed", "Die importierten Projekte konnten nicht konfiguriert werden"),
+  ("add-settings-yourself", "ihre Einstellungen selbst in {file} eintragen:\n\n{settings}"),
+  ("invalid-amount", "{amount} ist kein gültiger Betrag"),
+  ("amount-currency", "Beträge sind in der Währung, in der das Projekt abgerechnet wird, z. B. 34.50"),
+  ("expense-failed", "Die Ausgabe für '{name}' konnte nicht erfasst werden"),
+  ("invalid-cursor", "{cursor} ist kein gültiger Cursor"),
+  ("use-last-cursor", "den Cursor der zuletzt exportierten Zeile verwenden"),
+  ("the-export", "der Export"),
+  ("no-jsonl-exports", "Dieser Build unterstützt keine jsonl-Exporte"),
+  ("since-needs-jsonl", "--since gilt nur für jsonl-Exporte"),
+  ("export-failed", "Export fehlgeschlagen"),
+  ("normalized", "Vereinheitlicht"),
+  ("records-normalized", "{normalized}: {count} Einträge auf die Zeitzone dieses Rechners"),
+  ("normalize-failed", "Die Zeitzonen der Einträge konnten nicht vereinheitlicht werden"),
+  ("tracked-offsets", "Einträge mit {count} Zeitversätzen erfasst: {offsets}"),
+  ("clock-jump", "Uhrsprung"),
+  ("no-clock-jumps", "Keine Uhrsprünge gefunden"),
+  ("clock-jumps-found", "{count} Einträge wirken auf der Uhr verschoben, `timek doctor timezones --normalize` drückt alle in der Zeitzone dieses Rechners aus"),
+  ("project-here-unknown", "Das Projekt des aktuellen Verzeichnisses ist unklar"),
+  ("map-directories", "in den directories eines Projekts in config.toml aufführen, z. B. directories = [\"~/src/backend\"]"),
+  ("which-one", "Welches?"),
+  ("tracked-column", "Erfasst"),
+  ("sessions-column", "Sitzungen"),
+  ("switches-column", "Wechsel"),
+  ("median-column", "Median"),
+  ("focus-column", "Fokus"),
+  ("unknown-client", "Kein Kunde namens '{name}'"),
+  ("clients-configured", "Kunden werden in config.toml konfiguriert, mit den ihnen abgerechneten Projekten"),
+  ("render-failed", "{file} konnte nicht gerendert werden"),
+  ("templates-are-handlebars", "Vorlagen sind Handlebars-Vorlagen, z. B. {{#each projects}}{{name}} {{hours seconds}}{{/each}}"),
+];
+
+const FR: &[(&str, &str)] = &[
+  ("created", "Créé"),
+  ("deleted", "Supprimé"),
+  ("archived", "Archivé"),
+  ("unarchived", "Désarchivé"),
+  ("failed", "Échec"),
+  ("started", "Démarré"),
+  ("stopped", "Arrêté"),
+  ("cancelled", "Annulé"),
+  ("added", "Ajouté"),
+  ("noted", "Noté"),
+  ("spent", "Dépensé"),
+  ("project-created", "{created} : projet '{name}'"),
+  (
+    "project-not-created",
+    "{failed} de la création du projet '{name}'",
+  ),
+  ("project-deleted", "{deleted} : projet '{name}'"),
+  (
+    "delete-failed",
+    "Impossible de supprimer le projet '{name}'",
+  ),
+  ("project-archived", "{archived} : projet '{name}'"),
+  ("project-unarchived", "{unarchived} : projet '{name}'"),
+  ("archive-failed", "Impossible d'archiver le projet '{name}'"),
+  (
+    "unarchive-failed",
+    "Impossible de désarchiver le projet '{name}'",
+  ),
+  ("is-archived", "'{name}' est archivé"),
+  (
+    "unarchive-first",
+    "désarchivez-le d'abord, avec `project unarchive {name}` ou --unarchive",
+  ),
+  (
+    "stop-before-archiving",
+    "arrêtez-le d'abord, avec `stop {name}`",
+  ),
+  ("no-projects", "Aucun projet"),
+  ("no-projects-hint", "{none}, 'add' en crée un"),
+  ("should-create", "Faut-il créer '{name}' ? [y/N]"),
+  ("not-tracking", "'{name}' n'est pas suivi"),
+  ("unknown-project", "Aucun projet nommé '{name}'"),
+  ("did-you-mean", "vouliez-vous dire '{name}' ?"),
+  (
+    "tracking-started",
+    "{started} : suivi du temps sur '{name}'",
+  ),
+  (
+    "tracking-started-at",
+    "{started} : suivi du temps sur '{name}', {when}",
+  ),
+  (
+    "tracking-stopped",
+    "{stopped} : {name} - {duration} enregistrées",
+  ),
+  (
+    "start-failed",
+    "Impossible de démarrer le suivi de '{name}'",
+  ),
+  ("stop-failed", "Impossible d'arrêter le suivi de '{name}'"),
+  ("storage-unwritable", "le stockage n'a pas pu être écrit"),
+  ("nothing-tracked", "Aucun projet suivi"),
+  ("nothing-to-stop", "{none} à arrêter"),
+  ("nothing-to-cancel", "{none} à annuler"),
+  (
+    "tracking-cancelled",
+    "{cancelled} : {name} - {duration} écartées",
+  ),
+  ("cancel-failed", "Impossible d'annuler le suivi de '{name}'"),
+  ("record-added", "{added} : {duration} à '{name}', {when}"),
+  ("add-failed", "Impossible d'ajouter un suivi à '{name}'"),
+  ("note-added", "{noted} : sur '{name}'"),
+  ("nothing-to-note", "{none} à annoter"),
+  ("note-failed", "Impossible d'annoter '{name}'"),
+  ("not-running", "'{name}' n'est pas en cours"),
+  ("still-running", "'{name}' est encore en cours"),
+  (
+    "stop-first",
+    "arrêtez-le d'abord, avec `stop {name}` ou --stop-first",
+  ),
+  ("nothing-running", "rien n'est suivi"),
+  ("one-running", "{name} l'est, `stop` sans nom l'arrête"),
+  ("many-running", "{names} le sont"),
+  ("expense-recorded", "{spent} : {amount} sur {name}"),
+  ("working-on", "En cours sur {name} depuis {duration}"),
+  ("idle", "Rien en cours !"),
+  (
+    "last-worked-on",
+    "Dernier travail sur {name} pendant {duration}, arrêté {when}",
+  ),
+  ("today-total-one", "Aujourd'hui : {duration} sur 1 projet"),
+  (
+    "today-total",
+    "Aujourd'hui : {duration} sur {count} projets",
+  ),
+  ("just-now", "à l'instant"),
+  ("minutes-ago-one", "il y a une minute"),
+  ("minutes-ago", "il y a {count} minutes"),
+  ("hours-ago-one", "il y a une heure"),
+  ("hours-ago", "il y a {count} heures"),
+  ("yesterday", "hier"),
+  ("days-ago", "il y a {count} jours"),
+  ("weeks-ago", "il y a {count} semaines"),
+  ("months-ago", "il y a {count} mois"),
+  ("years-ago-one", "il y a un an"),
+  ("years-ago", "il y a {count} ans"),
+  ("relative-at", "{ago} à {time}"),
+  ("relative-on", "{ago}, le {date} à {time}"),
+  ("nothing-tracked-on", "Rien de suivi le {date}"),
+  ("nothing-changed", "Rien de changé"),
+  (
+    "no-favorites",
+    "Pas encore de favoris, ajoutez-en avec `timek fav add <NAME>`",
+  ),
+  ("nothing-stopped", "Rien d'arrêté pour l'instant"),
+  ("review-needs-terminal", "La revue a besoin d'un terminal"),
+  ("run-interactively", "lancez-la en interactif"),
+  ("reviewing", "Revue"),
+  (
+    "reviewing-records",
+    "{reviewing} de {count} enregistrements le {date}",
+  ),
+  ("now", "maintenant"),
+  ("left-running", "Toujours en cours, laissé tel quel"),
+  (
+    "review-choices",
+    "(k) garder, (t) horaires, (r) réaffecter, (n) note, (d) supprimer ou (q) quitter ?",
+  ),
+  ("start-prompt", "Début"),
+  ("end-prompt", "Fin"),
+  ("project-prompt", "Projet :"),
+  ("note-prompt", "Note :"),
+  ("kept", "Gardé"),
+  (
+    "kept-ends-before-start",
+    "{kept}, il finirait avant de commencer",
+  ),
+  ("kept-not-a-time", "{kept}, ce n'est pas une heure"),
+  ("kept-unknown-project", "{kept}, pas de projet '{name}'"),
+  ("kept-empty-note", "{kept}, la note est vide"),
+  ("amended", "Modifié"),
+  ("records-amended", "{amended} : {count} enregistrements"),
+  ("amend-failed", "Impossible de modifier les enregistrements"),
+  ("nothing-was-changed", "rien n'a été modifié"),
+  ("favorite", "Favori"),
+  ("removed", "Retiré"),
+  (
+    "favorite-added",
+    "{favorite} '{name}' à l'emplacement {slot}, démarrez-le avec `timek {slot}`",
+  ),
+  (
+    "favorite-failed",
+    "Impossible d'ajouter '{name}' aux favoris",
+  ),
+  ("favorites-full", "les {count} emplacements sont pris"),
+  (
+    "remove-a-favorite",
+    "retirez-en un d'abord, avec `timek fav del <NAME>`",
+  ),
+  ("not-a-favorite", "'{name}' n'est pas un favori"),
+  ("favorite-removed", "{removed} : '{name}' des favoris"),
+  (
+    "no-favorite-in-slot",
+    "Pas de favori à l'emplacement {slot}",
+  ),
+  (
+    "add-a-favorite",
+    "ajoutez-en un avec `timek fav add <NAME>`",
+  ),
+  ("recently-stopped", "{number}. {name} arrêté {when}"),
+  ("nothing-numbered", "Rien de numéroté {number} récemment"),
+  ("see-recent", "les numéros sont dans `timek recent`"),
+  ("hard-stopped", "arrêté à l'arrêt forcé"),
+  (
+    "ran-past-hard-stop",
+    "{name} tournait encore après {at}, arrêté à ce moment - {duration} enregistré",
+  ),
+  (
+    "hard-stop-failed",
+    "Impossible d'arrêter les entrées dépassant l'arrêt forcé",
+  ),
+  ("note-label", "Note :"),
+  ("invalid-weeks", "{weeks} n'est pas un nombre de semaines valide"),
+  ("trends-of-all", "utilisez all pour les tendances de tous les projets"),
+  ("all-projects", "Tous"),
+  ("this-week", "{duration} cette semaine"),
+  ("no-daily-target", "Aucun objectif quotidien pour tenir une série"),
+  ("set-daily-target", "définissez-en un dans {file}, p. ex. daily-target = \"6h\""),
+  ("streak", "Série"),
+  ("day-count-one", "1 jour"),
+  ("day-count", "{count} jours"),
+  ("streak-summary", "{streak} : {current} d'affilée, plus longue série {longest}"),
+  ("target-met-today", "L'objectif du jour de {duration} est atteint"),
+  ("to-go-today", "encore {duration} aujourd'hui pour la poursuivre"),
+  ("read-failed", "Impossible de lire {file}"),
+  ("no-jsonl-imports", "Cette version ne prend pas en charge les imports jsonl"),
+  ("import-failed", "Impossible d'importer {file}"),
+  ("nothing-was-imported", "rien n'a été importé"),
+  ("imported", "Importé"),
+  ("records-imported", "{imported} {count} entrées depuis {file}"),
+  ("projects-created", "{count} projets créés"),
+  ("skipped-already-tracked", "{count} ignorées, déjà suivies"),
+  ("skipped-overlapping", "{count} ignorées, en chevauchent d'autres"),
+  ("skipped-in-flight", "{count} ignorées, encore en cours"),
+  ("merged", "Fusionné"),
+  ("entries-merged", "{merged} {count} nouvelles écritures depuis {file}"),
+  ("refusing-to-merge", "Refus de fusionner {file}"),
+  ("merge-would-not-replay", "rien n'a été modifié, le journal fusionné ne se rejouerait pas"),
+  ("merge-failed", "Impossible de fusionner {file}"),
+  ("moved", "Déplacé"),
+  ("records-moved", "{moved} {count} entrées de {from} vers {to}"),
+  ("move-failed", "Impossible de déplacer les entrées de '{name}'"),
+  ("invalid-age", "{age} n'est pas un âge valide"),
+  ("age-examples", "essayez par exemple 90d, 12w, 6m ou 3y"),
+  ("pruned", "Purgé"),
+  ("records-pruned", "{pruned} {count} entrées commencées avant le {date}"),
+  ("prune-failed", "Impossible de purger les entrées"),
+  ("no-budget", "{name} n'a pas de budget à prévoir"),
+  ("configure-budget", "configurez-en un, p. ex. budget = \"120h\" sous [projects.{name}]"),
+  ("budget-label", "Budget :"),
+  ("pace-label", "Rythme :"),
+  ("runs-out-label", "Épuisé :"),
+  ("deadline-label", "Échéance :"),
+  ("budget-spent", "{budget}, {spent} dépensé"),
+  ("weekly-pace", "{duration} par semaine, sur les {weeks} dernières semaines"),
+  ("runs-out-on", "{date}, {remaining} restant"),
+  ("ran-out", "déjà, {over} de dépassement"),
+  ("not-spent-lately", "pas dépensé ces derniers temps"),
+  ("needs-a-week", "demande {duration} par semaine"),
+  ("deadline-passed", "dépassée"),
+  ("total-label", "Total :"),
+  ("records-label", "Entrées :"),
+  ("first-label", "Première :"),
+  ("last-label", "Dernière :"),
+  ("recent-label", "Récentes :"),
+  ("running-now", "en cours"),
+  ("by-user", "par {user}"),
+  ("on-host", "sur {host}"),
+  ("on-branch", "branche {branch}"),
+  ("in-directory", "dans {directory}"),
+  ("workday-count-one", "1 jour ouvré"),
+  ("workday-count", "{count} jours ouvrés"),
+  ("per-workday", "{duration} par jour ouvré sur {workdays}"),
+  ("no-workday", "aucun jour ouvré"),
+  ("averaging", "En moyenne {duration} par jour sur {days}, {per_workday}"),
+  ("expenses", "Dépenses :"),
+  ("no-daily-target-short", "pas d'objectif quotidien"),
+  ("holiday", "jour férié"),
+  ("day-off", "jour de repos"),
+  ("progress-done", "atteint"),
+  ("progress-on-track", "en bonne voie"),
+  ("progress-behind", "en retard"),
+  ("of-target", "{tracked} sur {target}"),
+  ("editor-server-failed", "Le serveur d'éditeur a échoué"),
+  ("verified", "Vérifié"),
+  ("entries-verified", "{verified} {entries} écritures : {projects} projets, {records} entrées"),
+  ("entries-from-device", "{count} écritures depuis {device}"),
+  ("no-problems", "Aucun problème trouvé"),
+  ("problem", "Problème"),
+  ("unrenderable-report", "Le rapport ne peut pas être rendu en {format} ici"),
+  ("the-report", "le rapport"),
+  ("the-timesheet", "la feuille de temps"),
+  ("the-invoice", "la facture"),
+  ("write-failed", "Impossible d'écrire {what} dans {path}"),
+  ("xlsx-needs-file", "Un rapport xlsx doit être écrit dans un fichier"),
+  ("add-out-report", "ajoutez --out report.xlsx"),
+  ("mail-failed", "Impossible d'envoyer le rapport à {address}"),
+  ("sendmail-hint", "le rapport est confié à `sendmail -t`, est-il installé et configuré ?"),
+  ("pdf-needs-file", "Une feuille de temps pdf doit être écrite dans un fichier"),
+  ("add-out-timesheet", "ajoutez --out timesheet.pdf"),
+  ("week-of", "Semaine du {date}"),
+  ("overall", "Ensemble"),
+  ("no-rate", "Aucun taux pour facturer '{name}'"),
+  ("set-rate", "définissez son taux, ou celui du client, dans config.toml"),
+  ("invoice-unnumbered", "Impossible de numéroter la facture"),
+  ("seal-failed", "Impossible de suivre la chaîne de hachage du journal pour sceller les ajouts"),
+  ("audit-hint", "timek verify --audit indique quelles écritures sont fausses"),
+  ("entry-not-sealed", "écriture n°{number} : n'est pas ce qui a été scellé"),
+  ("seals", "{count} sceaux, le dernier {head}"),
+  ("nothing-sealed", "Rien n'est scellé, audit = true dans config.toml scelle les ajouts"),
+  ("unsealed-entries", "{count} écritures après le dernier sceau ont été ajoutées sans audit"),
+  ("seal-missing", "le sceau {seal} n'est plus dans le journal"),
+  ("dry-run", "Simulation :"),
+  ("would-write-nothing", "{dry_run} rien ne serait écrit"),
+  ("would-rewrite", "{dry_run} réécrirait le journal en {count} écritures"),
+  ("would-append", "{dry_run} ajouterait"),
+  ("reached-target", "Objectif atteint"),
+  ("target-reached", "{reached} : {duration} pour aujourd'hui !"),
+  ("target-reached-streak", "{reached} : {duration} pour aujourd'hui, {count} jours d'affilée !"),
+  ("missed-heartbeats", "{note} aucune nouvelle de cette machine depuis {when}, elle était peut-être éteinte"),
+  ("stop-at-heartbeat", "Arrêter ce qui tournait alors ? (y/n)"),
+  ("heartbeat-stop-failed", "Impossible d'arrêter les entrées au dernier signal de vie"),
+  ("copy-merged", "{note} {count} nouvelles écritures fusionnées depuis {file}"),
+  ("set-aside-failed", "Impossible de mettre {file} de côté"),
+  ("remove-merged-copy", "elle a été fusionnée, supprimez-la"),
+  ("fix-or-remove-copy", "corrigez-la ou supprimez-la, puis lancez `timek merge` dessus si besoin"),
+  ("reminder", "Rappel :"),
+  ("still-working-on", "{reminder} toujours sur {name} ? {duration} jusqu'ici"),
+  ("nothing-to-nag", "Rien à rappeler"),
+  ("nag-not-configured", "work-hours et nag-after ne sont pas configurés tous les deux"),
+  ("set-them-in", "définissez-les dans {file}"),
+  ("forgot-timer", "Rien de suivi depuis {duration}, oublié de lancer un minuteur ?"),
+  ("database", "Base de données"),
+  ("log-label", "Journal"),
+  ("replay-label", "Rejeu"),
+  ("projects-label", "Projets"),
+  ("records-column", "Entrées"),
+  ("compacted-label", "Compacté"),
+  ("log-size", "{size}, {count} écritures"),
+  ("compacted-size", "{size}, économise {saving} ({share}%)"),
+  ("worth-compacting", "Compactage utile"),
+  ("log-mostly-stale", "{worth} une bonne partie du journal n'a plus d'importance, `timek prune` le réécrit dès qu'il a des entrées à retirer"),
+  ("no-maintenance", "Aucune maintenance nécessaire"),
+  ("warning-label", "Attention :"),
+  ("skipped-entries", "{warning} {count} écritures incohérentes ignorées, voir `timek verify`"),
+  ("already-existed", "{count} existaient déjà"),
+  ("left-out", "{name} laissé de côté"),
+  ("settings-added", "{added} les réglages dans {file}"),
+  ("configure-imported-failed", "Impossible de configurer les projets importés"),
+  ("add-settings-yourself", "ajoutez vous-même leurs réglages à {file} :\n\n{settings}"),
+  ("invalid-amount", "{amount} n'est pas un montant valide"),
+  ("amount-currency", "les montants sont dans la devise de facturation du projet, p. ex. 34.50"),
+  ("expense-failed", "Impossible d'enregistrer la dépense sur '{name}'"),
+  ("invalid-cursor", "{cursor} n'est pas un curseur valide"),
+  ("use-last-cursor", "utilisez le curseur de la dernière ligne exportée"),
+  ("the-export", "l'export"),
+  ("no-jsonl-exports", "Cette version ne prend pas en charge les exports jsonl"),
+  ("since-needs-jsonl", "--since ne s'applique qu'aux exports jsonl"),
+  ("export-failed", "L'export a échoué"),
+  ("normalized", "Normalisé"),
+  ("records-normalized", "{normalized} {count} entrées vers le fuseau horaire de cette machine"),
+  ("normalize-failed", "Impossible de normaliser les fuseaux horaires des entrées"),
+  ("tracked-offsets", "Entrées suivies à {count} décalages : {offsets}"),
+  ("clock-jump", "Saut d'horloge"),
+  ("no-clock-jumps", "Aucun saut d'horloge trouvé"),
+  ("clock-jumps-found", "{count} entrées semblent décalées sur l'horloge, `timek doctor timezones --normalize` les exprime toutes dans le fuseau de cette machine"),
+  ("project-here-unknown", "Impossible de déterminer le projet du répertoire courant"),
+  ("map-directories", "ajoutez-le aux directories d'un projet dans config.toml, p. ex. directories = [\"~/src/backend\"]"),
+  ("which-one", "Lequel ?"),
+  ("tracked-column", "Suivi"),
+  ("sessions-column", "Sessions"),
+  ("switches-column", "Bascules"),
+  ("median-column", "Médiane"),
+  ("focus-column", "Focus"),
+  ("unknown-client", "Aucun client nommé '{name}'"),
+  ("clients-configured", "les clients sont configurés dans config.toml, avec les projets qui leur sont facturés"),
+  ("render-failed", "Impossible de rendre {file}"),
+  ("templates-are-handlebars", "les modèles sont au format Handlebars, p. ex. {{#each projects}}{{name}} {{hours seconds}}{{/each}}"),
+];
+
+fn catalog(language: Language) -> &'static [(&'static str, &'static str)] {
+  match language {
+    Language::De => DE,
+    Language::Fr => FR,
+    _ => EN,
+  }
+}
+
+fn lookup(language: Language, key: &str) -> Option<&'static str> {
+  catalog(language)
+    .iter()
+    .chain(EN)
+    .find(|(k, _)| *k == key)
+    .map(|(_, text)| *text)
+}
+
+/// The message for `key`, its placeholders replaced by the matching `args`. See [`t!`]
+pub fn message(key: &str, args: &[(&str, &dyn Display)]) -> String {
+  let text = lookup(locale::current().language(), key).unwrap_or(key);
+  fill(text, args)
+}
+
+fn fill(text: &str, args: &[(&str, &dyn Display)]) -> String {
+  let mut out = String::with_capacity(text.len());
+  let mut rest = text;
+  while let Some(open) = rest.find('{') {
+    out.push_str(&rest[..open]);
+    let placeholder = &rest[open + 1..];
+    match placeholder.find('}').and_then(|close| {
+      Some((
+        close,
+        args.iter().find(|(n, _)| *n == &placeholder[..close])?,
+      ))
+    }) {
+      Some((close, (_, value))) => {
+        out.push_str(&value.to_string());
+        rest = &placeholder[close + 1..];
+      }
+      None => {
+        out.push('{');
+        rest = placeholder;
+      }
+    }
+  }
+  out.push_str(rest);
+  out
+}
+
+/// Looks a message up in the catalog: `t!("working-on", name = project, duration = spent)`
+#[macro_export]
+macro_rules! t {
+  ($key:literal $(, $name:ident = $value:expr)* $(,)?) => {
+    $crate::messages::message($key, &[$((stringify!($name), &$value as &dyn std::fmt::Display)),*])
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::locale::Language;
+  use crate::messages::{catalog, fill, EN};
+
+  fn placeholders(text: &str) -> Vec<&str> {
+    let mut names: Vec<&str> = text
+      .split('{')
+      .skip(1)
+      .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+      .collect();
+    names.sort_unstable();
+    names
+  }
+
+  #[test]
+  fn translations_match_english() {
+    for language in [Language::De, Language::Fr] {
+      for (key, text) in catalog(language) {
+        let english = EN.iter().find(|(k, _)| k == key);
+        let english = english.unwrap_or_else(|| panic!("{:?} has unknown key {}", language, key));
+        assert_eq!(placeholders(text), placeholders(english.1), "{}", key);
+      }
+    }
+  }
+
+  #[test]
+  fn fills_placeholders() {
+    let text = "{stopped} tracking on {name} - {duration} recorded";
+    assert_eq!(
+      fill(
+        text,
+        &[
+          ("name", &"backend"),
+          ("stopped", &"Stopped"),
+          ("duration", &42)
+        ]
+      ),
+      "Stopped tracking on backend - 42 recorded"
+    );
+    assert_eq!(fill("{unknown} {", &[]), "{unknown} {");
+  }
+}
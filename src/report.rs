@@ -0,0 +1,685 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::core::Record;
+use crate::db::ProjectTotals;
+use crate::export::csv_field;
+use crate::locale::Locale;
+use crate::zone::Zone;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, FixedOffset, NaiveDate};
+use console::{measure_text_width, truncate_str, Term};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// What a report covers, relative to today, or as an explicit range of days
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+  Ever,
+  Today,
+  Yesterday,
+  ThisWeek,
+  LastWeek,
+  ThisMonth,
+  LastMonth,
+  /// From the first to the last day, both included
+  Range(NaiveDate, NaiveDate),
+}
+
+impl Period {
+  /// The days covered, as of `today`, weeks starting on Mondays. `None` for [`Period::Ever`].
+  pub fn days(&self, today: NaiveDate) -> Option<RangeInclusive<NaiveDate>> {
+    let monday = today - ChronoDuration::days(today.weekday().num_days_from_monday() as i64);
+    let first_of_month = today.with_day(1).expect("Every month has a first");
+    let days = match *self {
+      Period::Ever => return None,
+      Period::Today => today..=today,
+      Period::Yesterday => today.pred()..=today.pred(),
+      Period::ThisWeek => monday..=today,
+      Period::LastWeek => monday - ChronoDuration::days(7)..=monday.pred(),
+      Period::ThisMonth => first_of_month..=today,
+      Period::LastMonth => {
+        let last = first_of_month.pred();
+        last.with_day(1).expect("Every month has a first")..=last
+      }
+      Period::Range(start, end) => start..=end,
+    };
+    Some(days)
+  }
+
+  /// Like [`Period::days`], but spanning all representable days for [`Period::Ever`]
+  pub fn bounds(&self, today: NaiveDate) -> RangeInclusive<NaiveDate> {
+    self
+      .days(today)
+      .unwrap_or(chrono::naive::MIN_DATE..=chrono::naive::MAX_DATE)
+  }
+
+  /// The instants the days covered start and end at in `zone`, so that a week over a change to or
+  /// from daylight saving time lasts an hour less or more than 168. `None` for [`Period::Ever`].
+  pub fn instants(&self, today: NaiveDate, zone: Zone) -> Option<Range<DateTime<FixedOffset>>> {
+    let days = self.days(today)?;
+    Some(zone.bounds(*days.start()).start..zone.bounds(*days.end()).end)
+  }
+}
+
+impl FromStr for Period {
+  type Err = String;
+
+  /// One of the named periods, or a range of days: `2022-03-01..2022-03-15`
+  fn from_str(raw: &str) -> Result<Self, Self::Err> {
+    let period = match raw {
+      "ever" => Period::Ever,
+      "today" => Period::Today,
+      "yesterday" => Period::Yesterday,
+      "week" => Period::ThisWeek,
+      "lastweek" => Period::LastWeek,
+      "month" => Period::ThisMonth,
+      "lastmonth" => Period::LastMonth,
+      _ => {
+        let day = |raw: &str| NaiveDate::parse_from_str(raw, "%Y-%m-%d").ok();
+        match raw
+          .split_once("..")
+          .map(|(start, end)| (day(start), day(end)))
+        {
+          Some((Some(start), Some(end))) if start <= end => Period::Range(start, end),
+          _ => {
+            return Err(format!(
+              "'{}' isn't a period, expected e.g. week or 2022-03-01..2022-03-15",
+              raw
+            ))
+          }
+        }
+      }
+    };
+    Ok(period)
+  }
+}
+
+impl Display for Period {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Period::Ever => write!(f, "ever"),
+      Period::Today => write!(f, "today"),
+      Period::Yesterday => write!(f, "yesterday"),
+      Period::ThisWeek => write!(f, "week"),
+      Period::LastWeek => write!(f, "lastweek"),
+      Period::ThisMonth => write!(f, "month"),
+      Period::LastMonth => write!(f, "lastmonth"),
+      Period::Range(start, end) => write!(f, "{}..{}", start, end),
+    }
+  }
+}
+
+/// A condition on the records to report on, about what they're labelled with or where they were
+/// started from, e.g. `host=laptop`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Criterion {
+  /// Started on that machine
+  Host(String),
+  /// Started with that git branch checked out
+  Branch(String),
+  /// Started from within that directory
+  Dir(PathBuf),
+  /// Started by that user, in a shared database
+  User(String),
+  /// Tagged with that tag
+  Tag(String),
+  Billable(bool),
+}
+
+impl Criterion {
+  pub fn matches(&self, record: &Record) -> bool {
+    let context = record.context();
+    match self {
+      Criterion::Host(host) => context.and_then(|c| c.host.as_ref()) == Some(host),
+      Criterion::Branch(branch) => context.and_then(|c| c.branch.as_ref()) == Some(branch),
+      Criterion::Dir(dir) => context
+        .and_then(|c| c.cwd.as_ref())
+        .is_some_and(|cwd| Path::new(cwd).starts_with(dir)),
+      Criterion::User(user) => context.and_then(|c| c.user.as_ref()) == Some(user),
+      Criterion::Tag(tag) => record.tags().contains(tag),
+      Criterion::Billable(billable) => record.is_billable() == *billable,
+    }
+  }
+}
+
+impl FromStr for Criterion {
+  type Err = String;
+
+  /// `key=value`, the key being one of host (or device), branch, dir, user, tag or billable
+  fn from_str(raw: &str) -> Result<Self, Self::Err> {
+    let (key, value) = match raw.split_once('=') {
+      Some((key, value)) if !value.is_empty() => (key.trim(), value.trim()),
+      _ => {
+        return Err(format!(
+          "'{}' isn't a condition, expected e.g. host=laptop",
+          raw
+        ))
+      }
+    };
+    match key {
+      "host" | "device" => Ok(Criterion::Host(value.to_string())),
+      "branch" => Ok(Criterion::Branch(value.to_string())),
+      "dir" => Ok(Criterion::Dir(PathBuf::from(value))),
+      "user" => Ok(Criterion::User(value.to_string())),
+      "tag" => Ok(Criterion::Tag(value.to_string())),
+      "billable" => match value {
+        "yes" | "true" => Ok(Criterion::Billable(true)),
+        "no" | "false" => Ok(Criterion::Billable(false)),
+        _ => Err(format!("billable is either yes or no, not '{}'", value)),
+      },
+      _ => Err(format!(
+        "can't filter on '{}', only on host, branch, dir, user, tag or billable",
+        key
+      )),
+    }
+  }
+}
+
+/// The time tracked on a project, over the whole period, or on a given day
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ReportRow {
+  pub project: String,
+  pub day: Option<NaiveDate>,
+  pub seconds: u64,
+}
+
+impl ReportRow {
+  pub fn duration(&self) -> Duration {
+    Duration::from_secs(self.seconds)
+  }
+}
+
+/// A row per project, with its total over `days`, or per project and day with `by_day`
+pub fn rows(
+  projects: &[ProjectTotals],
+  days: &RangeInclusive<NaiveDate>,
+  by_day: bool,
+) -> Vec<ReportRow> {
+  projects
+    .iter()
+    .flat_map(|p| {
+      let tracked = p.days.range(days.clone());
+      match by_day {
+        true => tracked
+          .map(|(day, secs)| ReportRow {
+            project: p.name.clone(),
+            day: Some(*day),
+            seconds: *secs,
+          })
+          .collect(),
+        false => vec![ReportRow {
+          project: p.name.clone(),
+          day: None,
+          seconds: tracked.map(|(_, secs)| secs).sum(),
+        }],
+      }
+    })
+    .collect()
+}
+
+/// How much was tracked on average over a period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Averages {
+  pub days: u32,
+  pub per_day: Duration,
+  pub workdays: u32,
+  /// All that was tracked, days off included, over the workdays alone. `None` without any.
+  pub per_workday: Option<Duration>,
+}
+
+/// What `rows` add up to, averaged over `days`, and over the ones `is_workday`
+pub fn averages(
+  rows: &[ReportRow],
+  days: RangeInclusive<NaiveDate>,
+  is_workday: impl Fn(NaiveDate) -> bool,
+) -> Averages {
+  let total: u64 = rows.iter().map(|row| row.seconds).sum();
+  let (mut count, mut workdays) = (0, 0);
+  let mut day = *days.start();
+  while day <= *days.end() {
+    count += 1;
+    if is_workday(day) {
+      workdays += 1;
+    }
+    day = day.succ();
+  }
+  Averages {
+    days: count,
+    per_day: Duration::from_secs(total.checked_div(count as u64).unwrap_or(0)),
+    workdays,
+    per_workday: total.checked_div(workdays as u64).map(Duration::from_secs),
+  }
+}
+
+/// The rows as the cells of the report's table: project, period or day, and duration
+pub fn cells(rows: &[ReportRow], period: &Period, locale: &Locale) -> Vec<[String; 3]> {
+  rows
+    .iter()
+    .map(|row| {
+      [
+        row.project.clone(),
+        row
+          .day
+          .map(|day| locale.date(day))
+          .unwrap_or_else(|| period.to_string()),
+        locale.duration(row.duration()),
+      ]
+    })
+    .collect()
+}
+
+/// What the report covers, spelled out
+pub fn title(period: &Period, today: NaiveDate, locale: &Locale) -> String {
+  match period.days(today) {
+    None => "Time report".to_string(),
+    Some(days) if days.start() == days.end() => format!(
+      "Time report for {}",
+      locale.format(*days.start(), "%a %b %e %Y")
+    ),
+    Some(days) => format!(
+      "Time report for {} to {}",
+      locale.format(*days.start(), "%a %b %e"),
+      locale.format(*days.end(), "%a %b %e %Y"),
+    ),
+  }
+}
+
+/// A report, as handed to a [`ReportRenderer`]
+pub struct Report<'a> {
+  pub title: &'a str,
+  pub period: &'a Period,
+  /// The days the period covers, as of when reported, `None` for [`Period::Ever`]
+  pub days: Option<RangeInclusive<NaiveDate>>,
+  pub rows: &'a [ReportRow],
+  pub locale: &'a Locale,
+}
+
+/// Renders reports in a given format, e.g. as picked by `report --format`
+pub trait ReportRenderer {
+  fn render(&self, report: &Report) -> String;
+}
+
+/// The renderer for `format`: table, csv, json, markdown or html. json needs the serde feature.
+pub fn renderer(format: &str) -> Option<Box<dyn ReportRenderer>> {
+  match format {
+    "table" => Some(Box::new(Table::default())),
+    "csv" => Some(Box::new(Csv)),
+    #[cfg(feature = "serde")]
+    "json" => Some(Box::new(Json)),
+    "markdown" => Some(Box::new(Markdown)),
+    "html" => Some(Box::new(Html)),
+    _ => None,
+  }
+}
+
+/// A table drawn with box characters, for terminals. Given a `max_width`, project names, then
+/// periods, get cut short with an ellipsis for it to fit, durations never are.
+#[derive(Debug, Default)]
+pub struct Table {
+  pub max_width: Option<usize>,
+}
+
+impl Table {
+  /// How narrow project and period columns get, ellipsis included, however little room there is
+  const NARROWEST: usize = 4;
+  /// What the borders and padding take up
+  const FRAME: usize = 10;
+
+  /// A table no wider than the terminal, when printing to one
+  pub fn for_terminal() -> Self {
+    Table {
+      max_width: Term::stdout()
+        .size_checked()
+        .map(|(_, columns)| columns as usize),
+    }
+  }
+}
+
+impl ReportRenderer for Table {
+  fn render(&self, report: &Report) -> String {
+    let lines = cells(report.rows, report.period, report.locale);
+    let (h1, h2, h3) = ("Project", "Period", "Duration");
+    let width = measure_text_width;
+    let (mut w1, mut w2, w3) = lines
+      .iter()
+      .map(|[n, p, d]| (width(n), width(p), width(d)))
+      .fold((h1.len(), h2.len(), h3.len()), |(m1, m2, m3), (n, p, d)| {
+        (m1.max(n), m2.max(p), m3.max(d))
+      });
+    if let Some(max_width) = self.max_width {
+      let mut excess = (w1 + w2 + w3 + Self::FRAME).saturating_sub(max_width);
+      for column in [&mut w1, &mut w2] {
+        let cut = excess.min(column.saturating_sub(Self::NARROWEST));
+        *column -= cut;
+        excess -= cut;
+      }
+    }
+    let fit = |cell: &str, fits: usize| match width(cell) <= fits {
+      true => cell.to_string(),
+      false => truncate_str(cell, fits, "…").to_string(),
+    };
+
+    let mut out = format!("┏━{0:━>w1$}━┯━{0:━>w2$}━┯━{0:━^w3$}━┓\n", "━");
+    out.push_str(&format!(
+      "┃ {: ^w1$} │ {: ^w2$} │ {h3: ^w3$} ┃\n",
+      fit(h1, w1),
+      fit(h2, w2)
+    ));
+    out.push_str(&format!("┠─{0:─>w1$}─┼─{0:─>w2$}─┼─{0:─^w3$}─┨\n", "─"));
+    for [project, period, duration] in &lines {
+      out.push_str(&format!(
+        "┃ {: >w1$} │ {: ^w2$} │ {duration: <w3$} ┃\n",
+        fit(project, w1),
+        fit(period, w2)
+      ));
+    }
+    out.push_str(&format!("┗━{0:━>w1$}━┷━{0:━>w2$}━┷━{0:━^w3$}━┛\n", "━"));
+    out
+  }
+}
+
+/// `project,period,seconds` lines, the period being the day when reported by day
+pub struct Csv;
+
+impl ReportRenderer for Csv {
+  fn render(&self, report: &Report) -> String {
+    let mut out = "project,period,seconds\n".to_string();
+    for row in report.rows {
+      let period = match row.day {
+        Some(day) => day.to_string(),
+        None => report.period.to_string(),
+      };
+      out.push_str(&format!(
+        "{},{},{}\n",
+        csv_field(&row.project),
+        period,
+        row.seconds
+      ));
+    }
+    out
+  }
+}
+
+/// The title, period, with its first and last day, and rows as a JSON object
+#[cfg(feature = "serde")]
+pub struct Json;
+
+#[cfg(feature = "serde")]
+impl ReportRenderer for Json {
+  fn render(&self, report: &Report) -> String {
+    let json = serde_json::json!({
+      "title": report.title,
+      "period": report.period.to_string(),
+      "start": report.days.as_ref().map(|days| days.start().to_string()),
+      "end": report.days.as_ref().map(|days| days.end().to_string()),
+      "rows": report.rows,
+    });
+    format!("{}\n", json)
+  }
+}
+
+/// A markdown table, under the title, e.g. to be mailed
+pub struct Markdown;
+
+impl ReportRenderer for Markdown {
+  fn render(&self, report: &Report) -> String {
+    let escape = |cell: &str| cell.replace('|', "\\|");
+    let mut out = format!(
+      "# {}\n\n| Project | Period | Duration |\n|---|---|---|\n",
+      report.title
+    );
+    for [project, period, duration] in cells(report.rows, report.period, report.locale) {
+      out.push_str(&format!(
+        "| {} | {} | {} |\n",
+        escape(&project),
+        escape(&period),
+        duration,
+      ));
+    }
+    out
+  }
+}
+
+/// A standalone HTML page
+pub struct Html;
+
+impl ReportRenderer for Html {
+  fn render(&self, report: &Report) -> String {
+    let escape = |cell: &str| {
+      cell
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+    };
+    let mut out = format!(
+      "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{0}</title></head>\n<body>\n\
+       <h1>{0}</h1>\n<table>\n<tr><th>Project</th><th>Period</th><th>Duration</th></tr>\n",
+      escape(report.title),
+    );
+    for [project, period, duration] in cells(report.rows, report.period, report.locale) {
+      out.push_str(&format!(
+        "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+        escape(&project),
+        escape(&period),
+        duration,
+      ));
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::core::{Context, Labels, Record};
+  use crate::db::ProjectTotals;
+  use crate::locale::Locale;
+  use crate::report::{
+    averages, cells, renderer, rows, Criterion, Markdown, Period, Report, ReportRenderer,
+    ReportRow, Table,
+  };
+  use chrono::{DateTime, Datelike, NaiveDate};
+  use std::time::Duration;
+
+  fn day(d: u32) -> NaiveDate {
+    NaiveDate::from_ymd(2022, 3, d)
+  }
+
+  #[test]
+  fn rows_per_period_or_day() {
+    let mut backend = ProjectTotals::new("back|end".to_string());
+    backend.add(day(1), 3600);
+    backend.add(day(2), 1800);
+    backend.add(day(3), 60);
+    let period = Period::Range(day(1), day(2));
+    let days = period.bounds(day(9));
+    let locale = Locale::default();
+
+    let totals = rows(&[backend.clone()], &days, false);
+    assert_eq!(
+      totals,
+      vec![ReportRow {
+        project: "back|end".to_string(),
+        day: None,
+        seconds: 5400,
+      }]
+    );
+    assert_eq!(
+      cells(&totals, &period, &locale)[0],
+      ["back|end", "2022-03-01..2022-03-02", "1 hour 30 minutes"]
+    );
+    let by_day = rows(&[backend], &days, true);
+    assert_eq!(by_day.len(), 2);
+    assert_eq!(by_day[1].day, Some(day(2)));
+    let report = Report {
+      title: "Report",
+      period: &period,
+      days: Some(days.clone()),
+      rows: &by_day,
+      locale: &locale,
+    };
+    assert!(Markdown
+      .render(&report)
+      .contains("| back\\|end | 2022-03-01 | 1 hour |"));
+    let csv = renderer("csv").unwrap().render(&report);
+    assert_eq!(
+      csv,
+      "project,period,seconds\nback|end,2022-03-01,3600\nback|end,2022-03-02,1800\n"
+    );
+    let table = renderer("table").unwrap().render(&report);
+    assert!(table.contains("┃ back|end │ 2022-03-01 │ 1 hour     ┃"));
+    #[cfg(feature = "serde")]
+    {
+      let json: serde_json::Value =
+        serde_json::from_str(&renderer("json").unwrap().render(&report)).unwrap();
+      assert_eq!(json["start"], "2022-03-01");
+      assert_eq!(json["end"], "2022-03-02");
+      assert_eq!(json["rows"][1]["day"], "2022-03-02");
+      assert_eq!(json["rows"][1]["seconds"], 1800);
+    }
+    assert!(renderer("pdf").is_none());
+  }
+
+  #[test]
+  fn tables_fit_the_width_given() {
+    let row = |project: &str, seconds| ReportRow {
+      project: project.to_string(),
+      day: None,
+      seconds,
+    };
+    let rows = [row("Infrastructure migration", 3600), row("Ops", 60)];
+    let (period, locale) = (Period::LastMonth, Locale::default());
+    let report = Report {
+      title: "Report",
+      period: &period,
+      days: None,
+      rows: &rows,
+      locale: &locale,
+    };
+    let width = |table: &str| {
+      table
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap()
+    };
+    let unbounded = Table::default().render(&report);
+    assert_eq!(width(&unbounded), 51);
+    let narrow = Table {
+      max_width: Some(40),
+    }
+    .render(&report);
+    assert_eq!(width(&narrow), 40);
+    assert!(narrow.contains("┃ Infrastructu… │ lastmonth │ 1 hour   ┃"));
+    // Durations are never cut, however narrow
+    let narrowest = Table { max_width: Some(1) }.render(&report);
+    assert!(narrowest.contains("┃ Inf… │ las… │ 1 hour   ┃"));
+  }
+
+  #[test]
+  fn averages_over_days_and_workdays() {
+    let row = |seconds| ReportRow {
+      project: "Foo".to_string(),
+      day: None,
+      seconds,
+    };
+    let rows = [row(20 * 3600), row(4 * 3600)];
+    let weekday = |day: NaiveDate| day.weekday().number_from_monday() <= 5;
+    // Tuesday 1st through Monday 7th
+    let week = averages(&rows, day(1)..=day(7), weekday);
+    assert_eq!(week.days, 7);
+    assert_eq!(week.per_day, Duration::from_secs(24 * 3600 / 7));
+    assert_eq!(week.workdays, 5);
+    assert_eq!(week.per_workday, Some(Duration::from_secs(24 * 3600 / 5)));
+
+    let weekend = averages(&rows, day(5)..=day(6), weekday);
+    assert_eq!(weekend.per_day, Duration::from_secs(12 * 3600));
+    assert_eq!(weekend.per_workday, None);
+  }
+
+  #[test]
+  fn periods_as_of_today() {
+    let wednesday = day(9);
+    let days = |period: &str| period.parse::<Period>().unwrap().days(wednesday);
+    assert_eq!(days("ever"), None);
+    assert_eq!(days("today"), Some(day(9)..=day(9)));
+    assert_eq!(days("yesterday"), Some(day(8)..=day(8)));
+    assert_eq!(days("week"), Some(day(7)..=day(9)));
+    assert_eq!(
+      days("lastweek"),
+      Some(NaiveDate::from_ymd(2022, 2, 28)..=day(6))
+    );
+    assert_eq!(days("month"), Some(day(1)..=day(9)));
+    assert_eq!(
+      days("lastmonth"),
+      Some(NaiveDate::from_ymd(2022, 2, 1)..=NaiveDate::from_ymd(2022, 2, 28))
+    );
+    assert_eq!(days("2022-03-02..2022-03-04"), Some(day(2)..=day(4)));
+    assert!("2022-03-04..2022-03-02".parse::<Period>().is_err());
+    assert!("fortnight".parse::<Period>().is_err());
+
+    let january = NaiveDate::from_ymd(2023, 1, 15);
+    assert_eq!(
+      Period::LastMonth.days(january),
+      Some(NaiveDate::from_ymd(2022, 12, 1)..=NaiveDate::from_ymd(2022, 12, 31))
+    );
+
+    let india = "+05:30".parse().unwrap();
+    let week = Period::LastWeek.instants(wednesday, india).unwrap();
+    assert_eq!(
+      week.start,
+      DateTime::parse_from_rfc3339("2022-02-28T00:00:00+05:30").unwrap()
+    );
+    assert_eq!((week.end - week.start).num_hours(), 7 * 24);
+    assert_eq!(Period::Ever.instants(wednesday, india), None);
+  }
+
+  #[test]
+  fn criteria_match_records_metadata() {
+    let start = DateTime::parse_from_rfc3339("2022-03-14T09:00:00-04:00").unwrap();
+    let mut record = Record::started_on(start);
+    record.label(&Labels {
+      billable: Some(false),
+      tags: Some(vec!["meeting".to_string()]),
+    });
+    record.set_context(Context {
+      host: Some("laptop".to_string()),
+      branch: Some("main".to_string()),
+      cwd: Some("/home/me/src/backend".to_string()),
+      user: Some("alice".to_string()),
+    });
+    let matches = |raw: &str| raw.parse::<Criterion>().unwrap().matches(&record);
+    assert!(matches("host=laptop"));
+    assert!(matches("device=laptop"));
+    assert!(!matches("host=desktop"));
+    assert!(matches("branch=main"));
+    assert!(matches("dir=/home/me/src"));
+    assert!(!matches("dir=/home/me/src/back"));
+    assert!(matches("user=alice"));
+    assert!(!matches("user=bob"));
+    assert!(matches("tag=meeting"));
+    assert!(matches("billable=no"));
+    assert!(!Criterion::Host("laptop".to_string()).matches(&Record::started_on(start)));
+    assert!("source=import".parse::<Criterion>().is_err());
+    assert!("host=".parse::<Criterion>().is_err());
+  }
+}
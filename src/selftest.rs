@@ -0,0 +1,155 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::core::Labels;
+use crate::db::{verify, Database, Load};
+use chrono::{DateTime, FixedOffset, Local, TimeZone};
+use std::path::Path;
+
+const PROJECT: &str = "Selftest";
+
+/// A step of the self test, and how it went
+pub struct Step {
+  pub name: &'static str,
+  pub outcome: Result<(), String>,
+}
+
+/// Takes a database at `location`, an empty directory, through its whole life: from creating it,
+/// tracking and cancelling records, to compacting its log. Stops at the first step that fails,
+/// returning the ones taken.
+pub fn run(location: &Path) -> Vec<Step> {
+  let mut steps = Vec::new();
+  let now = Local.timestamp(Local::now().timestamp(), 0);
+  let ago = |minutes| DateTime::<FixedOffset>::from(now - chrono::Duration::minutes(minutes));
+  let open = || Database::open(location, Load::Full).map_err(|kind| kind.to_string());
+
+  let created = open();
+  let mut database = match take(&mut steps, "create a database", created) {
+    Some(database) => database,
+    None => return steps,
+  };
+  let added = database
+    .add_project(PROJECT.to_string())
+    .map(|_| ())
+    .map_err(|err| err.to_string());
+  if take(&mut steps, "add a project", added).is_none() {
+    return steps;
+  }
+  let started = database
+    .start_at(PROJECT.to_string(), false, Labels::default(), ago(90))
+    .map(|_| ())
+    .map_err(|err| err.to_string());
+  if take(&mut steps, "start a record", started).is_none() {
+    return steps;
+  }
+  let stopped = database
+    .stop_as_of(None, ago(30), None)
+    .map(|_| ())
+    .map_err(|err| err.to_string());
+  if take(&mut steps, "stop it", stopped).is_none() {
+    return steps;
+  }
+  let cancelled = database
+    .start_on(PROJECT.to_string(), false, Labels::default())
+    .map(|_| ())
+    .and_then(|_| database.cancel(None).map(|_| ()))
+    .map_err(|err| err.to_string());
+  if take(&mut steps, "start and cancel another", cancelled).is_none() {
+    return steps;
+  }
+  drop(database);
+
+  let replayed = open().and_then(|database| {
+    let records: Vec<_> = database
+      .project(PROJECT)
+      .map(|p| p.records().cloned().collect())
+      .unwrap_or_default();
+    match (records.as_slice(), database.skipped()) {
+      (_, skipped) if skipped > 0 => Err(format!("{} entries didn't make sense", skipped)),
+      ([record], _) if record.duration().as_secs() == 3600 && !record.is_on_going() => Ok(()),
+      (records, _) => Err(format!("{} records read back instead of 1", records.len())),
+    }
+  });
+  if take(&mut steps, "replay the log", replayed).is_none() {
+    return steps;
+  }
+  let reported = match Database::daily_totals(location, None) {
+    Ok(totals) => match totals.iter().flat_map(|p| p.days.values()).sum::<u64>() {
+      3600 => Ok(()),
+      secs => Err(format!("{} seconds reported instead of 3600", secs)),
+    },
+    Err(kind) => Err(kind.to_string()),
+  };
+  if take(&mut steps, "report the totals", reported).is_none() {
+    return steps;
+  }
+  let compacted = open()
+    .and_then(|mut database| {
+      database
+        .prune(now.into(), true)
+        .map_err(|err| err.to_string())
+    })
+    .and_then(|_| open())
+    .and_then(|database| {
+      let tracked: u64 = database
+        .entries()
+        .map(|(_, record)| record.duration().as_secs())
+        .sum();
+      match tracked {
+        3600 => Ok(()),
+        secs => Err(format!("{} seconds left instead of 3600", secs)),
+      }
+    });
+  if take(&mut steps, "compact the log", compacted).is_none() {
+    return steps;
+  }
+  let verified = match verify(location) {
+    Ok(verification) if verification.problems.is_empty() => Ok(()),
+    Ok(verification) => Err(verification.problems.join(", ")),
+    Err(kind) => Err(kind.to_string()),
+  };
+  take(&mut steps, "verify the log", verified);
+  steps
+}
+
+/// Notes how the step `name` went, returning what it gave when it went well
+fn take<T>(steps: &mut Vec<Step>, name: &'static str, outcome: Result<T, String>) -> Option<T> {
+  let (outcome, value) = match outcome {
+    Ok(value) => (Ok(()), Some(value)),
+    Err(err) => (Err(err), None),
+  };
+  steps.push(Step { name, outcome });
+  value
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::selftest::run;
+  use std::env;
+  use std::fs::{create_dir, remove_dir_all};
+
+  #[test]
+  fn passes_on_this_platform() {
+    let location = env::temp_dir().join("timeknightTest_selftest_passes_on_this_platform");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    let steps = run(location.as_path());
+    for step in &steps {
+      assert_eq!(step.outcome, Ok(()), "{}", step.name);
+    }
+    assert_eq!(steps.len(), 9);
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+}
@@ -0,0 +1,361 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::db::ProjectTotals;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::collections::BTreeMap;
+
+/// Runs of consecutive days the daily target was met on
+#[derive(Debug, PartialEq, Eq)]
+pub struct Streak {
+  /// Days in a row the target was met on, up until today, which only counts once met
+  pub current: u32,
+  pub longest: u32,
+  pub met_today: bool,
+}
+
+/// The streak of days in `daily` totals, in seconds, reaching `target` up until `today`. Only
+/// `workdays` count: a day off neither breaks nor extends a streak.
+pub fn streak(
+  daily: &BTreeMap<NaiveDate, u64>,
+  target: u64,
+  workdays: &[Weekday],
+  today: NaiveDate,
+) -> Streak {
+  let mut streak = Streak {
+    current: 0,
+    longest: 0,
+    met_today: daily.get(&today).is_some_and(|secs| *secs >= target),
+  };
+  let mut day = match daily.keys().next() {
+    Some(first) => *first,
+    None => return streak,
+  };
+  while day <= today {
+    let met = daily.get(&day).is_some_and(|secs| *secs >= target);
+    if met {
+      streak.current += 1;
+      streak.longest = streak.longest.max(streak.current);
+    } else if workdays.contains(&day.weekday()) && day < today {
+      streak.current = 0;
+    }
+    day += Duration::days(1);
+  }
+  streak
+}
+
+/// The totals of all `projects` together, per day
+pub fn daily(projects: &[ProjectTotals]) -> BTreeMap<NaiveDate, u64> {
+  let mut daily = BTreeMap::new();
+  for (day, secs) in projects.iter().flat_map(|p| p.days.iter()) {
+    *daily.entry(*day).or_default() += secs;
+  }
+  daily
+}
+
+/// How fragmented the time tracked was
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Focus {
+  /// Seconds tracked
+  pub tracked: u64,
+  pub sessions: usize,
+  /// How many times a session followed one on another project
+  pub switches: usize,
+  /// Median session length, in seconds
+  pub median: u64,
+  /// Seconds tracked in sessions of at least `DEEP_WORK`
+  pub deep: u64,
+}
+
+/// The least a session lasts to count as deep work, in seconds
+pub const DEEP_WORK: u64 = 25 * 60;
+
+impl Focus {
+  /// Share of the time tracked spent in deep work, as a percentage
+  pub fn score(&self) -> u64 {
+    match self.tracked {
+      0 => 0,
+      tracked => self.deep * 100 / tracked,
+    }
+  }
+}
+
+/// The focus of `sessions`, the project and length of each, in the order they started
+pub fn focus<'a>(sessions: impl Iterator<Item = (&'a str, u64)>) -> Focus {
+  let mut focus = Focus::default();
+  let mut lengths = Vec::new();
+  let mut previous: Option<String> = None;
+  for (project, secs) in sessions {
+    let project = project.to_lowercase();
+    if previous
+      .as_ref()
+      .is_some_and(|previous| previous != &project)
+    {
+      focus.switches += 1;
+    }
+    previous = Some(project);
+    focus.tracked += secs;
+    if secs >= DEEP_WORK {
+      focus.deep += secs;
+    }
+    lengths.push(secs);
+  }
+  focus.sessions = lengths.len();
+  lengths.sort_unstable();
+  focus.median = match lengths.len() {
+    0 => 0,
+    n if n % 2 == 0 => (lengths[n / 2 - 1] + lengths[n / 2]) / 2,
+    n => lengths[n / 2],
+  };
+  focus
+}
+
+/// The totals of the last `weeks` weeks, the current one last, from `daily` totals
+pub fn weekly(daily: &BTreeMap<NaiveDate, u64>, weeks: u32, today: NaiveDate) -> Vec<u64> {
+  let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+  (0..weeks as i64)
+    .rev()
+    .map(|ago| {
+      let start = monday - Duration::weeks(ago);
+      daily
+        .range(start..start + Duration::weeks(1))
+        .map(|(_, secs)| secs)
+        .sum()
+    })
+    .collect()
+}
+
+/// `values` drawn with block characters, relative to the greatest. Nothing at all gets the lowest
+/// block, anything else at least the next one up.
+pub fn sparkline(values: &[u64]) -> String {
+  const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+  let max = values.iter().copied().max().unwrap_or(0);
+  values
+    .iter()
+    .map(|value| match value {
+      0 => BLOCKS[0],
+      value => BLOCKS[1 + ((value - 1) * 7 / max) as usize],
+    })
+    .collect()
+}
+
+/// Where the time tracked on a day stands against its target
+#[derive(Debug, PartialEq, Eq)]
+pub enum Progress {
+  Done,
+  OnTrack,
+  Behind,
+  Upcoming,
+}
+
+/// The progress of `tracked` seconds towards `target`, `elapsed` being the share of the day's
+/// work hours already past: 1 for days gone, 0 for days to come.
+pub fn progress(tracked: u64, target: u64, elapsed: f64) -> Progress {
+  if tracked >= target {
+    Progress::Done
+  } else if elapsed <= 0.0 {
+    Progress::Upcoming
+  } else if tracked as f64 >= target as f64 * elapsed {
+    Progress::OnTrack
+  } else {
+    Progress::Behind
+  }
+}
+
+/// How many weeks back the pace a budget is spent at is measured over
+pub const BURN_WEEKS: i64 = 4;
+
+/// Where a project's budget is headed, at the pace it was spent at over the past [`BURN_WEEKS`]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Forecast {
+  /// Seconds tracked so far
+  pub spent: u64,
+  /// Seconds left of the budget, none once over it
+  pub remaining: u64,
+  /// Seconds tracked per week, lately
+  pub weekly_burn: u64,
+  /// The day the budget runs out at that pace, `None` if it did already or isn't being spent
+  pub runs_out: Option<NaiveDate>,
+  /// Seconds a week to track for what's left to be spent by the deadline, `None` without one
+  /// ahead
+  pub weekly_pace: Option<u64>,
+}
+
+/// The forecast of a `budget` in seconds, spent as per the `daily` totals up until `today`
+pub fn forecast(
+  daily: &BTreeMap<NaiveDate, u64>,
+  budget: u64,
+  today: NaiveDate,
+  deadline: Option<NaiveDate>,
+) -> Forecast {
+  let spent: u64 = daily.range(..=today).map(|(_, secs)| secs).sum();
+  let remaining = budget.saturating_sub(spent);
+  // Projects younger than the window are measured over the days they've been around
+  let since = match daily.keys().next() {
+    Some(first) => (*first).max(today - Duration::weeks(BURN_WEEKS) + Duration::days(1)),
+    None => today,
+  };
+  let days = ((today - since).num_days() + 1).max(1) as u64;
+  let burnt: u64 = daily.range(since..=today).map(|(_, secs)| secs).sum();
+  let runs_out = match (remaining, burnt) {
+    (0, _) | (_, 0) => None,
+    (remaining, burnt) => {
+      let days = (remaining * days).div_ceil(burnt);
+      Some(today + Duration::days(days as i64))
+    }
+  };
+  let weekly_pace = deadline
+    .map(|deadline| (deadline - today).num_days())
+    .filter(|days| *days > 0)
+    .map(|days| remaining * 7 / days as u64);
+  Forecast {
+    spent,
+    remaining,
+    weekly_burn: burnt * 7 / days,
+    runs_out,
+    weekly_pace,
+  }
+}
+
+/// A bar `width` characters wide, filled as much as `tracked` is of `target`
+pub fn bar(tracked: u64, target: u64, width: usize) -> String {
+  let filled = match target {
+    0 => width,
+    target => (tracked.min(target) * width as u64 / target) as usize,
+  };
+  format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::stats::{
+    bar, focus, forecast, progress, sparkline, streak, weekly, Focus, Forecast, Progress, Streak,
+  };
+  use chrono::{NaiveDate, Weekday};
+  use std::collections::BTreeMap;
+
+  const WEEKDAYS: [Weekday; 5] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+  ];
+
+  #[test]
+  fn weekends_dont_break_streaks() {
+    // Thu 2022-03-24 through Tue 2022-03-29, but the weekend
+    let daily: BTreeMap<NaiveDate, u64> = [(22, 3600), (24, 7200), (25, 7200), (28, 9000)]
+      .into_iter()
+      .map(|(day, secs)| (NaiveDate::from_ymd(2022, 3, day), secs))
+      .collect();
+    let today = NaiveDate::from_ymd(2022, 3, 29);
+    assert_eq!(
+      streak(&daily, 7200, &WEEKDAYS, today),
+      Streak {
+        current: 3,
+        longest: 3,
+        met_today: false,
+      }
+    );
+    let tomorrow = NaiveDate::from_ymd(2022, 3, 30);
+    assert_eq!(streak(&daily, 7200, &WEEKDAYS, tomorrow).current, 0);
+    assert_eq!(streak(&daily, 7201, &WEEKDAYS, today).longest, 1);
+  }
+
+  #[test]
+  fn switching_breaks_focus() {
+    let sessions = [
+      ("backend", 3600),
+      ("Backend", 600),
+      ("email", 300),
+      ("backend", 1800),
+    ];
+    let stats = focus(sessions.into_iter());
+    assert_eq!(
+      stats,
+      Focus {
+        tracked: 6300,
+        sessions: 4,
+        switches: 2,
+        median: 1200,
+        deep: 5400,
+      }
+    );
+    assert_eq!(stats.score(), 85);
+    assert_eq!(focus(std::iter::empty()).score(), 0);
+  }
+
+  #[test]
+  fn sparklines_of_weekly_totals() {
+    let daily: BTreeMap<NaiveDate, u64> = [(7, 3600), (13, 3600), (14, 100), (28, 7200)]
+      .into_iter()
+      .map(|(day, secs)| (NaiveDate::from_ymd(2022, 3, day), secs))
+      .collect();
+    // Tuesday of the week of Monday, the 28th
+    let weeks = weekly(&daily, 5, NaiveDate::from_ymd(2022, 3, 29));
+    assert_eq!(weeks, vec![0, 7200, 100, 0, 7200]);
+    assert_eq!(sparkline(&weeks), "▁█▂▁█");
+    assert_eq!(sparkline(&[0, 0]), "▁▁");
+    assert_eq!(sparkline(&[]), "");
+  }
+
+  #[test]
+  fn progress_towards_the_target() {
+    assert_eq!(progress(3600, 3600, 0.5), Progress::Done);
+    assert_eq!(progress(1800, 3600, 0.5), Progress::OnTrack);
+    assert_eq!(progress(1799, 3600, 0.5), Progress::Behind);
+    assert_eq!(progress(0, 3600, 0.0), Progress::Upcoming);
+    assert_eq!(progress(3599, 3600, 1.0), Progress::Behind);
+    assert_eq!(bar(1800, 3600, 4), "██░░");
+    assert_eq!(bar(7200, 3600, 4), "████");
+  }
+
+  #[test]
+  fn forecasts_budgets_at_the_recent_pace() {
+    let day = |d: u32| NaiveDate::from_ymd(2022, 3, d);
+    // 10h early in the month, then 2h a day over the past four weeks, from the 4th to the 31st
+    let mut daily: BTreeMap<NaiveDate, u64> = (4..=31).map(|d| (day(d), 2 * 3600)).collect();
+    daily.insert(day(1), 10 * 3600);
+    let hours = |h: u64| h * 3600;
+    assert_eq!(
+      forecast(
+        &daily,
+        hours(80),
+        day(31),
+        Some(NaiveDate::from_ymd(2022, 4, 14))
+      ),
+      Forecast {
+        spent: hours(66),
+        remaining: hours(14),
+        weekly_burn: hours(14),
+        runs_out: Some(NaiveDate::from_ymd(2022, 4, 7)),
+        weekly_pace: Some(hours(7)),
+      }
+    );
+    let over = forecast(&daily, hours(60), day(31), Some(day(30)));
+    assert_eq!(
+      (over.remaining, over.runs_out, over.weekly_pace),
+      (0, None, None)
+    );
+
+    // A project started yesterday burns at the pace of its two days
+    let young: BTreeMap<NaiveDate, u64> = [(day(30), hours(4))].into_iter().collect();
+    let young = forecast(&young, hours(10), day(31), None);
+    assert_eq!(young.weekly_burn, hours(14));
+    assert_eq!(young.runs_out, Some(NaiveDate::from_ymd(2022, 4, 3)));
+  }
+}
@@ -0,0 +1,221 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::config::Config;
+use crate::core::Project;
+use crate::db::ProjectTotals;
+use crate::invoice::Invoice;
+use crate::timesheet::{hours, Timesheet};
+use chrono::NaiveDate;
+use handlebars::{handlebars_helper, no_escape, Handlebars, RenderError};
+use serde_json::{json, Value};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+handlebars_helper!(hours_helper: |secs: u64| hours(secs));
+handlebars_helper!(money_helper: |amount: f64| format!("{:.2}", amount));
+
+/// Renders the Handlebars template at `path`, whose content is `template`, against `context`.
+/// Values are only HTML-escaped in `.html` templates. Seconds can be formatted with
+/// `{{hours seconds}}` and amounts with `{{money amount}}`.
+pub fn render(path: &Path, template: &str, context: &Value) -> Result<String, RenderError> {
+  let mut handlebars = Handlebars::new();
+  handlebars.set_strict_mode(true);
+  handlebars.register_helper("hours", Box::new(hours_helper));
+  handlebars.register_helper("money", Box::new(money_helper));
+  if !matches!(
+    path.extension().and_then(|e| e.to_str()),
+    Some("html" | "htm")
+  ) {
+    handlebars.register_escape_fn(no_escape);
+  }
+  handlebars.render_template(template, context)
+}
+
+/// The `title`, `start` and `end` of the report, its `total` and its `projects`, each with its
+/// `name`, `seconds`, `rate` and `amount` when a rate is configured, `days` and `expenses`.
+pub fn report_context(
+  title: &str,
+  config: &Config,
+  projects: &[ProjectTotals],
+  expenses: &[Project],
+  days: RangeInclusive<NaiveDate>,
+) -> Value {
+  let projects: Vec<Value> = projects
+    .iter()
+    .map(|project| {
+      let daily: Vec<Value> = project
+        .days
+        .range(days.clone())
+        .map(|(date, secs)| json!({ "date": date.to_string(), "seconds": secs }))
+        .collect();
+      let seconds = project.days.range(days.clone()).map(|(_, secs)| secs).sum();
+      let spent: Vec<Value> = expenses
+        .iter()
+        .filter(|spent| spent.name() == project.name)
+        .flat_map(|spent| spent.expenses())
+        .map(|expense| {
+          json!({
+            "date": config.timezone.day(expense.at()).to_string(),
+            "description": expense.description(),
+            "amount": expense.amount(),
+          })
+        })
+        .collect();
+      let mut project = billed(config, &project.name, seconds);
+      project["days"] = Value::Array(daily);
+      project["expenses"] = Value::Array(spent);
+      project
+    })
+    .collect();
+  json!({
+    "title": title,
+    "start": days.start().to_string(),
+    "end": days.end().to_string(),
+    "total": total(&projects),
+    "projects": projects,
+  })
+}
+
+/// The `title`, `employee` and `client` of the timesheet, its `total` and its `days`, each with
+/// its `date`, `weekday`, `seconds` and `projects`, as billed in a report.
+pub fn timesheet_context(sheet: &Timesheet, config: &Config) -> Value {
+  let days: Vec<Value> = sheet
+    .days
+    .iter()
+    .map(|day| {
+      let projects: Vec<Value> = day
+        .projects
+        .iter()
+        .map(|(name, secs)| billed(config, name, *secs))
+        .collect();
+      json!({
+        "date": day.date.to_string(),
+        "weekday": day.date.format("%A").to_string(),
+        "seconds": day.total(),
+        "amount": total(&projects)["amount"],
+        "projects": projects,
+      })
+    })
+    .collect();
+  json!({
+    "title": sheet.title,
+    "employee": sheet.employee,
+    "client": sheet.client,
+    "total": total(&days),
+    "days": days,
+  })
+}
+
+/// The `number` of the invoice, null for drafts, its `date`, `client`, `currency`, `start` and
+/// `end`, its `lines`, each with its `project`, `seconds`, `rate` and `amount`, its `expenses`,
+/// each with its `project`, `date`, `description` and `amount`, and its `subtotal`, `tax_rate`,
+/// `tax` and `total`.
+pub fn invoice_context(invoice: &Invoice) -> Value {
+  let lines: Vec<Value> = invoice
+    .lines
+    .iter()
+    .map(|line| {
+      json!({
+        "project": line.project,
+        "seconds": line.seconds,
+        "rate": line.rate,
+        "amount": line.amount(),
+      })
+    })
+    .collect();
+  let expenses: Vec<Value> = invoice
+    .expenses
+    .iter()
+    .map(|expense| {
+      json!({
+        "project": expense.project,
+        "date": expense.date.to_string(),
+        "description": expense.description,
+        "amount": expense.amount(),
+      })
+    })
+    .collect();
+  json!({
+    "number": invoice.number,
+    "date": invoice.date.to_string(),
+    "client": invoice.client,
+    "currency": invoice.currency,
+    "start": invoice.period.start().to_string(),
+    "end": invoice.period.end().to_string(),
+    "lines": lines,
+    "expenses": expenses,
+    "subtotal": invoice.subtotal(),
+    "tax_rate": invoice.tax_rate,
+    "tax": invoice.tax(),
+    "total": invoice.total(),
+  })
+}
+
+fn billed(config: &Config, name: &str, seconds: u64) -> Value {
+  let rate = config.rate(name);
+  json!({
+    "name": name,
+    "seconds": seconds,
+    "rate": rate,
+    "amount": rate.map(|rate| (seconds as f64 / 3600.0 * rate * 100.0).round() / 100.0),
+  })
+}
+
+/// The sum of the `seconds` and `amount` of `entries`, the amount being null when none has any
+fn total(entries: &[Value]) -> Value {
+  let seconds: u64 = entries.iter().filter_map(|e| e["seconds"].as_u64()).sum();
+  let amounts: Vec<f64> = entries
+    .iter()
+    .filter_map(|e| e["amount"].as_f64())
+    .collect();
+  json!({
+    "seconds": seconds,
+    "amount": (!amounts.is_empty()).then(|| amounts.iter().sum::<f64>()),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::Config;
+  use crate::db::ProjectTotals;
+  use crate::template::{render, report_context};
+  use chrono::NaiveDate;
+  use std::path::Path;
+
+  fn context() -> serde_json::Value {
+    let config = Config::load(Path::new("/nonexistent")).unwrap();
+    let day = NaiveDate::from_ymd(2022, 3, 27);
+    let mut project = ProjectTotals::new("R&D".to_string());
+    project.add(day, 5400);
+    report_context("Report", &config, &[project], &[], day..=day)
+  }
+
+  #[test]
+  fn renders_reports() {
+    let template =
+      "{{#each projects}}{{name}}: {{hours seconds}}\n{{/each}}Total {{hours total.seconds}}";
+    let rendered = render(Path::new("report.txt"), template, &context()).unwrap();
+    assert_eq!(rendered, "R&D: 1:30\nTotal 1:30");
+    let rendered = render(Path::new("report.html"), "{{projects.0.name}}", &context()).unwrap();
+    assert_eq!(rendered, "R&amp;D");
+  }
+
+  #[test]
+  fn unknown_variables_are_errors() {
+    assert!(render(Path::new("report.txt"), "{{client}}", &context()).is_err());
+  }
+}
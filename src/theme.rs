@@ -0,0 +1,185 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use console::{Style, StyledObject};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+const ROLES: [&str; 5] = ["success", "failure", "warning", "info", "muted"];
+
+const ATTRIBUTES: [&str; 22] = [
+  "black",
+  "red",
+  "green",
+  "yellow",
+  "blue",
+  "magenta",
+  "cyan",
+  "white",
+  "bright",
+  "on_black",
+  "on_red",
+  "on_green",
+  "on_yellow",
+  "on_blue",
+  "on_magenta",
+  "on_cyan",
+  "on_white",
+  "on_bright",
+  "bold",
+  "dim",
+  "underlined",
+  "reverse",
+];
+
+/// How styled output looks, per what it tells: that something went well, failed, deserves
+/// attention, is merely informative, or is secondary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+  success: Style,
+  failure: Style,
+  warning: Style,
+  info: Style,
+  muted: Style,
+}
+
+impl Default for Theme {
+  fn default() -> Self {
+    Self {
+      success: Style::new().green(),
+      failure: Style::new().red(),
+      warning: Style::new().yellow(),
+      info: Style::new().cyan(),
+      muted: Style::new().dim(),
+    }
+  }
+}
+
+impl Theme {
+  /// Solarized's accents, for terminals whose palette isn't itself solarized
+  pub fn solarized() -> Self {
+    Self {
+      success: Style::new().color256(64),
+      failure: Style::new().color256(160),
+      warning: Style::new().color256(136),
+      info: Style::new().color256(37),
+      muted: Style::new().color256(240),
+    }
+  }
+
+  /// No colors at all, output is only ever bold or dimmed
+  pub fn monochrome() -> Self {
+    Self {
+      success: Style::new(),
+      failure: Style::new(),
+      warning: Style::new(),
+      info: Style::new(),
+      muted: Style::new().dim(),
+    }
+  }
+
+  /// This theme, but for `role` being styled as `style`, dot separated, e.g. "blue.bold", colors
+  /// being named or numbered in the 256 colors palette, e.g. "33" or "on_235"
+  pub fn with(mut self, role: &str, style: &str) -> Result<Self, String> {
+    let valid = |part: &str| {
+      ATTRIBUTES.contains(&part)
+        || part
+          .strip_prefix("on_")
+          .unwrap_or(part)
+          .parse::<u8>()
+          .is_ok()
+    };
+    if let Some(part) = style.split('.').find(|part| !valid(part)) {
+      return Err(format!(
+        "invalid style '{}' for {}, '{}' is neither a color nor an attribute, e.g. blue.bold",
+        style, role, part
+      ));
+    }
+    let style = Style::from_dotted_str(style);
+    match role {
+      "success" => self.success = style,
+      "failure" => self.failure = style,
+      "warning" => self.warning = style,
+      "info" => self.info = style,
+      "muted" => self.muted = style,
+      _ => {
+        return Err(format!(
+          "unknown role '{}', expected one of {}",
+          role,
+          ROLES.join(", ")
+        ))
+      }
+    }
+    Ok(self)
+  }
+}
+
+impl FromStr for Theme {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "default" => Ok(Theme::default()),
+      "solarized" => Ok(Theme::solarized()),
+      "monochrome" => Ok(Theme::monochrome()),
+      _ => Err(format!(
+        "unknown theme '{}', expected default, solarized or monochrome",
+        s
+      )),
+    }
+  }
+}
+
+static CURRENT: OnceLock<Theme> = OnceLock::new();
+
+static DEFAULT: OnceLock<Theme> = OnceLock::new();
+
+/// Sets the theme output is styled with, once, as soon as the configuration is loaded
+pub fn set(theme: Theme) {
+  let _ = CURRENT.set(theme);
+}
+
+/// The theme output is styled with, the default one until [`set`]
+pub fn current() -> &'static Theme {
+  CURRENT
+    .get()
+    .unwrap_or_else(|| DEFAULT.get_or_init(Theme::default))
+}
+
+/// Styles what went well, e.g. a project created
+pub fn success<D>(val: D) -> StyledObject<D> {
+  current().success.apply_to(val)
+}
+
+/// Styles what went wrong, e.g. a failure to delete a project
+pub fn failure<D>(val: D) -> StyledObject<D> {
+  current().failure.apply_to(val)
+}
+
+/// Styles what deserves attention, e.g. a note or a dry run
+pub fn warning<D>(val: D) -> StyledObject<D> {
+  current().warning.apply_to(val)
+}
+
+/// Styles what is merely informative, e.g. a reminder or a hint
+pub fn info<D>(val: D) -> StyledObject<D> {
+  current().info.apply_to(val)
+}
+
+/// Styles what is secondary, e.g. a record's context
+pub fn muted<D>(val: D) -> StyledObject<D> {
+  current().muted.apply_to(val)
+}
@@ -0,0 +1,217 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::config::TimesheetConfig;
+use crate::db::ProjectTotals;
+use crate::locale::Locale;
+use chrono::{Duration, NaiveDate};
+use pdf_writer::{Content, Name, Pdf, Rect, Ref, Str};
+use std::ops::RangeInclusive;
+
+/// A day by day account of the time tracked over a period, as handed in to whoever pays for it
+pub struct Timesheet {
+  pub title: String,
+  pub employee: Option<String>,
+  pub client: Option<String>,
+  pub days: Vec<Day>,
+}
+
+pub struct Day {
+  pub date: NaiveDate,
+  /// Seconds tracked on each project that day
+  pub projects: Vec<(String, u64)>,
+}
+
+impl Day {
+  pub fn total(&self) -> u64 {
+    self.projects.iter().map(|(_, secs)| secs).sum()
+  }
+}
+
+impl Timesheet {
+  /// Every day of `days`, including those nothing was tracked on, as timesheets usually expect
+  pub fn new(
+    title: String,
+    header: &TimesheetConfig,
+    projects: &[ProjectTotals],
+    days: RangeInclusive<NaiveDate>,
+  ) -> Self {
+    let mut sheet = Timesheet {
+      title,
+      employee: header.employee.clone(),
+      client: header.client.clone(),
+      days: Vec::new(),
+    };
+    let mut date = *days.start();
+    while date <= *days.end() {
+      let projects = projects
+        .iter()
+        .filter_map(|p| p.days.get(&date).map(|secs| (p.name.clone(), *secs)))
+        .filter(|(_, secs)| *secs > 0)
+        .collect();
+      sheet.days.push(Day { date, projects });
+      date += Duration::days(1);
+    }
+    sheet
+  }
+
+  pub fn total(&self) -> u64 {
+    self.days.iter().map(Day::total).sum()
+  }
+
+  /// A single A4 page, set in Helvetica so that no font needs embedding
+  pub fn to_pdf(&self, locale: &Locale) -> Vec<u8> {
+    let catalog = Ref::new(1);
+    let tree = Ref::new(2);
+    let page = Ref::new(3);
+    let font = Ref::new(4);
+    let contents = Ref::new(5);
+    let (regular, bold) = (Name(b"F1"), Name(b"F2"));
+    let bold_font = Ref::new(6);
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog).pages(tree);
+    pdf.pages(tree).kids([page]).count(1);
+    let mut writer = pdf.page(page);
+    writer
+      .media_box(Rect::new(0.0, 0.0, 595.0, 842.0))
+      .parent(tree)
+      .contents(contents);
+    writer
+      .resources()
+      .fonts()
+      .pair(regular, font)
+      .pair(bold, bold_font);
+    drop(writer);
+    for (id, name) in [(font, &b"Helvetica"[..]), (bold_font, b"Helvetica-Bold")] {
+      pdf
+        .type1_font(id)
+        .base_font(Name(name))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+
+    let mut content = Content::new();
+    let mut text = |font: Name, size: f32, x: f32, y: f32, value: &str| {
+      content.begin_text();
+      content.set_font(font, size);
+      content.next_line(x, y);
+      content.show(Str(&win_ansi(value)));
+      content.end_text();
+    };
+
+    text(bold, 16.0, 50.0, 780.0, &self.title);
+    let mut y = 755.0;
+    for (label, value) in [("Employee", &self.employee), ("Client", &self.client)] {
+      if let Some(value) = value {
+        text(bold, 10.0, 50.0, y, label);
+        text(regular, 10.0, 120.0, y, value);
+        y -= 14.0;
+      }
+    }
+
+    y -= 16.0;
+    text(bold, 10.0, 50.0, y, "Date");
+    text(bold, 10.0, 150.0, y, "Projects");
+    text(bold, 10.0, 500.0, y, "Hours");
+    let mut rules = vec![y - 5.0];
+    for day in &self.days {
+      y -= 16.0;
+      text(regular, 10.0, 50.0, y, &locale.format(day.date, "%a %b %e"));
+      let names: Vec<&str> = day.projects.iter().map(|(name, _)| name.as_str()).collect();
+      text(regular, 10.0, 150.0, y, &names.join(", "));
+      if day.total() > 0 {
+        text(regular, 10.0, 500.0, y, &hours(day.total()));
+      }
+    }
+    rules.push(y - 5.0);
+    y -= 20.0;
+    text(bold, 10.0, 150.0, y, "Total");
+    text(bold, 10.0, 500.0, y, &hours(self.total()));
+
+    y -= 60.0;
+    let signatures = [(50.0, "Employee signature"), (330.0, "Client approval")];
+    for (x, label) in signatures {
+      text(regular, 9.0, x, y - 12.0, label);
+    }
+
+    content.set_line_width(0.5);
+    for rule in rules {
+      content.move_to(50.0, rule).line_to(545.0, rule);
+    }
+    for (x, _) in signatures {
+      content.move_to(x, y).line_to(x + 215.0, y);
+    }
+    content.stroke();
+
+    pdf.stream(contents, &content.finish());
+    pdf.finish()
+  }
+}
+
+/// `secs` as `h:mm`, the way timesheets account for time
+pub fn hours(secs: u64) -> String {
+  let minutes = (secs + 30) / 60;
+  format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// The WinAnsi bytes of `value`, close enough to Latin-1 for names, anything else becoming `?`
+fn win_ansi(value: &str) -> Vec<u8> {
+  value
+    .chars()
+    .map(|c| match c as u32 {
+      code @ (0x20..=0x7e | 0xa0..=0xff) => code as u8,
+      _ => b'?',
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::TimesheetConfig;
+  use crate::db::ProjectTotals;
+  use crate::locale::Locale;
+  use crate::timesheet::{hours, Timesheet};
+  use chrono::NaiveDate;
+
+  #[test]
+  fn lists_every_day() {
+    let mut backend = ProjectTotals::new("backend".to_string());
+    backend.add(NaiveDate::from_ymd(2022, 3, 1), 3600);
+    backend.add(NaiveDate::from_ymd(2022, 3, 3), 1800);
+    let mut frontend = ProjectTotals::new("frontend".to_string());
+    frontend.add(NaiveDate::from_ymd(2022, 3, 3), 900);
+    let header = TimesheetConfig {
+      employee: Some("Jane Doe".to_string()),
+      client: None,
+    };
+    let days = NaiveDate::from_ymd(2022, 3, 1)..=NaiveDate::from_ymd(2022, 3, 31);
+    let sheet = Timesheet::new("March".to_string(), &header, &[backend, frontend], days);
+
+    assert_eq!(sheet.days.len(), 31);
+    assert!(sheet.days[1].projects.is_empty());
+    assert_eq!(sheet.days[2].projects.len(), 2);
+    assert_eq!(hours(sheet.total()), "1:45");
+    assert!(sheet.to_pdf(&Locale::default()).starts_with(b"%PDF-"));
+  }
+
+  #[test]
+  fn hours_round_to_the_minute() {
+    assert_eq!(hours(0), "0:00");
+    assert_eq!(hours(29), "0:00");
+    assert_eq!(hours(30), "0:01");
+    assert_eq!(hours(36000 + 59 * 60), "10:59");
+  }
+}
@@ -0,0 +1,235 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::config::{self, Config};
+use crate::core::{Labels, Record};
+use crate::db::{Database, Load};
+use crate::report::{self, Period, ReportRow};
+use chrono::Local;
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for another process, e.g. the command line, to release the database
+const LOCK_WAIT: Duration = Duration::from_secs(10);
+
+/// Tracks time for frontends other than the command line, e.g. menu bar apps. Every call opens
+/// the database, does its thing and closes it again, so that the command line can still be used
+/// alongside. Calls from different threads are serialized.
+pub struct Tracker {
+  location: PathBuf,
+  guard: Mutex<()>,
+}
+
+/// What's currently being tracked, in the order it was started, and what was last
+#[derive(Clone)]
+pub struct Status {
+  pub running: Vec<(String, Record)>,
+  pub last: Option<(String, Record)>,
+}
+
+#[derive(Debug)]
+pub enum TrackerError {
+  /// The database couldn't be opened, e.g. another process held its lock for too long
+  Storage(ErrorKind),
+  UnknownProject(String),
+  /// The project is archived, and can't be started
+  Archived(String),
+  NotRunning,
+  /// The change couldn't be written to the database
+  Unwritable,
+}
+
+impl Display for TrackerError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      TrackerError::Storage(kind) => write!(f, "couldn't open the database: {:?}", kind),
+      TrackerError::UnknownProject(name) => write!(f, "no project named '{}'", name),
+      TrackerError::Archived(name) => write!(f, "'{}' is archived", name),
+      TrackerError::NotRunning => write!(f, "nothing is running"),
+      TrackerError::Unwritable => write!(f, "couldn't write to the database"),
+    }
+  }
+}
+
+impl std::error::Error for TrackerError {}
+
+impl Tracker {
+  /// A tracker for the database at `location`, e.g. `~/.timeknight`
+  pub fn new(location: &Path) -> Self {
+    Tracker {
+      location: location.to_path_buf(),
+      guard: Mutex::new(()),
+    }
+  }
+
+  /// Starts tracking `name`, stopping whatever is running. Returns the record started, along
+  /// with the name of its project.
+  pub fn start(&self, name: &str) -> Result<(String, Record), TrackerError> {
+    let (_guard, mut database) = self.open(Load::Headers)?;
+    match database.project(name) {
+      None => return Err(TrackerError::UnknownProject(name.to_string())),
+      Some(project) if project.archived() => {
+        return Err(TrackerError::Archived(project.name().to_string()))
+      }
+      Some(_) => {}
+    }
+    match database.start_on(name.to_string(), false, Labels::default()) {
+      Ok(project) => Ok((
+        project.name().to_string(),
+        project.records().last().expect("Just started").clone(),
+      )),
+      Err(_) => Err(TrackerError::Unwritable),
+    }
+  }
+
+  /// Stops everything that's running, returning the records that were stopped
+  pub fn stop(&self) -> Result<Vec<(String, Record)>, TrackerError> {
+    let (_guard, mut database) = self.open(Load::Headers)?;
+    if database.running_projects().is_empty() {
+      return Err(TrackerError::NotRunning);
+    }
+    match database.stop() {
+      Ok(projects) => Ok(
+        projects
+          .iter()
+          .map(|p| (p.name().to_string(), p.records().last().unwrap().clone()))
+          .collect(),
+      ),
+      Err(_) => Err(TrackerError::Unwritable),
+    }
+  }
+
+  /// What's running, if anything, and what ran last. Never waits for the database's lock.
+  pub fn status(&self) -> Result<Status, TrackerError> {
+    let _guard = self.guard();
+    Ok(Status {
+      running: Database::running(&self.location).map_err(TrackerError::Storage)?,
+      last: Database::last_session(&self.location).map_err(TrackerError::Storage)?,
+    })
+  }
+
+  /// The names of all projects, sorted
+  pub fn projects(&self) -> Result<Vec<String>, TrackerError> {
+    let _guard = self.guard();
+    match Database::open_read_only(&self.location, Load::Headers) {
+      Ok(database) => Ok(
+        database
+          .list_projects()
+          .iter()
+          .map(|p| p.name().to_string())
+          .collect(),
+      ),
+      Err(err) => Err(TrackerError::Storage(err)),
+    }
+  }
+
+  /// The time tracked on each project over `period`, sorted by project
+  pub fn report(&self, period: &Period) -> Result<Vec<ReportRow>, TrackerError> {
+    let _guard = self.guard();
+    let today = Local::today().naive_local();
+    let mut projects =
+      Database::daily_totals(&self.location, period.days(today)).map_err(TrackerError::Storage)?;
+    projects.sort_by_key(|p| p.name.to_lowercase());
+    Ok(report::rows(&projects, &period.bounds(today), false))
+  }
+
+  fn guard(&self) -> MutexGuard<'_, ()> {
+    // Nothing is left half done by a panicking call, all state lives in the database
+    self
+      .guard
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  /// Opens the database, waiting for its lock if needed, holding the guard for as long as it's open
+  fn open(&self, load: Load) -> Result<(MutexGuard<'_, ()>, Database), TrackerError> {
+    let guard = self.guard();
+    let deadline = Instant::now() + LOCK_WAIT;
+    loop {
+      match Database::open(&self.location, load.clone()) {
+        Err(ErrorKind::AlreadyExists) if Instant::now() < deadline => {
+          thread::sleep(Duration::from_millis(100))
+        }
+        Ok(mut database) => {
+          // Attribution is best effort, entries are fine without
+          if let Ok(device) = config::device_id() {
+            database.attribute_to(device);
+          }
+          if let Ok(config) = Config::load(&self.location) {
+            for (name, defaults) in config.defaults() {
+              database.set_defaults(name, defaults);
+            }
+            if let Some(context) = config.context() {
+              database.capture_context(context);
+            }
+            if config.audit && database.seal_appends().is_err() {
+              return Err(TrackerError::Unwritable);
+            }
+          }
+          return Ok((guard, database));
+        }
+        Err(err) => return Err(TrackerError::Storage(err)),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::db::{Database, Load};
+  use crate::report::Period;
+  use crate::tracker::{Tracker, TrackerError};
+  use std::env;
+  use std::fs::{create_dir, remove_dir_all};
+  use std::sync::Arc;
+  use std::thread;
+
+  #[test]
+  fn test_tracks_from_many_threads() {
+    let location = env::temp_dir().join("timeknightTest_tracks_from_many_threads");
+    create_dir(location.as_path()).expect("failed to create temp directory");
+    {
+      let mut database = Database::open(location.as_path(), Load::Full).expect("Failed opening");
+      database.add_project("Menubar".to_string()).unwrap();
+    }
+    let tracker = Arc::new(Tracker::new(location.as_path()));
+    assert!(matches!(
+      tracker.start("nope"),
+      Err(TrackerError::UnknownProject(_))
+    ));
+    let starts: Vec<_> = (0..4)
+      .map(|_| {
+        let tracker = tracker.clone();
+        thread::spawn(move || tracker.start("menubar").is_ok())
+      })
+      .collect();
+    assert!(starts.into_iter().all(|t| t.join().unwrap()));
+    let status = tracker.status().unwrap();
+    assert_eq!(status.running.len(), 1);
+    assert_eq!(status.running[0].0, "Menubar");
+    assert_eq!(tracker.stop().unwrap().len(), 1);
+    assert!(matches!(tracker.stop(), Err(TrackerError::NotRunning)));
+    assert_eq!(tracker.projects().unwrap(), vec!["Menubar".to_string()]);
+    let report = tracker.report(&Period::Today).unwrap();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].project, "Menubar");
+    remove_dir_all(location.as_path()).expect("couldn't cleanup our test directory!")
+  }
+}
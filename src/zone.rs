@@ -0,0 +1,341 @@
+/*
+ * Copyright 2022 Alex Snaps
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use chrono::{
+  DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone,
+  Utc,
+};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// The timezone records are bucketed into days and shown in. Records are instants, along with the
+/// offset they were tracked at, and read in a single zone, the day they fall on doesn't depend on
+/// where nor when in the year each was tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Zone {
+  /// This machine's timezone, at the time of each record, daylight saving time included
+  #[default]
+  Local,
+  Utc,
+  /// A fixed offset from UTC, e.g. +05:30
+  Fixed(FixedOffset),
+  /// The offset each record was tracked at
+  Recorded,
+}
+
+impl Zone {
+  /// `at`, expressed in this zone
+  pub fn at(&self, at: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+    match self {
+      Zone::Local => at.with_timezone(&Local.offset_from_utc_datetime(&at.naive_utc()).fix()),
+      Zone::Utc => at.with_timezone(&Utc.fix()),
+      Zone::Fixed(offset) => at.with_timezone(offset),
+      Zone::Recorded => at,
+    }
+  }
+
+  /// The day `at` falls on, in this zone
+  pub fn day(&self, at: DateTime<FixedOffset>) -> NaiveDate {
+    self.at(at).date().naive_local()
+  }
+
+  /// The instants `day` starts and ends at in this zone, 23 or 25 hours apart when [`Zone::Local`]
+  /// changes to or from daylight saving time that day. Each record's offset being of no help
+  /// there, [`Zone::Recorded`] days are this machine's.
+  pub fn bounds(&self, day: NaiveDate) -> Range<DateTime<FixedOffset>> {
+    match self {
+      Zone::Local | Zone::Recorded => day_bounds(&Local, day),
+      Zone::Utc => day_bounds(&Utc, day),
+      Zone::Fixed(offset) => day_bounds(offset, day),
+    }
+  }
+}
+
+/// The instants `day` starts and ends at in `tz`
+pub(crate) fn day_bounds<Tz: TimeZone>(tz: &Tz, day: NaiveDate) -> Range<DateTime<FixedOffset>> {
+  midnight(tz, day)..midnight(tz, day.succ())
+}
+
+/// The instant `day` starts at in `tz`: the earliest of two midnights when clocks were turned back
+/// over it, the instant they were turned forward when midnight was skipped
+fn midnight<Tz: TimeZone>(tz: &Tz, day: NaiveDate) -> DateTime<FixedOffset> {
+  let midnight = day.and_hms(0, 0, 0);
+  let offset = tz
+    .offset_from_local_datetime(&midnight)
+    .earliest()
+    .or_else(|| {
+      tz.offset_from_local_datetime(&(midnight - Duration::days(1)))
+        .earliest()
+    })
+    .expect("Clocks don't skip a whole day")
+    .fix();
+  offset
+    .from_local_datetime(&midnight)
+    .single()
+    .expect("Fixed offsets are unambiguous")
+}
+
+/// When `raw` was, as of `now` and read in `tz`: a time of day, today, e.g. "09:30", a date and
+/// time, e.g. "2022-03-14 09:30", or some time ago, e.g. "15 minutes ago" or "1h30m ago"
+pub fn parse_instant<Tz: TimeZone>(
+  raw: &str,
+  tz: &Tz,
+  now: DateTime<Utc>,
+) -> Result<DateTime<FixedOffset>, String> {
+  let in_tz =
+    |at: DateTime<Utc>| at.with_timezone(&tz.offset_from_utc_datetime(&at.naive_utc()).fix());
+  let raw = raw.trim();
+  if let Some(ago) = raw.strip_suffix("ago") {
+    return match elapsed(ago) {
+      Some(elapsed) => Ok(in_tz(now - elapsed)),
+      None => Err(format!(
+        "'{}' isn't a time ago, e.g. 15 minutes ago or 1h30m ago",
+        raw
+      )),
+    };
+  }
+  let local = NaiveTime::parse_from_str(raw, "%H:%M")
+    .or_else(|_| NaiveTime::parse_from_str(raw, "%H:%M:%S"))
+    .map(|time| in_tz(now).date().naive_local().and_time(time))
+    .or_else(|_| NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M"))
+    .map_err(|_| {
+      format!(
+        "'{}' isn't a time, e.g. 09:30, 2022-03-14 09:30 or 15 minutes ago",
+        raw
+      )
+    })?;
+  match tz.offset_from_local_datetime(&local).earliest() {
+    Some(offset) => Ok(
+      offset
+        .fix()
+        .from_local_datetime(&local)
+        .single()
+        .expect("Fixed offsets are unambiguous"),
+    ),
+    None => Err(format!("'{}' never was, the clocks skipped it", raw)),
+  }
+}
+
+/// Hours and minutes, spelled out or not: "15 minutes", "2 hours", "1h30m"
+fn elapsed(raw: &str) -> Option<Duration> {
+  let mut compact: String = raw.split_whitespace().collect();
+  for (word, unit) in [
+    ("hours", "h"),
+    ("hour", "h"),
+    ("minutes", "m"),
+    ("minute", "m"),
+    ("mins", "m"),
+    ("min", "m"),
+  ] {
+    compact = compact.replace(word, unit);
+  }
+  let (hours, minutes) = match compact.split_once('h') {
+    Some((hours, minutes)) => (hours, minutes),
+    None => ("0", compact.as_str()),
+  };
+  let minutes = match minutes {
+    "" => "0",
+    minutes => minutes.strip_suffix('m')?,
+  };
+  Some(Duration::minutes(
+    hours.parse::<i64>().ok()? * 60 + minutes.parse::<i64>().ok()?,
+  ))
+}
+
+impl FromStr for Zone {
+  type Err = String;
+
+  /// local, utc, recorded, or an offset like +05:30
+  fn from_str(raw: &str) -> Result<Self, Self::Err> {
+    match raw {
+      "local" => Ok(Zone::Local),
+      "utc" | "UTC" => Ok(Zone::Utc),
+      "recorded" => Ok(Zone::Recorded),
+      _ => {
+        let offset = raw
+          .strip_prefix('+')
+          .map(|rest| (1, rest))
+          .or_else(|| raw.strip_prefix('-').map(|rest| (-1, rest)))
+          .and_then(|(sign, rest)| {
+            let (hours, minutes) = rest.split_once(':')?;
+            let (hours, minutes) = (hours.parse::<i32>().ok()?, minutes.parse::<i32>().ok()?);
+            (hours < 24 && minutes < 60).then(|| sign * (hours * 3600 + minutes * 60))
+          });
+        match offset {
+          Some(secs) => Ok(Zone::Fixed(FixedOffset::east(secs))),
+          None => Err(format!(
+            "'{}' isn't a timezone, expected local, utc, recorded or an offset like +05:30",
+            raw
+          )),
+        }
+      }
+    }
+  }
+}
+
+impl Display for Zone {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Zone::Local => write!(f, "local"),
+      Zone::Utc => write!(f, "utc"),
+      Zone::Fixed(offset) => write!(f, "{}", offset),
+      Zone::Recorded => write!(f, "recorded"),
+    }
+  }
+}
+
+/// The instant `ts`, in seconds since the epoch, at the offset it was recorded at, `tz` seconds
+/// west of UTC
+pub fn instant(ts: i64, tz: i32) -> DateTime<FixedOffset> {
+  Utc.timestamp(ts, 0).with_timezone(&FixedOffset::west(tz))
+}
+
+static CURRENT: OnceLock<Zone> = OnceLock::new();
+
+/// Sets the zone records are read in, once, as soon as the configuration is loaded
+pub fn set(zone: Zone) {
+  let _ = CURRENT.set(zone);
+}
+
+/// The zone records are read in, this machine's until [`set`]
+pub fn current() -> Zone {
+  CURRENT.get().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::zone::{day_bounds, instant, parse_instant, Zone};
+  use chrono::{DateTime, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, TimeZone};
+
+  /// A zone five hours west of UTC, turning its clocks forward an hour at `change`
+  #[derive(Clone, Copy)]
+  struct Dst {
+    change: NaiveDateTime,
+  }
+
+  impl Dst {
+    fn offset(&self, utc: &NaiveDateTime) -> FixedOffset {
+      let winter = FixedOffset::west(5 * 3600);
+      match *utc < self.change - winter {
+        true => winter,
+        false => FixedOffset::west(4 * 3600),
+      }
+    }
+  }
+
+  impl TimeZone for Dst {
+    type Offset = FixedOffset;
+
+    fn from_offset(_: &FixedOffset) -> Self {
+      unreachable!("Only ever used through offsets")
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+      self.offset_from_local_datetime(&local.and_hms(0, 0, 0))
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+      let (winter, summer) = (FixedOffset::west(5 * 3600), FixedOffset::west(4 * 3600));
+      let valid = |offset: FixedOffset| self.offset(&(*local - offset)) == offset;
+      match (valid(winter), valid(summer)) {
+        (true, true) => LocalResult::Ambiguous(winter, summer),
+        (true, false) => LocalResult::Single(winter),
+        (false, true) => LocalResult::Single(summer),
+        (false, false) => LocalResult::None,
+      }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+      self.offset(&utc.and_hms(0, 0, 0))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+      self.offset(utc)
+    }
+  }
+
+  #[test]
+  fn reads_records_in_one_zone() {
+    let late = DateTime::parse_from_rfc3339("2022-03-26T23:30:00-04:00").unwrap();
+    assert_eq!(instant(late.timestamp(), 4 * 3600), late);
+    let day = |zone: &str| zone.parse::<Zone>().unwrap().day(late);
+    assert_eq!(day("recorded"), NaiveDate::from_ymd(2022, 3, 26));
+    assert_eq!(day("utc"), NaiveDate::from_ymd(2022, 3, 27));
+    assert_eq!(day("+05:30"), NaiveDate::from_ymd(2022, 3, 27));
+    assert_eq!(day("-10:00"), NaiveDate::from_ymd(2022, 3, 26));
+    assert_eq!("-10:00".parse::<Zone>().unwrap().to_string(), "-10:00");
+    assert!("Europe/Paris".parse::<Zone>().is_err());
+    assert!("+25:00".parse::<Zone>().is_err());
+  }
+
+  #[test]
+  fn days_last_as_long_as_the_clocks_say() {
+    let day = |d: u32| NaiveDate::from_ymd(2022, 3, d);
+    let hours =
+      |bounds: std::ops::Range<DateTime<FixedOffset>>| (bounds.end - bounds.start).num_hours();
+    let new_york = Dst {
+      change: day(13).and_hms(2, 0, 0),
+    };
+    assert_eq!(hours(day_bounds(&new_york, day(12))), 24);
+    assert_eq!(hours(day_bounds(&new_york, day(13))), 23);
+    assert_eq!(hours(day_bounds(&new_york, day(14))), 24);
+    assert_eq!(
+      day_bounds(&new_york, day(14)).start,
+      DateTime::parse_from_rfc3339("2022-03-14T00:00:00-04:00").unwrap()
+    );
+    // Clocks going from 23:59:59 straight to 01:00, the day starts at 01:00
+    let skipping_midnight = Dst {
+      change: day(13).and_hms(0, 0, 0),
+    };
+    let bounds = day_bounds(&skipping_midnight, day(13));
+    assert_eq!(
+      bounds.start,
+      DateTime::parse_from_rfc3339("2022-03-13T01:00:00-04:00").unwrap()
+    );
+    assert_eq!(hours(bounds), 23);
+    assert_eq!(hours(day_bounds(&skipping_midnight, day(12))), 24);
+    assert_eq!(hours(Zone::Utc.bounds(day(13))), 24);
+  }
+
+  #[test]
+  fn instants_are_times_dates_or_some_time_ago() {
+    let new_york = Dst {
+      change: NaiveDate::from_ymd(2022, 3, 13).and_hms(2, 0, 0),
+    };
+    let now = DateTime::parse_from_rfc3339("2022-03-14T14:00:00Z").unwrap();
+    let at = |raw: &str| parse_instant(raw, &new_york, now.into()).map(|at| at.to_rfc3339());
+    assert_eq!(at("09:30"), Ok("2022-03-14T09:30:00-04:00".to_string()));
+    assert_eq!(
+      at("15 minutes ago"),
+      Ok("2022-03-14T09:45:00-04:00".to_string())
+    );
+    assert_eq!(at("1h30m ago"), Ok("2022-03-14T08:30:00-04:00".to_string()));
+    assert_eq!(
+      at("1 hour ago"),
+      Ok("2022-03-14T09:00:00-04:00".to_string())
+    );
+    assert_eq!(at("12h ago"), Ok("2022-03-13T22:00:00-04:00".to_string()));
+    assert_eq!(
+      at("2022-03-12 23:00"),
+      Ok("2022-03-12T23:00:00-05:00".to_string())
+    );
+    assert!(at("2022-03-13 02:30").is_err());
+    assert!(at("a while ago").is_err());
+    assert!(at("9.30").is_err());
+  }
+}